@@ -0,0 +1,104 @@
+// Package conditionals describes the variables and functions available in
+// Buildkite step `if` expressions.
+package conditionals
+
+import (
+	"sort"
+	"strings"
+)
+
+// Variable is a value or function that can be referenced in an `if` expression
+type Variable struct {
+	Name        string // Full dotted path, e.g. "build.branch"
+	Description string
+	IsFunction  bool
+}
+
+// Variables is the catalog of expression variables, kept in dotted-path order
+var Variables = []Variable{
+	{Name: "build.author.email", Description: "The unverified email address of the user who authored the build's commit"},
+	{Name: "build.author.id", Description: "The unverified ID of the user who authored the build's commit"},
+	{Name: "build.author.name", Description: "The unverified name of the user who authored the build's commit"},
+	{Name: "build.author.teams", Description: "The unverified teams of the user who authored the build's commit"},
+	{Name: "build.branch", Description: "The branch on which this build is created from"},
+	{Name: "build.commit", Description: "The commit number of the commit the current build is based on"},
+	{Name: "build.creator.email", Description: "The email address of the user who created the build"},
+	{Name: "build.creator.id", Description: "The ID of the user who created the build"},
+	{Name: "build.creator.name", Description: "The name of the user who created the build"},
+	{Name: "build.creator.teams", Description: "The teams of the user who created the build"},
+	{Name: "build.env", Description: "Returns the value of an environment variable, e.g. `build.env(\"NAME\")`", IsFunction: true},
+	{Name: "build.id", Description: "The ID of the current build"},
+	{Name: "build.message", Description: "The current build's message"},
+	{Name: "build.number", Description: "The number of the current build"},
+	{Name: "build.pull_request.base_branch", Description: "The base branch that the pull request is targeting, otherwise null"},
+	{Name: "build.pull_request.draft", Description: "Whether the pull request is a draft"},
+	{Name: "build.pull_request.id", Description: "The number of the pull request, otherwise null"},
+	{Name: "build.pull_request.labels", Description: "A list of label names attached to the pull request"},
+	{Name: "build.pull_request.repository", Description: "The repository URL of the pull request, otherwise null"},
+	{Name: "build.pull_request.repository.fork", Description: "Whether the pull request comes from a forked repository"},
+	{Name: "build.source", Description: "The source of the event that created the build (e.g. `webhook`, `ui`, `api`, `schedule`)"},
+	{Name: "build.state", Description: "The state the current build is in"},
+	{Name: "build.tag", Description: "The tag associated with the commit the current build is based on"},
+	{Name: "organization.id", Description: "The ID of the organization"},
+	{Name: "organization.slug", Description: "The slug of the organization"},
+	{Name: "pipeline.default_branch", Description: "The default branch of the pipeline the current build is from"},
+	{Name: "pipeline.id", Description: "The ID of the pipeline the current build is from"},
+	{Name: "pipeline.repository", Description: "The repository of the pipeline the current build is from"},
+	{Name: "pipeline.slug", Description: "The slug of the pipeline the current build is from"},
+}
+
+// Member is one segment of a dotted path that can follow a prefix
+type Member struct {
+	Name        string
+	Description string
+	IsFunction  bool
+	HasMembers  bool // The member is an object with its own members
+}
+
+// Members returns the path segments that can follow prefix, e.g. "build" or
+// "build.pull_request". An empty prefix returns the root objects.
+func Members(prefix string) []Member {
+	byName := make(map[string]*Member)
+
+	for _, variable := range Variables {
+		rest := variable.Name
+		if prefix != "" {
+			if !strings.HasPrefix(variable.Name, prefix+".") {
+				continue
+			}
+			rest = strings.TrimPrefix(variable.Name, prefix+".")
+		}
+
+		name, _, nested := strings.Cut(rest, ".")
+		member, exists := byName[name]
+		if !exists {
+			member = &Member{Name: name}
+			byName[name] = member
+		}
+
+		if nested {
+			member.HasMembers = true
+		} else {
+			member.Description = variable.Description
+			member.IsFunction = variable.IsFunction
+		}
+	}
+
+	members := make([]Member, 0, len(byName))
+	for _, member := range byName {
+		members = append(members, *member)
+	}
+	sort.Slice(members, func(i, j int) bool { return members[i].Name < members[j].Name })
+
+	return members
+}
+
+// Lookup returns the variable with the given dotted path
+func Lookup(name string) (Variable, bool) {
+	for _, variable := range Variables {
+		if variable.Name == name {
+			return variable, true
+		}
+	}
+	return Variable{}, false
+}
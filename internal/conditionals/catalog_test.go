@@ -0,0 +1,59 @@
+package conditionals
+
+import "testing"
+
+func TestMembers(t *testing.T) {
+	tests := []struct {
+		prefix   string
+		expected []string
+	}{
+		{prefix: "", expected: []string{"build", "organization", "pipeline"}},
+		{prefix: "organization", expected: []string{"id", "slug"}},
+		{prefix: "build.author", expected: []string{"email", "id", "name", "teams"}},
+		{prefix: "unknown", expected: []string{}},
+	}
+
+	for _, tt := range tests {
+		members := Members(tt.prefix)
+		if len(members) != len(tt.expected) {
+			t.Errorf("Members(%q): expected %d members, got %d", tt.prefix, len(tt.expected), len(members))
+			continue
+		}
+		for i, expected := range tt.expected {
+			if members[i].Name != expected {
+				t.Errorf("Members(%q)[%d]: expected %q, got %q", tt.prefix, i, expected, members[i].Name)
+			}
+		}
+	}
+}
+
+func TestMembers_NestedAndFunctions(t *testing.T) {
+	found := make(map[string]Member)
+	for _, member := range Members("build") {
+		found[member.Name] = member
+	}
+
+	if !found["author"].HasMembers {
+		t.Error("Expected build.author to have members")
+	}
+	if !found["env"].IsFunction {
+		t.Error("Expected build.env to be a function")
+	}
+
+	// build.pull_request.repository is both a value and an object
+	repository := Members("build.pull_request")
+	for _, member := range repository {
+		if member.Name == "repository" && (!member.HasMembers || member.Description == "") {
+			t.Errorf("Expected repository to have members and a description, got %+v", member)
+		}
+	}
+}
+
+func TestLookup(t *testing.T) {
+	if _, ok := Lookup("build.branch"); !ok {
+		t.Error("Expected build.branch to be in the catalog")
+	}
+	if _, ok := Lookup("build.nope"); ok {
+		t.Error("Did not expect build.nope in the catalog")
+	}
+}
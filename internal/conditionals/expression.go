@@ -0,0 +1,129 @@
+package conditionals
+
+import "strings"
+
+// Segment is one identifier of a member access, with its byte offsets in the
+// expression
+type Segment struct {
+	Name  string
+	Start int
+	End   int
+}
+
+// MemberAccess is a dotted path in an expression, e.g. build.pull_request.id
+type MemberAccess struct {
+	Segments []Segment
+}
+
+// Path returns the access as a dotted path
+func (a MemberAccess) Path() string {
+	names := make([]string, len(a.Segments))
+	for i, segment := range a.Segments {
+		names[i] = segment.Name
+	}
+	return strings.Join(names, ".")
+}
+
+// MemberAccesses returns the dotted paths in an `if` expression, skipping
+// string and regular expression literals
+func MemberAccesses(expression string) []MemberAccess {
+	var accesses []MemberAccess
+
+	for i := 0; i < len(expression); {
+		c := expression[i]
+		switch {
+		case c == '"' || c == '\'':
+			i = skipLiteral(expression, i)
+		case c == '/' && strings.HasSuffix(strings.TrimRight(expression[:i], " "), "~"):
+			i = skipLiteral(expression, i)
+		case isIdentifierStart(c):
+			// Members of a call result, e.g. build.env("X").y, aren't tracked
+			following := i > 0 && expression[i-1] == '.'
+
+			var access MemberAccess
+			for {
+				end := i + 1
+				for end < len(expression) && isIdentifierChar(expression[end]) {
+					end++
+				}
+				access.Segments = append(access.Segments, Segment{Name: expression[i:end], Start: i, End: end})
+				i = end
+				if i+1 >= len(expression) || expression[i] != '.' || !isIdentifierStart(expression[i+1]) {
+					break
+				}
+				i++
+			}
+
+			if !following && len(access.Segments) > 1 {
+				accesses = append(accesses, access)
+			}
+		case isIdentifierChar(c):
+			// Skip the rest of a number so 1.5 isn't read as a member access
+			for i < len(expression) && (isIdentifierChar(expression[i]) || expression[i] == '.') {
+				i++
+			}
+		default:
+			i++
+		}
+	}
+
+	return accesses
+}
+
+// UnknownMember returns the first segment of access that isn't a member of the
+// object before it, along with that object's path. Accesses that don't start at
+// one of the catalog's root objects are ignored.
+func UnknownMember(access MemberAccess) (Segment, string, bool) {
+	if len(access.Segments) < 2 {
+		return Segment{}, "", false
+	}
+	if _, ok := findMember("", access.Segments[0].Name); !ok {
+		return Segment{}, "", false
+	}
+
+	prefix := access.Segments[0].Name
+	for _, segment := range access.Segments[1:] {
+		member, ok := findMember(prefix, segment.Name)
+		if !ok {
+			return segment, prefix, true
+		}
+		if member.IsFunction {
+			break
+		}
+		prefix += "." + segment.Name
+	}
+
+	return Segment{}, "", false
+}
+
+func findMember(prefix, name string) (Member, bool) {
+	for _, member := range Members(prefix) {
+		if member.Name == name {
+			return member, true
+		}
+	}
+	return Member{}, false
+}
+
+// skipLiteral returns the offset just past the literal opened at start,
+// honouring backslash escapes
+func skipLiteral(expression string, start int) int {
+	quote := expression[start]
+	for i := start + 1; i < len(expression); i++ {
+		switch expression[i] {
+		case '\\':
+			i++
+		case quote:
+			return i + 1
+		}
+	}
+	return len(expression)
+}
+
+func isIdentifierStart(c byte) bool {
+	return c == '_' || (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z')
+}
+
+func isIdentifierChar(c byte) bool {
+	return isIdentifierStart(c) || (c >= '0' && c <= '9')
+}
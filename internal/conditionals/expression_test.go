@@ -0,0 +1,75 @@
+package conditionals
+
+import "testing"
+
+func TestMemberAccesses(t *testing.T) {
+	tests := []struct {
+		expression string
+		expected   []string
+	}{
+		{expression: `build.branch == "main"`, expected: []string{"build.branch"}},
+		{expression: `build.pull_request.id != null && pipeline.slug == 'a.b'`, expected: []string{"build.pull_request.id", "pipeline.slug"}},
+		{expression: `build.message =~ /skip.ci/`, expected: []string{"build.message"}},
+		{expression: `build.env("DEPLOY.TARGET") == "x.y"`, expected: []string{"build.env"}},
+		{expression: `build.number > 1.5`, expected: []string{"build.number"}},
+		{expression: `"build.branch" == build`, expected: nil},
+	}
+
+	for _, tt := range tests {
+		accesses := MemberAccesses(tt.expression)
+		if len(accesses) != len(tt.expected) {
+			t.Errorf("MemberAccesses(%q): expected %v, got %d accesses", tt.expression, tt.expected, len(accesses))
+			continue
+		}
+		for i, expected := range tt.expected {
+			if accesses[i].Path() != expected {
+				t.Errorf("MemberAccesses(%q)[%d]: expected %q, got %q", tt.expression, i, expected, accesses[i].Path())
+			}
+		}
+	}
+}
+
+func TestMemberAccesses_Spans(t *testing.T) {
+	expression := `build.pull_request.number == 1`
+	accesses := MemberAccesses(expression)
+	if len(accesses) != 1 {
+		t.Fatalf("Expected 1 access, got %d", len(accesses))
+	}
+
+	segment := accesses[0].Segments[2]
+	if expression[segment.Start:segment.End] != "number" {
+		t.Errorf("Expected the span to cover 'number', got %q", expression[segment.Start:segment.End])
+	}
+}
+
+func TestUnknownMember(t *testing.T) {
+	tests := []struct {
+		expression string
+		member     string
+		object     string
+	}{
+		{expression: `build.pull_request.number`, member: "number", object: "build.pull_request"},
+		{expression: `build.pull_request_number`, member: "pull_request_number", object: "build"},
+		{expression: `build.branch.name`, member: "name", object: "build.branch"},
+		{expression: `pipeline.default_branch`},
+		{expression: `build.pull_request.repository.fork`},
+		{expression: `build.env("X")`},
+		{expression: `deploy.target`},
+	}
+
+	for _, tt := range tests {
+		accesses := MemberAccesses(tt.expression)
+		if len(accesses) != 1 {
+			t.Fatalf("MemberAccesses(%q): expected 1 access, got %d", tt.expression, len(accesses))
+		}
+
+		segment, object, ok := UnknownMember(accesses[0])
+		if ok != (tt.member != "") {
+			t.Errorf("UnknownMember(%q): expected unknown=%v, got %v", tt.expression, tt.member != "", ok)
+			continue
+		}
+		if segment.Name != tt.member || object != tt.object {
+			t.Errorf("UnknownMember(%q): expected %q on %q, got %q on %q", tt.expression, tt.member, tt.object, segment.Name, object)
+		}
+	}
+}
@@ -271,3 +271,82 @@ func (info *ContextInfo) GetKeyPath() string {
 	}
 	return strings.Join(info.ParentKeys, ".")
 }
+
+// SiblingKeys returns the keys already present in the mapping that contains the
+// cursor, leaving out the cursor line itself
+func (p *PositionContext) SiblingKeys() []string {
+	lines := strings.Split(p.FullContent, "\n")
+	cursorLine := int(p.Position.Line)
+	if cursorLine >= len(lines) {
+		return nil
+	}
+
+	// A list item on the cursor line starts a new mapping
+	target := getIndentLevel(p.CurrentLine)
+	cursorOnItem := strings.HasPrefix(strings.TrimSpace(p.CurrentLine), "-")
+	if cursorOnItem {
+		target += 2
+	}
+
+	var keys []string
+	if !cursorOnItem {
+		for i := cursorLine - 1; i >= 0; i-- {
+			trimmed := strings.TrimSpace(strings.TrimSuffix(lines[i], "\r"))
+			if trimmed == "" || strings.HasPrefix(trimmed, "#") {
+				continue
+			}
+
+			indent := getIndentLevel(lines[i])
+			if strings.HasPrefix(trimmed, "- ") {
+				if indent+2 == target {
+					// The item that starts this mapping
+					if key, ok := mappingKey(trimmed[2:]); ok {
+						keys = append(keys, key)
+					}
+					break
+				}
+				if indent < target {
+					break
+				}
+				continue
+			}
+
+			if indent < target {
+				break
+			}
+			if indent == target {
+				if key, ok := mappingKey(trimmed); ok {
+					keys = append(keys, key)
+				}
+			}
+		}
+	}
+
+	for i := cursorLine + 1; i < len(lines); i++ {
+		trimmed := strings.TrimSpace(strings.TrimSuffix(lines[i], "\r"))
+		if trimmed == "" || strings.HasPrefix(trimmed, "#") {
+			continue
+		}
+
+		indent := getIndentLevel(lines[i])
+		if indent < target {
+			break
+		}
+		if indent == target && !strings.HasPrefix(trimmed, "-") {
+			if key, ok := mappingKey(trimmed); ok {
+				keys = append(keys, key)
+			}
+		}
+	}
+
+	return keys
+}
+
+// mappingKey returns the key of a "key: value" line, without quotes
+func mappingKey(content string) (string, bool) {
+	colon := strings.Index(content, ":")
+	if colon <= 0 || (colon+1 < len(content) && content[colon+1] != ' ') {
+		return "", false
+	}
+	return strings.Trim(strings.TrimSpace(content[:colon]), `"'`), true
+}
@@ -263,3 +263,47 @@ func TestParseKeyFromLine(t *testing.T) {
 		}
 	}
 }
+
+func TestPositionContext_SiblingKeys(t *testing.T) {
+	content := `env:
+  FOO: bar
+steps:
+  - label: "Build"
+    key: build
+    plugins:
+      - docker#v5.13.0:
+          image: node
+
+    command: make
+  - label: "Test"`
+
+	tests := []struct {
+		name     string
+		line     uint32
+		current  string
+		expected []string
+	}{
+		{name: "step mapping", line: 8, current: "    ", expected: []string{"plugins", "key", "label", "command"}},
+		{name: "plugin config", line: 7, current: "          ", expected: []string{}},
+		{name: "new list item", line: 10, current: "  - ", expected: []string{}},
+		{name: "top level", line: 2, current: "", expected: []string{"env"}},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(content, "\n")
+			lines[tt.line] = tt.current
+			posCtx := &PositionContext{
+				Position:    protocol.Position{Line: tt.line, Character: uint32(len(tt.current))},
+				CurrentLine: tt.current,
+				CharIndex:   len(tt.current),
+				FullContent: strings.Join(lines, "\n"),
+			}
+
+			keys := posCtx.SiblingKeys()
+			if strings.Join(keys, ",") != strings.Join(tt.expected, ",") {
+				t.Errorf("Expected sibling keys %v, got %v", tt.expected, keys)
+			}
+		})
+	}
+}
@@ -0,0 +1,75 @@
+// Package emoji lists the emoji shortcodes commonly used in Buildkite step labels.
+// Buildkite renders shortcodes like :rocket: in the UI, including logos such as
+// :docker: that have no Unicode equivalent.
+package emoji
+
+import "strings"
+
+// Emoji is a shortcode Buildkite renders in labels
+type Emoji struct {
+	// Shortcode is the name between the colons, e.g. "rocket"
+	Shortcode string
+	// Character is the Unicode emoji, empty for Buildkite's logo emoji
+	Character string
+}
+
+// Common are frequently used shortcodes, in alphabetical order
+var Common = []Emoji{
+	{Shortcode: "android"},
+	{Shortcode: "apple", Character: "🍎"},
+	{Shortcode: "aws"},
+	{Shortcode: "bash"},
+	{Shortcode: "bazel"},
+	{Shortcode: "bell", Character: "🔔"},
+	{Shortcode: "books", Character: "📚"},
+	{Shortcode: "broom", Character: "🧹"},
+	{Shortcode: "bug", Character: "🐛"},
+	{Shortcode: "buildkite"},
+	{Shortcode: "chart_with_upwards_trend", Character: "📈"},
+	{Shortcode: "checkered_flag", Character: "🏁"},
+	{Shortcode: "docker"},
+	{Shortcode: "eslint"},
+	{Shortcode: "gear", Character: "⚙️"},
+	{Shortcode: "git"},
+	{Shortcode: "github"},
+	{Shortcode: "golang"},
+	{Shortcode: "hammer", Character: "🔨"},
+	{Shortcode: "hammer_and_wrench", Character: "🛠️"},
+	{Shortcode: "helm"},
+	{Shortcode: "java"},
+	{Shortcode: "jest"},
+	{Shortcode: "kubernetes"},
+	{Shortcode: "linux"},
+	{Shortcode: "lock", Character: "🔒"},
+	{Shortcode: "mag", Character: "🔍"},
+	{Shortcode: "memo", Character: "📝"},
+	{Shortcode: "nodejs"},
+	{Shortcode: "package", Character: "📦"},
+	{Shortcode: "pipeline"},
+	{Shortcode: "python"},
+	{Shortcode: "rocket", Character: "🚀"},
+	{Shortcode: "rspec"},
+	{Shortcode: "ruby"},
+	{Shortcode: "rust"},
+	{Shortcode: "shipit"},
+	{Shortcode: "sparkles", Character: "✨"},
+	{Shortcode: "terraform"},
+	{Shortcode: "test_tube", Character: "🧪"},
+	{Shortcode: "typescript"},
+	{Shortcode: "warning", Character: "⚠️"},
+	{Shortcode: "white_check_mark", Character: "✅"},
+	{Shortcode: "windows"},
+	{Shortcode: "wrench", Character: "🔧"},
+	{Shortcode: "x", Character: "❌"},
+}
+
+// WithPrefix returns the common emoji whose shortcode starts with prefix
+func WithPrefix(prefix string) []Emoji {
+	var matches []Emoji
+	for _, emoji := range Common {
+		if strings.HasPrefix(emoji.Shortcode, prefix) {
+			matches = append(matches, emoji)
+		}
+	}
+	return matches
+}
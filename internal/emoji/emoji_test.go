@@ -0,0 +1,26 @@
+package emoji
+
+import (
+	"sort"
+	"testing"
+)
+
+func TestCommonIsSorted(t *testing.T) {
+	sorted := sort.SliceIsSorted(Common, func(i, j int) bool {
+		return Common[i].Shortcode < Common[j].Shortcode
+	})
+	if !sorted {
+		t.Error("Expected common emoji to be sorted by shortcode")
+	}
+}
+
+func TestWithPrefix(t *testing.T) {
+	matches := WithPrefix("ro")
+	if len(matches) != 1 || matches[0].Shortcode != "rocket" || matches[0].Character != "🚀" {
+		t.Errorf("Expected only rocket for 'ro', got %+v", matches)
+	}
+
+	if matches := WithPrefix(""); len(matches) != len(Common) {
+		t.Errorf("Expected every emoji for an empty prefix, got %d", len(matches))
+	}
+}
@@ -0,0 +1,102 @@
+// Package envvars describes the BUILDKITE_ environment variables the agent sets
+// for every job and the ones pipelines may set to change how the agent behaves.
+package envvars
+
+import "strings"
+
+// Variable is a Buildkite environment variable
+type Variable struct {
+	Name        string
+	Description string
+}
+
+// BuiltIns are set by the agent for every job, in alphabetical order
+var BuiltIns = []Variable{
+	{Name: "BUILDKITE", Description: "Always `true`"},
+	{Name: "BUILDKITE_AGENT_ID", Description: "The UUID of the agent running the job"},
+	{Name: "BUILDKITE_AGENT_NAME", Description: "The name of the agent running the job"},
+	{Name: "BUILDKITE_BRANCH", Description: "The branch being built"},
+	{Name: "BUILDKITE_BUILD_AUTHOR", Description: "The name of the user who authored the commit being built"},
+	{Name: "BUILDKITE_BUILD_AUTHOR_EMAIL", Description: "The email address of the user who authored the commit being built"},
+	{Name: "BUILDKITE_BUILD_CHECKOUT_PATH", Description: "The path where the agent has checked out the repository"},
+	{Name: "BUILDKITE_BUILD_CREATOR", Description: "The name of the user who created the build"},
+	{Name: "BUILDKITE_BUILD_CREATOR_EMAIL", Description: "The email address of the user who created the build"},
+	{Name: "BUILDKITE_BUILD_ID", Description: "The UUID of the build"},
+	{Name: "BUILDKITE_BUILD_NUMBER", Description: "The build number, unique within the pipeline"},
+	{Name: "BUILDKITE_BUILD_URL", Description: "The URL of the build on Buildkite"},
+	{Name: "BUILDKITE_COMMAND", Description: "The command the job runs"},
+	{Name: "BUILDKITE_COMMIT", Description: "The git commit being built"},
+	{Name: "BUILDKITE_JOB_ID", Description: "The UUID of the job"},
+	{Name: "BUILDKITE_LABEL", Description: "The label of the step that created the job"},
+	{Name: "BUILDKITE_MESSAGE", Description: "The build message, usually the commit message"},
+	{Name: "BUILDKITE_ORGANIZATION_SLUG", Description: "The slug of the organization"},
+	{Name: "BUILDKITE_PARALLEL_JOB", Description: "The index of the job in a parallel step, starting at 0"},
+	{Name: "BUILDKITE_PARALLEL_JOB_COUNT", Description: "The number of jobs in a parallel step"},
+	{Name: "BUILDKITE_PIPELINE_DEFAULT_BRANCH", Description: "The default branch of the pipeline"},
+	{Name: "BUILDKITE_PIPELINE_ID", Description: "The UUID of the pipeline"},
+	{Name: "BUILDKITE_PIPELINE_SLUG", Description: "The slug of the pipeline"},
+	{Name: "BUILDKITE_PULL_REQUEST", Description: "The pull request number, or `false` when the build isn't for a pull request"},
+	{Name: "BUILDKITE_PULL_REQUEST_BASE_BRANCH", Description: "The branch the pull request targets"},
+	{Name: "BUILDKITE_PULL_REQUEST_REPO", Description: "The repository URL of the pull request"},
+	{Name: "BUILDKITE_REBUILT_FROM_BUILD_ID", Description: "The UUID of the build this one was rebuilt from"},
+	{Name: "BUILDKITE_REPO", Description: "The repository URL of the pipeline"},
+	{Name: "BUILDKITE_RETRY_COUNT", Description: "How many times the job has been retried"},
+	{Name: "BUILDKITE_SOURCE", Description: "What created the build (e.g. `webhook`, `ui`, `api`, `schedule`)"},
+	{Name: "BUILDKITE_STEP_ID", Description: "The UUID of the step that created the job"},
+	{Name: "BUILDKITE_STEP_KEY", Description: "The key of the step that created the job"},
+	{Name: "BUILDKITE_TAG", Description: "The git tag being built, if any"},
+	{Name: "BUILDKITE_TIMEOUT", Description: "The job timeout in minutes, or `false` when there is none"},
+	{Name: "BUILDKITE_TRIGGERED_FROM_BUILD_ID", Description: "The UUID of the build that triggered this one"},
+}
+
+// UserSettable are BUILDKITE_ variables a pipeline may set in env to change how
+// the agent checks out code and uploads artifacts
+var UserSettable = []Variable{
+	{Name: "BUILDKITE_ARTIFACT_UPLOAD_DESTINATION", Description: "Upload artifacts to your own bucket (e.g. `s3://bucket/$BUILDKITE_PIPELINE_ID/$BUILDKITE_BUILD_ID/$BUILDKITE_JOB_ID`)"},
+	{Name: "BUILDKITE_CLEAN_CHECKOUT", Description: "Remove the existing checkout before each job (`true` or `false`)"},
+	{Name: "BUILDKITE_GIT_CLEAN_FLAGS", Description: "Flags passed to `git clean` (e.g. `-ffxdq`)"},
+	{Name: "BUILDKITE_GIT_CLONE_FLAGS", Description: "Flags passed to `git clone` (e.g. `-v --depth=1`)"},
+	{Name: "BUILDKITE_GIT_CLONE_MIRROR_FLAGS", Description: "Flags passed to `git clone` when creating a mirror"},
+	{Name: "BUILDKITE_GIT_FETCH_FLAGS", Description: "Flags passed to `git fetch`"},
+	{Name: "BUILDKITE_GIT_SUBMODULES", Description: "Whether to check out git submodules (`true` or `false`)"},
+	{Name: "BUILDKITE_PLUGINS_ALWAYS_CLONE_FRESH", Description: "Clone plugins again for every job instead of reusing them (`true` or `false`)"},
+	{Name: "BUILDKITE_REFSPEC", Description: "A custom refspec to fetch instead of the build's branch or commit"},
+}
+
+// userSettablePrefixes are families of storage settings pipelines may set
+var userSettablePrefixes = []string{
+	"BUILDKITE_S3_",
+	"BUILDKITE_GS_",
+	"BUILDKITE_ARTIFACTORY_",
+}
+
+// IsUserSettable reports whether a pipeline may set name in env
+func IsUserSettable(name string) bool {
+	for _, variable := range UserSettable {
+		if variable.Name == name {
+			return true
+		}
+	}
+	for _, prefix := range userSettablePrefixes {
+		if strings.HasPrefix(name, prefix) {
+			return true
+		}
+	}
+	return false
+}
+
+// IsReserved reports whether name is managed by the agent and shouldn't be set
+// in a pipeline's env
+func IsReserved(name string) bool {
+	return (name == "BUILDKITE" || strings.HasPrefix(name, "BUILDKITE_")) && !IsUserSettable(name)
+}
+
+// LookupBuiltIn returns the built-in variable called name
+func LookupBuiltIn(name string) (Variable, bool) {
+	for _, variable := range BuiltIns {
+		if variable.Name == name {
+			return variable, true
+		}
+	}
+	return Variable{}, false
+}
@@ -0,0 +1,45 @@
+package envvars
+
+import (
+	"sort"
+	"testing"
+)
+
+func TestIsReserved(t *testing.T) {
+	tests := map[string]bool{
+		"BUILDKITE":                   true,
+		"BUILDKITE_COMMIT":            true,
+		"BUILDKITE_SOMETHING_NEW":     true,
+		"BUILDKITE_CLEAN_CHECKOUT":    false,
+		"BUILDKITE_S3_DEFAULT_REGION": false,
+		"BUILDKITE_GS_ACL":            false,
+		"NODE_ENV":                    false,
+		"MY_BUILDKITE_TOKEN":          false,
+	}
+
+	for name, expected := range tests {
+		if got := IsReserved(name); got != expected {
+			t.Errorf("IsReserved(%q) = %v, want %v", name, got, expected)
+		}
+	}
+}
+
+func TestCatalogsAreSorted(t *testing.T) {
+	for _, catalog := range [][]Variable{BuiltIns, UserSettable} {
+		sorted := sort.SliceIsSorted(catalog, func(i, j int) bool {
+			return catalog[i].Name < catalog[j].Name
+		})
+		if !sorted {
+			t.Errorf("Expected catalog starting with %s to be sorted by name", catalog[0].Name)
+		}
+	}
+}
+
+func TestLookupBuiltIn(t *testing.T) {
+	if variable, ok := LookupBuiltIn("BUILDKITE_BRANCH"); !ok || variable.Description == "" {
+		t.Errorf("Expected BUILDKITE_BRANCH to be a documented built-in, got %+v", variable)
+	}
+	if _, ok := LookupBuiltIn("BUILDKITE_CLEAN_CHECKOUT"); ok {
+		t.Error("Did not expect a user-settable variable to be a built-in")
+	}
+}
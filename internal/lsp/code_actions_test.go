@@ -2,6 +2,7 @@ package lsp
 
 import (
 	"context"
+	"slices"
 	"strings"
 	"testing"
 
@@ -363,3 +364,102 @@ func TestServer_CodeActionGeneration(t *testing.T) {
 		}
 	})
 }
+
+// applyTextEdits applies non-overlapping edits to content, for checking the
+// result of a refactoring
+func applyTextEdits(content string, edits []protocol.TextEdit) string {
+	lines := strings.SplitAfter(content, "\n")
+	offset := func(pos protocol.Position) int {
+		total := 0
+		for i := 0; i < int(pos.Line) && i < len(lines); i++ {
+			total += len(lines[i])
+		}
+		return total + int(pos.Character)
+	}
+
+	sorted := slices.Clone(edits)
+	slices.SortFunc(sorted, func(a, b protocol.TextEdit) int {
+		return offset(b.Range.Start) - offset(a.Range.Start)
+	})
+	for _, edit := range sorted {
+		content = content[:offset(edit.Range.Start)] + edit.NewText + content[offset(edit.Range.End):]
+	}
+	return content
+}
+
+func TestServer_ExtractToAnchorAction(t *testing.T) {
+	server := newTestServer()
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+
+	content := `env:
+  CI: "true"
+steps:
+  - label: "Test (linux)"
+    command: "make test"
+    agents:
+      queue: default
+
+  - label: "Test (again)"
+    command: "make test"
+    agents:
+      queue: default
+
+  - label: "Lint"
+    command: "make lint"`
+	lines := splitLines(content)
+
+	action := server.createExtractToAnchorAction(uri, lines, 3)
+	if action == nil {
+		t.Fatal("Expected an extract to anchor action")
+	}
+	if action.Title != "Extract to YAML anchor" || action.Kind != protocol.RefactorExtract {
+		t.Errorf("Unexpected action %q (%s)", action.Title, action.Kind)
+	}
+
+	expected := `env:
+  CI: "true"
+x-shared: &shared
+  command: "make test"
+  agents:
+    queue: default
+
+steps:
+  - <<: *shared
+    label: "Test (linux)"
+
+  - <<: *shared
+    label: "Test (again)"
+
+  - label: "Lint"
+    command: "make lint"`
+	if got := applyTextEdits(content, action.Edit.Changes[uri]); got != expected {
+		t.Errorf("Unexpected result:\n%s\n\nExpected:\n%s", got, expected)
+	}
+
+	if action := server.createExtractToAnchorAction(uri, lines, 13); action != nil {
+		t.Errorf("Expected no action for a step without a duplicate, got %q", action.Title)
+	}
+}
+
+func TestServer_ExtractToAnchorActionAvoidsExistingAnchors(t *testing.T) {
+	server := newTestServer()
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+
+	content := `steps:
+  - label: "A"
+    env: &shared
+      FOO: bar
+    command: "make"
+  - label: "B"
+    env: &shared
+      FOO: bar
+    command: "make"`
+
+	action := server.createExtractToAnchorAction(uri, splitLines(content), 1)
+	if action == nil {
+		t.Fatal("Expected an extract to anchor action")
+	}
+	if hoisted := action.Edit.Changes[uri][0].NewText; !strings.HasPrefix(hoisted, "x-shared_2: &shared_2\n") {
+		t.Errorf("Expected a new anchor name, got %q", hoisted)
+	}
+}
@@ -3,19 +3,32 @@ package lsp
 import (
 	"fmt"
 	"log"
+	"regexp"
+	"slices"
+	"strconv"
 	"strings"
 
 	"go.lsp.dev/protocol"
 
+	"github.com/mcncl/buildkite-ls/internal/conditionals"
 	"github.com/mcncl/buildkite-ls/internal/context"
+	"github.com/mcncl/buildkite-ls/internal/emoji"
+	"github.com/mcncl/buildkite-ls/internal/envvars"
 	"github.com/mcncl/buildkite-ls/internal/plugins"
+	"github.com/mcncl/buildkite-ls/internal/schema"
 )
 
 // CompletionProvider handles context-aware completion
 type CompletionProvider struct {
 	pluginRegistry *plugins.Registry
+	schemaLoader   *schema.Loader
 	analyzer       *context.Analyzer
 	logger         *log.Logger
+
+	// insertRequiredFields adds the fields the schema requires to step type snippets
+	insertRequiredFields bool
+	// workspaceMetaDataKeys returns the meta-data keys used by other pipelines in the workspace
+	workspaceMetaDataKeys func() []string
 }
 
 // NewCompletionProvider creates a new completion provider
@@ -27,6 +40,24 @@ func NewCompletionProvider(pluginRegistry *plugins.Registry, logger *log.Logger)
 	}
 }
 
+// SetSchemaLoader gives the provider access to the pipeline schema, used to rank
+// required step properties first once the schema has loaded
+func (cp *CompletionProvider) SetSchemaLoader(loader *schema.Loader) {
+	cp.schemaLoader = loader
+}
+
+// SetWorkspaceMetaDataKeys sets where the provider finds meta-data keys used by
+// the other pipelines in the workspace
+func (cp *CompletionProvider) SetWorkspaceMetaDataKeys(keys func() []string) {
+	cp.workspaceMetaDataKeys = keys
+}
+
+// SetInsertRequiredFields controls whether step type snippets also scaffold the
+// fields the pipeline schema requires for that type
+func (cp *CompletionProvider) SetInsertRequiredFields(enabled bool) {
+	cp.insertRequiredFields = enabled
+}
+
 // GetContextAnalyzer returns the context analyzer for use by other components
 func (cp *CompletionProvider) GetContextAnalyzer() *context.Analyzer {
 	return cp.analyzer
@@ -34,9 +65,35 @@ func (cp *CompletionProvider) GetContextAnalyzer() *context.Analyzer {
 
 // GetCompletions returns context-aware completions for the given position
 func (cp *CompletionProvider) GetCompletions(posCtx *context.PositionContext) []protocol.CompletionItem {
+	items, _ := cp.completions(posCtx)
+	return items
+}
+
+// GetCompletionList returns the completions for the given position as a list.
+// Plugin names and env vars are narrowed to the word being typed and capped, so
+// those lists are marked incomplete for the client to ask again as it changes.
+func (cp *CompletionProvider) GetCompletionList(posCtx *context.PositionContext) *protocol.CompletionList {
+	items, incomplete := cp.completions(posCtx)
+	return &protocol.CompletionList{IsIncomplete: incomplete, Items: items}
+}
+
+// completions returns the completions for the given position and whether the
+// result was narrowed to what has been typed so far. Under a key that holds a
+// list, starting a new item is offered ahead of the context's completions.
+func (cp *CompletionProvider) completions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	items, incomplete := cp.contextCompletions(posCtx)
+	if item, ok := cp.getListItemCompletion(posCtx); ok {
+		items = append([]protocol.CompletionItem{item}, items...)
+	}
+	return items, incomplete
+}
+
+// contextCompletions returns the completions for the kind of position the
+// cursor is at, and whether they were narrowed to what has been typed so far
+func (cp *CompletionProvider) contextCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
 	if posCtx == nil {
 		cp.logger.Printf("GetCompletions called with nil position context")
-		return []protocol.CompletionItem{}
+		return []protocol.CompletionItem{}, false
 	}
 
 	cp.logger.Printf("GetCompletions - URI: %s, Line: %d, Char: %d",
@@ -49,23 +106,95 @@ func (cp *CompletionProvider) GetCompletions(posCtx *context.PositionContext) []
 	cp.logger.Printf("Context detected - Type: %d, PluginName: '%s', ParentKeys: %v, IndentLevel: %d",
 		contextInfo.Type, contextInfo.PluginName, contextInfo.ParentKeys, contextInfo.IndentLevel)
 
+	if items, ok := cp.getConditionalCompletions(posCtx); ok {
+		cp.logger.Printf("Returning conditional expression completions")
+		return items, false
+	}
+
+	// Matrix adjustments are nested deeply enough that the analyzer reports
+	// them as a plain step context, so detect them from the enclosing keys
+	if items, ok := cp.getMatrixAdjustmentCompletions(posCtx); ok {
+		cp.logger.Printf("Returning matrix adjustment completions")
+		return items, false
+	}
+
+	if items, ok := cp.getAgentsCompletions(posCtx); ok {
+		cp.logger.Printf("Returning agents completions")
+		return items, false
+	}
+
+	if items, ok := cp.getRetryCompletions(posCtx); ok {
+		cp.logger.Printf("Returning retry completions")
+		return items, false
+	}
+
+	if items, ok := cp.getMetaDataCompletions(posCtx); ok {
+		cp.logger.Printf("Returning meta-data key completions")
+		return items, false
+	}
+
+	if items, ok := cp.getRootEnvCompletions(posCtx); ok {
+		cp.logger.Printf("Returning pipeline env completions")
+		return limitCompletions(items, typedWord(posCtx)), true
+	}
+
+	if items, ok := cp.getLabelEmojiCompletions(posCtx); ok {
+		cp.logger.Printf("Returning label emoji completions")
+		return items, false
+	}
+
+	if items, ok := cp.getMergeKeyCompletions(posCtx); ok {
+		cp.logger.Printf("Returning merge key completions")
+		return items, false
+	}
+
+	if items, ok := cp.getStepKeyCompletions(posCtx); ok {
+		cp.logger.Printf("Returning step key completions")
+		return items, false
+	}
+
+	if items, ok := cp.getNumericValueCompletions(posCtx); ok {
+		cp.logger.Printf("Returning numeric value completions")
+		return items, false
+	}
+
+	// Keys already written in the enclosing mapping aren't offered again
+	existing := posCtx.SiblingKeys()
+
 	// Return completions based on context
 	switch contextInfo.Type {
 	case context.ContextTopLevel:
+		if isEffectivelyEmpty(posCtx) {
+			cp.logger.Printf("Returning pipeline skeleton and top-level completions")
+			return append([]protocol.CompletionItem{pipelineSkeletonCompletion()}, cp.getTopLevelCompletions()...), false
+		}
 		cp.logger.Printf("Returning top-level completions")
-		return cp.getTopLevelCompletions()
+		return cp.withPropertyTypes(withoutKeys(cp.getTopLevelCompletions(), existing)), false
 	case context.ContextStep:
+		if isNewStepItem(posCtx) {
+			cp.logger.Printf("Returning step form and step completions")
+			return append(cp.withRequiredFields(stepFormCompletions(), "  "), cp.withRequiredFields(cp.getStepCompletions(), "")...), false
+		}
+		if isInGroupStep(posCtx) {
+			cp.logger.Printf("Returning group step completions")
+			return cp.withPropertyTypes(withoutKeys(cp.getGroupStepCompletions(), existing)), false
+		}
 		cp.logger.Printf("Returning step completions")
-		return cp.getStepCompletions()
+		items := withoutKeys(cp.withRequiredFields(cp.getStepCompletions(), ""), existing)
+		return rankRequired(cp.withPropertyTypes(items), cp.requiredStepKeys(posCtx)), false
 	case context.ContextPlugins:
 		cp.logger.Printf("Returning plugin completions")
-		return cp.getPluginCompletions(posCtx, contextInfo)
+		return limitCompletions(cp.getPluginCompletions(posCtx, contextInfo), typedWord(posCtx)), true
 	case context.ContextPluginConfig:
 		cp.logger.Printf("Returning plugin config completions for plugin: %s", contextInfo.PluginName)
-		return cp.getPluginConfigCompletions(contextInfo)
+		items := withoutKeys(cp.getPluginConfigCompletions(contextInfo), existing)
+		if len(existing) == 0 {
+			items = append(cp.getPluginSnippetCompletions(contextInfo.PluginName), items...)
+		}
+		return items, false
 	default:
 		cp.logger.Printf("Returning default completions")
-		return cp.getDefaultCompletions()
+		return cp.getDefaultCompletions(), false
 	}
 }
 
@@ -293,6 +422,14 @@ func (cp *CompletionProvider) getStepCompletions() []protocol.CompletionItem {
 			Detail:        "Step priority",
 			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Priority of this step (-5 to 5, higher values run first)"},
 		},
+		{
+			Label:            "cache",
+			Kind:             protocol.CompletionItemKindProperty,
+			Detail:           "Cache paths between builds",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Paths to cache between builds, optionally with a cache `key` and `restore_keys`"},
+			InsertText:       "cache:\n  paths:\n    - \"${1:node_modules}\"\n  key: \"${2:v1-cache}\"",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+		},
 		{
 			Label:            "matrix",
 			Kind:             protocol.CompletionItemKindProperty,
@@ -446,7 +583,26 @@ func (cp *CompletionProvider) getPluginCompletions(posCtx *context.PositionConte
 		})
 	}
 
-	for _, plugin := range plugins.GetPopularPlugins() {
+	// A new list item gets the whole reference and configuration shape
+	if posCtx != nil {
+		before := posCtx.CurrentLine
+		if posCtx.CharIndex < len(before) {
+			before = before[:posCtx.CharIndex]
+		}
+		if strings.TrimSpace(before) == "-" {
+			items = append(items, protocol.CompletionItem{
+				Label:            "org/name#version",
+				Kind:             protocol.CompletionItemKindSnippet,
+				Detail:           "Plugin reference with configuration",
+				Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Insert a plugin reference pinned to a version, followed by its configuration"},
+				InsertText:       "${1:org}/${2:name}#${3:v1.0.0}:\n    ${4:option}: \"${5:value}\"",
+				InsertTextFormat: protocol.InsertTextFormatSnippet,
+				SortText:         "00-plugin-skeleton",
+			})
+		}
+	}
+
+	for _, plugin := range cp.pluginRegistry.PopularPlugins() {
 		fullName := plugin.Name + "#" + plugin.Version
 
 		// Create smart snippet templates based on plugin type
@@ -522,14 +678,37 @@ func (cp *CompletionProvider) getPluginConfigCompletions(contextInfo *context.Co
 	}
 
 	// Fetch plugin schema for the specific plugin
-	schema, err := cp.pluginRegistry.GetPluginSchema(contextInfo.PluginName)
+	pluginSchema, err := cp.pluginRegistry.GetPluginSchema(contextInfo.PluginName)
 	if err != nil {
 		// If we can't fetch the schema, return generic completions
 		return cp.getGenericPluginConfigCompletions()
 	}
 
-	// Generate completions from the plugin schema
-	return cp.generateCompletionsFromSchema(schema, contextInfo.PluginName, contextInfo.IndentLevel)
+	// Generate completions from the plugin schema, with required options first
+	items := cp.generateCompletionsFromSchema(pluginSchema, contextInfo.PluginName, contextInfo.IndentLevel)
+	return rankRequired(items, schema.RequiredProperties(pluginSchema.Configuration, pluginSchema.Configuration))
+}
+
+// getPluginSnippetCompletions returns the ready-made configuration for a
+// recognised plugin, offered while its configuration is still empty
+func (cp *CompletionProvider) getPluginSnippetCompletions(pluginName string) []protocol.CompletionItem {
+	snippet, ok := plugins.ConfigSnippet(pluginName)
+	if !ok {
+		return nil
+	}
+
+	name := plugins.ParsePluginReference(pluginName).Name
+	return []protocol.CompletionItem{
+		{
+			Label:            name + " configuration",
+			Kind:             protocol.CompletionItemKindSnippet,
+			Detail:           "Common " + name + " plugin configuration",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "```yaml\n" + snippet + "\n```"},
+			InsertText:       snippet,
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+			SortText:         "00-plugin-snippet",
+		},
+	}
 }
 
 // getGenericPluginConfigCompletions returns fallback completions when plugin schema is unavailable
@@ -659,3 +838,1346 @@ func (cp *CompletionProvider) getDefaultCompletions() []protocol.CompletionItem
 	items = append(items, cp.getStepCompletions()...)
 	return items
 }
+
+// propertyCommitCharacters accept a key completion and type the separator in one keystroke
+var propertyCommitCharacters = []string{":"}
+
+// addPropertyCommitCharacters sets commit characters on plain property items.
+// Items with their own insert text already include the separator, so they are left alone.
+func addPropertyCommitCharacters(items []protocol.CompletionItem) {
+	for i := range items {
+		if items[i].Kind == protocol.CompletionItemKindProperty && items[i].InsertText == "" {
+			items[i].CommitCharacters = propertyCommitCharacters
+		}
+	}
+}
+
+// withoutKeys drops property completions for keys that are already present
+func withoutKeys(items []protocol.CompletionItem, keys []string) []protocol.CompletionItem {
+	if len(keys) == 0 {
+		return items
+	}
+
+	filtered := make([]protocol.CompletionItem, 0, len(items))
+	for _, item := range items {
+		if item.Kind == protocol.CompletionItemKindProperty && slices.Contains(keys, item.Label) {
+			continue
+		}
+		filtered = append(filtered, item)
+	}
+	return filtered
+}
+
+// rankRequired sorts required property completions ahead of optional ones.
+// Items are left in their original order when nothing is required.
+func rankRequired(items []protocol.CompletionItem, required []string) []protocol.CompletionItem {
+	if len(required) == 0 {
+		return items
+	}
+
+	for i := range items {
+		if items[i].Kind != protocol.CompletionItemKindProperty {
+			continue
+		}
+		rank := "1"
+		if slices.Contains(required, items[i].Label) {
+			rank = "0"
+			items[i].Detail = strings.TrimSpace("(required) " + items[i].Detail)
+		}
+		items[i].SortText = rank + items[i].Label
+	}
+	return items
+}
+
+// withPropertyTypes adds the type the pipeline schema gives each property to its
+// completion detail, e.g. "Step label (string)". Items are left as they are until
+// the schema has loaded.
+func (cp *CompletionProvider) withPropertyTypes(items []protocol.CompletionItem) []protocol.CompletionItem {
+	if cp.schemaLoader == nil {
+		return items
+	}
+
+	for i := range items {
+		if items[i].Kind != protocol.CompletionItemKindProperty {
+			continue
+		}
+		if summary, ok := cp.schemaLoader.PropertyType(items[i].Label); ok {
+			items[i].Detail = strings.TrimSpace(items[i].Detail + " (" + summary + ")")
+		}
+	}
+	return items
+}
+
+// requiredStepKeys returns the keys the pipeline schema requires for the type of
+// step at the cursor, or nil when the type isn't known yet or the schema hasn't loaded
+func (cp *CompletionProvider) requiredStepKeys(posCtx *context.PositionContext) []string {
+	if cp.schemaLoader == nil {
+		return nil
+	}
+
+	stepData := make(map[string]interface{})
+	for _, property := range currentStepProperties(posCtx) {
+		stepData[property.Key] = property.Value
+	}
+
+	kind := stepKind(stepData)
+	if kind == "" {
+		return nil
+	}
+	return cp.schemaLoader.DefinitionRequired(kind + "Step")
+}
+
+// stepTypeDefinitions maps the completions that start a step of a given type to
+// that type's definition in the pipeline schema
+var stepTypeDefinitions = map[string]string{
+	"Command step (object)": "commandStep",
+	"block":                 "blockStep",
+	"group":                 "groupStep",
+	"input":                 "inputStep",
+	"trigger":               "triggerStep",
+	"wait":                  "waitStep",
+}
+
+// snippetTabStopPattern matches the tab stops in a snippet, e.g. $1 or ${2:value}
+var snippetTabStopPattern = regexp.MustCompile(`\$\{?([0-9]+)`)
+
+// withRequiredFields extends step type snippets with a tab stop for each field
+// the schema requires that the snippet doesn't already set. Added lines are
+// prefixed with indent, for snippets inserted after a list marker. Items are
+// unchanged unless insertRequiredFields is set and the schema has loaded.
+func (cp *CompletionProvider) withRequiredFields(items []protocol.CompletionItem, indent string) []protocol.CompletionItem {
+	if !cp.insertRequiredFields || cp.schemaLoader == nil {
+		return items
+	}
+
+	for i, item := range items {
+		definition, ok := stepTypeDefinitions[item.Label]
+		if !ok {
+			continue
+		}
+		required := cp.schemaLoader.DefinitionRequired(definition)
+		if len(required) == 0 {
+			continue
+		}
+
+		snippet := item.InsertText
+		if snippet == "" || item.InsertTextFormat != protocol.InsertTextFormatSnippet {
+			snippet = item.Label + ": \"$1\""
+		}
+
+		present := make(map[string]bool)
+		for _, line := range strings.Split(snippet, "\n") {
+			if key, _, found := strings.Cut(strings.TrimSpace(line), ":"); found {
+				present[key] = true
+			}
+		}
+
+		next := 1
+		for _, match := range snippetTabStopPattern.FindAllStringSubmatch(snippet, -1) {
+			if stop, err := strconv.Atoi(match[1]); err == nil && stop >= next {
+				next = stop + 1
+			}
+		}
+
+		for _, field := range required {
+			if present[field] {
+				continue
+			}
+			snippet += fmt.Sprintf("\n%s%s: $%d", indent, field, next)
+			next++
+		}
+
+		items[i].InsertText = snippet
+		items[i].InsertTextFormat = protocol.InsertTextFormatSnippet
+	}
+
+	return items
+}
+
+// maxListedCompletions caps the plugin-name and env var completions returned at
+// once, since the plugin list grows with an external catalog
+const maxListedCompletions = 50
+
+// typedWordPattern captures the plugin or variable name being typed at the cursor
+var typedWordPattern = regexp.MustCompile(`[A-Za-z0-9_][A-Za-z0-9_.#/-]*$`)
+
+// typedWord returns the name typed so far at the cursor
+func typedWord(posCtx *context.PositionContext) string {
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+	return typedWordPattern.FindString(before)
+}
+
+// limitCompletions keeps the items whose filter text starts with prefix, up to
+// maxListedCompletions. Snippets are always kept.
+func limitCompletions(items []protocol.CompletionItem, prefix string) []protocol.CompletionItem {
+	prefix = strings.ToLower(prefix)
+	limited := make([]protocol.CompletionItem, 0, min(len(items), maxListedCompletions))
+	listed := 0
+
+	for _, item := range items {
+		if item.Kind != protocol.CompletionItemKindSnippet {
+			text := item.FilterText
+			if text == "" {
+				text = item.Label
+			}
+			if !strings.HasPrefix(strings.ToLower(text), prefix) || listed == maxListedCompletions {
+				continue
+			}
+			listed++
+		}
+		limited = append(limited, item)
+	}
+	return limited
+}
+
+// enclosingKey is a mapping key that contains the cursor position
+type enclosingKey struct {
+	Key    string
+	Line   int
+	Indent int
+}
+
+// findEnclosingKeys walks up from the cursor line and returns the keys that
+// contain it, outermost first. List items are treated as part of the key that
+// owns the list, so siblings within the same item are not reported as parents.
+func findEnclosingKeys(lines []string, cursorLine int, cursorIndent int) []enclosingKey {
+	var keys []enclosingKey
+	target := cursorIndent
+
+	for i := cursorLine - 1; i >= 0 && target > 0; i-- {
+		trimmed := strings.TrimSpace(lines[i])
+		if trimmed == "" || strings.HasPrefix(trimmed, "#") {
+			continue
+		}
+
+		indent := countIndent(lines[i])
+		content := trimmed
+		contentIndent := indent
+		isItem := strings.HasPrefix(trimmed, "- ") || trimmed == "-"
+		if isItem {
+			content = strings.TrimSpace(strings.TrimPrefix(trimmed, "-"))
+			contentIndent = indent + 2
+		}
+
+		if contentIndent < target {
+			if colon := strings.Index(content, ":"); colon > 0 && strings.TrimSpace(content[colon+1:]) == "" {
+				key := strings.Trim(strings.TrimSpace(content[:colon]), `"'`)
+				keys = append([]enclosingKey{{Key: key, Line: i, Indent: contentIndent}}, keys...)
+			}
+			target = indent
+		} else if isItem && contentIndent == target {
+			// The cursor is inside this list item
+			target = indent
+		}
+	}
+
+	return keys
+}
+
+// countIndent counts leading spaces, treating tabs as two spaces like the analyzer
+func countIndent(line string) int {
+	indent := 0
+	for _, char := range line {
+		switch char {
+		case ' ':
+			indent++
+		case '\t':
+			indent += 2
+		default:
+			return indent
+		}
+	}
+	return indent
+}
+
+// getMatrixAdjustmentCompletions returns completions inside steps/N/matrix/adjustments/M
+func (cp *CompletionProvider) getMatrixAdjustmentCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	if cursorLine > len(lines) {
+		return nil, false
+	}
+
+	keys := findEnclosingKeys(lines, cursorLine, countIndent(posCtx.CurrentLine))
+	n := len(keys)
+
+	if n >= 2 && keys[n-1].Key == "adjustments" && keys[n-2].Key == "matrix" {
+		return cp.getAdjustmentKeyCompletions(), true
+	}
+
+	if n >= 3 && keys[n-1].Key == "with" && keys[n-2].Key == "adjustments" && keys[n-3].Key == "matrix" {
+		var items []protocol.CompletionItem
+		for _, dimension := range findMatrixDimensions(lines, keys[n-3]) {
+			items = append(items, protocol.CompletionItem{
+				Label:      dimension,
+				Kind:       protocol.CompletionItemKindField,
+				Detail:     "Matrix dimension",
+				InsertText: dimension + ": ",
+			})
+		}
+		return items, true
+	}
+
+	return nil, false
+}
+
+// getAdjustmentKeyCompletions returns the keys allowed in a matrix adjustment
+func (cp *CompletionProvider) getAdjustmentKeyCompletions() []protocol.CompletionItem {
+	return []protocol.CompletionItem{
+		{
+			Label:            "with",
+			Kind:             protocol.CompletionItemKindProperty,
+			Detail:           "Matrix combination to adjust",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "The matrix dimension values this adjustment applies to. Combinations not in the matrix setup are added as new jobs."},
+			InsertText:       "with:\n  $0",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+		},
+		{
+			Label:            "skip",
+			Kind:             protocol.CompletionItemKindProperty,
+			Detail:           "Skip this combination",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Skips the job for this matrix combination. Can be `true` or a string explaining why."},
+			InsertText:       "skip: ${1:true}",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+		},
+		{
+			Label:            "soft_fail",
+			Kind:             protocol.CompletionItemKindProperty,
+			Detail:           "Allow this combination to fail",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Allows the job for this matrix combination to fail without failing the build"},
+			InsertText:       "soft_fail: ${1:true}",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+		},
+	}
+}
+
+// findMatrixDimensions returns the dimension names declared under matrix.setup
+func findMatrixDimensions(lines []string, matrix enclosingKey) []string {
+	var dimensions []string
+	setupIndent := -1
+	dimensionIndent := -1
+
+	for i := matrix.Line + 1; i < len(lines); i++ {
+		trimmed := strings.TrimSpace(lines[i])
+		if trimmed == "" || strings.HasPrefix(trimmed, "#") {
+			continue
+		}
+
+		indent := countIndent(lines[i])
+		if indent < matrix.Indent+1 {
+			break
+		}
+
+		if setupIndent == -1 {
+			if strings.HasPrefix(trimmed, "setup:") {
+				setupIndent = indent
+			}
+			continue
+		}
+
+		if indent <= setupIndent {
+			// Left the setup block; only one setup is allowed per matrix
+			break
+		}
+
+		if dimensionIndent == -1 {
+			dimensionIndent = indent
+		}
+
+		if indent == dimensionIndent && !strings.HasPrefix(trimmed, "-") {
+			if colon := strings.Index(trimmed, ":"); colon > 0 {
+				dimensions = append(dimensions, strings.Trim(trimmed[:colon], `"'`))
+			}
+		}
+	}
+
+	return dimensions
+}
+
+// commonAgentTags are agent tags most Buildkite setups define, with the values
+// usually given to them
+var commonAgentTags = []struct {
+	Name        string
+	Description string
+	Values      []string
+}{
+	{Name: "queue", Description: "The agent queue to run on"},
+	{Name: "os", Description: "The agent operating system (e.g. `linux`, `macos`, `windows`)", Values: []string{"linux", "macos", "windows"}},
+	{Name: "arch", Description: "The agent CPU architecture (e.g. `amd64`, `arm64`)", Values: []string{"amd64", "arm64"}},
+	{Name: "docker", Description: "Whether the agent can run Docker"},
+}
+
+// getRetryCompletions suggests the automatic and manual keys inside a step's retry block
+func (cp *CompletionProvider) getRetryCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	if cursorLine > len(lines) {
+		return nil, false
+	}
+
+	keys := findEnclosingKeys(lines, cursorLine, countIndent(posCtx.CurrentLine))
+	n := len(keys)
+	if n < 2 || keys[n-1].Key != "retry" || keys[0].Key != "steps" {
+		return nil, false
+	}
+
+	// The values are mappings or booleans, so there's nothing to offer after the colon
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+	if strings.Contains(before, ":") {
+		return []protocol.CompletionItem{}, true
+	}
+
+	items := []protocol.CompletionItem{
+		{
+			Label:            "automatic",
+			Kind:             protocol.CompletionItemKindProperty,
+			Detail:           "Automatic retries",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Retry the job automatically when it fails. Use `true` for the defaults, or a list of rules matching `exit_status` or `signal_reason` with a `limit` of up to 10."},
+			InsertText:       "automatic:\n  - exit_status: ${1:\"*\"}\n    limit: ${2:2}",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+		},
+		{
+			Label:            "manual",
+			Kind:             protocol.CompletionItemKindProperty,
+			Detail:           "Manual retries",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Whether the job can be retried from the UI. Use `false` to prevent it, or a mapping with `allowed`, `reason` and `permit_on_passed`."},
+			InsertText:       "manual:\n  allowed: ${1|false,true|}\n  reason: \"$2\"",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+		},
+	}
+
+	return withoutKeys(items, posCtx.SiblingKeys()), true
+}
+
+// getAgentsCompletions suggests agent tag keys inside a root-level or step-level agents block
+func (cp *CompletionProvider) getAgentsCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	if cursorLine > len(lines) {
+		return nil, false
+	}
+
+	keys := findEnclosingKeys(lines, cursorLine, countIndent(posCtx.CurrentLine))
+	n := len(keys)
+	if n >= 2 && keys[n-2].Key == "agents" && (n == 2 || keys[0].Key == "steps") {
+		// Tag values are scalars, so there's nothing to nest under a tag
+		return []protocol.CompletionItem{}, true
+	}
+	if n == 0 || keys[n-1].Key != "agents" {
+		return nil, false
+	}
+
+	atRoot := n == 1
+	if !atRoot && keys[0].Key != "steps" {
+		return nil, false
+	}
+
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+	if colon := strings.Index(before, ":"); colon >= 0 {
+		return agentTagValueCompletions(strings.TrimSpace(before[:colon])), true
+	}
+
+	scope := "Targets agents for every step without its own `agents`"
+	if !atRoot {
+		scope = "Targets agents for this step, overriding the pipeline-level `agents`"
+	}
+
+	var items []protocol.CompletionItem
+	seen := make(map[string]bool)
+	for _, tag := range commonAgentTags {
+		seen[tag.Name] = true
+		items = append(items, protocol.CompletionItem{
+			Label:         tag.Name,
+			Kind:          protocol.CompletionItemKindProperty,
+			Detail:        "Agent tag",
+			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: tag.Description + "\n\n" + scope},
+			InsertText:    tag.Name + ": ",
+		})
+	}
+
+	// Offer tags already used elsewhere in the pipeline so custom tags stay consistent
+	for _, tag := range collectAgentTags(lines) {
+		if seen[tag] {
+			continue
+		}
+		seen[tag] = true
+		items = append(items, protocol.CompletionItem{
+			Label:         tag,
+			Kind:          protocol.CompletionItemKindProperty,
+			Detail:        "Agent tag used in this pipeline",
+			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: scope},
+			InsertText:    tag + ": ",
+		})
+	}
+
+	return items, true
+}
+
+// getMetaDataCompletions suggests meta-data keys inside a trigger step's
+// build.meta_data block, drawn from this pipeline and the rest of the workspace
+func (cp *CompletionProvider) getMetaDataCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	if cursorLine > len(lines) {
+		return nil, false
+	}
+
+	keys := findEnclosingKeys(lines, cursorLine, countIndent(posCtx.CurrentLine))
+	n := len(keys)
+	if n < 2 || keys[n-1].Key != "meta_data" || keys[n-2].Key != "build" {
+		return nil, false
+	}
+
+	// Values are free text, so there's nothing to offer after the colon
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+	if strings.Contains(before, ":") {
+		return []protocol.CompletionItem{}, true
+	}
+
+	seen := make(map[string]bool)
+	for _, key := range posCtx.SiblingKeys() {
+		seen[key] = true
+	}
+
+	items := []protocol.CompletionItem{}
+	add := func(key, detail string) {
+		if seen[key] {
+			return
+		}
+		seen[key] = true
+		items = append(items, protocol.CompletionItem{
+			Label:      key,
+			Kind:       protocol.CompletionItemKindProperty,
+			Detail:     detail,
+			InsertText: key + ": ",
+		})
+	}
+
+	for _, key := range collectMetaDataKeys(lines) {
+		add(key, "Meta-data key used in this pipeline")
+	}
+	if cp.workspaceMetaDataKeys != nil {
+		for _, key := range cp.workspaceMetaDataKeys() {
+			add(key, "Meta-data key used in the workspace")
+		}
+	}
+
+	return items, true
+}
+
+// metaDataCommandPattern matches a meta-data key read or written by
+// buildkite-agent in a command
+var metaDataCommandPattern = regexp.MustCompile(`buildkite-agent meta-data (?:get|set|exists)\s+["']?([A-Za-z0-9_\-./]+)`)
+
+// collectMetaDataKeys returns the meta-data keys set in any meta_data block or
+// used with buildkite-agent meta-data in a command, in document order
+func collectMetaDataKeys(lines []string) []string {
+	var keys []string
+	seen := make(map[string]bool)
+	add := func(key string) {
+		if key != "" && !seen[key] {
+			seen[key] = true
+			keys = append(keys, key)
+		}
+	}
+
+	for i := 0; i < len(lines); i++ {
+		for _, match := range metaDataCommandPattern.FindAllStringSubmatch(lines[i], -1) {
+			add(match[1])
+		}
+
+		if strings.TrimSpace(lines[i]) != "meta_data:" {
+			continue
+		}
+
+		metaDataIndent := countIndent(lines[i])
+		keyIndent := -1
+		for j := i + 1; j < len(lines); j++ {
+			content := strings.TrimSpace(lines[j])
+			if content == "" || strings.HasPrefix(content, "#") {
+				continue
+			}
+			indent := countIndent(lines[j])
+			if indent <= metaDataIndent {
+				break
+			}
+			if keyIndent == -1 {
+				keyIndent = indent
+			}
+			if indent != keyIndent {
+				continue
+			}
+			if colon := strings.Index(content, ":"); colon > 0 {
+				add(strings.Trim(content[:colon], `"'`))
+			}
+		}
+	}
+
+	return keys
+}
+
+// agentTagValueCompletions offers the usual values for a well-known agent tag
+func agentTagValueCompletions(tag string) []protocol.CompletionItem {
+	items := []protocol.CompletionItem{}
+	for _, common := range commonAgentTags {
+		if common.Name != tag {
+			continue
+		}
+		for i, value := range common.Values {
+			items = append(items, protocol.CompletionItem{
+				Label:    value,
+				Kind:     protocol.CompletionItemKindValue,
+				Detail:   fmt.Sprintf("Agent %s", tag),
+				SortText: fmt.Sprintf("%02d", i),
+			})
+		}
+	}
+	return items
+}
+
+// collectAgentTags returns the tag keys declared in any agents block, in document order
+func collectAgentTags(lines []string) []string {
+	var tags []string
+	seen := make(map[string]bool)
+
+	for i := 0; i < len(lines); i++ {
+		trimmed := strings.TrimSpace(strings.TrimPrefix(strings.TrimSpace(lines[i]), "- "))
+		if trimmed != "agents:" {
+			continue
+		}
+
+		agentsIndent := countIndent(lines[i])
+		if strings.HasPrefix(strings.TrimSpace(lines[i]), "- ") {
+			agentsIndent += 2
+		}
+
+		for j := i + 1; j < len(lines); j++ {
+			content := strings.TrimSpace(lines[j])
+			if content == "" || strings.HasPrefix(content, "#") {
+				continue
+			}
+			if countIndent(lines[j]) <= agentsIndent {
+				break
+			}
+
+			// Tags can be written as "key: value" or as a "- key=value" list
+			content = strings.Trim(strings.TrimPrefix(content, "- "), `"'`)
+			var tag string
+			if colon := strings.Index(content, ":"); colon > 0 {
+				tag = content[:colon]
+			} else if equals := strings.Index(content, "="); equals > 0 {
+				tag = content[:equals]
+			}
+			if tag != "" && !seen[tag] {
+				seen[tag] = true
+				tags = append(tags, tag)
+			}
+		}
+	}
+
+	return tags
+}
+
+// getRootEnvCompletions suggests new entries for the pipeline-level env block
+func (cp *CompletionProvider) getRootEnvCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	if cursorLine > len(lines) {
+		return nil, false
+	}
+
+	keys := findEnclosingKeys(lines, cursorLine, countIndent(posCtx.CurrentLine))
+	if len(keys) != 1 || keys[0].Key != "env" {
+		return nil, false
+	}
+
+	// Values are free-form strings, so only suggest when typing a new name
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+	if strings.Contains(before, ":") {
+		return []protocol.CompletionItem{}, true
+	}
+
+	items := []protocol.CompletionItem{
+		{
+			Label:            "New variable",
+			Kind:             protocol.CompletionItemKindSnippet,
+			Detail:           "New environment variable",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Sets an environment variable for every job in the pipeline. Values are strings, so quote numbers and booleans."},
+			InsertText:       "${1:NAME}: \"${2:value}\"",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+			SortText:         "0",
+		},
+	}
+
+	defined := make(map[string]bool)
+	for i := keys[0].Line + 1; i < len(lines); i++ {
+		trimmed := strings.TrimSpace(lines[i])
+		if trimmed == "" || strings.HasPrefix(trimmed, "#") {
+			continue
+		}
+		if countIndent(lines[i]) <= keys[0].Indent {
+			break
+		}
+		if colon := strings.Index(trimmed, ":"); colon > 0 {
+			defined[strings.Trim(trimmed[:colon], `"'`)] = true
+		}
+	}
+
+	for _, variable := range envvars.UserSettable {
+		if defined[variable.Name] {
+			continue
+		}
+		items = append(items, protocol.CompletionItem{
+			Label:            variable.Name,
+			Kind:             protocol.CompletionItemKindVariable,
+			Detail:           "Environment variable (string)",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: variable.Description},
+			InsertText:       variable.Name + ": \"${1}\"",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+			SortText:         "1" + variable.Name,
+		})
+	}
+
+	return items, true
+}
+
+var (
+	// ifValuePattern matches an `if` key and captures the expression typed so far
+	ifValuePattern = regexp.MustCompile(`^\s*(?:-\s+)?if:\s*(.*)$`)
+	// expressionPathPattern captures the dotted path being typed at the end of an expression
+	expressionPathPattern = regexp.MustCompile(`([a-z_][a-z0-9_.]*)$`)
+)
+
+// IsInConditional reports whether the cursor is inside an `if` expression
+func (cp *CompletionProvider) IsInConditional(posCtx *context.PositionContext) bool {
+	_, ok := conditionalExpression(posCtx)
+	return ok
+}
+
+// conditionalExpression returns the `if` expression text before the cursor
+func conditionalExpression(posCtx *context.PositionContext) (string, bool) {
+	if posCtx == nil {
+		return "", false
+	}
+
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+
+	match := ifValuePattern.FindStringSubmatch(before)
+	if match == nil {
+		return "", false
+	}
+	return match[1], true
+}
+
+// getConditionalCompletions offers variables and functions inside an `if` expression
+func (cp *CompletionProvider) getConditionalCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	expression, ok := conditionalExpression(posCtx)
+	if !ok {
+		return nil, false
+	}
+
+	// The whole expression may be quoted in YAML, so only string literals
+	// inside it (an odd number of double quotes after the first) are skipped
+	quoted := strings.HasPrefix(expression, "'")
+	inner := strings.TrimPrefix(expression, "'")
+	if !quoted {
+		inner = strings.TrimPrefix(expression, `"`)
+	}
+	if strings.Count(inner, `"`)%2 == 1 {
+		return []protocol.CompletionItem{}, true
+	}
+
+	prefix := ""
+	if path := expressionPathPattern.FindString(inner); strings.Contains(path, ".") {
+		prefix = path[:strings.LastIndex(path, ".")]
+	}
+
+	var items []protocol.CompletionItem
+	for _, member := range conditionals.Members(prefix) {
+		item := protocol.CompletionItem{
+			Label:  member.Name,
+			Kind:   protocol.CompletionItemKindField,
+			Detail: member.Description,
+		}
+
+		switch {
+		case member.IsFunction:
+			item.Kind = protocol.CompletionItemKindFunction
+			item.InsertText = member.Name + `("${1}")`
+			item.InsertTextFormat = protocol.InsertTextFormatSnippet
+		case member.HasMembers && member.Description == "":
+			item.Kind = protocol.CompletionItemKindModule
+			item.Detail = "Object"
+		}
+
+		items = append(items, item)
+	}
+
+	return items, true
+}
+
+var (
+	// keyValuePattern matches a `key` property with the value typed so far
+	keyValuePattern = regexp.MustCompile(`^(\s*)(-\s+)?key:\s*["']?[A-Za-z0-9_-]*$`)
+	// existingKeyPattern captures the value of a `key` property
+	existingKeyPattern = regexp.MustCompile(`^\s*(?:-\s+)?key:\s*["']?([^"'\s#]+)`)
+	// emojiPattern matches Buildkite emoji shortcodes like :hammer:
+	emojiPattern = regexp.MustCompile(`:[a-z0-9_+-]+:`)
+	// nonSlugPattern matches runs of characters that can't appear in a slug
+	nonSlugPattern = regexp.MustCompile(`[^a-z0-9]+`)
+)
+
+// getStepKeyCompletions suggests a unique key derived from the step's label or command
+func (cp *CompletionProvider) getStepKeyCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+
+	match := keyValuePattern.FindStringSubmatch(before)
+	if match == nil {
+		return nil, false
+	}
+
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	if cursorLine > len(lines) {
+		return nil, false
+	}
+
+	propertyIndent := countIndent(match[1]) + len(match[2])
+	keys := findEnclosingKeys(lines, cursorLine, propertyIndent)
+	if len(keys) == 0 || keys[len(keys)-1].Key != "steps" {
+		return nil, false
+	}
+
+	var label, command string
+	for _, property := range stepProperties(lines, cursorLine, propertyIndent, match[2] != "") {
+		switch property.Key {
+		case "label":
+			label = property.Value
+		case "command":
+			command = property.Value
+		}
+	}
+
+	base, detail := slugify(label), "label"
+	if base == "" {
+		base, detail = slugify(command), "command"
+	}
+	if base == "" {
+		return []protocol.CompletionItem{}, true
+	}
+
+	existing := make(map[string]bool)
+	for i, line := range lines {
+		if i == cursorLine {
+			continue
+		}
+		if keyMatch := existingKeyPattern.FindStringSubmatch(line); keyMatch != nil {
+			existing[keyMatch[1]] = true
+		}
+	}
+
+	key := base
+	for n := 2; existing[key]; n++ {
+		key = fmt.Sprintf("%s-%d", base, n)
+	}
+
+	return []protocol.CompletionItem{
+		{
+			Label:      key,
+			Kind:       protocol.CompletionItemKindValue,
+			Detail:     "Step key from " + detail,
+			InsertText: key,
+		},
+	}, true
+}
+
+// numericValue is a commonly used value for an integer property
+type numericValue struct {
+	Value  int
+	Detail string
+}
+
+// numericValues lists common values for integer properties, offered in order
+var numericValues = map[string][]numericValue{
+	"timeout_in_minutes": {
+		{Value: 10, Detail: "10 minutes"},
+		{Value: 30, Detail: "30 minutes"},
+		{Value: 60, Detail: "60 minutes (1 hour)"},
+		{Value: 120, Detail: "120 minutes (2 hours)"},
+	},
+}
+
+// numericValuePattern captures a property and the digits typed so far for its value
+var numericValuePattern = regexp.MustCompile(`^\s*(?:-\s+)?([a-z_]+):\s+\d*$`)
+
+// getNumericValueCompletions suggests common values for integer properties
+// such as timeout_in_minutes
+func (cp *CompletionProvider) getNumericValueCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+
+	match := numericValuePattern.FindStringSubmatch(before)
+	if match == nil {
+		return nil, false
+	}
+	values, ok := numericValues[match[1]]
+	if !ok {
+		return nil, false
+	}
+
+	items := make([]protocol.CompletionItem, 0, len(values))
+	for i, value := range values {
+		items = append(items, protocol.CompletionItem{
+			Label:    strconv.Itoa(value.Value),
+			Kind:     protocol.CompletionItemKindValue,
+			Detail:   value.Detail,
+			SortText: fmt.Sprintf("%02d", i),
+		})
+	}
+
+	return items, true
+}
+
+var (
+	// labelValuePattern captures the value typed so far for a `label` property
+	labelValuePattern = regexp.MustCompile(`^\s*(?:-\s+)?label:\s+(.*)$`)
+	// partialEmojiPattern captures a shortcode being typed at the end of a value
+	partialEmojiPattern = regexp.MustCompile(`:([a-z0-9_+-]*)$`)
+)
+
+// getLabelEmojiCompletions suggests emoji shortcodes when a ':' is typed in a
+// label value, replacing the partial shortcode typed so far
+func (cp *CompletionProvider) getLabelEmojiCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+
+	match := labelValuePattern.FindStringSubmatch(before)
+	if match == nil {
+		return nil, false
+	}
+
+	// Finished shortcodes are skipped so their closing ':' doesn't start a new one
+	value := emojiPattern.ReplaceAllStringFunc(match[1], func(shortcode string) string {
+		return strings.Repeat(" ", len(shortcode))
+	})
+	partial := partialEmojiPattern.FindStringSubmatch(value)
+	if partial == nil {
+		return nil, false
+	}
+
+	start := uint32(len(before) - len(partial[0]))
+	items := []protocol.CompletionItem{}
+	for _, e := range emoji.WithPrefix(partial[1]) {
+		shortcode := ":" + e.Shortcode + ":"
+		detail := e.Character
+		if detail == "" {
+			detail = "Buildkite emoji"
+		}
+		items = append(items, protocol.CompletionItem{
+			Label:  shortcode,
+			Kind:   protocol.CompletionItemKindValue,
+			Detail: detail,
+			TextEdit: &protocol.TextEdit{
+				Range: protocol.Range{
+					Start: protocol.Position{Line: posCtx.Position.Line, Character: start},
+					End:   protocol.Position{Line: posCtx.Position.Line, Character: uint32(len(before))},
+				},
+				NewText: shortcode,
+			},
+		})
+	}
+
+	return items, true
+}
+
+// mergeKeyPattern matches a line where a `<<:` merge key is being typed
+var mergeKeyPattern = regexp.MustCompile(`^(\s*)(-\s+)?(<{1,2})$`)
+
+// getMergeKeyCompletions offers a `<<: *anchor` merge for each anchor defined in
+// the document when the user starts typing `<` in a step mapping
+func (cp *CompletionProvider) getMergeKeyCompletions(posCtx *context.PositionContext) ([]protocol.CompletionItem, bool) {
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+
+	match := mergeKeyPattern.FindStringSubmatch(before)
+	if match == nil {
+		return nil, false
+	}
+
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	if cursorLine > len(lines) {
+		return nil, false
+	}
+
+	propertyIndent := countIndent(match[1]) + len(match[2])
+	keys := findEnclosingKeys(lines, cursorLine, propertyIndent)
+	if len(keys) == 0 || keys[len(keys)-1].Key != "steps" {
+		return nil, false
+	}
+
+	start := uint32(len(before) - len(match[3]))
+	items := []protocol.CompletionItem{}
+	for _, anchor := range findAnchors(lines) {
+		if anchor.Line == cursorLine {
+			continue
+		}
+		text := "<<: *" + anchor.Name
+		items = append(items, protocol.CompletionItem{
+			Label:         text,
+			Kind:          protocol.CompletionItemKindReference,
+			Detail:        fmt.Sprintf("Merge anchor '%s'", anchor.Name),
+			Documentation: fmt.Sprintf("Merges the mapping defined by `&%s` on line %d into this step", anchor.Name, anchor.Line+1),
+			TextEdit: &protocol.TextEdit{
+				Range: protocol.Range{
+					Start: protocol.Position{Line: posCtx.Position.Line, Character: start},
+					End:   protocol.Position{Line: posCtx.Position.Line, Character: uint32(len(before))},
+				},
+				NewText: text,
+			},
+		})
+	}
+
+	return items, true
+}
+
+// listKeyPattern matches a key with no value after it, e.g. "steps:" or
+// "- fields:", capturing its indentation, list marker and name
+var listKeyPattern = regexp.MustCompile(`^(\s*)(-\s+)?([A-Za-z_][A-Za-z0-9_-]*):\s*$`)
+
+// expectsList reports whether a key's value is a list. Steps always are, at the
+// top level and in groups; other keys need the schema to say so.
+func (cp *CompletionProvider) expectsList(key string) bool {
+	if key == "steps" {
+		return true
+	}
+	return cp.schemaLoader != nil && cp.schemaLoader.IsArrayProperty(key)
+}
+
+// getListItemCompletion offers to start the first item of a list, either at the
+// end of the key's line or on the blank line below it. The marker is indented
+// like the items already in the list, or two spaces in from the key.
+func (cp *CompletionProvider) getListItemCompletion(posCtx *context.PositionContext) (protocol.CompletionItem, bool) {
+	if posCtx == nil || posCtx.CharIndex > len(posCtx.CurrentLine) {
+		return protocol.CompletionItem{}, false
+	}
+	before := posCtx.CurrentLine[:posCtx.CharIndex]
+	if strings.TrimSpace(posCtx.CurrentLine[posCtx.CharIndex:]) != "" {
+		return protocol.CompletionItem{}, false
+	}
+
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	keyLine := cursorLine
+	start := 0
+	prefix := ""
+
+	match := listKeyPattern.FindStringSubmatch(before)
+	if match != nil {
+		// Right after the key, so the item goes on a new line
+		start = len(strings.TrimRight(before, " \t"))
+		prefix = "\n"
+	} else if strings.TrimSpace(before) == "" {
+		keyLine = -1
+		for i := min(cursorLine, len(lines)) - 1; i >= 0; i-- {
+			trimmed := strings.TrimSpace(lines[i])
+			if trimmed != "" && !strings.HasPrefix(trimmed, "#") {
+				keyLine = i
+				break
+			}
+		}
+		if keyLine < 0 {
+			return protocol.CompletionItem{}, false
+		}
+		match = listKeyPattern.FindStringSubmatch(lines[keyLine])
+	}
+	if match == nil || !cp.expectsList(match[3]) {
+		return protocol.CompletionItem{}, false
+	}
+
+	keyIndent := countIndent(match[1]) + len(match[2])
+	indent := strings.Repeat(" ", keyIndent+2)
+	for i := keyLine + 1; i < len(lines); i++ {
+		trimmed := strings.TrimSpace(lines[i])
+		if i == cursorLine || trimmed == "" || strings.HasPrefix(trimmed, "#") {
+			continue
+		}
+		if itemIndent := countIndent(lines[i]); itemIndent >= keyIndent && strings.HasPrefix(trimmed, "-") {
+			indent = strings.Repeat(" ", itemIndent)
+		}
+		break
+	}
+
+	return protocol.CompletionItem{
+		Label:         "- (new list item)",
+		Kind:          protocol.CompletionItemKindSnippet,
+		Detail:        fmt.Sprintf("Start an item in %s", match[3]),
+		Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: fmt.Sprintf("`%s` takes a list, so each entry starts with a `- ` marker", match[3])},
+		TextEdit: &protocol.TextEdit{
+			Range: protocol.Range{
+				Start: protocol.Position{Line: posCtx.Position.Line, Character: uint32(start)},
+				End:   protocol.Position{Line: posCtx.Position.Line, Character: uint32(len(before))},
+			},
+			NewText: prefix + indent + "- ",
+		},
+		FilterText: before[start:],
+		SortText:   "00-new-list-item",
+	}, true
+}
+
+// stepProperty is a scalar property of a step
+type stepProperty struct {
+	Key   string
+	Value string
+}
+
+// stepProperties returns the scalar properties of the step containing the cursor,
+// whose properties start at propertyIndent
+func stepProperties(lines []string, cursorLine, propertyIndent int, cursorOnItem bool) []stepProperty {
+	itemLine := cursorLine
+	if !cursorOnItem {
+		itemLine = -1
+		for i := cursorLine - 1; i >= 0; i-- {
+			trimmed := strings.TrimSpace(lines[i])
+			if trimmed == "" {
+				continue
+			}
+			indent := countIndent(lines[i])
+			if indent == propertyIndent-2 && strings.HasPrefix(trimmed, "- ") {
+				itemLine = i
+				break
+			}
+			if indent < propertyIndent {
+				break
+			}
+		}
+		if itemLine == -1 {
+			return nil
+		}
+	}
+
+	var properties []stepProperty
+	for i := itemLine; i < len(lines); i++ {
+		trimmed := strings.TrimSpace(lines[i])
+		if trimmed == "" || strings.HasPrefix(trimmed, "#") {
+			continue
+		}
+		if i > itemLine && countIndent(lines[i]) < propertyIndent {
+			break
+		}
+		if i == cursorLine {
+			continue
+		}
+
+		content := strings.TrimPrefix(trimmed, "- ")
+		if i > itemLine && countIndent(lines[i]) != propertyIndent {
+			continue
+		}
+
+		if colon := strings.Index(content, ":"); colon > 0 {
+			value := strings.TrimSpace(content[colon+1:])
+			properties = append(properties, stepProperty{
+				Key:   strings.TrimSpace(content[:colon]),
+				Value: strings.Trim(value, `"'`),
+			})
+		}
+	}
+
+	return properties
+}
+
+// slugify turns a label or command into a lowercase, dash-separated step key
+func slugify(text string) string {
+	slug := emojiPattern.ReplaceAllString(strings.ToLower(text), " ")
+	slug = nonSlugPattern.ReplaceAllString(slug, "-")
+	return strings.Trim(slug, "-")
+}
+
+// isEffectivelyEmpty reports whether the document has nothing but comments and
+// blank lines, apart from a word being typed on the cursor line
+func isEffectivelyEmpty(posCtx *context.PositionContext) bool {
+	if strings.Contains(posCtx.CurrentLine, ":") {
+		return false
+	}
+
+	for i, line := range splitLines(posCtx.FullContent) {
+		if i == int(posCtx.Position.Line) {
+			continue
+		}
+		trimmed := strings.TrimSpace(line)
+		if trimmed != "" && !strings.HasPrefix(trimmed, "#") {
+			return false
+		}
+	}
+
+	return true
+}
+
+// pipelineSkeletonCompletion scaffolds a new pipeline with a single command step
+func pipelineSkeletonCompletion() protocol.CompletionItem {
+	return protocol.CompletionItem{
+		Label:            "Buildkite pipeline skeleton",
+		Kind:             protocol.CompletionItemKindSnippet,
+		Detail:           "New pipeline with one command step",
+		Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Scaffolds `steps:` with a single command step to get a new pipeline started"},
+		InsertText:       "steps:\n  - label: \"${1:Build}\"\n    key: \"${2:build}\"\n    command: \"${3:make build}\"\n$0",
+		InsertTextFormat: protocol.InsertTextFormatSnippet,
+		FilterText:       "steps pipeline skeleton",
+		SortText:         "0",
+	}
+}
+
+// isInGroupStep reports whether the cursor is on a property line of a group step
+func isInGroupStep(posCtx *context.PositionContext) bool {
+	for _, property := range currentStepProperties(posCtx) {
+		if property.Key == "group" {
+			return true
+		}
+	}
+	return false
+}
+
+// currentStepProperties returns the other properties of the step whose property
+// line the cursor is on, or nil when the cursor isn't in a step mapping
+func currentStepProperties(posCtx *context.PositionContext) []stepProperty {
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+	if strings.HasPrefix(strings.TrimSpace(before), "-") {
+		return nil
+	}
+
+	lines := splitLines(posCtx.FullContent)
+	cursorLine := int(posCtx.Position.Line)
+	if cursorLine > len(lines) {
+		return nil
+	}
+
+	propertyIndent := countIndent(before)
+	keys := findEnclosingKeys(lines, cursorLine, propertyIndent)
+	if len(keys) == 0 || keys[len(keys)-1].Key != "steps" {
+		return nil
+	}
+
+	return stepProperties(lines, cursorLine, propertyIndent, false)
+}
+
+// getGroupStepCompletions returns the properties a group step accepts. Settings
+// such as command or agents belong on the steps inside the group.
+func (cp *CompletionProvider) getGroupStepCompletions() []protocol.CompletionItem {
+	return []protocol.CompletionItem{
+		{
+			Label:         "group",
+			Kind:          protocol.CompletionItemKindProperty,
+			Detail:        "Group name",
+			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: "The name shown for the group in the pipeline UI"},
+		},
+		{
+			Label:            "steps",
+			Kind:             protocol.CompletionItemKindProperty,
+			Detail:           "Steps in the group",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "The steps that run inside this group"},
+			InsertText:       "steps:\n  - $0",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+		},
+		{
+			Label:         "key",
+			Kind:          protocol.CompletionItemKindProperty,
+			Detail:        "Group key",
+			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: "A unique key other steps can use to depend on the whole group"},
+		},
+		{
+			Label:         "depends_on",
+			Kind:          protocol.CompletionItemKindProperty,
+			Detail:        "Group dependencies",
+			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Step keys that must finish before any step in the group runs"},
+		},
+		{
+			Label:         "allow_dependency_failure",
+			Kind:          protocol.CompletionItemKindProperty,
+			Detail:        "Run even if dependencies fail",
+			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: "Run the group even when the steps it depends on fail"},
+		},
+		{
+			Label:         "if",
+			Kind:          protocol.CompletionItemKindProperty,
+			Detail:        "Conditional group",
+			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: "A boolean condition to determine if the group should run"},
+		},
+		{
+			Label:         "label",
+			Kind:          protocol.CompletionItemKindProperty,
+			Detail:        "Group label",
+			Documentation: &protocol.MarkupContent{Kind: protocol.Markdown, Value: "An alternative to `group` for the name shown in the pipeline UI"},
+		},
+	}
+}
+
+// isNewStepItem reports whether the cursor is on a list item marker in steps
+// with nothing typed after it yet
+func isNewStepItem(posCtx *context.PositionContext) bool {
+	before := posCtx.CurrentLine
+	if posCtx.CharIndex < len(before) {
+		before = before[:posCtx.CharIndex]
+	}
+	if strings.TrimSpace(before) != "-" {
+		return false
+	}
+
+	lines := splitLines(posCtx.FullContent)
+	keys := findEnclosingKeys(lines, int(posCtx.Position.Line), countIndent(before)+2)
+	return len(keys) > 0 && keys[len(keys)-1].Key == "steps"
+}
+
+// stepFormCompletions offer a new command step as either a bare command string
+// or a full step object
+func stepFormCompletions() []protocol.CompletionItem {
+	return []protocol.CompletionItem{
+		{
+			Label:            "Command (shorthand)",
+			Kind:             protocol.CompletionItemKindSnippet,
+			Detail:           "Command step as a string",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "A bare string in `steps` runs as a command step with no other settings"},
+			InsertText:       "\"${1:make build}\"",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+			SortText:         "0a",
+		},
+		{
+			Label:            "Command step (object)",
+			Kind:             protocol.CompletionItemKindSnippet,
+			Detail:           "Command step with a label",
+			Documentation:    &protocol.MarkupContent{Kind: protocol.Markdown, Value: "A command step object, which can take a label, key, plugins and other settings"},
+			InsertText:       "label: \"${1:Build}\"\n  command: \"${2:make build}\"",
+			InsertTextFormat: protocol.InsertTextFormatSnippet,
+			SortText:         "0b",
+		},
+	}
+}
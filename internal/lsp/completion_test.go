@@ -1,8 +1,11 @@
 package lsp
 
 import (
+	"fmt"
 	"log"
 	"os"
+	"path/filepath"
+	"slices"
 	"strings"
 	"testing"
 
@@ -10,6 +13,7 @@ import (
 
 	"github.com/mcncl/buildkite-ls/internal/context"
 	"github.com/mcncl/buildkite-ls/internal/plugins"
+	"github.com/mcncl/buildkite-ls/internal/schema"
 )
 
 func newTestCompletionProvider() *CompletionProvider {
@@ -27,8 +31,8 @@ func TestCompletionProvider_GetCompletions_TopLevel(t *testing.T) {
 		Position:     protocol.Position{Line: 1, Character: 0},
 		CurrentLine:  "",
 		CharIndex:    0,
-		ContextLines: []string{"steps:", ""},
-		FullContent:  "steps:\n",
+		ContextLines: []string{"skip: false", ""},
+		FullContent:  "skip: false\n",
 	}
 
 	completions := provider.GetCompletions(posCtx)
@@ -100,6 +104,63 @@ func TestCompletionProvider_GetCompletions_StepLevel(t *testing.T) {
 	}
 }
 
+func TestCompletionProvider_ExistingKeysNotOffered(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	tests := []struct {
+		name       string
+		content    string
+		line       uint32
+		present    []string
+		notOffered []string
+	}{
+		{
+			name:       "top level",
+			content:    "env:\n  FOO: bar\n\nsteps:\n  - command: test",
+			line:       2,
+			present:    []string{"agents", "notify"},
+			notOffered: []string{"env", "steps"},
+		},
+		{
+			name:       "step",
+			content:    "steps:\n  - label: \"Build\"\n    \n    command: make\n  - label: \"Test\"\n    timeout_in_minutes: 5",
+			line:       2,
+			present:    []string{"plugins", "timeout_in_minutes"},
+			notOffered: []string{"label", "command"},
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(tt.content, "\n")
+			currentLine := lines[tt.line]
+			completions := provider.GetCompletions(&context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(currentLine))},
+				CurrentLine:  currentLine,
+				CharIndex:    len(currentLine),
+				ContextLines: lines[:tt.line+1],
+				FullContent:  tt.content,
+			})
+
+			found := make(map[string]bool)
+			for _, completion := range completions {
+				found[completion.Label] = true
+			}
+			for _, label := range tt.present {
+				if !found[label] {
+					t.Errorf("Expected completion '%s'", label)
+				}
+			}
+			for _, label := range tt.notOffered {
+				if found[label] {
+					t.Errorf("Did not expect completion for existing key '%s'", label)
+				}
+			}
+		})
+	}
+}
+
 func TestCompletionProvider_GetCompletions_PluginsArray(t *testing.T) {
 	provider := newTestCompletionProvider()
 
@@ -277,6 +338,46 @@ func TestCompletionProvider_PluginSnippets(t *testing.T) {
 	}
 }
 
+func TestCompletionProvider_PluginSkeleton(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	complete := func(currentLine string) []protocol.CompletionItem {
+		content := "steps:\n  - label: \"test\"\n    plugins:\n" + currentLine
+		return provider.GetCompletions(&context.PositionContext{
+			URI:          protocol.DocumentURI("file:///test.yml"),
+			Position:     protocol.Position{Line: 3, Character: uint32(len(currentLine))},
+			CurrentLine:  currentLine,
+			CharIndex:    len(currentLine),
+			ContextLines: strings.Split(content, "\n"),
+			FullContent:  content,
+		})
+	}
+
+	findSkeleton := func(items []protocol.CompletionItem) *protocol.CompletionItem {
+		for i := range items {
+			if items[i].Label == "org/name#version" {
+				return &items[i]
+			}
+		}
+		return nil
+	}
+
+	skeleton := findSkeleton(complete("      - "))
+	if skeleton == nil {
+		t.Fatal("Expected a plugin skeleton on a new list item")
+	}
+	if skeleton.InsertText != "${1:org}/${2:name}#${3:v1.0.0}:\n    ${4:option}: \"${5:value}\"" {
+		t.Errorf("Unexpected skeleton snippet: %q", skeleton.InsertText)
+	}
+	if skeleton.InsertTextFormat != protocol.InsertTextFormatSnippet {
+		t.Error("Expected the skeleton to be a snippet")
+	}
+
+	if findSkeleton(complete("      - dock")) != nil {
+		t.Error("Expected no skeleton once a plugin name is being typed")
+	}
+}
+
 func TestCompletionProvider_TopLevelSnippets(t *testing.T) {
 	provider := newTestCompletionProvider()
 
@@ -313,6 +414,365 @@ func TestCompletionProvider_TopLevelSnippets(t *testing.T) {
 	}
 }
 
+func TestCompletionProvider_PipelineSkeleton(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	tests := []struct {
+		name         string
+		content      string
+		line         uint32
+		currentLine  string
+		wantSkeleton bool
+	}{
+		{name: "empty document", content: "", wantSkeleton: true},
+		{name: "only comments", content: "# CI pipeline\n\n", line: 2, wantSkeleton: true},
+		{name: "word being typed", content: "st", currentLine: "st", wantSkeleton: true},
+		{name: "existing pipeline", content: "env:\n  FOO: bar\n\n", line: 3},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			completions := provider.GetCompletions(&context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(tt.currentLine))},
+				CurrentLine:  tt.currentLine,
+				CharIndex:    len(tt.currentLine),
+				ContextLines: strings.Split(tt.content, "\n"),
+				FullContent:  tt.content,
+			})
+
+			hasSkeleton := len(completions) > 0 && completions[0].Label == "Buildkite pipeline skeleton"
+			if hasSkeleton != tt.wantSkeleton {
+				t.Errorf("Expected skeleton %v, got %v", tt.wantSkeleton, hasSkeleton)
+			}
+			if hasSkeleton && !strings.HasPrefix(completions[0].InsertText, "steps:\n  - label:") {
+				t.Errorf("Unexpected skeleton snippet %q", completions[0].InsertText)
+			}
+		})
+	}
+}
+
+func TestCompletionProvider_StepForms(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	tests := []struct {
+		name      string
+		content   string
+		line      uint32
+		wantForms bool
+	}{
+		{name: "new step", content: "steps:\n  - command: make\n  - ", line: 2, wantForms: true},
+		{name: "new group step", content: "steps:\n  - group: \"Tests\"\n    steps:\n      - ", line: 3, wantForms: true},
+		{name: "new plugin", content: "steps:\n  - command: make\n    plugins:\n      - ", line: 3},
+		{name: "step property", content: "steps:\n  - command: make\n    ", line: 2},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(tt.content, "\n")
+			currentLine := lines[tt.line]
+			completions := provider.GetCompletions(&context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(currentLine))},
+				CurrentLine:  currentLine,
+				CharIndex:    len(currentLine),
+				ContextLines: lines,
+				FullContent:  tt.content,
+			})
+
+			found := make(map[string]protocol.CompletionItem)
+			for _, completion := range completions {
+				found[completion.Label] = completion
+			}
+
+			shorthand, hasShorthand := found["Command (shorthand)"]
+			object, hasObject := found["Command step (object)"]
+			if hasShorthand != tt.wantForms || hasObject != tt.wantForms {
+				t.Fatalf("Expected step forms %v, got shorthand %v and object %v", tt.wantForms, hasShorthand, hasObject)
+			}
+			if !tt.wantForms {
+				return
+			}
+			if shorthand.InsertText != `"${1:make build}"` {
+				t.Errorf("Unexpected shorthand snippet %q", shorthand.InsertText)
+			}
+			if !strings.HasPrefix(object.InsertText, "label:") || !strings.Contains(object.InsertText, "\n  command:") {
+				t.Errorf("Unexpected object snippet %q", object.InsertText)
+			}
+		})
+	}
+}
+
+func TestCompletionProvider_InsertRequiredFields(t *testing.T) {
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	schemaData := `{
+		"definitions": {
+			"commandStep": {"required": ["command"]},
+			"triggerStep": {"required": ["trigger", "build"]},
+			"groupStep": {"required": ["group", "steps"]}
+		}
+	}`
+	if err := os.WriteFile(schemaPath, []byte(schemaData), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	schemaLoader, err := schema.NewLoaderFromFile(schemaPath)
+	if err != nil {
+		t.Fatalf("Failed to load schema: %v", err)
+	}
+
+	content := "steps:\n  - command: make\n  - "
+	lines := strings.Split(content, "\n")
+	posCtx := &context.PositionContext{
+		URI:          protocol.DocumentURI("file:///test.yml"),
+		Position:     protocol.Position{Line: 2, Character: uint32(len(lines[2]))},
+		CurrentLine:  lines[2],
+		CharIndex:    len(lines[2]),
+		ContextLines: lines,
+		FullContent:  content,
+	}
+
+	tests := []struct {
+		name    string
+		enabled bool
+		want    map[string]string
+	}{
+		{
+			name:    "enabled",
+			enabled: true,
+			want: map[string]string{
+				"trigger":               "trigger: \"$1\"\nbuild: $2",
+				"group":                 "group: \"$1\"\nsteps:\n  - $0",
+				"Command step (object)": "label: \"${1:Build}\"\n  command: \"${2:make build}\"",
+			},
+		},
+		{
+			name: "disabled",
+			want: map[string]string{
+				"trigger":               "",
+				"group":                 "group: \"$1\"\nsteps:\n  - $0",
+				"Command step (object)": "label: \"${1:Build}\"\n  command: \"${2:make build}\"",
+			},
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			provider := newTestCompletionProvider()
+			provider.SetSchemaLoader(schemaLoader)
+			provider.SetInsertRequiredFields(tt.enabled)
+
+			found := make(map[string]protocol.CompletionItem)
+			for _, completion := range provider.GetCompletions(posCtx) {
+				found[completion.Label] = completion
+			}
+
+			for label, want := range tt.want {
+				item, ok := found[label]
+				if !ok {
+					t.Errorf("Expected a %q completion", label)
+					continue
+				}
+				if item.InsertText != want {
+					t.Errorf("Expected %q snippet %q, got %q", label, want, item.InsertText)
+				}
+			}
+		})
+	}
+}
+
+func TestCompletionProvider_MergeKey(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	content := `defaults: &defaults
+  timeout_in_minutes: 10
+linux: &linux
+  agents:
+    os: linux
+steps:
+  - label: "Build"
+    <
+  - <`
+
+	tests := []struct {
+		name string
+		line uint32
+	}{
+		{name: "step property", line: 7},
+		{name: "new step", line: 8},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(content, "\n")
+			currentLine := lines[tt.line]
+			completions := provider.GetCompletions(&context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(currentLine))},
+				CurrentLine:  currentLine,
+				CharIndex:    len(currentLine),
+				ContextLines: lines,
+				FullContent:  content,
+			})
+
+			var labels []string
+			for _, completion := range completions {
+				labels = append(labels, completion.Label)
+			}
+			if fmt.Sprint(labels) != fmt.Sprint([]string{"<<: *defaults", "<<: *linux"}) {
+				t.Fatalf("Expected a merge per anchor, got %v", labels)
+			}
+
+			edit := completions[0].TextEdit
+			if edit == nil {
+				t.Fatal("Expected merge completion to replace the typed prefix")
+			}
+			if edit.Range.Start.Character != uint32(len(currentLine)-1) || edit.Range.End.Character != uint32(len(currentLine)) {
+				t.Errorf("Expected edit over the '<', got %v", edit.Range)
+			}
+		})
+	}
+
+	t.Run("outside steps", func(t *testing.T) {
+		content := "defaults: &defaults\n  <"
+		lines := strings.Split(content, "\n")
+		completions := provider.GetCompletions(&context.PositionContext{
+			URI:          protocol.DocumentURI("file:///test.yml"),
+			Position:     protocol.Position{Line: 1, Character: 3},
+			CurrentLine:  lines[1],
+			CharIndex:    3,
+			ContextLines: lines,
+			FullContent:  content,
+		})
+		for _, completion := range completions {
+			if strings.HasPrefix(completion.Label, "<<:") {
+				t.Errorf("Unexpected merge completion %q outside steps", completion.Label)
+			}
+		}
+	})
+}
+
+func TestCompletionProvider_MetaDataKeys(t *testing.T) {
+	provider := newTestCompletionProvider()
+	provider.SetWorkspaceMetaDataKeys(func() []string { return []string{"deploy-target", "release-version"} })
+
+	content := `steps:
+  - label: "Build"
+    command: "buildkite-agent meta-data set release-version 1.2.3"
+  - trigger: "deploy"
+    build:
+      meta_data:
+        release-name: "x"
+        `
+	lines := strings.Split(content, "\n")
+	currentLine := lines[7]
+
+	completions := provider.GetCompletions(&context.PositionContext{
+		URI:          protocol.DocumentURI("file:///test.yml"),
+		Position:     protocol.Position{Line: 7, Character: uint32(len(currentLine))},
+		CurrentLine:  currentLine,
+		CharIndex:    len(currentLine),
+		ContextLines: lines,
+		FullContent:  content,
+	})
+
+	expected := []struct{ label, detail string }{
+		{"release-version", "Meta-data key used in this pipeline"},
+		{"deploy-target", "Meta-data key used in the workspace"},
+	}
+	if len(completions) != len(expected) {
+		t.Fatalf("Expected %d meta-data keys, got %v", len(expected), completions)
+	}
+	for i, want := range expected {
+		if completions[i].Label != want.label || completions[i].Detail != want.detail {
+			t.Errorf("Completion %d: expected %s (%s), got %s (%s)", i, want.label, want.detail, completions[i].Label, completions[i].Detail)
+		}
+	}
+}
+
+func TestCollectMetaDataKeys(t *testing.T) {
+	lines := strings.Split(`steps:
+  - command: |
+      buildkite-agent meta-data get "release-version"
+      buildkite-agent meta-data exists deploy-target
+  - trigger: "deploy"
+    build:
+      meta_data:
+        release-name: "x"
+        nested:
+          ignored: true
+        deploy-target: "prod"`, "\n")
+
+	got := collectMetaDataKeys(lines)
+	expected := []string{"release-version", "deploy-target", "release-name", "nested"}
+	if !slices.Equal(got, expected) {
+		t.Errorf("Expected %v, got %v", expected, got)
+	}
+}
+
+func TestCompletionProvider_LabelEmoji(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	complete := func(currentLine string) []protocol.CompletionItem {
+		content := "steps:\n" + currentLine
+		lines := strings.Split(content, "\n")
+		return provider.GetCompletions(&context.PositionContext{
+			URI:          protocol.DocumentURI("file:///test.yml"),
+			Position:     protocol.Position{Line: 1, Character: uint32(len(currentLine))},
+			CurrentLine:  currentLine,
+			CharIndex:    len(currentLine),
+			ContextLines: lines,
+			FullContent:  content,
+		})
+	}
+
+	t.Run("after a colon", func(t *testing.T) {
+		completions := complete(`  - label: ":`)
+		found := make(map[string]protocol.CompletionItem)
+		for _, completion := range completions {
+			found[completion.Label] = completion
+		}
+		for _, shortcode := range []string{":rocket:", ":docker:", ":test_tube:", ":warning:"} {
+			if _, ok := found[shortcode]; !ok {
+				t.Errorf("Expected %s to be suggested", shortcode)
+			}
+		}
+		if rocket := found[":rocket:"]; rocket.Detail != "🚀" {
+			t.Errorf("Expected the rocket to show the rendered emoji, got %q", rocket.Detail)
+		}
+		if docker := found[":docker:"]; docker.Detail != "Buildkite emoji" {
+			t.Errorf("Expected docker to be described as a Buildkite emoji, got %q", docker.Detail)
+		}
+	})
+
+	t.Run("partial shortcode", func(t *testing.T) {
+		currentLine := `  - label: ":hammer: Build :ro`
+		completions := complete(currentLine)
+		if len(completions) != 1 || completions[0].Label != ":rocket:" {
+			t.Fatalf("Expected only :rocket:, got %v", completions)
+		}
+		edit := completions[0].TextEdit
+		if edit == nil || edit.Range.Start.Character != uint32(len(currentLine)-3) || edit.NewText != ":rocket:" {
+			t.Errorf("Expected the edit to replace ':ro', got %+v", edit)
+		}
+	})
+
+	t.Run("closing colon of a shortcode", func(t *testing.T) {
+		for _, completion := range complete(`  - label: ":rocket:`) {
+			if strings.HasPrefix(completion.Label, ":") {
+				t.Errorf("Unexpected emoji completion %q after a finished shortcode", completion.Label)
+			}
+		}
+	})
+
+	t.Run("other values", func(t *testing.T) {
+		for _, completion := range complete(`  - command: "echo :`) {
+			if strings.HasPrefix(completion.Label, ":") {
+				t.Errorf("Unexpected emoji completion %q outside a label", completion.Label)
+			}
+		}
+	})
+}
+
 func TestCompletionProvider_Integration_ContextDetection(t *testing.T) {
 	// Simplified integration test focusing on working cases
 	provider := newTestCompletionProvider()
@@ -558,3 +1018,818 @@ func TestCompletionProvider_EdgeCases(t *testing.T) {
 		}
 	})
 }
+
+func TestCompletionProvider_MatrixAdjustments(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	content := `steps:
+  - label: "Test"
+    command: "make test"
+    matrix:
+      setup:
+        os: [linux, windows]
+        arch: [amd64, arm64]
+      adjustments:
+        - with:
+            os: windows
+`
+
+	tests := []struct {
+		name           string
+		line           uint32
+		currentLine    string
+		expectedLabels []string
+	}{
+		{
+			name:           "adjustment item keys",
+			line:           10,
+			currentLine:    "          ",
+			expectedLabels: []string{"with", "skip", "soft_fail"},
+		},
+		{
+			name:           "dimension names inside with",
+			line:           10,
+			currentLine:    "            ",
+			expectedLabels: []string{"os", "arch"},
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(content, "\n")
+			contextLines := append([]string{}, lines[:tt.line]...)
+			contextLines = append(contextLines, tt.currentLine)
+
+			posCtx := &context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(tt.currentLine))},
+				CurrentLine:  tt.currentLine,
+				CharIndex:    len(tt.currentLine),
+				ContextLines: contextLines,
+				FullContent:  content,
+			}
+
+			completions := provider.GetCompletions(posCtx)
+
+			if len(completions) != len(tt.expectedLabels) {
+				t.Fatalf("Expected %d completions, got %d", len(tt.expectedLabels), len(completions))
+			}
+			for i, expected := range tt.expectedLabels {
+				if completions[i].Label != expected {
+					t.Errorf("Completion %d: expected %q, got %q", i, expected, completions[i].Label)
+				}
+			}
+		})
+	}
+}
+
+func TestAddPropertyCommitCharacters(t *testing.T) {
+	items := []protocol.CompletionItem{
+		{Label: "label", Kind: protocol.CompletionItemKindProperty},
+		{Label: "steps", Kind: protocol.CompletionItemKindProperty, InsertText: "steps:\n  - $0"},
+		{Label: "docker#v5.13.0", Kind: protocol.CompletionItemKindModule},
+	}
+
+	addPropertyCommitCharacters(items)
+
+	if len(items[0].CommitCharacters) != 1 || items[0].CommitCharacters[0] != ":" {
+		t.Errorf("Expected ':' commit character on plain property, got %v", items[0].CommitCharacters)
+	}
+	if len(items[1].CommitCharacters) != 0 {
+		t.Errorf("Expected no commit characters on snippet property, got %v", items[1].CommitCharacters)
+	}
+	if len(items[2].CommitCharacters) != 0 {
+		t.Errorf("Expected no commit characters on non-property item, got %v", items[2].CommitCharacters)
+	}
+}
+
+func TestCompletionProvider_RetryKeys(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	complete := func(content string, line uint32) []protocol.CompletionItem {
+		lines := strings.Split(content, "\n")
+		currentLine := lines[line]
+		return provider.GetCompletions(&context.PositionContext{
+			URI:          protocol.DocumentURI("file:///test.yml"),
+			Position:     protocol.Position{Line: line, Character: uint32(len(currentLine))},
+			CurrentLine:  currentLine,
+			CharIndex:    len(currentLine),
+			ContextLines: lines[:line+1],
+			FullContent:  content,
+		})
+	}
+
+	t.Run("both keys", func(t *testing.T) {
+		completions := complete("steps:\n  - command: make\n    retry:\n      ", 3)
+		var labels []string
+		for _, item := range completions {
+			labels = append(labels, item.Label)
+		}
+		if strings.Join(labels, ",") != "automatic,manual" {
+			t.Errorf("Expected automatic and manual, got %v", labels)
+		}
+	})
+
+	t.Run("existing key not offered", func(t *testing.T) {
+		completions := complete("steps:\n  - command: make\n    retry:\n      manual: false\n      ", 4)
+		if len(completions) != 1 || completions[0].Label != "automatic" {
+			t.Errorf("Expected only automatic, got %v", completions)
+		}
+	})
+
+	t.Run("value", func(t *testing.T) {
+		completions := complete("steps:\n  - command: make\n    retry:\n      manual: ", 3)
+		if len(completions) != 0 {
+			t.Errorf("Expected no completions for a retry value, got %d", len(completions))
+		}
+	})
+}
+
+func TestCompletionProvider_AgentTagValues(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	tests := []struct {
+		currentLine string
+		expected    []string
+	}{
+		{currentLine: "      os: ", expected: []string{"linux", "macos", "windows"}},
+		{currentLine: "      arch: ", expected: []string{"amd64", "arm64"}},
+		{currentLine: "      queue: ", expected: nil},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.currentLine, func(t *testing.T) {
+			content := "steps:\n  - command: make\n    agents:\n" + tt.currentLine
+			completions := provider.GetCompletions(&context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: 3, Character: uint32(len(tt.currentLine))},
+				CurrentLine:  tt.currentLine,
+				CharIndex:    len(tt.currentLine),
+				ContextLines: strings.Split(content, "\n"),
+				FullContent:  content,
+			})
+
+			var labels []string
+			for _, item := range completions {
+				labels = append(labels, item.Label)
+			}
+			if strings.Join(labels, ",") != strings.Join(tt.expected, ",") {
+				t.Errorf("Expected %v, got %v", tt.expected, labels)
+			}
+		})
+	}
+}
+
+func TestCompletionProvider_AgentTagsNotNested(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	for _, content := range []string{
+		"agents:\n  queue:\n    ",
+		"steps:\n  - command: make\n    agents:\n      os:\n        ",
+	} {
+		lines := strings.Split(content, "\n")
+		line := len(lines) - 1
+		completions := provider.GetCompletions(&context.PositionContext{
+			URI:          protocol.DocumentURI("file:///test.yml"),
+			Position:     protocol.Position{Line: uint32(line), Character: uint32(len(lines[line]))},
+			CurrentLine:  lines[line],
+			CharIndex:    len(lines[line]),
+			ContextLines: lines,
+			FullContent:  content,
+		})
+
+		if len(completions) != 0 {
+			t.Errorf("Expected no completions under an agent tag in %q, got %d", content, len(completions))
+		}
+	}
+}
+
+func TestCompletionProvider_AgentsScope(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	content := `agents:
+  queue: "default"
+  gpu: "true"
+
+steps:
+  - label: "Build"
+    command: "make build"
+    agents:
+      size: "large"
+`
+
+	tests := []struct {
+		name        string
+		line        uint32
+		currentLine string
+		docContains string
+	}{
+		{
+			name:        "root agents",
+			line:        3,
+			currentLine: "  ",
+			docContains: "every step",
+		},
+		{
+			name:        "step agents",
+			line:        9,
+			currentLine: "      ",
+			docContains: "overriding the pipeline-level",
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(content, "\n")
+			contextLines := append([]string{}, lines[:tt.line]...)
+			contextLines = append(contextLines, tt.currentLine)
+
+			posCtx := &context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(tt.currentLine))},
+				CurrentLine:  tt.currentLine,
+				CharIndex:    len(tt.currentLine),
+				ContextLines: contextLines,
+				FullContent:  content,
+			}
+
+			completions := provider.GetCompletions(posCtx)
+
+			found := make(map[string]protocol.CompletionItem)
+			for _, item := range completions {
+				found[item.Label] = item
+			}
+
+			for _, expected := range []string{"queue", "os", "gpu", "size"} {
+				if _, ok := found[expected]; !ok {
+					t.Errorf("Expected agent tag completion %q", expected)
+				}
+			}
+
+			queue := found["queue"]
+			doc, ok := queue.Documentation.(*protocol.MarkupContent)
+			if !ok || !strings.Contains(doc.Value, tt.docContains) {
+				t.Errorf("Expected queue documentation to mention %q, got %v", tt.docContains, queue.Documentation)
+			}
+
+			if _, ok := found["steps"]; ok {
+				t.Error("Did not expect top-level completions inside agents")
+			}
+		})
+	}
+}
+
+func TestCompletionProvider_RootEnv(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	content := `env:
+  BUILDKITE_CLEAN_CHECKOUT: "true"
+
+steps:
+  - command: "make"
+`
+
+	posCtx := func(currentLine string) *context.PositionContext {
+		return &context.PositionContext{
+			URI:          protocol.DocumentURI("file:///test.yml"),
+			Position:     protocol.Position{Line: 2, Character: uint32(len(currentLine))},
+			CurrentLine:  currentLine,
+			CharIndex:    len(currentLine),
+			ContextLines: []string{"env:", "  BUILDKITE_CLEAN_CHECKOUT: \"true\"", currentLine},
+			FullContent:  content,
+		}
+	}
+
+	t.Run("new entry", func(t *testing.T) {
+		completions := provider.GetCompletions(posCtx("  "))
+
+		found := make(map[string]protocol.CompletionItem)
+		for _, item := range completions {
+			found[item.Label] = item
+		}
+
+		snippet, ok := found["New variable"]
+		if !ok {
+			t.Fatal("Expected a snippet for a new environment variable")
+		}
+		if snippet.InsertText != `${1:NAME}: "${2:value}"` {
+			t.Errorf("Unexpected snippet insert text %q", snippet.InsertText)
+		}
+
+		if _, ok := found["BUILDKITE_GIT_CLONE_FLAGS"]; !ok {
+			t.Error("Expected well-known variable BUILDKITE_GIT_CLONE_FLAGS")
+		}
+		if _, ok := found["BUILDKITE_CLEAN_CHECKOUT"]; ok {
+			t.Error("Did not expect a variable that is already defined")
+		}
+		if _, ok := found["steps"]; ok {
+			t.Error("Did not expect top-level completions inside env")
+		}
+	})
+
+	t.Run("value", func(t *testing.T) {
+		completions := provider.GetCompletions(posCtx("  FOO: "))
+		if len(completions) != 0 {
+			t.Errorf("Expected no completions for an env value, got %d", len(completions))
+		}
+	})
+}
+
+func TestCompletionProvider_CompletionListIncomplete(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	complete := func(content string, line uint32) *protocol.CompletionList {
+		lines := strings.Split(content, "\n")
+		currentLine := lines[line]
+		return provider.GetCompletionList(&context.PositionContext{
+			URI:          protocol.DocumentURI("file:///test.yml"),
+			Position:     protocol.Position{Line: line, Character: uint32(len(currentLine))},
+			CurrentLine:  currentLine,
+			CharIndex:    len(currentLine),
+			ContextLines: lines[:line+1],
+			FullContent:  content,
+		})
+	}
+
+	t.Run("env names narrowed to the typed prefix", func(t *testing.T) {
+		list := complete("env:\n  BUILDKITE_GIT_CL", 1)
+		if !list.IsIncomplete {
+			t.Error("Expected env completions to be marked incomplete")
+		}
+		for _, item := range list.Items {
+			if item.Kind != protocol.CompletionItemKindSnippet && !strings.HasPrefix(item.Label, "BUILDKITE_GIT_CL") {
+				t.Errorf("Unexpected env completion %q for prefix BUILDKITE_GIT_CL", item.Label)
+			}
+		}
+		if len(list.Items) < 2 {
+			t.Errorf("Expected the new variable snippet and clone flag variables, got %d items", len(list.Items))
+		}
+	})
+
+	t.Run("plugin names narrowed to the typed prefix", func(t *testing.T) {
+		list := complete("steps:\n  - command: make\n    plugins:\n      - dock", 3)
+		if !list.IsIncomplete {
+			t.Error("Expected plugin completions to be marked incomplete")
+		}
+		found := false
+		for _, item := range list.Items {
+			if item.Kind == protocol.CompletionItemKindModule && !strings.HasPrefix(item.FilterText, "dock") {
+				t.Errorf("Unexpected plugin completion %q for prefix dock", item.Label)
+			}
+			if strings.HasPrefix(item.Label, "docker#") {
+				found = true
+			}
+		}
+		if !found {
+			t.Error("Expected the docker plugin to be offered")
+		}
+	})
+
+	t.Run("step properties are complete", func(t *testing.T) {
+		list := complete("steps:\n  - command: make\n    ", 2)
+		if list.IsIncomplete {
+			t.Error("Expected step property completions to be complete")
+		}
+	})
+}
+
+func TestLimitCompletions(t *testing.T) {
+	items := []protocol.CompletionItem{{Label: "New", Kind: protocol.CompletionItemKindSnippet}}
+	for i := 0; i < maxListedCompletions+10; i++ {
+		items = append(items, protocol.CompletionItem{Label: fmt.Sprintf("VAR_%d", i), Kind: protocol.CompletionItemKindVariable})
+	}
+
+	limited := limitCompletions(items, "var")
+	if len(limited) != maxListedCompletions+1 {
+		t.Fatalf("Expected %d items, got %d", maxListedCompletions+1, len(limited))
+	}
+	if limited[0].Label != "New" {
+		t.Errorf("Expected snippets to be kept, got %q first", limited[0].Label)
+	}
+}
+
+func TestCompletionProvider_GroupStepKeys(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	content := `steps:
+  - group: "Tests"
+    
+    steps:
+      - command: "make test"
+        `
+
+	complete := func(line uint32) map[string]bool {
+		lines := strings.Split(content, "\n")
+		currentLine := lines[line]
+		completions := provider.GetCompletions(&context.PositionContext{
+			URI:          protocol.DocumentURI("file:///test.yml"),
+			Position:     protocol.Position{Line: line, Character: uint32(len(currentLine))},
+			CurrentLine:  currentLine,
+			CharIndex:    len(currentLine),
+			ContextLines: lines[:line+1],
+			FullContent:  content,
+		})
+
+		found := make(map[string]bool)
+		for _, item := range completions {
+			found[item.Label] = true
+		}
+		return found
+	}
+
+	t.Run("group properties", func(t *testing.T) {
+		found := complete(2)
+		for _, key := range []string{"key", "depends_on", "allow_dependency_failure", "if", "label"} {
+			if !found[key] {
+				t.Errorf("Expected group key %q to be offered", key)
+			}
+		}
+		for _, key := range []string{"command", "agents", "plugins", "group", "steps"} {
+			if found[key] {
+				t.Errorf("Did not expect %q to be offered on a group", key)
+			}
+		}
+	})
+
+	t.Run("steps inside the group", func(t *testing.T) {
+		found := complete(5)
+		if !found["agents"] || !found["plugins"] {
+			t.Error("Expected step properties for a step inside a group")
+		}
+	})
+}
+
+func TestCompletionProvider_RequiredPluginOptionsFirst(t *testing.T) {
+	provider := newTestCompletionProvider()
+	provider.pluginRegistry.SetCatalog([]plugins.CatalogEntry{{
+		Name: "acme/deploy",
+		Configuration: map[string]any{
+			"properties": map[string]any{
+				"dry_run": map[string]any{"type": "boolean"},
+				"region":  map[string]any{"type": "string", "description": "Region to deploy to"},
+			},
+			"required": []any{"region"},
+		},
+	}})
+
+	items := provider.getPluginConfigCompletions(&context.ContextInfo{PluginName: "acme/deploy#v1.0.0"})
+
+	sortText := make(map[string]string)
+	for _, item := range items {
+		sortText[item.Label] = item.SortText
+		if item.Label == "region" && item.Detail != "(required) Region to deploy to" {
+			t.Errorf("Expected region to be marked required, got detail %q", item.Detail)
+		}
+	}
+	if sortText["region"] == "" || sortText["region"] >= sortText["dry_run"] {
+		t.Errorf("Expected required region to sort before dry_run, got %v", sortText)
+	}
+}
+
+func TestCompletionProvider_PluginConfigSnippets(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	items := provider.getPluginSnippetCompletions("docker-compose#v5.10.0")
+	if len(items) != 1 {
+		t.Fatalf("Expected one docker-compose snippet, got %v", items)
+	}
+	snippet := items[0]
+	if snippet.Label != "docker-compose configuration" || snippet.InsertTextFormat != protocol.InsertTextFormatSnippet {
+		t.Errorf("Unexpected snippet item %+v", snippet)
+	}
+	if !strings.HasPrefix(snippet.InsertText, "run: ${1:app}\nconfig:") {
+		t.Errorf("Expected the snippet to scaffold run and config, got %q", snippet.InsertText)
+	}
+
+	if items := provider.getPluginSnippetCompletions("acme/deploy#v1.0.0"); len(items) != 0 {
+		t.Errorf("Expected no snippet for an unrecognised plugin, got %v", items)
+	}
+}
+
+func TestRankRequired(t *testing.T) {
+	items := []protocol.CompletionItem{
+		{Label: "label", Kind: protocol.CompletionItemKindProperty},
+		{Label: "trigger", Kind: protocol.CompletionItemKindProperty},
+	}
+
+	unranked := rankRequired(items, nil)
+	if unranked[0].SortText != "" || unranked[1].SortText != "" {
+		t.Error("Expected no sort text when nothing is required")
+	}
+
+	ranked := rankRequired(items, []string{"trigger"})
+	if ranked[1].SortText >= ranked[0].SortText {
+		t.Errorf("Expected trigger to sort first, got %q and %q", ranked[0].SortText, ranked[1].SortText)
+	}
+}
+
+func TestCompletionProvider_StepKeyValues(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	content := `steps:
+  - label: ":hammer: Build"
+    key: "build"
+    command: "make build"
+
+  - label: ":hammer: Build"
+    key: 
+    command: "make build"
+
+  - command: "make test"
+    key: "
+
+  - block: ":rocket: Release?"
+    fields:
+      - text: "Version"
+        key: `
+
+	tests := []struct {
+		name     string
+		line     uint32
+		expected []string
+	}{
+		{name: "label collides with an existing key", line: 6, expected: []string{"build-2"}},
+		{name: "falls back to the command", line: 10, expected: []string{"make-test"}},
+		{name: "block field keys are not step keys", line: 15},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(content, "\n")
+			currentLine := lines[tt.line]
+
+			completions := provider.GetCompletions(&context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(currentLine))},
+				CurrentLine:  currentLine,
+				CharIndex:    len(currentLine),
+				ContextLines: lines[:tt.line+1],
+				FullContent:  content,
+			})
+
+			var labels []string
+			for _, item := range completions {
+				if item.Kind == protocol.CompletionItemKindValue {
+					labels = append(labels, item.Label)
+				}
+			}
+
+			if strings.Join(labels, ",") != strings.Join(tt.expected, ",") {
+				t.Errorf("Expected key suggestions %v, got %v", tt.expected, labels)
+			}
+		})
+	}
+}
+
+func TestCompletionProvider_NumericValues(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	content := `steps:
+  - command: "make test"
+    timeout_in_minutes: 
+  - command: "make build"
+    timeout_in_minutes: 3
+  - command: "make lint"
+    timeout_in_minutes:`
+
+	tests := []struct {
+		name     string
+		line     uint32
+		expected []string
+	}{
+		{name: "empty value", line: 2, expected: []string{"10", "30", "60", "120"}},
+		{name: "partial value", line: 4, expected: []string{"10", "30", "60", "120"}},
+		{name: "no space after the colon", line: 6},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(content, "\n")
+			currentLine := lines[tt.line]
+
+			completions := provider.GetCompletions(&context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(currentLine))},
+				CurrentLine:  currentLine,
+				CharIndex:    len(currentLine),
+				ContextLines: lines[:tt.line+1],
+				FullContent:  content,
+			})
+
+			var labels []string
+			for _, item := range completions {
+				if item.Kind == protocol.CompletionItemKindValue {
+					labels = append(labels, item.Label)
+					if !strings.HasSuffix(item.Detail, "minutes") && !strings.Contains(item.Detail, "minutes (") {
+						t.Errorf("Expected %s to note the unit, got %q", item.Label, item.Detail)
+					}
+				}
+			}
+
+			if strings.Join(labels, ",") != strings.Join(tt.expected, ",") {
+				t.Errorf("Expected values %v, got %v", tt.expected, labels)
+			}
+		})
+	}
+}
+
+func TestCompletionProvider_NewListItem(t *testing.T) {
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	schemaData := `{
+		"definitions": {
+			"inputStep": {"properties": {"fields": {"type": "array"}, "prompt": {"type": "string"}}}
+		}
+	}`
+	if err := os.WriteFile(schemaPath, []byte(schemaData), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	schemaLoader, err := schema.NewLoaderFromFile(schemaPath)
+	if err != nil {
+		t.Fatalf("Failed to load schema: %v", err)
+	}
+
+	provider := newTestCompletionProvider()
+	provider.SetSchemaLoader(schemaLoader)
+
+	tests := []struct {
+		name    string
+		content string
+		line    uint32
+		start   uint32
+		newText string
+	}{
+		{name: "line below steps", content: "steps:\n", line: 1, newText: "  - "},
+		{name: "end of the steps line", content: "steps:", start: 6, newText: "\n  - "},
+		{name: "existing items at the key's indent", content: "steps:\n\n- command: make", line: 1, newText: "- "},
+		{name: "array in the schema", content: "steps:\n  - input: \"Release\"\n    fields:\n    ", line: 3, newText: "      - "},
+		{name: "not a list", content: "env:\n  "},
+		{name: "string in the schema", content: "steps:\n  - input: \"Release\"\n    prompt:\n      ", line: 3},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(tt.content, "\n")
+			currentLine := lines[tt.line]
+			completions := provider.GetCompletions(&context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: tt.line, Character: uint32(len(currentLine))},
+				CurrentLine:  currentLine,
+				CharIndex:    len(currentLine),
+				ContextLines: lines[:tt.line+1],
+				FullContent:  tt.content,
+			})
+
+			var item *protocol.CompletionItem
+			for i := range completions {
+				if completions[i].Label == "- (new list item)" {
+					item = &completions[i]
+				}
+			}
+			if tt.newText == "" {
+				if item != nil {
+					t.Errorf("Did not expect a new list item, got %q", item.TextEdit.NewText)
+				}
+				return
+			}
+
+			if item == nil || item != &completions[0] {
+				t.Fatalf("Expected a new list item first, got %v", completions)
+			}
+			if item.TextEdit.NewText != tt.newText {
+				t.Errorf("Expected %q to be inserted, got %q", tt.newText, item.TextEdit.NewText)
+			}
+			expected := protocol.Range{
+				Start: protocol.Position{Line: tt.line, Character: tt.start},
+				End:   protocol.Position{Line: tt.line, Character: uint32(len(currentLine))},
+			}
+			if item.TextEdit.Range != expected {
+				t.Errorf("Expected the edit to cover %v, got %v", expected, item.TextEdit.Range)
+			}
+		})
+	}
+}
+
+func TestSlugify(t *testing.T) {
+	tests := map[string]string{
+		":hammer: Build & Test": "build-test",
+		"make build":            "make-build",
+		"  Deploy (prod)  ":     "deploy-prod",
+		":rocket:":              "",
+	}
+
+	for input, expected := range tests {
+		if got := slugify(input); got != expected {
+			t.Errorf("slugify(%q) = %q, want %q", input, got, expected)
+		}
+	}
+}
+
+func TestCompletionProvider_ConditionalExpressions(t *testing.T) {
+	provider := newTestCompletionProvider()
+
+	tests := []struct {
+		name        string
+		currentLine string
+		expected    []string
+	}{
+		{
+			name:        "root objects",
+			currentLine: "    if: ",
+			expected:    []string{"build", "organization", "pipeline"},
+		},
+		{
+			name:        "build members",
+			currentLine: `    if: build.`,
+			expected:    []string{"author", "branch", "commit", "env", "source", "tag"},
+		},
+		{
+			name:        "nested members after an operator",
+			currentLine: `    if: build.branch == "main" && build.pull_request.`,
+			expected:    []string{"base_branch", "draft", "labels"},
+		},
+		{
+			name:        "quoted expression",
+			currentLine: `    if: "pipeline.`,
+			expected:    []string{"slug", "default_branch"},
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			posCtx := &context.PositionContext{
+				URI:          protocol.DocumentURI("file:///test.yml"),
+				Position:     protocol.Position{Line: 2, Character: uint32(len(tt.currentLine))},
+				CurrentLine:  tt.currentLine,
+				CharIndex:    len(tt.currentLine),
+				ContextLines: []string{"steps:", "  - command: \"make\"", tt.currentLine},
+				FullContent:  "steps:\n  - command: \"make\"\n" + tt.currentLine,
+			}
+
+			found := make(map[string]protocol.CompletionItem)
+			for _, item := range provider.GetCompletions(posCtx) {
+				found[item.Label] = item
+			}
+
+			for _, expected := range tt.expected {
+				if _, ok := found[expected]; !ok {
+					t.Errorf("Expected completion %q in %v", expected, found)
+				}
+			}
+
+			if _, ok := found["label"]; ok {
+				t.Error("Did not expect step completions inside an if expression")
+			}
+		})
+	}
+
+	t.Run("inside a string literal", func(t *testing.T) {
+		currentLine := `    if: build.branch == "ma`
+		posCtx := &context.PositionContext{
+			CurrentLine:  currentLine,
+			CharIndex:    len(currentLine),
+			ContextLines: []string{"steps:", currentLine},
+			FullContent:  "steps:\n" + currentLine,
+		}
+
+		if items := provider.GetCompletions(posCtx); len(items) != 0 {
+			t.Errorf("Expected no completions inside a string literal, got %d", len(items))
+		}
+	})
+}
+
+func TestCompletionProvider_PropertyTypes(t *testing.T) {
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	schemaData := `{
+		"properties": {"env": {"type": "object"}},
+		"definitions": {
+			"commandStep": {"properties": {"label": {"type": "string"}, "parallelism": {"type": "integer"}}}
+		}
+	}`
+	if err := os.WriteFile(schemaPath, []byte(schemaData), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	schemaLoader, err := schema.NewLoaderFromFile(schemaPath)
+	if err != nil {
+		t.Fatalf("Failed to load schema: %v", err)
+	}
+
+	provider := newTestCompletionProvider()
+	provider.SetSchemaLoader(schemaLoader)
+
+	details := make(map[string]string)
+	for _, item := range provider.withPropertyTypes(provider.getStepCompletions()) {
+		details[item.Label] = item.Detail
+	}
+	if details["label"] != "Step label (string)" {
+		t.Errorf("Expected label to show its type, got %q", details["label"])
+	}
+	if details["command"] != "Command to run" {
+		t.Errorf("Expected command without a schema type to be left alone, got %q", details["command"])
+	}
+
+	for _, item := range provider.withPropertyTypes(provider.getTopLevelCompletions()) {
+		if item.Label == "env" && item.Detail != "Environment variables (object)" {
+			t.Errorf("Expected env to show its type, got %q", item.Detail)
+		}
+	}
+}
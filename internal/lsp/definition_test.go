@@ -60,6 +60,39 @@ func TestServer_Definition(t *testing.T) {
 			shouldFind:   true,
 			targetLine:   1, // Should point to the "Build App" step
 		},
+		{
+			name: "step reference to a keyed wait step",
+			content: `steps:
+  - command: "make build"
+  - wait: ~
+    key: "barrier"
+  - command: "make deploy"
+    depends_on:
+      - "barrier"
+    `,
+			line:         6,
+			char:         10, // Position on "barrier"
+			expectedLocs: 1,
+			shouldFind:   true,
+			targetLine:   2, // Should point to the wait step
+		},
+		{
+			name: "step reference to a block step keyed after its fields",
+			content: `steps:
+  - block: "Release?"
+    fields:
+      - text: "Version"
+        key: "version"
+    key: "release"
+  - command: "make release"
+    depends_on: "release"
+    `,
+			line:         7,
+			char:         18, // Position on "release"
+			expectedLocs: 1,
+			shouldFind:   true,
+			targetLine:   1, // Should point to the block step
+		},
 		{
 			name: "non-existent step reference",
 			content: `steps:
@@ -85,9 +118,24 @@ func TestServer_Definition(t *testing.T) {
       - docker-compose#v4.7.0:
           run: app
     `,
-			line:         5,
+			line:         4,
 			char:         10, // Position on "docker-compose"
-			expectedLocs: 0,  // Plugin definitions not implemented yet
+			expectedLocs: 1,  // The plugin's GitHub repository
+			shouldFind:   true,
+			targetLine:   0,
+		},
+		{
+			name: "plugin configuration key",
+			content: `steps:
+  - label: "Build"
+    command: "make build"
+    plugins:
+      - docker-compose#v4.7.0:
+          run: app
+    `,
+			line:         5,
+			char:         10, // Position on "run"
+			expectedLocs: 0,
 			shouldFind:   false,
 		},
 		{
@@ -270,6 +318,63 @@ func TestServer_DefinitionHelpers(t *testing.T) {
 }
 
 // Helper function to extract line numbers from locations for debugging
+func TestServer_DefinitionPluginRepository(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - command: "make build"
+    plugins:
+      - docker-compose#v4.7.0:
+          run: app
+      - "mcncl/example#v1.0.0"
+      - ./.buildkite/plugins/local:
+          enabled: true`
+
+	tests := []struct {
+		name        string
+		line        uint32
+		char        uint32
+		expectedURI string
+	}{
+		{name: "official plugin", line: 3, char: 10, expectedURI: "https://github.com/buildkite-plugins/docker-compose-buildkite-plugin"},
+		{name: "organisation plugin", line: 5, char: 15, expectedURI: "https://github.com/mcncl/example-buildkite-plugin"},
+		{name: "local plugin", line: 6, char: 20},
+		{name: "configuration value", line: 4, char: 16},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			lines := strings.Split(content, "\n")
+			ctx := &bkcontext.PositionContext{
+				URI:         "file:///test/.buildkite/pipeline.yml",
+				Position:    protocol.Position{Line: tt.line, Character: tt.char},
+				CurrentLine: lines[tt.line],
+				CharIndex:   int(tt.char),
+				FullContent: content,
+			}
+
+			locations := server.findDefinitions(ctx)
+
+			if tt.expectedURI == "" {
+				if len(locations) != 0 {
+					t.Errorf("Expected no locations, got %v", locations)
+				}
+				return
+			}
+
+			if len(locations) != 1 {
+				t.Fatalf("Expected 1 location, got %d", len(locations))
+			}
+			if string(locations[0].URI) != tt.expectedURI {
+				t.Errorf("Expected %s, got %s", tt.expectedURI, locations[0].URI)
+			}
+			if locations[0].Range != (protocol.Range{}) {
+				t.Errorf("Expected a zero range, got %+v", locations[0].Range)
+			}
+		})
+	}
+}
+
 func getLocationLines(locations []protocol.Location) []uint32 {
 	lines := make([]uint32, len(locations))
 	for i, loc := range locations {
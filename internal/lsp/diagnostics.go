@@ -0,0 +1,2728 @@
+package lsp
+
+import (
+	"fmt"
+	"maps"
+	"math"
+	"net/url"
+	"path"
+	"regexp"
+	"slices"
+	"sort"
+	"strings"
+	"unicode"
+	"unicode/utf16"
+	"unicode/utf8"
+
+	"go.lsp.dev/protocol"
+
+	"github.com/mcncl/buildkite-ls/internal/conditionals"
+	"github.com/mcncl/buildkite-ls/internal/envvars"
+	"github.com/mcncl/buildkite-ls/internal/parser"
+	"github.com/mcncl/buildkite-ls/internal/plugins"
+)
+
+// keyLocation records where a mapping key appears in the document
+type keyLocation struct {
+	Line   int
+	Column int
+	Length int
+}
+
+// Range returns the LSP range covering the key token
+func (loc *keyLocation) Range() protocol.Range {
+	return protocol.Range{
+		Start: protocol.Position{Line: uint32(loc.Line), Character: uint32(loc.Column)},
+		End:   protocol.Position{Line: uint32(loc.Line), Character: uint32(loc.Column + loc.Length)},
+	}
+}
+
+// findKeyInLines finds the first occurrence of a mapping key between startLine and endLine (inclusive)
+func (s *Server) findKeyInLines(key string, lines []string, startLine, endLine int) *keyLocation {
+	if startLine < 0 {
+		startLine = 0
+	}
+
+	for i := startLine; i <= endLine && i < len(lines); i++ {
+		content := strings.TrimLeft(lines[i], " \t")
+		// Keys can share a line with the list item marker (e.g. "- key: value")
+		if strings.HasPrefix(content, "- ") {
+			content = strings.TrimLeft(content[2:], " ")
+		}
+		column := len(lines[i]) - len(content)
+
+		for _, candidate := range []string{key, `"` + key + `"`, "'" + key + "'"} {
+			if strings.HasPrefix(content, candidate+":") {
+				return &keyLocation{Line: i, Column: column, Length: len(candidate)}
+			}
+		}
+	}
+
+	return nil
+}
+
+// findStepProperty finds a key that belongs directly to the step spanning startLine to endLine,
+// ignoring keys with the same name nested deeper (e.g. plugin options)
+func (s *Server) findStepProperty(key string, lines []string, startLine, endLine int) *keyLocation {
+	if startLine < 0 || startLine >= len(lines) {
+		return nil
+	}
+
+	first := strings.TrimLeft(lines[startLine], " \t")
+	column := len(lines[startLine]) - len(strings.TrimLeft(strings.TrimPrefix(first, "-"), " "))
+
+	for line := startLine; line <= endLine; line++ {
+		loc := s.findKeyInLines(key, lines, line, endLine)
+		if loc == nil {
+			return nil
+		}
+		if loc.Column == column {
+			return loc
+		}
+		line = loc.Line
+	}
+
+	return nil
+}
+
+// stepLineRange returns the first and last line of the step at stepIndex
+func (s *Server) stepLineRange(stepLines []int, stepIndex int, lines []string) (int, int) {
+	start := stepLines[stepIndex]
+	end := len(lines) - 1
+
+	if stepIndex+1 < len(stepLines) {
+		end = stepLines[stepIndex+1] - 1
+	} else {
+		// The last step ends where the steps section ends
+		for i := start + 1; i < len(lines); i++ {
+			if len(lines[i]) > 0 && lines[i][0] != ' ' && lines[i][0] != '\t' {
+				end = i - 1
+				break
+			}
+		}
+	}
+
+	return start, end
+}
+
+// pipelineStep is a step of the pipeline and the lines it spans
+type pipelineStep struct {
+	Data map[string]interface{}
+	// Index is the step's zero-based position in the steps list it's in
+	Index int
+	// Start and End are the first and last lines of the step
+	Start, End int
+	// InGroup is set for the steps inside a group
+	InGroup bool
+}
+
+// pipelineSteps returns the steps of the pipeline with the lines they span,
+// each group followed by the steps inside it. Steps are found at any
+// indentation, and steps whose lines can't be found are left out.
+func (s *Server) pipelineSteps(pipelineData map[string]interface{}, lines []string) []pipelineStep {
+	steps, ok := pipelineData["steps"].([]interface{})
+	if !ok {
+		return nil
+	}
+
+	// A steps: key nested in an anchored template isn't part of the pipeline
+	for i := range lines {
+		if loc := s.findKeyInLines("steps", lines, i, i); loc != nil && loc.Column == 0 {
+			return s.appendPipelineSteps(nil, steps, lines, loc, len(lines)-1, false)
+		}
+	}
+	return nil
+}
+
+// appendPipelineSteps appends the steps listed under the steps key at stepsLoc,
+// and those inside any groups among them
+func (s *Server) appendPipelineSteps(found []pipelineStep, steps []interface{}, lines []string, stepsLoc *keyLocation, end int, inGroup bool) []pipelineStep {
+	itemLines := s.findStepItemLines(lines, stepsLoc, end)
+	for index, item := range steps {
+		stepData, ok := item.(map[string]interface{})
+		if !ok || index >= len(itemLines) {
+			continue
+		}
+
+		start := itemLines[index]
+		stepEnd := s.findListItemEnd(lines, start, end)
+		found = append(found, pipelineStep{Data: stepData, Index: index, Start: start, End: stepEnd, InGroup: inGroup})
+
+		if nested, ok := stepData["steps"].([]interface{}); ok {
+			if loc := s.findStepProperty("steps", lines, start, stepEnd); loc != nil {
+				found = s.appendPipelineSteps(found, nested, lines, loc, stepEnd, true)
+			}
+		}
+	}
+	return found
+}
+
+// findStepItemLines returns the first line of each item in the block list
+// under the key at loc. Unlike findListItemLines it allows the items to be
+// indented no further than the key, as in a steps list written at column 0.
+func (s *Server) findStepItemLines(lines []string, loc *keyLocation, endLine int) []int {
+	var itemLines []int
+	itemIndent := -1
+
+	for i := loc.Line + 1; i <= endLine && i < len(lines); i++ {
+		trimmed := strings.TrimSpace(lines[i])
+		if trimmed == "" || strings.HasPrefix(trimmed, "#") {
+			continue
+		}
+
+		indent := s.getIndentLevel(lines[i])
+		isItem := trimmed == "-" || strings.HasPrefix(trimmed, "- ")
+		if indent < loc.Column || indent == loc.Column && !isItem {
+			break
+		}
+		if !isItem {
+			continue
+		}
+
+		if itemIndent == -1 {
+			itemIndent = indent
+		}
+		if indent == itemIndent {
+			itemLines = append(itemLines, i)
+		}
+	}
+
+	return itemLines
+}
+
+// findBlockEnd returns the last line of the block nested under the key on startLine
+func (s *Server) findBlockEnd(lines []string, startLine, endLine int) int {
+	indent := s.getIndentLevel(lines[startLine])
+	if strings.HasPrefix(strings.TrimLeft(lines[startLine], " \t"), "- ") {
+		// Content of a list item starts after the "- " marker
+		indent += 2
+	}
+
+	blockEnd := startLine
+	for i := startLine + 1; i <= endLine && i < len(lines); i++ {
+		if strings.TrimSpace(lines[i]) == "" {
+			continue
+		}
+		if s.getIndentLevel(lines[i]) <= indent {
+			break
+		}
+		blockEnd = i
+	}
+
+	return blockEnd
+}
+
+// validMetaDataKey matches the characters Buildkite allows in meta-data keys
+var validMetaDataKey = regexp.MustCompile(`^[a-z0-9_\-./]+$`)
+
+func (s *Server) validateMetaDataKeys(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData := step.Data
+
+		build, ok := stepData["build"].(map[string]interface{})
+		if !ok {
+			continue
+		}
+
+		metaData, ok := build["meta_data"].(map[string]interface{})
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		metaDataLoc := s.findKeyInLines("meta_data", lines, start, end)
+		if metaDataLoc == nil {
+			continue
+		}
+		blockEnd := s.findBlockEnd(lines, metaDataLoc.Line, end)
+
+		// Sort keys so diagnostics are reported in a stable order
+		keys := make([]string, 0, len(metaData))
+		for key := range metaData {
+			keys = append(keys, key)
+		}
+		sort.Strings(keys)
+
+		for _, key := range keys {
+			if validMetaDataKey.MatchString(key) {
+				continue
+			}
+
+			rng := protocol.Range{
+				Start: protocol.Position{Line: uint32(metaDataLoc.Line), Character: 0},
+				End:   protocol.Position{Line: uint32(metaDataLoc.Line), Character: 999},
+			}
+			if loc := s.findKeyInLines(key, lines, metaDataLoc.Line+1, blockEnd); loc != nil {
+				rng = loc.Range()
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityError,
+				Message:  fmt.Sprintf("Meta-data key '%s' contains invalid characters - only lowercase letters, digits, '-', '_', '.' and '/' are allowed", key),
+				Source:   "buildkite-ls",
+				Code:     "invalid-meta-data-key",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// stepKey returns the key other steps use to reference this step
+func stepKey(stepData map[string]interface{}) string {
+	for _, field := range []string{"key", "identifier", "id"} {
+		if key, ok := stepData[field].(string); ok && key != "" {
+			return key
+		}
+	}
+	return ""
+}
+
+// describeStep names a step in diagnostic messages by its label, or by its key
+// when it has no label, falling back to its position in the steps list
+func describeStep(stepData map[string]interface{}, stepIndex int) string {
+	for _, field := range []string{"label", "name", "group", "block", "input"} {
+		if label, ok := stepData[field].(string); ok && strings.TrimSpace(label) != "" {
+			return fmt.Sprintf("Step '%s'", label)
+		}
+	}
+	if key := stepKey(stepData); key != "" {
+		return fmt.Sprintf("Step '%s'", key)
+	}
+	return fmt.Sprintf("Step %d", stepIndex+1)
+}
+
+// stepDependencies returns the step keys listed in a step's depends_on
+func stepDependencies(stepData map[string]interface{}) []string {
+	var dependencies []string
+
+	switch dependsOn := stepData["depends_on"].(type) {
+	case string:
+		dependencies = append(dependencies, dependsOn)
+	case []interface{}:
+		for _, item := range dependsOn {
+			switch dep := item.(type) {
+			case string:
+				dependencies = append(dependencies, dep)
+			case map[string]interface{}:
+				if key, ok := dep["step"].(string); ok {
+					dependencies = append(dependencies, key)
+				}
+			}
+		}
+	}
+
+	return dependencies
+}
+
+// isSkipped reports whether a step's skip value means it never runs
+func isSkipped(stepData map[string]interface{}) bool {
+	switch skip := stepData["skip"].(type) {
+	case bool:
+		return skip
+	case string:
+		// A skip reason is treated as skipping the step
+		return skip != "" && skip != "false"
+	}
+	return false
+}
+
+func (s *Server) validateSkippedSteps(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	steps := s.pipelineSteps(pipelineData, lines)
+	skippedKeys := make(map[string]bool)
+
+	for _, step := range steps {
+		stepData, stepIndex := step.Data, step.Index
+		if !isSkipped(stepData) {
+			continue
+		}
+
+		if key := stepKey(stepData); key != "" {
+			skippedKeys[key] = true
+		}
+
+		start, end := step.Start, step.End
+		loc := s.findStepProperty("skip", lines, start, end)
+		if loc == nil {
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    loc.Range(),
+			Severity: protocol.DiagnosticSeverityInformation,
+			Message:  fmt.Sprintf("%s is skipped and will never run", describeStep(stepData, stepIndex)),
+			Source:   "buildkite-ls",
+			Code:     "skipped-step",
+		})
+	}
+
+	if len(skippedKeys) == 0 {
+		return diagnostics
+	}
+
+	for _, step := range steps {
+		stepData, stepIndex := step.Data, step.Index
+
+		for _, dependency := range stepDependencies(stepData) {
+			if !skippedKeys[dependency] {
+				continue
+			}
+
+			start, end := step.Start, step.End
+			rng := protocol.Range{
+				Start: protocol.Position{Line: uint32(start), Character: uint32(s.getIndentLevel(lines[start]))},
+				End:   protocol.Position{Line: uint32(start), Character: 999},
+			}
+			if loc := s.findStepProperty("depends_on", lines, start, end); loc != nil {
+				rng = loc.Range()
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  fmt.Sprintf("%s depends on '%s', which is skipped and will never run", describeStep(stepData, stepIndex), dependency),
+				Source:   "buildkite-ls",
+				Code:     "depends-on-skipped-step",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+func (s *Server) validateAllowDependencyFailure(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		if allow, ok := stepData["allow_dependency_failure"].(bool); !ok || !allow {
+			continue
+		}
+		if len(stepDependencies(stepData)) > 0 {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		loc := s.findStepProperty("allow_dependency_failure", lines, start, end)
+		if loc == nil {
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    loc.Range(),
+			Severity: protocol.DiagnosticSeverityWarning,
+			Message:  fmt.Sprintf("%s sets 'allow_dependency_failure' but has no 'depends_on', so it has no effect", describeStep(stepData, stepIndex)),
+			Source:   "buildkite-ls",
+			Code:     "allow-dependency-failure-without-depends-on",
+		})
+	}
+
+	return diagnostics
+}
+
+// validateIfAndBranches notes steps that filter on both an if condition and the
+// older branches setting. Teams that use both on purpose can turn it off with
+// severityOverrides.
+func (s *Server) validateIfAndBranches(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		if stepData["if"] == nil || stepData["branches"] == nil {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		loc := s.findStepProperty("branches", lines, start, end)
+		if loc == nil {
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    loc.Range(),
+			Severity: protocol.DiagnosticSeverityInformation,
+			Message:  fmt.Sprintf("%s uses both 'if' and 'branches' - consider moving the branch filter into 'if' (e.g. build.branch == \"main\")", describeStep(stepData, stepIndex)),
+			Source:   "buildkite-ls",
+			Code:     "if-and-branches",
+		})
+	}
+
+	return diagnostics
+}
+
+// validateConditionalMembers warns about `if` expressions that access a field
+// the build, pipeline or organization objects don't have, e.g.
+// build.pull_request.number instead of build.pull_request.id
+func (s *Server) validateConditionalMembers(lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for lineNum, line := range lines {
+		match := ifValuePattern.FindStringSubmatchIndex(line)
+		if match == nil {
+			continue
+		}
+
+		expression, offset := conditionalValue(line[match[2]:match[3]])
+		offset += match[2]
+		for _, access := range conditionals.MemberAccesses(expression) {
+			segment, object, ok := conditionals.UnknownMember(access)
+			if !ok {
+				continue
+			}
+
+			message := fmt.Sprintf("'%s' has no member '%s'", object, segment.Name)
+			if members := conditionals.Members(object); len(members) > 0 {
+				names := make([]string, len(members))
+				for i, member := range members {
+					names[i] = member.Name
+				}
+				message += fmt.Sprintf(" - expected one of: %s", strings.Join(names, ", "))
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range: protocol.Range{
+					Start: protocol.Position{Line: uint32(lineNum), Character: uint32(offset + segment.Start)},
+					End:   protocol.Position{Line: uint32(lineNum), Character: uint32(offset + segment.End)},
+				},
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  message,
+				Source:   "buildkite-ls",
+				Code:     "unknown-conditional-member",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// conditionalValue strips YAML quoting and trailing comments from an `if`
+// value, returning the expression and its offset within value. Escaped quotes
+// are replaced with strings of the same length so offsets still line up with
+// the document. Block scalars are skipped.
+func conditionalValue(value string) (string, int) {
+	if value == "" || value[0] == '|' || value[0] == '>' {
+		return "", 0
+	}
+
+	switch value[0] {
+	case '\'':
+		end := 1
+		for end < len(value) {
+			if value[end] == '\'' {
+				if end+1 < len(value) && value[end+1] == '\'' {
+					end += 2
+					continue
+				}
+				break
+			}
+			end++
+		}
+		return strings.ReplaceAll(value[1:end], "''", `\'`), 1
+	case '"':
+		end := 1
+		for end < len(value) && value[end] != '"' {
+			if value[end] == '\\' {
+				end++
+			}
+			end++
+		}
+		return strings.ReplaceAll(value[1:min(end, len(value))], `\"`, ` "`), 1
+	}
+
+	if comment := strings.Index(value, " #"); comment != -1 {
+		value = value[:comment]
+	}
+	return value, 0
+}
+
+func (s *Server) validateCommandAndCommands(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		start, end := step.Start, step.End
+		diagnostics = append(diagnostics, s.validateCommandEntries(stepData, stepIndex, lines, start, end)...)
+
+		_, hasCommand := stepData["command"]
+		_, hasCommands := stepData["commands"]
+		if !hasCommand || !hasCommands {
+			continue
+		}
+
+		rng := protocol.Range{
+			Start: protocol.Position{Line: uint32(start), Character: uint32(s.getIndentLevel(lines[start]))},
+			End:   protocol.Position{Line: uint32(start), Character: 999},
+		}
+
+		// Point at whichever of the two keys is declared second
+		commandLoc := s.findStepProperty("command", lines, start, end)
+		commandsLoc := s.findStepProperty("commands", lines, start, end)
+		second := commandsLoc
+		if commandLoc != nil && (commandsLoc == nil || commandLoc.Line > commandsLoc.Line) {
+			second = commandLoc
+		}
+		if second != nil {
+			rng = second.Range()
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    rng,
+			Severity: protocol.DiagnosticSeverityWarning,
+			Message:  fmt.Sprintf("%s declares both 'command' and 'commands' - use only one of them", describeStep(stepData, stepIndex)),
+			Source:   "buildkite-ls",
+			Code:     "command-and-commands",
+		})
+	}
+
+	return diagnostics
+}
+
+// validateCommandEntries checks that each entry in the list form of command or
+// commands is a string, since a nested list or mapping there is a mistake
+func (s *Server) validateCommandEntries(stepData map[string]interface{}, stepIndex int, lines []string, start, end int) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, key := range []string{"command", "commands"} {
+		entries, ok := stepData[key].([]interface{})
+		if !ok {
+			continue
+		}
+		loc := s.findStepProperty(key, lines, start, end)
+		if loc == nil {
+			continue
+		}
+		itemLines := s.findListItemLines(lines, loc.Line, end)
+
+		for entryIndex, entry := range entries {
+			if _, ok := entry.(string); ok {
+				continue
+			}
+			kind := valueKind(entry)
+
+			// Flow lists have no item lines, so fall back to the key
+			rng := loc.Range()
+			if entryIndex < len(itemLines) {
+				line := lines[itemLines[entryIndex]]
+				marker := strings.Index(line, "-")
+				rng = valueRange(lines, &keyLocation{Line: itemLines[entryIndex], Column: marker, Length: 0})
+				if rng.Start.Character == rng.End.Character {
+					// A nested block list or mapping starts on the lines below the marker
+					rng = (&keyLocation{Line: itemLines[entryIndex], Column: marker, Length: 1}).Range()
+				}
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityError,
+				Message: fmt.Sprintf("%s '%s' entry %d must be a string, not %s - quote it or split it into separate entries",
+					describeStep(stepData, stepIndex), key, entryIndex+1, kind),
+				Source: "buildkite-ls",
+				Code:   "invalid-command-entry",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// valueKind describes the type of a decoded YAML value for messages, e.g. "a list"
+func valueKind(value interface{}) string {
+	switch value.(type) {
+	case string:
+		return "a string"
+	case []interface{}:
+		return "a list"
+	case map[string]interface{}:
+		return "a mapping"
+	case bool:
+		return "a boolean"
+	case nil:
+		return "null"
+	default:
+		return "a number"
+	}
+}
+
+// valueRange returns the range of the scalar value following the key at loc
+func valueRange(lines []string, loc *keyLocation) protocol.Range {
+	line := lines[loc.Line]
+	start := loc.Column + loc.Length + 1
+	for start < len(line) && line[start] == ' ' {
+		start++
+	}
+
+	end := len(strings.TrimRight(line, " \t"))
+	if comment := strings.Index(line[start:], " #"); comment != -1 {
+		end = start + comment
+	}
+	if end < start {
+		end = start
+	}
+
+	return protocol.Range{
+		Start: protocol.Position{Line: uint32(loc.Line), Character: uint32(start)},
+		End:   protocol.Position{Line: uint32(loc.Line), Character: uint32(end)},
+	}
+}
+
+// agentQueue extracts the queue name from an agents value in map or "key=value" list form
+func agentQueue(agents interface{}) (string, bool) {
+	switch agentsValue := agents.(type) {
+	case map[string]interface{}:
+		queue, ok := agentsValue["queue"].(string)
+		return queue, ok
+	case []interface{}:
+		for _, item := range agentsValue {
+			if rule, ok := item.(string); ok && strings.HasPrefix(rule, "queue=") {
+				return strings.TrimPrefix(rule, "queue="), true
+			}
+		}
+	}
+	return "", false
+}
+
+func (s *Server) validateAgentQueues(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	if len(s.settings.AllowedQueues) == 0 {
+		return diagnostics
+	}
+
+	check := func(agents interface{}, start, end int) {
+		queue, ok := agentQueue(agents)
+		if !ok {
+			return
+		}
+		for _, allowed := range s.settings.AllowedQueues {
+			if queue == allowed {
+				return
+			}
+		}
+
+		rng := protocol.Range{
+			Start: protocol.Position{Line: uint32(start), Character: 0},
+			End:   protocol.Position{Line: uint32(start), Character: 999},
+		}
+		agentsLoc := s.findKeyInLines("agents", lines, start, end)
+		if agentsLoc != nil {
+			rng = agentsLoc.Range()
+			blockEnd := s.findBlockEnd(lines, agentsLoc.Line, end)
+			if loc := s.findKeyInLines("queue", lines, agentsLoc.Line+1, blockEnd); loc != nil {
+				rng = valueRange(lines, loc)
+			} else {
+				for i := agentsLoc.Line + 1; i <= blockEnd; i++ {
+					if column := strings.Index(lines[i], "queue="); column != -1 {
+						rng = protocol.Range{
+							Start: protocol.Position{Line: uint32(i), Character: uint32(column)},
+							End:   protocol.Position{Line: uint32(i), Character: uint32(column + len("queue=") + len(queue))},
+						}
+						break
+					}
+				}
+			}
+		}
+
+		message := fmt.Sprintf("Queue '%s' is not in the list of allowed queues", queue)
+		if suggestion := closestMatch(queue, s.settings.AllowedQueues); suggestion != "" {
+			message += fmt.Sprintf(" - did you mean '%s'?", suggestion)
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    rng,
+			Severity: protocol.DiagnosticSeverityWarning,
+			Message:  message,
+			Source:   "buildkite-ls",
+			Code:     "unknown-queue",
+		})
+	}
+
+	if agents, ok := pipelineData["agents"]; ok {
+		// Top-level agents are declared at column zero, outside the steps block
+		agentsLine := -1
+		for i, line := range lines {
+			if strings.HasPrefix(line, "agents:") {
+				agentsLine = i
+				break
+			}
+		}
+		if agentsLine != -1 {
+			check(agents, agentsLine, len(lines)-1)
+		}
+	}
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData := step.Data
+		agents, ok := stepData["agents"]
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		if loc := s.findStepProperty("agents", lines, start, end); loc != nil {
+			start = loc.Line
+		}
+		check(agents, start, end)
+	}
+
+	return diagnostics
+}
+
+// validateAgentTags reports agent tags whose value is a mapping or a list.
+// Agents are matched on flat tag: value pairs, so nesting is always a mistake.
+func (s *Server) validateAgentTags(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	check := func(agents interface{}, agentsLoc *keyLocation, end int) {
+		tags, ok := agents.(map[string]interface{})
+		if !ok || agentsLoc == nil {
+			return
+		}
+
+		blockEnd := s.findBlockEnd(lines, agentsLoc.Line, end)
+		for _, tag := range slices.Sorted(maps.Keys(tags)) {
+			switch tags[tag].(type) {
+			case map[string]interface{}, []interface{}:
+			default:
+				continue
+			}
+
+			rng := agentsLoc.Range()
+			if loc := s.findKeyInLines(tag, lines, agentsLoc.Line+1, blockEnd); loc != nil {
+				rng = loc.Range()
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityError,
+				Message: fmt.Sprintf("Agent tag '%s' must be a single value, not %s - agents can't be nested, so write it as '%s: value'",
+					tag, valueKind(tags[tag]), tag),
+				Source: "buildkite-ls",
+				Code:   "nested-agent-tag",
+			})
+		}
+	}
+
+	if agents, ok := pipelineData["agents"]; ok {
+		// Top-level agents are declared at column zero, outside the steps block
+		for i, line := range lines {
+			if strings.HasPrefix(line, "agents:") {
+				check(agents, &keyLocation{Line: i, Column: 0, Length: len("agents")}, len(lines)-1)
+				break
+			}
+		}
+	}
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData := step.Data
+		agents, ok := stepData["agents"]
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		check(agents, s.findStepProperty("agents", lines, start, end), end)
+	}
+
+	return diagnostics
+}
+
+// isStringList reports whether value is a list containing only strings
+func isStringList(value interface{}) bool {
+	list, ok := value.([]interface{})
+	if !ok {
+		return false
+	}
+	for _, item := range list {
+		if _, ok := item.(string); !ok {
+			return false
+		}
+	}
+	return true
+}
+
+func (s *Server) validateCacheSettings(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData := step.Data
+
+		cache, ok := stepData["cache"]
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		cacheLoc := s.findStepProperty("cache", lines, start, end)
+		if cacheLoc == nil {
+			continue
+		}
+		blockEnd := s.findBlockEnd(lines, cacheLoc.Line, end)
+
+		// Report at the offending sub-key when it can be found, otherwise at 'cache'
+		report := func(key, message string) {
+			rng := cacheLoc.Range()
+			if key != "" {
+				if loc := s.findKeyInLines(key, lines, cacheLoc.Line+1, blockEnd); loc != nil {
+					rng = loc.Range()
+				}
+			}
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityError,
+				Message:  message,
+				Source:   "buildkite-ls",
+				Code:     "invalid-cache",
+			})
+		}
+
+		switch cacheValue := cache.(type) {
+		case string:
+			// A single path is shorthand for paths: [path]
+		case []interface{}:
+			if !isStringList(cacheValue) {
+				report("", "Cache paths must be strings")
+			}
+		case map[string]interface{}:
+			if paths, ok := cacheValue["paths"]; ok {
+				if _, isString := paths.(string); !isString && !isStringList(paths) {
+					report("paths", "Cache 'paths' must be a string or a list of strings")
+				}
+			} else {
+				report("", "Cache must specify 'paths'")
+			}
+			if key, ok := cacheValue["key"]; ok {
+				if _, isString := key.(string); !isString {
+					report("key", "Cache 'key' must be a string")
+				}
+			}
+			if restoreKeys, ok := cacheValue["restore_keys"]; ok && !isStringList(restoreKeys) {
+				report("restore_keys", "Cache 'restore_keys' must be a list of strings")
+			}
+		default:
+			report("", "Cache must be a path, a list of paths, or an object with 'paths'")
+		}
+	}
+
+	return diagnostics
+}
+
+func (s *Server) validateDuplicateStepKeys(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	// Group keys and the keys of the steps inside groups share one namespace
+	// for depends_on, so nested steps are checked against the same map
+	firstDefined := make(map[string]protocol.Range)
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		key := stepKey(step.Data)
+		if key == "" {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		rng := protocol.Range{
+			Start: protocol.Position{Line: uint32(start), Character: uint32(s.getIndentLevel(lines[start]))},
+			End:   protocol.Position{Line: uint32(start), Character: 999},
+		}
+		for _, field := range []string{"key", "identifier", "id"} {
+			if loc := s.findStepProperty(field, lines, start, end); loc != nil {
+				rng = valueRange(lines, loc)
+				break
+			}
+		}
+
+		original, seen := firstDefined[key]
+		if !seen {
+			firstDefined[key] = rng
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    rng,
+			Severity: protocol.DiagnosticSeverityError,
+			Message:  fmt.Sprintf("Step key '%s' is already used by another step - step keys must be unique", key),
+			Source:   "buildkite-ls",
+			Code:     "duplicate-step-key",
+			// The URI is filled in by validateDocument, which knows the document being validated
+			RelatedInformation: []protocol.DiagnosticRelatedInformation{
+				{
+					Location: protocol.Location{Range: original},
+					Message:  "First defined here",
+				},
+			},
+		})
+	}
+
+	return diagnostics
+}
+
+// validateDuplicateFieldKeys flags block and input fields that reuse a key
+// within the same step, since only the last value would be kept
+func (s *Server) validateDuplicateFieldKeys(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		fields, ok := stepData["fields"].([]interface{})
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		fieldsLoc := s.findStepProperty("fields", lines, start, end)
+		if fieldsLoc == nil {
+			continue
+		}
+		itemLines := s.findListItemLines(lines, fieldsLoc.Line, end)
+
+		firstDefined := make(map[string]protocol.Range)
+		for fieldIndex, field := range fields {
+			fieldData, ok := field.(map[string]interface{})
+			if !ok || fieldIndex >= len(itemLines) {
+				continue
+			}
+			key, ok := fieldData["key"].(string)
+			if !ok || key == "" {
+				continue
+			}
+
+			itemLine := itemLines[fieldIndex]
+			loc := s.findKeyInLines("key", lines, itemLine, s.findListItemEnd(lines, itemLine, end))
+			if loc == nil {
+				continue
+			}
+			rng := valueRange(lines, loc)
+
+			original, seen := firstDefined[key]
+			if !seen {
+				firstDefined[key] = rng
+				continue
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityError,
+				Message:  fmt.Sprintf("%s field key '%s' is already used by another field - only the last value would be kept", describeStep(stepData, stepIndex), key),
+				Source:   "buildkite-ls",
+				Code:     "duplicate-field-key",
+				RelatedInformation: []protocol.DiagnosticRelatedInformation{
+					{
+						Location: protocol.Location{Range: original},
+						Message:  "First defined here",
+					},
+				},
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// validateTopLevelKeyOrder notes top-level keys that come before a key the
+// topLevelKeyOrder setting puts ahead of them. It's off unless an order is set.
+func (s *Server) validateTopLevelKeyOrder(lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	order := s.settings.TopLevelKeyOrder
+	if len(order) == 0 {
+		return diagnostics
+	}
+
+	latest := -1
+	for i, line := range lines {
+		line = strings.TrimRight(line, "\r")
+		if line == "---" {
+			latest = -1
+			continue
+		}
+		if line == "" || line[0] == ' ' || line[0] == '\t' || line[0] == '#' {
+			continue
+		}
+
+		key, keyLength, _, ok := rawMappingKey(line)
+		if !ok {
+			continue
+		}
+		rank := slices.Index(order, key)
+		if rank == -1 {
+			continue
+		}
+		if rank >= latest {
+			latest = rank
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range: protocol.Range{
+				Start: protocol.Position{Line: uint32(i), Character: 0},
+				End:   protocol.Position{Line: uint32(i), Character: uint32(keyLength)},
+			},
+			Severity: protocol.DiagnosticSeverityInformation,
+			Message: fmt.Sprintf("'%s' should come before '%s' - the preferred order of top-level keys is %s",
+				key, order[latest], strings.Join(order, ", ")),
+			Source: "buildkite-ls",
+			Code:   "top-level-key-order",
+		})
+	}
+
+	return diagnostics
+}
+
+// mappingScope tracks the keys seen in one block mapping during a raw key scan
+type mappingScope struct {
+	column int
+	keys   map[string]protocol.Range
+}
+
+// validateDuplicateMappingKeys scans the raw lines for a key repeated within the
+// same mapping. The YAML decoder rejects the whole document when that happens,
+// so the scan runs on the lines to point at each duplicate rather than at the
+// parse error.
+func (s *Server) validateDuplicateMappingKeys(lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	var scopes []mappingScope
+	blockScalarColumn := -1
+
+	for i, rawLine := range lines {
+		line := strings.TrimRight(rawLine, "\r")
+		content := strings.TrimLeft(line, " \t")
+		column := len(line) - len(content)
+		content = strings.TrimRight(content, " \t")
+
+		// The lines of a block scalar are text, not keys
+		if blockScalarColumn >= 0 {
+			if content == "" || column > blockScalarColumn {
+				continue
+			}
+			blockScalarColumn = -1
+		}
+
+		if content == "" || strings.HasPrefix(content, "#") {
+			continue
+		}
+		if content == "---" || content == "..." {
+			scopes = nil
+			continue
+		}
+
+		// Each list item starts a new mapping after its "- " marker
+		for content == "-" || strings.HasPrefix(content, "- ") {
+			scopes = closeScopes(scopes, column)
+			rest := strings.TrimLeft(content[1:], " ")
+			column += len(content) - len(rest)
+			content = rest
+		}
+		scopes = closeScopes(scopes, column)
+
+		key, keyLength, value, ok := rawMappingKey(content)
+		if !ok {
+			continue
+		}
+		if strings.HasPrefix(value, "|") || strings.HasPrefix(value, ">") {
+			blockScalarColumn = column
+		}
+
+		if len(scopes) == 0 || scopes[len(scopes)-1].column != column {
+			scopes = append(scopes, mappingScope{column: column, keys: make(map[string]protocol.Range)})
+		}
+		scope := scopes[len(scopes)-1]
+
+		rng := protocol.Range{
+			Start: protocol.Position{Line: uint32(i), Character: uint32(column)},
+			End:   protocol.Position{Line: uint32(i), Character: uint32(column + keyLength)},
+		}
+		original, seen := scope.keys[key]
+		if !seen {
+			scope.keys[key] = rng
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    rng,
+			Severity: protocol.DiagnosticSeverityError,
+			Message:  fmt.Sprintf("Key '%s' is already defined in this mapping - remove or rename the duplicate, as YAML doesn't allow a key to repeat", key),
+			Source:   "buildkite-ls",
+			Code:     "duplicate-key",
+			RelatedInformation: []protocol.DiagnosticRelatedInformation{
+				{
+					Location: protocol.Location{Range: original},
+					Message:  "First defined here",
+				},
+			},
+		})
+	}
+
+	return diagnostics
+}
+
+// closeScopes drops the mappings nested deeper than column, which a line at
+// column has ended
+func closeScopes(scopes []mappingScope, column int) []mappingScope {
+	for len(scopes) > 0 && scopes[len(scopes)-1].column > column {
+		scopes = scopes[:len(scopes)-1]
+	}
+	return scopes
+}
+
+// rawMappingKey splits a "key: value" line into its key, the length of the key
+// as written (including any quotes) and its value. Merge keys are skipped, since
+// repeating them isn't a mistake worth flagging.
+func rawMappingKey(content string) (string, int, string, bool) {
+	if content == "" || strings.ContainsRune("{[?&*!<", rune(content[0])) {
+		return "", 0, "", false
+	}
+
+	var key string
+	var keyLength, colon int
+	if quote := content[0]; quote == '"' || quote == '\'' {
+		end := strings.IndexByte(content[1:], quote)
+		if end == -1 {
+			return "", 0, "", false
+		}
+		key = content[1 : end+1]
+		keyLength = end + 2
+		colon = keyLength
+		if !strings.HasPrefix(content[colon:], ":") {
+			return "", 0, "", false
+		}
+	} else {
+		colon = strings.Index(content, ": ")
+		if colon == -1 {
+			if !strings.HasSuffix(content, ":") {
+				return "", 0, "", false
+			}
+			colon = len(content) - 1
+		}
+		key = strings.TrimRight(content[:colon], " ")
+		keyLength = len(key)
+		if key == "" || strings.Contains(key, " #") {
+			return "", 0, "", false
+		}
+	}
+
+	value := strings.TrimSpace(content[colon+1:])
+	if strings.HasPrefix(value, "#") {
+		value = ""
+	}
+	return key, keyLength, value, true
+}
+
+// setRelatedInformationURI points related information without a location URI at the validated document
+func setRelatedInformationURI(diagnostics []protocol.Diagnostic, uri protocol.DocumentURI) {
+	for i := range diagnostics {
+		for j := range diagnostics[i].RelatedInformation {
+			if diagnostics[i].RelatedInformation[j].Location.URI == "" {
+				diagnostics[i].RelatedInformation[j].Location.URI = uri
+			}
+		}
+	}
+}
+
+// checkTimeout validates a timeout_in_minutes value, returning the timeout and
+// any diagnostic. The same rules apply at pipeline and step level.
+func checkTimeout(value interface{}, rng protocol.Range, scope string) (float64, *protocol.Diagnostic) {
+	minutes, ok := value.(float64)
+	if ok && minutes == math.Trunc(minutes) && minutes > 0 {
+		return minutes, nil
+	}
+
+	return 0, &protocol.Diagnostic{
+		Range:    rng,
+		Severity: protocol.DiagnosticSeverityError,
+		Message:  fmt.Sprintf("%s 'timeout_in_minutes' must be a positive whole number of minutes", scope),
+		Source:   "buildkite-ls",
+		Code:     "invalid-timeout",
+	}
+}
+
+func (s *Server) validateTimeouts(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	var pipelineTimeout float64
+	if value, ok := pipelineData["timeout_in_minutes"]; ok {
+		line := 0
+		for i, content := range lines {
+			if strings.HasPrefix(content, "timeout_in_minutes:") {
+				line = i
+				break
+			}
+		}
+		rng := valueRange(lines, &keyLocation{Line: line, Length: len("timeout_in_minutes")})
+
+		timeout, diagnostic := checkTimeout(value, rng, "Pipeline")
+		if diagnostic != nil {
+			diagnostics = append(diagnostics, *diagnostic)
+		}
+		pipelineTimeout = timeout
+	}
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+
+		value, ok := stepData["timeout_in_minutes"]
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		rng := protocol.Range{
+			Start: protocol.Position{Line: uint32(start), Character: uint32(s.getIndentLevel(lines[start]))},
+			End:   protocol.Position{Line: uint32(start), Character: 999},
+		}
+		if loc := s.findStepProperty("timeout_in_minutes", lines, start, end); loc != nil {
+			rng = valueRange(lines, loc)
+		}
+
+		timeout, diagnostic := checkTimeout(value, rng, describeStep(stepData, stepIndex))
+		if diagnostic != nil {
+			diagnostics = append(diagnostics, *diagnostic)
+			continue
+		}
+
+		if pipelineTimeout > 0 && timeout > pipelineTimeout {
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  fmt.Sprintf("%s timeout of %.0f minutes exceeds the pipeline timeout of %.0f minutes", describeStep(stepData, stepIndex), timeout, pipelineTimeout),
+				Source:   "buildkite-ls",
+				Code:     "timeout-exceeds-pipeline",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+func (s *Server) validateGroupLabels(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+
+		group, ok := stepData["group"]
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		rng := protocol.Range{
+			Start: protocol.Position{Line: uint32(start), Character: uint32(s.getIndentLevel(lines[start]))},
+			End:   protocol.Position{Line: uint32(start), Character: 999},
+		}
+		if loc := s.findStepProperty("group", lines, start, end); loc != nil {
+			rng = loc.Range()
+		}
+
+		switch group := group.(type) {
+		case string, nil:
+			// A group can take its label from 'label' instead
+			name, _ := group.(string)
+			label, _ := stepData["label"].(string)
+			if strings.TrimSpace(name) != "" || strings.TrimSpace(label) != "" {
+				continue
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityInformation,
+				Message:  fmt.Sprintf("%s is a group without a label - give 'group' a descriptive name so it's easy to follow in the Buildkite UI", describeStep(stepData, stepIndex)),
+				Source:   "buildkite-ls",
+				Code:     "unlabeled-group",
+			})
+		default:
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityError,
+				Message:  fmt.Sprintf("%s 'group' must be a label string (or null for an unlabeled group)", describeStep(stepData, stepIndex)),
+				Source:   "buildkite-ls",
+				Code:     "invalid-group-label",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// deprecatedStepKeys maps deprecated step keys to a hint about their replacement
+var deprecatedStepKeys = []struct {
+	Key         string
+	Replacement string
+}{
+	{Key: "agent_query_rules", Replacement: "use 'agents' instead"},
+	{Key: "identifier", Replacement: "use 'key' instead"},
+	{Key: "id", Replacement: "use 'key' instead"},
+	{Key: "type", Replacement: "use the step type key directly (e.g. 'command', 'wait', 'block')"},
+}
+
+func (s *Server) validateDeprecatedKeys(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	schemaDeprecated := s.schemaDeprecatedStepKeys()
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData := step.Data
+
+		start, end := step.Start, step.End
+
+		// A key is only deprecated on the type of step the schema says it is
+		definitionDeprecated := schemaDeprecated[stepKind(stepData)+"Step"]
+		for _, key := range slices.Sorted(maps.Keys(definitionDeprecated)) {
+			if _, ok := stepData[key]; !ok {
+				continue
+			}
+
+			loc := s.findStepProperty(key, lines, start, end)
+			if loc == nil {
+				continue
+			}
+
+			message := fmt.Sprintf("'%s' is deprecated", key)
+			if description := definitionDeprecated[key]; description != "" {
+				message += " - " + description
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    loc.Range(),
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  message,
+				Source:   "buildkite-ls",
+				Code:     "deprecated-key",
+				Tags:     []protocol.DiagnosticTag{protocol.DiagnosticTagDeprecated},
+			})
+		}
+
+		for _, deprecated := range deprecatedStepKeys {
+			if _, ok := stepData[deprecated.Key]; !ok {
+				continue
+			}
+
+			loc := s.findStepProperty(deprecated.Key, lines, start, end)
+			if loc == nil {
+				continue
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    loc.Range(),
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  fmt.Sprintf("'%s' is deprecated - %s", deprecated.Key, deprecated.Replacement),
+				Source:   "buildkite-ls",
+				Code:     "deprecated-key",
+				Tags:     []protocol.DiagnosticTag{protocol.DiagnosticTagDeprecated},
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// schemaDeprecatedStepKeys returns the step keys the loaded schema marks
+// deprecated, keyed by step definition (such as "commandStep") and then by key,
+// mapped to their description. Keys in deprecatedStepKeys are left out since
+// they carry a more specific hint.
+func (s *Server) schemaDeprecatedStepKeys() map[string]map[string]string {
+	tabled := make(map[string]bool)
+	for _, entry := range deprecatedStepKeys {
+		tabled[entry.Key] = true
+	}
+
+	keys := make(map[string]map[string]string)
+	for _, property := range s.schemaLoader.DeprecatedProperties() {
+		// Only properties of step definitions such as commandStep or blockStep
+		definition := path.Base(strings.TrimSuffix(property.Path, "/properties/"+property.Name))
+		if !strings.HasSuffix(definition, "Step") || tabled[property.Name] {
+			continue
+		}
+		if keys[definition] == nil {
+			keys[definition] = make(map[string]string)
+		}
+		if _, exists := keys[definition][property.Name]; !exists {
+			keys[definition][property.Name] = property.Description
+		}
+	}
+	return keys
+}
+
+// stepKind returns the type of a step, or "" when it has no type or more than one.
+// Steps with only plugins run as command steps.
+func stepKind(stepData map[string]interface{}) string {
+	var kinds []string
+	if stepData["command"] != nil || stepData["commands"] != nil {
+		kinds = append(kinds, "command")
+	}
+	if _, ok := stepData["wait"]; ok {
+		kinds = append(kinds, "wait")
+	}
+	for _, kind := range []string{"block", "input", "trigger"} {
+		if stepData[kind] != nil {
+			kinds = append(kinds, kind)
+		}
+	}
+	if _, ok := stepData["group"]; ok {
+		kinds = append(kinds, "group")
+	}
+
+	switch {
+	case len(kinds) == 1:
+		return kinds[0]
+	case len(kinds) == 0 && stepData["plugins"] != nil:
+		return "command"
+	default:
+		return ""
+	}
+}
+
+// stepTypeKeys lists keys that only apply to some step types. Keys missing from
+// the table are shared by every step type or validated elsewhere.
+var stepTypeKeys = []struct {
+	Key       string
+	StepTypes []string
+}{
+	{Key: "agents", StepTypes: []string{"command"}},
+	{Key: "artifact_paths", StepTypes: []string{"command"}},
+	{Key: "cache", StepTypes: []string{"command"}},
+	{Key: "concurrency", StepTypes: []string{"command"}},
+	{Key: "concurrency_group", StepTypes: []string{"command"}},
+	{Key: "concurrency_method", StepTypes: []string{"command"}},
+	{Key: "matrix", StepTypes: []string{"command"}},
+	{Key: "parallelism", StepTypes: []string{"command"}},
+	{Key: "plugins", StepTypes: []string{"command"}},
+	{Key: "priority", StepTypes: []string{"command"}},
+	{Key: "retry", StepTypes: []string{"command"}},
+	{Key: "timeout_in_minutes", StepTypes: []string{"command"}},
+	{Key: "continue_on_failure", StepTypes: []string{"wait"}},
+	{Key: "fields", StepTypes: []string{"block", "input"}},
+	{Key: "prompt", StepTypes: []string{"block", "input"}},
+	{Key: "blocked_state", StepTypes: []string{"block"}},
+	{Key: "async", StepTypes: []string{"trigger"}},
+	{Key: "build", StepTypes: []string{"trigger"}},
+	{Key: "steps", StepTypes: []string{"group"}},
+}
+
+func (s *Server) validateStepTypeKeys(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+
+		// Steps without a single type are reported by validateSingleStep
+		kind := stepKind(stepData)
+		if kind == "" {
+			continue
+		}
+
+		start, end := step.Start, step.End
+
+		for _, entry := range stepTypeKeys {
+			if _, ok := stepData[entry.Key]; !ok || slices.Contains(entry.StepTypes, kind) {
+				continue
+			}
+
+			loc := s.findStepProperty(entry.Key, lines, start, end)
+			if loc == nil {
+				continue
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    loc.Range(),
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message: fmt.Sprintf("%s is a %s step - '%s' only applies to %s steps",
+					describeStep(stepData, stepIndex), kind, entry.Key, strings.Join(entry.StepTypes, " and ")),
+				Source: "buildkite-ls",
+				Code:   "key-not-valid-for-step-type",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// groupStepKeys are the keys the schema's groupStep definition accepts
+var groupStepKeys = []string{
+	"allow_dependency_failure", "depends_on", "group", "id", "identifier", "if",
+	"if_changed", "key", "label", "notify", "skip", "steps",
+}
+
+// validateGroupKeys flags keys on a group step that only apply to the steps
+// inside it. Keys covered by stepTypeKeys or deprecatedStepKeys are reported by
+// their own checks.
+func (s *Server) validateGroupKeys(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	reported := make(map[string]bool)
+	for _, entry := range stepTypeKeys {
+		reported[entry.Key] = true
+	}
+	for _, entry := range deprecatedStepKeys {
+		reported[entry.Key] = true
+	}
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		if stepKind(stepData) != "group" {
+			continue
+		}
+
+		start, end := step.Start, step.End
+
+		for _, key := range slices.Sorted(maps.Keys(stepData)) {
+			if reported[key] || slices.Contains(groupStepKeys, key) {
+				continue
+			}
+
+			loc := s.findStepProperty(key, lines, start, end)
+			if loc == nil {
+				continue
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    loc.Range(),
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  fmt.Sprintf("%s is a group step - '%s' isn't valid on a group, set it on the steps inside instead", describeStep(stepData, stepIndex), key),
+				Source:   "buildkite-ls",
+				Code:     "key-not-valid-for-group",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// validateNestedGroups flags group steps inside another group, which Buildkite
+// rejects when the pipeline is uploaded
+func (s *Server) validateNestedGroups(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		// A group inside a group is reported with the group it's in
+		if step.InGroup || stepKind(stepData) != "group" {
+			continue
+		}
+
+		children, _ := stepData["steps"].([]interface{})
+		var nestedGroups []map[string]interface{}
+		for _, child := range collectAllSteps(children) {
+			if childData, ok := child.(map[string]interface{}); ok && stepKind(childData) == "group" {
+				nestedGroups = append(nestedGroups, childData)
+			}
+		}
+		if len(nestedGroups) == 0 {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		groupRanges := s.findNestedGroupRanges(lines, start, end)
+
+		for i, groupData := range nestedGroups {
+			if i >= len(groupRanges) {
+				break
+			}
+
+			inner := "the inner group"
+			if label, ok := groupData["group"].(string); ok && strings.TrimSpace(label) != "" {
+				inner = fmt.Sprintf("'%s'", label)
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    groupRanges[i],
+				Severity: protocol.DiagnosticSeverityError,
+				Message: fmt.Sprintf("%s contains another group - Buildkite doesn't support nested groups, move %s to the top level of steps",
+					describeStep(stepData, stepIndex), inner),
+				Source: "buildkite-ls",
+				Code:   "nested-group",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// findNestedGroupRanges returns the range of each group line nested anywhere
+// inside the group spanning start to end, in the order collectAllSteps visits them
+func (s *Server) findNestedGroupRanges(lines []string, start, end int) []protocol.Range {
+	var ranges []protocol.Range
+
+	var walk func(start, end int)
+	walk = func(start, end int) {
+		stepsLoc := s.findStepProperty("steps", lines, start, end)
+		if stepsLoc == nil {
+			return
+		}
+		for _, itemLine := range s.findListItemLines(lines, stepsLoc.Line, end) {
+			itemEnd := s.findListItemEnd(lines, itemLine, end)
+			if loc := s.findStepProperty("group", lines, itemLine, itemEnd); loc != nil {
+				ranges = append(ranges, protocol.Range{
+					Start: protocol.Position{Line: uint32(loc.Line), Character: uint32(loc.Column)},
+					End:   protocol.Position{Line: uint32(loc.Line), Character: uint32(len(strings.TrimRight(lines[loc.Line], " \t")))},
+				})
+			}
+			walk(itemLine, itemEnd)
+		}
+	}
+	walk(start, end)
+
+	return ranges
+}
+
+// retryManualKeys maps each key of a retry.manual mapping to its expected type
+var retryManualKeys = map[string]string{
+	"allowed":          "boolean",
+	"permit_on_passed": "boolean",
+	"reason":           "string",
+}
+
+// validateRetryManual checks the keys and value types of a retry.manual mapping
+func (s *Server) validateRetryManual(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		retry, ok := stepData["retry"].(map[string]interface{})
+		if !ok {
+			continue
+		}
+		manual, ok := retry["manual"].(map[string]interface{})
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		retryLoc := s.findStepProperty("retry", lines, start, end)
+		if retryLoc == nil {
+			continue
+		}
+		manualLoc := s.findKeyInLines("manual", lines, retryLoc.Line+1, s.findBlockEnd(lines, retryLoc.Line, end))
+		if manualLoc == nil {
+			continue
+		}
+		manualEnd := s.findBlockEnd(lines, manualLoc.Line, end)
+
+		for _, key := range slices.Sorted(maps.Keys(manual)) {
+			// Flow mappings keep their keys on the manual line, so fall back to it
+			loc := s.findKeyInLines(key, lines, manualLoc.Line+1, manualEnd)
+			keyRange, valRange := manualLoc.Range(), manualLoc.Range()
+			if loc != nil {
+				keyRange, valRange = loc.Range(), valueRange(lines, loc)
+			}
+
+			expected, known := retryManualKeys[key]
+			if !known {
+				diagnostics = append(diagnostics, protocol.Diagnostic{
+					Range:    keyRange,
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  fmt.Sprintf("%s 'retry.manual' doesn't support '%s' - use allowed, permit_on_passed or reason", describeStep(stepData, stepIndex), key),
+					Source:   "buildkite-ls",
+					Code:     "invalid-retry-manual",
+				})
+				continue
+			}
+
+			value := manual[key]
+			_, isBool := value.(bool)
+			_, isString := value.(string)
+			if (expected == "boolean" && isBool) || (expected == "string" && isString) {
+				continue
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    valRange,
+				Severity: protocol.DiagnosticSeverityError,
+				Message:  fmt.Sprintf("%s 'retry.manual.%s' must be a %s", describeStep(stepData, stepIndex), key, expected),
+				Source:   "buildkite-ls",
+				Code:     "invalid-retry-manual",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// validateSoftFailRetry notes command steps that soft fail on any exit status
+// but also retry automatically. Retries still run before the step is marked as
+// soft failed, which is rarely what was intended.
+func (s *Server) validateSoftFailRetry(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		if stepKind(stepData) != "command" {
+			continue
+		}
+		if softFail, _ := stepData["soft_fail"].(bool); !softFail {
+			continue
+		}
+		retry, ok := stepData["retry"].(map[string]interface{})
+		if !ok {
+			continue
+		}
+		if automatic, exists := retry["automatic"]; !exists || automatic == nil || automatic == false {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		loc := s.findStepProperty("retry", lines, start, end)
+		if loc == nil {
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    loc.Range(),
+			Severity: protocol.DiagnosticSeverityInformation,
+			Message:  fmt.Sprintf("%s soft fails on any exit status and also retries automatically - failed jobs are retried before being soft failed, which may not behave as expected", describeStep(stepData, stepIndex)),
+			Source:   "buildkite-ls",
+			Code:     "soft-fail-with-retry",
+		})
+	}
+
+	return diagnostics
+}
+
+// validateTriggerAsync checks that a trigger step's async flag is a boolean rather
+// than a quoted "true" or "false"
+func (s *Server) validateTriggerAsync(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		if stepKind(stepData) != "trigger" {
+			continue
+		}
+		async, ok := stepData["async"]
+		if !ok {
+			continue
+		}
+		if _, isBool := async.(bool); isBool {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		loc := s.findStepProperty("async", lines, start, end)
+		if loc == nil {
+			continue
+		}
+
+		message := fmt.Sprintf("%s 'async' must be true or false", describeStep(stepData, stepIndex))
+		if value, isString := async.(string); isString {
+			message = fmt.Sprintf("%s 'async' must be a boolean, not the string %q - remove the quotes", describeStep(stepData, stepIndex), value)
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    valueRange(lines, loc),
+			Severity: protocol.DiagnosticSeverityError,
+			Message:  message,
+			Source:   "buildkite-ls",
+			Code:     "invalid-trigger-async",
+		})
+	}
+
+	return diagnostics
+}
+
+// validateIndentation flags the first line that nests by a different number of
+// spaces than the rest of the document. Mixed widths are valid YAML, but make it
+// easy to attach a key to the wrong parent.
+func (s *Server) validateIndentation(lines []string) []protocol.Diagnostic {
+	indentation := parser.AnalyzeIndentation(lines)
+	line := indentation.InconsistentLine
+	if line < 0 {
+		return nil
+	}
+
+	rng := protocol.Range{
+		Start: protocol.Position{Line: uint32(line), Character: uint32(indentation.Indents[line])},
+		End:   protocol.Position{Line: uint32(line), Character: uint32(len(strings.TrimRight(lines[line], " ")))},
+	}
+
+	return []protocol.Diagnostic{{
+		Range:    rng,
+		Severity: protocol.DiagnosticSeverityInformation,
+		Message: fmt.Sprintf("This line is indented %d spaces deeper than its parent, but the rest of the document uses %d - check it's nested where you expect",
+			indentation.Step, indentation.Unit),
+		Source: "buildkite-ls",
+		Code:   "inconsistent-indentation",
+	}}
+}
+
+// invisibleCharacterNames names the invisible characters most often pasted in
+// from rich text editors
+var invisibleCharacterNames = map[rune]string{
+	'\u00a0': "no-break space",
+	'\u00ad': "soft hyphen",
+	'\u200b': "zero-width space",
+	'\u200c': "zero-width non-joiner",
+	'\u200d': "zero-width joiner",
+	'\u200e': "left-to-right mark",
+	'\u200f': "right-to-left mark",
+	'\u2028': "line separator",
+	'\u2029': "paragraph separator",
+	'\u202f': "narrow no-break space",
+	'\u2060': "word joiner",
+	'\u3000': "ideographic space",
+	'\ufeff': "zero-width no-break space",
+}
+
+// validateInvisibleCharacters warns about control, format and non-standard
+// space characters. They're easy to paste in unnoticed and make commands fail
+// in ways that are hard to see in the editor.
+func (s *Server) validateInvisibleCharacters(lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for lineNum, line := range lines {
+		if strings.HasPrefix(strings.TrimSpace(line), "#") {
+			continue
+		}
+
+		// Columns are counted in UTF-16 code units, as LSP positions are
+		column := 0
+		previous := rune(0)
+		for _, r := range line {
+			start := column
+			column += utf16.RuneLen(r)
+
+			invisible := r != ' ' && r != '\t' && unicode.In(r, unicode.Cc, unicode.Cf, unicode.Zs, unicode.Zl, unicode.Zp)
+			switch {
+			case r == '\ufeff' && lineNum == 0 && start == 0:
+				// A byte order mark at the start of the file is harmless
+				invisible = false
+			case r == '\u200d' && unicode.Is(unicode.So, previous):
+				// Joins emoji into a single glyph, e.g. in labels
+				invisible = false
+			}
+			previous = r
+			if !invisible {
+				continue
+			}
+
+			name := ""
+			if known, ok := invisibleCharacterNames[r]; ok {
+				name = fmt.Sprintf(" (%s)", known)
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range: protocol.Range{
+					Start: protocol.Position{Line: uint32(lineNum), Character: uint32(start)},
+					End:   protocol.Position{Line: uint32(lineNum), Character: uint32(column)},
+				},
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  fmt.Sprintf("Invisible character U+%04X%s - it can break commands at runtime, so replace it with a plain space or delete it", r, name),
+				Source:   "buildkite-ls",
+				Code:     "invisible-character",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// findListItemLines returns the line of each item in the block list nested under the key on keyLine
+func (s *Server) findListItemLines(lines []string, keyLine, endLine int) []int {
+	var itemLines []int
+	itemIndent := -1
+
+	for i := keyLine + 1; i <= s.findBlockEnd(lines, keyLine, endLine); i++ {
+		trimmed := strings.TrimSpace(lines[i])
+		if trimmed != "-" && !strings.HasPrefix(trimmed, "- ") {
+			continue
+		}
+
+		indent := s.getIndentLevel(lines[i])
+		if itemIndent == -1 {
+			itemIndent = indent
+		}
+		if indent == itemIndent {
+			itemLines = append(itemLines, i)
+		}
+	}
+
+	return itemLines
+}
+
+// findListItemEnd returns the last line of the list item starting on itemLine
+func (s *Server) findListItemEnd(lines []string, itemLine, endLine int) int {
+	indent := s.getIndentLevel(lines[itemLine])
+
+	itemEnd := itemLine
+	for i := itemLine + 1; i <= endLine && i < len(lines); i++ {
+		if strings.TrimSpace(lines[i]) == "" {
+			continue
+		}
+		if s.getIndentLevel(lines[i]) <= indent {
+			break
+		}
+		itemEnd = i
+	}
+
+	return itemEnd
+}
+
+func (s *Server) validatePluginEntries(entries []parser.PluginEntry, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, entry := range entries {
+		var message string
+		switch plugin := entry.Value.(type) {
+		case string:
+			// A bare plugin reference without configuration
+			continue
+		case map[string]interface{}:
+			if len(plugin) == 1 {
+				continue
+			}
+			if len(plugin) == 0 {
+				message = fmt.Sprintf("Plugin %d in step %d is empty - each plugin must be a single plugin reference", entry.Index+1, entry.Step+1)
+			} else {
+				message = fmt.Sprintf("Plugin %d in step %d has %d keys - each plugin must be a single plugin reference (check the indentation of its configuration)", entry.Index+1, entry.Step+1, len(plugin))
+			}
+		default:
+			message = fmt.Sprintf("Plugin %d in step %d must be a plugin reference or a map of a plugin reference to its configuration", entry.Index+1, entry.Step+1)
+		}
+
+		line := entry.Position.Line
+		if line < 0 || line >= len(lines) {
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range: protocol.Range{
+				Start: protocol.Position{Line: uint32(line), Character: uint32(s.getIndentLevel(lines[line]))},
+				End:   protocol.Position{Line: uint32(s.findListItemEnd(lines, line, len(lines)-1)), Character: 999},
+			},
+			Severity: protocol.DiagnosticSeverityError,
+			Message:  message,
+			Source:   "buildkite-ls",
+			Code:     "invalid-plugin-entry",
+		})
+	}
+
+	return diagnostics
+}
+
+// pluginIdentity reduces a plugin reference to the org/name it resolves to, so
+// "docker#v5.13.0" and "buildkite-plugins/docker" compare equal. Local paths and
+// full URLs are returned without their version.
+func pluginIdentity(ref string) string {
+	reference := parsePluginReference(ref)
+	if reference.Org == "" {
+		return reference.Name
+	}
+	return reference.Org + "/" + reference.Name
+}
+
+func (s *Server) validateAllowedPlugins(references []PluginReference) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	if len(s.settings.AllowedPlugins) == 0 {
+		return diagnostics
+	}
+
+	allowed := make(map[string]bool)
+	for _, plugin := range s.settings.AllowedPlugins {
+		allowed[pluginIdentity(plugin)] = true
+	}
+
+	for _, reference := range references {
+		if allowed[pluginIdentity(reference.Ref)] {
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    reference.Range,
+			Severity: protocol.DiagnosticSeverityError,
+			Message:  fmt.Sprintf("Plugin '%s' is not in the list of allowed plugins", reference.Ref),
+			Source:   "buildkite-ls",
+			Code:     "plugin-not-allowed",
+		})
+	}
+
+	return diagnostics
+}
+
+func (s *Server) validateUnpinnedPlugins(references []PluginReference) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, reference := range references {
+		// Local plugins are checked out with the repository, so there's nothing to pin
+		if reference.Version != "" || reference.Local {
+			continue
+		}
+
+		ref := strings.TrimSuffix(reference.Ref, "#")
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    reference.Range,
+			Severity: protocol.DiagnosticSeverityInformation,
+			Message:  fmt.Sprintf("Plugin '%s' isn't pinned to a version - add a version (e.g. '%s#v1.0.0') so builds are reproducible", ref, ref),
+			Source:   "buildkite-ls",
+			Code:     "unpinned-plugin",
+		})
+	}
+
+	return diagnostics
+}
+
+func (s *Server) validateUnknownPluginOptions(references []PluginReference) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, reference := range references {
+		options, ok := reference.Config.(map[string]interface{})
+		if !ok {
+			continue
+		}
+
+		// Only plugins whose schema is already loaded are checked, and only if
+		// the schema lists its options without allowing others, or allows none
+		pluginSchema := s.pluginRegistry.LoadedPluginSchema(reference.Ref)
+		if pluginSchema == nil {
+			continue
+		}
+		properties, hasProperties := pluginSchema.Configuration["properties"].(map[string]interface{})
+		additional, set := pluginSchema.Configuration["additionalProperties"]
+		if set && additional != false || !set && !hasProperties {
+			continue
+		}
+		known := slices.Sorted(maps.Keys(properties))
+
+		for _, option := range slices.Sorted(maps.Keys(options)) {
+			if _, exists := properties[option]; exists {
+				continue
+			}
+
+			// Options merged in from an anchor aren't written here, so are
+			// reported at the reference
+			rng := reference.Range
+			if written, ok := pluginOption(reference.Options, option); ok {
+				rng = written.Range()
+			}
+
+			message := fmt.Sprintf("Plugin '%s' doesn't have a '%s' option", reference.Ref, option)
+			if suggestion := closestMatch(option, known); suggestion != "" {
+				message += fmt.Sprintf(" - did you mean '%s'?", suggestion)
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  message,
+				Source:   "buildkite-ls",
+				Code:     "unknown-plugin-option",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// pluginOption returns the location of the option called name where it's
+// written in a plugin's configuration
+func pluginOption(options []parser.PluginOption, name string) (keyLocation, bool) {
+	for _, option := range options {
+		if option.Name == name {
+			return keyLocation{Line: option.Position.Line, Column: option.Position.Character, Length: option.Length}, true
+		}
+	}
+	return keyLocation{}, false
+}
+
+// pluginTypeNames describes the JSON schema types of plugin options for messages
+var pluginTypeNames = map[string]string{
+	"array":   "a list",
+	"boolean": "a boolean",
+	"integer": "an integer",
+	"number":  "a number",
+	"object":  "a mapping",
+	"string":  "a string",
+}
+
+// validatePluginOptionTypes checks the option values of plugins with bundled
+// option types, such as docker and docker-compose, reporting values of the
+// wrong type at the value itself
+func (s *Server) validatePluginOptionTypes(references []PluginReference, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	for _, reference := range references {
+		options, ok := reference.Config.(map[string]interface{})
+		if !ok {
+			continue
+		}
+		types, ok := plugins.OptionTypes(reference.Ref)
+		if !ok {
+			continue
+		}
+
+		for _, option := range slices.Sorted(maps.Keys(options)) {
+			expected, known := types[option]
+			if !known || plugins.HasType(options[option], expected) {
+				continue
+			}
+
+			// Options merged in from an anchor aren't written here, so are
+			// reported at the reference
+			rng := reference.Range
+			if written, ok := pluginOption(reference.Options, option); ok {
+				rng = valueRange(lines, &written)
+				if rng.Start.Character == rng.End.Character {
+					// A block list or mapping starts on the lines below the key
+					rng = written.Range()
+				}
+			}
+
+			names := make([]string, len(expected))
+			for i, name := range expected {
+				names[i] = pluginTypeNames[name]
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityError,
+				Message: fmt.Sprintf("Plugin '%s' option '%s' should be %s, not %s",
+					reference.Ref, option, strings.Join(names, " or "), valueKind(options[option])),
+				Source: "buildkite-ls",
+				Code:   "plugin-option-type",
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// defaultMaxSteps is the step count above which a pipeline is reported as large
+const defaultMaxSteps = 500
+
+// collectAllSteps flattens a steps list, including the steps nested in groups.
+// Groups are counted as steps themselves, followed by their children.
+func collectAllSteps(steps []interface{}) []interface{} {
+	var all []interface{}
+	for _, step := range steps {
+		all = append(all, step)
+		if stepData, ok := step.(map[string]interface{}); ok {
+			if nested, ok := stepData["steps"].([]interface{}); ok {
+				all = append(all, collectAllSteps(nested)...)
+			}
+		}
+	}
+	return all
+}
+
+func (s *Server) validateStepCount(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	steps, ok := pipelineData["steps"].([]interface{})
+	if !ok {
+		return diagnostics
+	}
+
+	maxSteps := s.settings.MaxSteps
+	if maxSteps <= 0 {
+		maxSteps = defaultMaxSteps
+	}
+
+	count := len(collectAllSteps(steps))
+	if count <= maxSteps {
+		return diagnostics
+	}
+
+	rng := protocol.Range{
+		Start: protocol.Position{Line: 0, Character: 0},
+		End:   protocol.Position{Line: 0, Character: 999},
+	}
+	for i, line := range lines {
+		if strings.HasPrefix(line, "steps:") {
+			rng = (&keyLocation{Line: i, Column: 0, Length: len("steps")}).Range()
+			break
+		}
+	}
+
+	diagnostics = append(diagnostics, protocol.Diagnostic{
+		Range:    rng,
+		Severity: protocol.DiagnosticSeverityInformation,
+		Message: fmt.Sprintf("Pipeline has %d steps, more than the limit of %d - large pipelines are slow to upload and may hit Buildkite's step limits",
+			count, maxSteps),
+		Source: "buildkite-ls",
+		Code:   "too-many-steps",
+	})
+
+	return diagnostics
+}
+
+// defaultMaxLabelLength is the label length above which a step label is reported as too long
+const defaultMaxLabelLength = 100
+
+// validateLabelLength notes step labels longer than the configured limit, which
+// are truncated in the Buildkite UI
+func (s *Server) validateLabelLength(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	maxLength := s.settings.MaxLabelLength
+	if maxLength <= 0 {
+		maxLength = defaultMaxLabelLength
+	}
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		label, ok := stepData["label"].(string)
+		if !ok {
+			continue
+		}
+		length := utf8.RuneCountInString(label)
+		if length <= maxLength {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		loc := s.findStepProperty("label", lines, start, end)
+		if loc == nil {
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    valueRange(lines, loc),
+			Severity: protocol.DiagnosticSeverityInformation,
+			Message: fmt.Sprintf("Step %d label is %d characters, more than the limit of %d - long labels are cut off in the Buildkite UI",
+				stepIndex+1, length, maxLength),
+			Source: "buildkite-ls",
+			Code:   "label-too-long",
+		})
+	}
+
+	return diagnostics
+}
+
+// slackChannels returns the channels a notify slack entry posts to, from either
+// the string form or the channels list of the object form
+func slackChannels(slack interface{}) []string {
+	var channels []string
+
+	switch value := slack.(type) {
+	case string:
+		channels = append(channels, value)
+	case map[string]interface{}:
+		if list, ok := value["channels"].([]interface{}); ok {
+			for _, item := range list {
+				if channel, ok := item.(string); ok {
+					channels = append(channels, channel)
+				}
+			}
+		}
+	}
+
+	return channels
+}
+
+// findScalarInLines finds value as a whole scalar token between startLine and endLine
+func findScalarInLines(value string, lines []string, startLine, endLine int) *keyLocation {
+	for i := startLine; i <= endLine && i < len(lines); i++ {
+		line := lines[i]
+		for offset := 0; offset < len(line); {
+			column := strings.Index(line[offset:], value)
+			if column == -1 {
+				break
+			}
+			column += offset
+			end := column + len(value)
+
+			before := byte(' ')
+			if column > 0 {
+				before = line[column-1]
+			}
+			after := byte(' ')
+			if end < len(line) {
+				after = line[end]
+			}
+			if strings.IndexByte(" \"'[,", before) != -1 && strings.IndexByte(" \"',]", after) != -1 {
+				return &keyLocation{Line: i, Column: column, Length: len(value)}
+			}
+			offset = column + 1
+		}
+	}
+
+	return nil
+}
+
+func (s *Server) validateNotifySlack(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	check := func(notify interface{}, start, end int) {
+		entries, ok := notify.([]interface{})
+		if !ok {
+			return
+		}
+
+		searchFrom := start
+		for _, entry := range entries {
+			entryData, ok := entry.(map[string]interface{})
+			if !ok {
+				continue
+			}
+
+			for _, channel := range slackChannels(entryData["slack"]) {
+				// Channels can name a workspace first, as in "workspace#channel"
+				if channel == "" || strings.ContainsAny(channel, "#@") {
+					continue
+				}
+
+				rng := protocol.Range{
+					Start: protocol.Position{Line: uint32(start), Character: 0},
+					End:   protocol.Position{Line: uint32(start), Character: 999},
+				}
+				if loc := findScalarInLines(channel, lines, searchFrom, end); loc != nil {
+					rng = loc.Range()
+					searchFrom = loc.Line + 1
+				}
+
+				diagnostics = append(diagnostics, protocol.Diagnostic{
+					Range:    rng,
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  fmt.Sprintf("Slack channel '%s' should start with '#' (or '@' for a user) - did you mean '#%s'?", channel, channel),
+					Source:   "buildkite-ls",
+					Code:     "invalid-slack-channel",
+				})
+			}
+		}
+	}
+
+	if notify, ok := pipelineData["notify"]; ok {
+		for i, line := range lines {
+			if strings.HasPrefix(line, "notify:") {
+				check(notify, i, s.findBlockEnd(lines, i, len(lines)-1))
+				break
+			}
+		}
+	}
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData := step.Data
+		notify, ok := stepData["notify"]
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		if loc := s.findStepProperty("notify", lines, start, end); loc != nil {
+			check(notify, loc.Line, s.findBlockEnd(lines, loc.Line, end))
+		}
+	}
+
+	return diagnostics
+}
+
+// validWebhookURL reports whether a notify webhook is an absolute http(s) URL
+func validWebhookURL(webhook string) bool {
+	parsed, err := url.Parse(webhook)
+	if err != nil || parsed.Host == "" {
+		return false
+	}
+	return parsed.Scheme == "http" || parsed.Scheme == "https"
+}
+
+// validateNotifyWebhooks warns about notify webhooks that aren't http(s) URLs.
+// Webhooks are only sent for builds, so only pipeline-level notify is checked.
+// Values using environment variables are only known once the pipeline is
+// uploaded, so they're skipped.
+func (s *Server) validateNotifyWebhooks(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	entries, ok := pipelineData["notify"].([]interface{})
+	if !ok {
+		return diagnostics
+	}
+
+	start := -1
+	for i, line := range lines {
+		if strings.HasPrefix(line, "notify:") {
+			start = i
+			break
+		}
+	}
+	if start < 0 {
+		return diagnostics
+	}
+	end := s.findBlockEnd(lines, start, len(lines)-1)
+
+	searchFrom := start + 1
+	for _, entry := range entries {
+		entryData, ok := entry.(map[string]interface{})
+		if !ok {
+			continue
+		}
+		webhook, ok := entryData["webhook"].(string)
+		if !ok {
+			continue
+		}
+
+		loc := s.findKeyInLines("webhook", lines, searchFrom, end)
+		if loc == nil {
+			continue
+		}
+		searchFrom = loc.Line + 1
+		if strings.Contains(webhook, "$") || validWebhookURL(webhook) {
+			continue
+		}
+
+		diagnostics = append(diagnostics, protocol.Diagnostic{
+			Range:    valueRange(lines, loc),
+			Severity: protocol.DiagnosticSeverityWarning,
+			Message:  fmt.Sprintf("Webhook '%s' isn't a valid URL - Buildkite can only deliver notifications to an http:// or https:// address", webhook),
+			Source:   "buildkite-ls",
+			Code:     "invalid-webhook-url",
+		})
+	}
+
+	return diagnostics
+}
+
+// validateReservedEnv warns when pipeline or step env sets a BUILDKITE_ variable
+// the agent manages itself
+func (s *Server) validateReservedEnv(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	check := func(env interface{}, start, end int) {
+		envData, ok := env.(map[string]interface{})
+		if !ok {
+			return
+		}
+
+		names := make([]string, 0, len(envData))
+		for name := range envData {
+			if envvars.IsReserved(name) {
+				names = append(names, name)
+			}
+		}
+		sort.Strings(names)
+
+		for _, name := range names {
+			loc := s.findKeyInLines(name, lines, start+1, end)
+			if loc == nil {
+				continue
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    loc.Range(),
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message:  fmt.Sprintf("'%s' is set by the Buildkite agent and shouldn't be redefined in env", name),
+				Source:   "buildkite-ls",
+				Code:     "reserved-env-variable",
+			})
+		}
+	}
+
+	if env, ok := pipelineData["env"]; ok {
+		for i, line := range lines {
+			if strings.HasPrefix(line, "env:") {
+				check(env, i, s.findBlockEnd(lines, i, len(lines)-1))
+				break
+			}
+		}
+	}
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData := step.Data
+		env, ok := stepData["env"]
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		if loc := s.findStepProperty("env", lines, start, end); loc != nil {
+			check(env, loc.Line, s.findBlockEnd(lines, loc.Line, end))
+		}
+	}
+
+	return diagnostics
+}
+
+// defaultMaxEnvValueLength is the env value size, in bytes, above which a value
+// is reported as too large. Linux refuses a single environment variable over
+// 128 KiB, so a job exporting a larger one can't start its commands.
+const defaultMaxEnvValueLength = 128 * 1024
+
+// validateEnvValueLength warns about pipeline and step env values larger than
+// the configured limit, which usually means a certificate or other blob has
+// been pasted into the pipeline
+func (s *Server) validateEnvValueLength(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	maxLength := s.settings.MaxEnvValueLength
+	if maxLength <= 0 {
+		maxLength = defaultMaxEnvValueLength
+	}
+
+	check := func(env interface{}, start, end int) {
+		envData, ok := env.(map[string]interface{})
+		if !ok {
+			return
+		}
+
+		for _, name := range slices.Sorted(maps.Keys(envData)) {
+			value, ok := envData[name].(string)
+			if !ok || len(value) <= maxLength {
+				continue
+			}
+
+			loc := s.findKeyInLines(name, lines, start+1, end)
+			if loc == nil {
+				continue
+			}
+
+			// A block scalar's value, or a plain one continued onto the
+			// following lines, is the lines under the key
+			rng := valueRange(lines, loc)
+			if indicator := lines[loc.Line][min(int(rng.Start.Character), len(lines[loc.Line])):]; indicator == "" || strings.HasPrefix(indicator, "|") || strings.HasPrefix(indicator, ">") {
+				last := s.findBlockEnd(lines, loc.Line, end)
+				rng.End = protocol.Position{Line: uint32(last), Character: uint32(len(strings.TrimRight(lines[last], " \t")))}
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityWarning,
+				Message: fmt.Sprintf("Env '%s' value is %d bytes, more than the limit of %d - large values can make the upload or the job fail, so load them from a secret or a file instead",
+					name, len(value), maxLength),
+				Source: "buildkite-ls",
+				Code:   "env-value-too-long",
+			})
+		}
+	}
+
+	if env, ok := pipelineData["env"]; ok {
+		for i, line := range lines {
+			if strings.HasPrefix(line, "env:") {
+				check(env, i, s.findBlockEnd(lines, i, len(lines)-1))
+				break
+			}
+		}
+	}
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData := step.Data
+		env, ok := stepData["env"]
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		if loc := s.findStepProperty("env", lines, start, end); loc != nil {
+			check(env, loc.Line, s.findBlockEnd(lines, loc.Line, end))
+		}
+	}
+
+	return diagnostics
+}
+
+// validateEnvShadowing notes step env entries that override a pipeline-level
+// env entry, so the override is visible. Overriding with an empty string is
+// called out since it clears the variable for the step.
+func (s *Server) validateEnvShadowing(pipelineData map[string]interface{}, lines []string) []protocol.Diagnostic {
+	var diagnostics []protocol.Diagnostic
+
+	pipelineEnv, ok := pipelineData["env"].(map[string]interface{})
+	if !ok || len(pipelineEnv) == 0 {
+		return diagnostics
+	}
+
+	pipelineEnvLine := -1
+	for i, line := range lines {
+		if strings.HasPrefix(line, "env:") {
+			pipelineEnvLine = i
+			break
+		}
+	}
+	if pipelineEnvLine < 0 {
+		return diagnostics
+	}
+	pipelineEnvEnd := s.findBlockEnd(lines, pipelineEnvLine, len(lines)-1)
+
+	for _, step := range s.pipelineSteps(pipelineData, lines) {
+		stepData, stepIndex := step.Data, step.Index
+		stepEnv, ok := stepData["env"].(map[string]interface{})
+		if !ok {
+			continue
+		}
+
+		start, end := step.Start, step.End
+		envLoc := s.findStepProperty("env", lines, start, end)
+		if envLoc == nil {
+			continue
+		}
+		envEnd := s.findBlockEnd(lines, envLoc.Line, end)
+
+		for _, name := range slices.Sorted(maps.Keys(stepEnv)) {
+			// Reserved names are already reported by validateReservedEnv
+			if _, shadows := pipelineEnv[name]; !shadows || envvars.IsReserved(name) {
+				continue
+			}
+
+			loc := s.findKeyInLines(name, lines, envLoc.Line+1, envEnd)
+			original := s.findKeyInLines(name, lines, pipelineEnvLine+1, pipelineEnvEnd)
+			if loc == nil || original == nil {
+				continue
+			}
+
+			message := fmt.Sprintf("%s env '%s' overrides the pipeline-level value", describeStep(stepData, stepIndex), name)
+			if value, isString := stepEnv[name].(string); isString && value == "" {
+				message = fmt.Sprintf("%s env '%s' overrides the pipeline-level value with an empty string, clearing it for this step", describeStep(stepData, stepIndex), name)
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    loc.Range(),
+				Severity: protocol.DiagnosticSeverityInformation,
+				Message:  message,
+				Source:   "buildkite-ls",
+				Code:     "env-shadows-pipeline-env",
+				RelatedInformation: []protocol.DiagnosticRelatedInformation{
+					{
+						Location: protocol.Location{Range: original.Range()},
+						Message:  "Pipeline-level value set here",
+					},
+				},
+			})
+		}
+	}
+
+	return diagnostics
+}
+
+// githubActionsKeys are top-level keys of a GitHub Actions workflow that a
+// Buildkite pipeline never uses
+var githubActionsKeys = []string{"on", "jobs", "runs-on"}
+
+// foreignPipelineDiagnostic returns a single diagnostic for a document that
+// looks like another CI system's config (e.g. a GitHub Actions workflow) rather
+// than a Buildkite pipeline, or nil if it doesn't
+func (s *Server) foreignPipelineDiagnostic(pipelineData map[string]interface{}, lines []string) *protocol.Diagnostic {
+	if _, hasSteps := pipelineData["steps"]; hasSteps {
+		return nil
+	}
+
+	var found []string
+	for _, key := range githubActionsKeys {
+		if _, ok := pipelineData[key]; ok {
+			found = append(found, "'"+key+"'")
+		}
+	}
+	if len(found) == 0 {
+		return nil
+	}
+
+	rng := protocol.Range{}
+	for i, line := range lines {
+		if line != "" && line[0] != ' ' && line[0] != '\t' && line[0] != '#' {
+			rng.Start.Line = uint32(i)
+			rng.End = protocol.Position{Line: uint32(i), Character: uint32(len(strings.TrimRight(line, " \t")))}
+			break
+		}
+	}
+
+	message := fmt.Sprintf("This doesn't look like a Buildkite pipeline - it has top-level %s but no 'steps', like a GitHub Actions workflow, so it hasn't been validated",
+		strings.Join(found, ", "))
+	return &protocol.Diagnostic{
+		Range:    rng,
+		Severity: protocol.DiagnosticSeverityInformation,
+		Message:  message,
+		Source:   "buildkite-ls",
+		Code:     "not-a-buildkite-pipeline",
+	}
+}
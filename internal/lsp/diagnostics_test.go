@@ -1,11 +1,17 @@
 package lsp
 
 import (
+	"encoding/json"
+	"os"
+	"path/filepath"
+	"slices"
+	"strings"
 	"testing"
 
 	"go.lsp.dev/protocol"
 
 	"github.com/mcncl/buildkite-ls/internal/parser"
+	"github.com/mcncl/buildkite-ls/internal/plugins"
 )
 
 func TestServer_EnhancedDiagnostics(t *testing.T) {
@@ -45,7 +51,23 @@ steps:
 				{
 					Code:     "missing-label",
 					Severity: protocol.DiagnosticSeverityInformation,
-					Message:  "Consider adding a 'label' to make this step easier to identify in the UI",
+					Message:  "Consider adding a 'label' or 'key' to make this step easier to identify in the UI and to reference from depends_on",
+				},
+			},
+		},
+		{
+			name: "env written as a list",
+			content: `env:
+  - NODE_ENV=production
+  - DEBUG=1
+steps:
+  - label: "Test"
+    command: "test"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-env",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Pipeline 'env' must be a map of KEY: value pairs, not a list - write '- NODE_ENV=production' as 'NODE_ENV: production'",
 				},
 			},
 		},
@@ -57,7 +79,7 @@ steps:
 				{
 					Code:     "missing-step-type",
 					Severity: protocol.DiagnosticSeverityError,
-					Message:  "Step 1 must specify a step type: command, wait, block, input, trigger, or group",
+					Message:  "Step 'No Command' must specify a step type: command, wait, block, input, trigger, or group",
 				},
 			},
 		},
@@ -75,7 +97,7 @@ steps:
 				{
 					Code:     "missing-label",
 					Severity: protocol.DiagnosticSeverityInformation,
-					Message:  "Consider adding a 'label' to make this step easier to identify in the UI",
+					Message:  "Consider adding a 'label' or 'key' to make this step easier to identify in the UI and to reference from depends_on",
 				},
 			},
 		},
@@ -100,10 +122,17 @@ steps:
 				{
 					Code:     "missing-label",
 					Severity: protocol.DiagnosticSeverityInformation,
-					Message:  "Consider adding a 'label' to make this step easier to identify in the UI",
+					Message:  "Consider adding a 'label' or 'key' to make this step easier to identify in the UI and to reference from depends_on",
 				},
 			},
 		},
+		{
+			name: "command step with a key but no label",
+			content: `steps:
+  - key: "build"
+    command: "make build"`,
+			expectedDiagnostics: []ExpectedDiagnostic{},
+		},
 		{
 			name: "invalid wait value",
 			content: `steps:
@@ -194,7 +223,7 @@ steps:
 				{
 					Code:     "no-step-type-with-plugins",
 					Severity: protocol.DiagnosticSeverityInformation,
-					Message:  "Step 1 has no explicit step type, but plugins may provide command execution via hooks",
+					Message:  "Step 'Build with plugin' has no explicit step type, but plugins may provide command execution via hooks",
 				},
 			},
 		},
@@ -227,123 +256,2323 @@ steps:
 				},
 			},
 		},
-	}
-
-	for _, tt := range tests {
-		t.Run(tt.name, func(t *testing.T) {
-			// Parse content
-			pipeline, err := parser.ParseYAML([]byte(tt.content))
-			if err != nil {
-				t.Fatalf("Failed to parse YAML: %v", err)
-			}
-
-			// Get diagnostics
-			diagnostics := server.validatePlugins(pipeline)
-
-			// Check expected diagnostics
-			if len(tt.expectedDiagnostics) == 0 {
-				if len(diagnostics) != 0 {
-					t.Errorf("Expected no diagnostics, but got %d:", len(diagnostics))
-					for i, d := range diagnostics {
-						t.Errorf("  [%d] %s: %s", i, d.Code, d.Message)
-					}
-				}
-				return
-			}
-
-			if len(diagnostics) != len(tt.expectedDiagnostics) {
-				t.Errorf("Expected %d diagnostics, got %d:", len(tt.expectedDiagnostics), len(diagnostics))
-				for i, d := range diagnostics {
-					t.Errorf("  [%d] %s: %s", i, d.Code, d.Message)
-				}
-				return
-			}
-
-			// Check each diagnostic
-			for i, expected := range tt.expectedDiagnostics {
-				actual := diagnostics[i]
-
-				if actual.Code != expected.Code {
-					t.Errorf("Diagnostic %d: expected code %s, got %s", i, expected.Code, actual.Code)
-				}
-
-				if actual.Severity != expected.Severity {
-					t.Errorf("Diagnostic %d: expected severity %v, got %v", i, expected.Severity, actual.Severity)
-				}
-
-				if actual.Message != expected.Message {
-					t.Errorf("Diagnostic %d: expected message %q, got %q", i, expected.Message, actual.Message)
-				}
-
-				if actual.Source != "buildkite-ls" {
-					t.Errorf("Diagnostic %d: expected source 'buildkite-ls', got %q", i, actual.Source)
-				}
-			}
-		})
-	}
-}
-
-func TestServer_DiagnosticsHelpers(t *testing.T) {
-	server := newTestServer()
-
-	t.Run("findLineForProperty", func(t *testing.T) {
-		lines := []string{
-			"env:",
-			"  NODE_ENV: production",
-			"",
-			"steps:",
-			"  - command: test",
-		}
-
-		// Test finding existing property
-		line := server.findLineForProperty("env", lines)
-		if line != 0 {
-			t.Errorf("Expected line 0 for 'env', got %d", line)
-		}
-
-		line = server.findLineForProperty("steps", lines)
-		if line != 3 {
-			t.Errorf("Expected line 3 for 'steps', got %d", line)
-		}
-
-		// Test non-existent property
-		line = server.findLineForProperty("agents", lines)
-		if line != len(lines)-1 {
-			t.Errorf("Expected line %d for non-existent property, got %d", len(lines)-1, line)
-		}
-	})
-
-	t.Run("findStepLines", func(t *testing.T) {
-		lines := []string{
-			"steps:",
-			"  - label: \"Build\"",
-			"    command: \"make build\"",
-			"  ",
-			"  - wait: ~",
-			"  ",
-			"  - trigger: \"deploy\"",
-			"    build:",
-			"      message: \"Deploy\"",
-		}
-
-		stepLines := server.findStepLines(lines)
-		expectedLines := []int{1, 4, 6}
-
-		if len(stepLines) != len(expectedLines) {
-			t.Errorf("Expected %d step lines, got %d: %v", len(expectedLines), len(stepLines), stepLines)
-			return
-		}
-
-		for i, expected := range expectedLines {
-			if stepLines[i] != expected {
-				t.Errorf("Expected step line %d at index %d, got %d", expected, i, stepLines[i])
-			}
-		}
-	})
-}
-
-type ExpectedDiagnostic struct {
-	Code     string
-	Severity protocol.DiagnosticSeverity
-	Message  string
+		{
+			name: "trigger meta_data keys with invalid characters",
+			content: `steps:
+  - trigger: "deploy"
+    build:
+      meta_data:
+        release-version: "1.0"
+        "Release Version": "1.0"
+        Owner: "platform"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-meta-data-key",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Meta-data key 'Owner' contains invalid characters - only lowercase letters, digits, '-', '_', '.' and '/' are allowed",
+				},
+				{
+					Code:     "invalid-meta-data-key",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Meta-data key 'Release Version' contains invalid characters - only lowercase letters, digits, '-', '_', '.' and '/' are allowed",
+				},
+			},
+		},
+		{
+			name: "step with both command and commands",
+			content: `steps:
+  - label: "Build"
+    command: "make build"
+    commands:
+      - "make test"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "command-and-commands",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Build' declares both 'command' and 'commands' - use only one of them",
+				},
+			},
+		},
+		{
+			name: "cache with wrongly typed paths and key",
+			content: `steps:
+  - label: "Build"
+    command: "make build"
+    cache:
+      paths:
+        nested: "node_modules"
+      key: 42`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-cache",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Cache 'paths' must be a string or a list of strings",
+				},
+				{
+					Code:     "invalid-cache",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Cache 'key' must be a string",
+				},
+			},
+		},
+		{
+			name: "valid cache forms",
+			content: `steps:
+  - label: "Shorthand"
+    command: "make build"
+    cache: "node_modules"
+  - label: "List"
+    command: "make build"
+    cache:
+      - "node_modules"
+      - ".bundle"
+  - label: "Object"
+    command: "make build"
+    cache:
+      paths: "vendor"
+      key: "v1-deps"
+      restore_keys:
+        - "v1-"`,
+			expectedDiagnostics: []ExpectedDiagnostic{},
+		},
+		{
+			name: "duplicate step keys",
+			content: `steps:
+  - label: "Build"
+    key: "build"
+    command: "make build"
+  - label: "Build again"
+    key: "build"
+    command: "make build"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "duplicate-step-key",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step key 'build' is already used by another step - step keys must be unique",
+				},
+			},
+		},
+		{
+			name: "group key reused by a nested step",
+			content: `steps:
+  - group: "Tests"
+    key: "tests"
+    steps:
+      - label: "Unit"
+        key: "tests"
+        command: "make test"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "duplicate-step-key",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step key 'tests' is already used by another step - step keys must be unique",
+				},
+			},
+		},
+		{
+			name: "duplicate field keys",
+			content: `steps:
+  - block: "Release"
+    fields:
+      - text: "Version"
+        key: "version"
+      - select: "Channel"
+        key: "channel"
+        options:
+          - label: "Stable"
+            value: "stable"
+      - text: "Version again"
+        key: "version"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "duplicate-field-key",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Release' field key 'version' is already used by another field - only the last value would be kept",
+				},
+			},
+		},
+		{
+			name: "invalid timeouts at pipeline and step level",
+			content: `timeout_in_minutes: -5
+steps:
+  - label: "Build"
+    command: "make build"
+    timeout_in_minutes: 1.5`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-timeout",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Pipeline 'timeout_in_minutes' must be a positive whole number of minutes",
+				},
+				{
+					Code:     "invalid-timeout",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Build' 'timeout_in_minutes' must be a positive whole number of minutes",
+				},
+			},
+		},
+		{
+			name: "step timeout exceeding pipeline timeout",
+			content: `timeout_in_minutes: 30
+steps:
+  - label: "Build"
+    command: "make build"
+    timeout_in_minutes: 60
+  - label: "Test"
+    command: "make test"
+    timeout_in_minutes: 20`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "timeout-exceeds-pipeline",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Build' timeout of 60 minutes exceeds the pipeline timeout of 30 minutes",
+				},
+			},
+		},
+		{
+			name: "group with a mapping as its label",
+			content: `steps:
+  - group:
+      name: "Tests"
+    steps:
+      - label: "Unit"
+        command: "make test"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-group-label",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 1 'group' must be a label string (or null for an unlabeled group)",
+				},
+			},
+		},
+		{
+			name: "unlabeled group",
+			content: `steps:
+  - group: ~
+    steps:
+      - label: "Unit"
+        command: "make test"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "unlabeled-group",
+					Severity: protocol.DiagnosticSeverityInformation,
+					Message:  "Step 1 is a group without a label - give 'group' a descriptive name so it's easy to follow in the Buildkite UI",
+				},
+			},
+		},
+		{
+			name: "group labelled with label",
+			content: `steps:
+  - group: ""
+    label: "Tests"
+    steps:
+      - label: "Unit"
+        command: "make test"`,
+			expectedDiagnostics: []ExpectedDiagnostic{},
+		},
+		{
+			name: "deprecated step keys",
+			content: `steps:
+  - label: "Build"
+    identifier: "build"
+    command: "make build"
+    agent_query_rules:
+      - "queue=default"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "deprecated-key",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "'agent_query_rules' is deprecated - use 'agents' instead",
+				},
+				{
+					Code:     "deprecated-key",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "'identifier' is deprecated - use 'key' instead",
+				},
+			},
+		},
+		{
+			name: "keys that do not apply to the step type",
+			content: `steps:
+  - wait: ~
+    continue_on_failure: true
+  - block: "Release?"
+    blocked_state: "running"
+    timeout_in_minutes: 10
+  - label: "Build"
+    command: "make build"
+    fields:
+      - text: "Version"
+        key: "version"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Release?' is a block step - 'timeout_in_minutes' only applies to command steps",
+				},
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Build' is a command step - 'fields' only applies to block and input steps",
+				},
+			},
+		},
+		{
+			name: "retry on steps that are not command steps",
+			content: `steps:
+  - label: "Build"
+    command: "make build"
+    retry:
+      automatic: true
+  - wait: ~
+    retry:
+      automatic: true
+  - trigger: "deploy"
+    retry:
+      manual: false`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 2 is a wait step - 'retry' only applies to command steps",
+				},
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 3 is a trigger step - 'retry' only applies to command steps",
+				},
+			},
+		},
+		{
+			name: "priority and concurrency on steps that are not command steps",
+			content: `steps:
+  - label: "Deploy"
+    command: "make deploy"
+    priority: 1
+    concurrency: 1
+    concurrency_group: "deploy"
+  - wait: ~
+    priority: 1
+  - block: "Release?"
+    concurrency: 1
+    concurrency_group: "release"
+  - group: "Tests"
+    priority: 2
+    steps:
+      - label: "Unit"
+        command: "make unit"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 2 is a wait step - 'priority' only applies to command steps",
+				},
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Release?' is a block step - 'concurrency' only applies to command steps",
+				},
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Release?' is a block step - 'concurrency_group' only applies to command steps",
+				},
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Tests' is a group step - 'priority' only applies to command steps",
+				},
+			},
+		},
+		{
+			name: "duplicate keys on wait and block steps",
+			content: `steps:
+  - wait: ~
+    key: "gate"
+  - block: "Release?"
+    key: "gate"
+  - label: "Release"
+    command: "make release"
+    depends_on: "gate"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "duplicate-step-key",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step key 'gate' is already used by another step - step keys must be unique",
+				},
+			},
+		},
+		{
+			name: "soft fail with automatic retries",
+			content: `steps:
+  - label: "Flaky"
+    command: "make flaky"
+    soft_fail: true
+    retry:
+      automatic: true
+  - label: "Exit codes"
+    command: "make lint"
+    soft_fail:
+      - exit_status: 1
+    retry:
+      automatic: true
+  - label: "Manual only"
+    command: "make deploy"
+    soft_fail: true
+    retry:
+      automatic: false`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "soft-fail-with-retry",
+					Severity: protocol.DiagnosticSeverityInformation,
+					Message:  "Step 'Flaky' soft fails on any exit status and also retries automatically - failed jobs are retried before being soft failed, which may not behave as expected",
+				},
+			},
+		},
+		{
+			name: "command list entries that aren't strings",
+			content: `steps:
+  - label: "Build"
+    commands:
+      - make deps
+      - [make build, make test]
+      - timeout: 5
+      - 42
+  - label: "Lint"
+    command: ["make lint", true]`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-command-entry",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Build' 'commands' entry 2 must be a string, not a list - quote it or split it into separate entries",
+				},
+				{
+					Code:     "invalid-command-entry",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Build' 'commands' entry 3 must be a string, not a mapping - quote it or split it into separate entries",
+				},
+				{
+					Code:     "invalid-command-entry",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Build' 'commands' entry 4 must be a string, not a number - quote it or split it into separate entries",
+				},
+				{
+					Code:     "invalid-command-entry",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Lint' 'command' entry 2 must be a string, not a boolean - quote it or split it into separate entries",
+				},
+			},
+		},
+		{
+			name: "groups nested inside a group",
+			content: `steps:
+  - group: "Tests"
+    steps:
+      - label: "Unit"
+        command: "make unit"
+      - group: "Integration"
+        steps:
+          - group: "Database"
+            steps:
+              - label: "Postgres"
+                command: "make postgres"
+  - group: "Deploy"
+    steps:
+      - label: "Production"
+        command: "make deploy"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "nested-group",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Tests' contains another group - Buildkite doesn't support nested groups, move 'Integration' to the top level of steps",
+				},
+				{
+					Code:     "nested-group",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Tests' contains another group - Buildkite doesn't support nested groups, move 'Database' to the top level of steps",
+				},
+			},
+		},
+		{
+			name: "if condition accessing unknown members",
+			content: `steps:
+  - label: "Deploy"
+    command: "make deploy"
+    if: build.pull_request.number != null && build.branch.name == "main"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "unknown-conditional-member",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "'build.pull_request' has no member 'number' - expected one of: base_branch, draft, id, labels, repository",
+				},
+				{
+					Code:     "unknown-conditional-member",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "'build.branch' has no member 'name'",
+				},
+			},
+		},
+		{
+			name: "invisible characters in values",
+			content: "steps:\n  - label: \"Test \U0001F469\u200d\U0001F4BB\"\n    # pasted\u00a0notes\n    command: \"make\u00a0test\u200b\"",
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invisible-character",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Invisible character U+00A0 (no-break space) - it can break commands at runtime, so replace it with a plain space or delete it",
+				},
+				{
+					Code:     "invisible-character",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Invisible character U+200B (zero-width space) - it can break commands at runtime, so replace it with a plain space or delete it",
+				},
+			},
+		},
+		{
+			name: "nested agent tags",
+			content: `agents:
+  queue:
+    name: "deploy"
+steps:
+  - command: "make test"
+    agents:
+      os: "linux"
+      tags:
+        - "gpu"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "nested-agent-tag",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Agent tag 'queue' must be a single value, not a mapping - agents can't be nested, so write it as 'queue: value'",
+				},
+				{
+					Code:     "nested-agent-tag",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Agent tag 'tags' must be a single value, not a list - agents can't be nested, so write it as 'tags: value'",
+				},
+			},
+		},
+		{
+			name: "slack channels without a prefix",
+			content: `notify:
+  - slack: "general"
+  - slack: "buildkite-community#general"
+steps:
+  - label: "Deploy"
+    command: "make deploy"
+    notify:
+      - slack:
+          channels:
+            - "#deploys"
+            - "releases"
+            - "@oncall"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-slack-channel",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Slack channel 'general' should start with '#' (or '@' for a user) - did you mean '#general'?",
+				},
+				{
+					Code:     "invalid-slack-channel",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Slack channel 'releases' should start with '#' (or '@' for a user) - did you mean '#releases'?",
+				},
+			},
+		},
+		{
+			name: "malformed notify webhooks",
+			content: `notify:
+  - webhook: "https://example.com/buildkite"
+  - webhook: "example.com/hook"
+  - webhook: "ftp://example.com/hook"
+  - webhook: "${DEPLOY_WEBHOOK}"
+steps:
+  - label: "Deploy"
+    command: "make deploy"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-webhook-url",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Webhook 'example.com/hook' isn't a valid URL - Buildkite can only deliver notifications to an http:// or https:// address",
+				},
+				{
+					Code:     "invalid-webhook-url",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Webhook 'ftp://example.com/hook' isn't a valid URL - Buildkite can only deliver notifications to an http:// or https:// address",
+				},
+			},
+		},
+		{
+			name: "env redefines reserved variables",
+			content: `env:
+  BUILDKITE_BRANCH: "main"
+  BUILDKITE_GIT_CLONE_FLAGS: "--depth=1"
+steps:
+  - label: "Build"
+    command: "make build"
+    env:
+      BUILDKITE_S3_DEFAULT_REGION: "us-east-1"
+      BUILDKITE_COMMIT: "HEAD"
+      NODE_ENV: "production"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "reserved-env-variable",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "'BUILDKITE_BRANCH' is set by the Buildkite agent and shouldn't be redefined in env",
+				},
+				{
+					Code:     "reserved-env-variable",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "'BUILDKITE_COMMIT' is set by the Buildkite agent and shouldn't be redefined in env",
+				},
+			},
+		},
+		{
+			name: "allow_dependency_failure without depends_on",
+			content: `steps:
+  - label: "Build"
+    key: "build"
+    command: "make build"
+    allow_dependency_failure: true
+  - label: "Report"
+    command: "make report"
+    depends_on: "build"
+    allow_dependency_failure: true
+  - label: "Cleanup"
+    command: "make clean"
+    allow_dependency_failure: false`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "allow-dependency-failure-without-depends-on",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Build' sets 'allow_dependency_failure' but has no 'depends_on', so it has no effect",
+				},
+			},
+		},
+		{
+			name: "command shorthand steps",
+			content: `steps:
+  - "make build"
+  - wait
+  - label: "Test"
+    command: "make test"
+  - "make deploy"`,
+			expectedDiagnostics: []ExpectedDiagnostic{},
+		},
+		{
+			name: "trigger async must be a boolean",
+			content: `steps:
+  - trigger: "deploy"
+    async: "true"
+  - trigger: "notify"
+    async: 1
+  - trigger: "release"
+    async: true`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-trigger-async",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 1 'async' must be a boolean, not the string \"true\" - remove the quotes",
+				},
+				{
+					Code:     "invalid-trigger-async",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 2 'async' must be true or false",
+				},
+			},
+		},
+		{
+			name: "mixed indentation widths",
+			content: `steps:
+  - label: "Build"
+    command: "make build"
+    env:
+        FOO: "bar"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "inconsistent-indentation",
+					Severity: protocol.DiagnosticSeverityInformation,
+					Message:  "This line is indented 4 spaces deeper than its parent, but the rest of the document uses 2 - check it's nested where you expect",
+				},
+			},
+		},
+		{
+			name: "step-only keys on a group",
+			content: `steps:
+  - group: "Tests"
+    key: "tests"
+    retry:
+      automatic: true
+    agents:
+      queue: "default"
+    soft_fail: true
+    steps:
+      - label: "Unit"
+        command: "make test"
+        retry:
+          automatic: true`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Tests' is a group step - 'agents' only applies to command steps",
+				},
+				{
+					Code:     "key-not-valid-for-step-type",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Tests' is a group step - 'retry' only applies to command steps",
+				},
+				{
+					Code:     "key-not-valid-for-group",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Tests' is a group step - 'soft_fail' isn't valid on a group, set it on the steps inside instead",
+				},
+			},
+		},
+		{
+			name: "retry manual keys and types",
+			content: `steps:
+  - label: "Deploy"
+    command: "make deploy"
+    retry:
+      manual:
+        allowed: "false"
+        reason: "Deploys can't be retried"
+        permit_on_pass: true
+  - label: "Test"
+    command: "make test"
+    retry:
+      manual:
+        permit_on_passed: true`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "invalid-retry-manual",
+					Severity: protocol.DiagnosticSeverityError,
+					Message:  "Step 'Deploy' 'retry.manual.allowed' must be a boolean",
+				},
+				{
+					Code:     "invalid-retry-manual",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Deploy' 'retry.manual' doesn't support 'permit_on_pass' - use allowed, permit_on_passed or reason",
+				},
+			},
+		},
+		{
+			name: "if and branches on the same step",
+			content: `steps:
+  - label: "Deploy"
+    command: "make deploy"
+    if: build.tag != null
+    branches: "main"
+  - label: "Test"
+    command: "make test"
+    branches: "main"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "if-and-branches",
+					Severity: protocol.DiagnosticSeverityInformation,
+					Message:  "Step 'Deploy' uses both 'if' and 'branches' - consider moving the branch filter into 'if' (e.g. build.branch == \"main\")",
+				},
+			},
+		},
+		{
+			name: "step env shadows pipeline env",
+			content: `env:
+  NODE_ENV: "production"
+  DEBUG: "true"
+
+steps:
+  - label: "Test"
+    command: "make test"
+    env:
+      NODE_ENV: "test"
+      DEBUG: ""
+      CI: "true"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "env-shadows-pipeline-env",
+					Severity: protocol.DiagnosticSeverityInformation,
+					Message:  "Step 'Test' env 'DEBUG' overrides the pipeline-level value with an empty string, clearing it for this step",
+				},
+				{
+					Code:     "env-shadows-pipeline-env",
+					Severity: protocol.DiagnosticSeverityInformation,
+					Message:  "Step 'Test' env 'NODE_ENV' overrides the pipeline-level value",
+				},
+			},
+		},
+		{
+			name: "skipped step with dependents",
+			content: `steps:
+  - label: "Build"
+    key: "build"
+    command: "make build"
+    skip: "Temporarily disabled"
+  - label: "Test"
+    command: "make test"
+    depends_on: "build"`,
+			expectedDiagnostics: []ExpectedDiagnostic{
+				{
+					Code:     "skipped-step",
+					Severity: protocol.DiagnosticSeverityInformation,
+					Message:  "Step 'Build' is skipped and will never run",
+				},
+				{
+					Code:     "depends-on-skipped-step",
+					Severity: protocol.DiagnosticSeverityWarning,
+					Message:  "Step 'Test' depends on 'build', which is skipped and will never run",
+				},
+			},
+		},
+		{
+			name: "skip false is not reported",
+			content: `steps:
+  - label: "Build"
+    key: "build"
+    command: "make build"
+    skip: false
+  - label: "Test"
+    command: "make test"
+    depends_on:
+      - step: "build"`,
+			expectedDiagnostics: []ExpectedDiagnostic{},
+		},
+		{
+			name: "valid trigger meta_data keys",
+			content: `steps:
+  - trigger: "deploy"
+    build:
+      meta_data:
+        release-version: "1.0"
+        deploy/target_env.name: "production"`,
+			expectedDiagnostics: []ExpectedDiagnostic{},
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			// Parse content
+			pipeline, err := parser.ParseYAML([]byte(tt.content))
+			if err != nil {
+				t.Fatalf("Failed to parse YAML: %v", err)
+			}
+
+			// Get diagnostics
+			diagnostics := server.validatePlugins(pipeline)
+
+			// Check expected diagnostics
+			if len(tt.expectedDiagnostics) == 0 {
+				if len(diagnostics) != 0 {
+					t.Errorf("Expected no diagnostics, but got %d:", len(diagnostics))
+					for i, d := range diagnostics {
+						t.Errorf("  [%d] %s: %s", i, d.Code, d.Message)
+					}
+				}
+				return
+			}
+
+			if len(diagnostics) != len(tt.expectedDiagnostics) {
+				t.Errorf("Expected %d diagnostics, got %d:", len(tt.expectedDiagnostics), len(diagnostics))
+				for i, d := range diagnostics {
+					t.Errorf("  [%d] %s: %s", i, d.Code, d.Message)
+				}
+				return
+			}
+
+			// Check each diagnostic
+			for i, expected := range tt.expectedDiagnostics {
+				actual := diagnostics[i]
+
+				if actual.Code != expected.Code {
+					t.Errorf("Diagnostic %d: expected code %s, got %s", i, expected.Code, actual.Code)
+				}
+
+				if actual.Severity != expected.Severity {
+					t.Errorf("Diagnostic %d: expected severity %v, got %v", i, expected.Severity, actual.Severity)
+				}
+
+				if actual.Message != expected.Message {
+					t.Errorf("Diagnostic %d: expected message %q, got %q", i, expected.Message, actual.Message)
+				}
+
+				if actual.Source != "buildkite-ls" {
+					t.Errorf("Diagnostic %d: expected source 'buildkite-ls', got %q", i, actual.Source)
+				}
+			}
+		})
+	}
+}
+
+func TestServer_DiagnosticsHelpers(t *testing.T) {
+	server := newTestServer()
+
+	t.Run("findLineForProperty", func(t *testing.T) {
+		lines := []string{
+			"env:",
+			"  NODE_ENV: production",
+			"",
+			"steps:",
+			"  - command: test",
+		}
+
+		// Test finding existing property
+		line := server.findLineForProperty("env", lines)
+		if line != 0 {
+			t.Errorf("Expected line 0 for 'env', got %d", line)
+		}
+
+		line = server.findLineForProperty("steps", lines)
+		if line != 3 {
+			t.Errorf("Expected line 3 for 'steps', got %d", line)
+		}
+
+		// Test non-existent property
+		line = server.findLineForProperty("agents", lines)
+		if line != len(lines)-1 {
+			t.Errorf("Expected line %d for non-existent property, got %d", len(lines)-1, line)
+		}
+	})
+
+	t.Run("findStepLines", func(t *testing.T) {
+		lines := []string{
+			"steps:",
+			"  - label: \"Build\"",
+			"    command: \"make build\"",
+			"  ",
+			"  - wait: ~",
+			"  ",
+			"  - trigger: \"deploy\"",
+			"    build:",
+			"      message: \"Deploy\"",
+		}
+
+		stepLines := server.findStepLines(lines)
+		expectedLines := []int{1, 4, 6}
+
+		if len(stepLines) != len(expectedLines) {
+			t.Errorf("Expected %d step lines, got %d: %v", len(expectedLines), len(stepLines), stepLines)
+			return
+		}
+
+		for i, expected := range expectedLines {
+			if stepLines[i] != expected {
+				t.Errorf("Expected step line %d at index %d, got %d", expected, i, stepLines[i])
+			}
+		}
+	})
+}
+
+type ExpectedDiagnostic struct {
+	Code     string
+	Severity protocol.DiagnosticSeverity
+	Message  string
+}
+
+func TestServer_MetaDataKeyRange(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - trigger: "deploy"
+    build:
+      meta_data:
+        "Release Version": "1.0"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	diagnostics := server.validatePlugins(pipeline)
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+	}
+
+	// The range should cover the quoted key on line 4
+	expected := protocol.Range{
+		Start: protocol.Position{Line: 4, Character: 8},
+		End:   protocol.Position{Line: 4, Character: 25},
+	}
+	if diagnostics[0].Range != expected {
+		t.Errorf("Expected range %v, got %v", expected, diagnostics[0].Range)
+	}
+}
+
+func TestServer_CommandAndCommandsRange(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - label: "Build"
+    commands:
+      - "make build"
+    plugins:
+      - docker#v5.13.0:
+          command: ["make"]
+    command: "make test"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var pipelineData map[string]interface{}
+	if err := json.Unmarshal(pipeline.JSONBytes, &pipelineData); err != nil {
+		t.Fatalf("Failed to decode pipeline: %v", err)
+	}
+
+	diagnostics := server.validateCommandAndCommands(pipelineData, splitLines(content))
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+	}
+	found := diagnostics[0]
+
+	// The step-level 'command' on line 7 is declared second; the plugin option is ignored
+	if found.Range.Start.Line != 7 || found.Range.Start.Character != 4 {
+		t.Errorf("Expected diagnostic at 7:4, got %d:%d", found.Range.Start.Line, found.Range.Start.Character)
+	}
+}
+
+func TestServer_AgentQueueValidation(t *testing.T) {
+	content := `agents:
+  queue: "default"
+steps:
+  - label: "Deploy"
+    command: "make deploy"
+    agents:
+      queue: "deloy"
+  - label: "Test"
+    command: "make test"
+    agents:
+      - "queue=macos"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	t.Run("no allowed list configured", func(t *testing.T) {
+		server := newTestServer()
+		for _, d := range server.validatePlugins(pipeline) {
+			if d.Code == "unknown-queue" {
+				t.Errorf("Unexpected queue diagnostic without allowedQueues: %s", d.Message)
+			}
+		}
+	})
+
+	t.Run("allowed list configured", func(t *testing.T) {
+		server := newTestServer()
+		server.settings.AllowedQueues = []string{"default", "deploy"}
+
+		var queueDiagnostics []protocol.Diagnostic
+		for _, d := range server.validatePlugins(pipeline) {
+			if d.Code == "unknown-queue" {
+				queueDiagnostics = append(queueDiagnostics, d)
+			}
+		}
+
+		if len(queueDiagnostics) != 2 {
+			t.Fatalf("Expected 2 queue diagnostics, got %d", len(queueDiagnostics))
+		}
+
+		first := queueDiagnostics[0]
+		if first.Message != "Queue 'deloy' is not in the list of allowed queues - did you mean 'deploy'?" {
+			t.Errorf("Unexpected message: %q", first.Message)
+		}
+		if first.Severity != protocol.DiagnosticSeverityWarning {
+			t.Errorf("Expected warning severity, got %v", first.Severity)
+		}
+		if first.Range.Start.Line != 6 || first.Range.Start.Character != 13 {
+			t.Errorf("Expected range to start at 6:13, got %d:%d", first.Range.Start.Line, first.Range.Start.Character)
+		}
+
+		second := queueDiagnostics[1]
+		if second.Message != "Queue 'macos' is not in the list of allowed queues" {
+			t.Errorf("Unexpected message: %q", second.Message)
+		}
+		if second.Range.Start.Line != 10 {
+			t.Errorf("Expected list-form queue on line 10, got %d", second.Range.Start.Line)
+		}
+	})
+}
+
+func TestServer_DuplicateStepKeyRelatedInformation(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - label: "Build"
+    key: "build"
+    command: "make build"
+  - label: "Build again"
+    key: "build"
+    command: "make build"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	diagnostics := server.validatePlugins(pipeline)
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	setRelatedInformationURI(diagnostics, uri)
+
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+	}
+
+	diagnostic := diagnostics[0]
+	if diagnostic.Range.Start.Line != 5 {
+		t.Errorf("Expected duplicate reported on line 5, got %d", diagnostic.Range.Start.Line)
+	}
+
+	if len(diagnostic.RelatedInformation) != 1 {
+		t.Fatalf("Expected 1 related information entry, got %d", len(diagnostic.RelatedInformation))
+	}
+
+	related := diagnostic.RelatedInformation[0]
+	if related.Location.URI != uri {
+		t.Errorf("Expected related URI %s, got %s", uri, related.Location.URI)
+	}
+	if related.Location.Range.Start.Line != 2 {
+		t.Errorf("Expected related location on line 2, got %d", related.Location.Range.Start.Line)
+	}
+	if related.Message != "First defined here" {
+		t.Errorf("Unexpected related message: %q", related.Message)
+	}
+}
+
+func TestServer_DuplicateStepKeyInGroup(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - label: "Lint"
+    key: "lint"
+    command: "make lint"
+  - group: "Checks"
+    key: "checks"
+    steps:
+      - label: "Lint again"
+        key: "lint"
+        command: "make lint"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var duplicates []protocol.Diagnostic
+	for _, d := range server.validatePlugins(pipeline) {
+		if d.Code == "duplicate-step-key" {
+			duplicates = append(duplicates, d)
+		}
+	}
+	if len(duplicates) != 1 {
+		t.Fatalf("Expected 1 duplicate key diagnostic, got %d", len(duplicates))
+	}
+
+	expected := protocol.Range{
+		Start: protocol.Position{Line: 8, Character: 13},
+		End:   protocol.Position{Line: 8, Character: 19},
+	}
+	if duplicates[0].Range != expected {
+		t.Errorf("Expected range %+v, got %+v", expected, duplicates[0].Range)
+	}
+	if related := duplicates[0].RelatedInformation; len(related) != 1 || related[0].Location.Range.Start.Line != 2 {
+		t.Errorf("Expected the first definition on line 2, got %+v", related)
+	}
+}
+
+func TestServer_SkippedStepInGroup(t *testing.T) {
+	server := newTestServer()
+
+	// The steps list items aren't indented, and the skipped step is in a group
+	content := `steps:
+- group: "Checks"
+  steps:
+  - label: "Lint"
+    key: "lint"
+    skip: true
+    command: "make lint"
+- label: "Deploy"
+  depends_on: "lint"
+  command: "make deploy"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	found := make(map[string]protocol.Diagnostic)
+	for _, d := range server.validatePlugins(pipeline) {
+		if code, _ := d.Code.(string); code == "skipped-step" || code == "depends-on-skipped-step" {
+			found[code] = d
+		}
+	}
+
+	expected := map[string]protocol.Position{
+		"skipped-step":            {Line: 5, Character: 4},
+		"depends-on-skipped-step": {Line: 8, Character: 2},
+	}
+	for code, start := range expected {
+		d, ok := found[code]
+		if !ok {
+			t.Errorf("Expected a %s diagnostic", code)
+			continue
+		}
+		if d.Range.Start != start {
+			t.Errorf("Expected %s to start at %+v, got %+v", code, start, d.Range.Start)
+		}
+	}
+	if msg := found["skipped-step"].Message; msg != "Step 'Lint' is skipped and will never run" {
+		t.Errorf("Unexpected message: %s", msg)
+	}
+}
+
+func TestServer_DuplicateFieldKeyRelatedInformation(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - block: "Release"
+    fields:
+      - text: "Version"
+        key: "version"
+      - select: "Channel"
+        key: "channel"
+        options:
+          - label: "Stable"
+            value: "stable"
+      - text: "Version again"
+        key: "version"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	diagnostics := server.validatePlugins(pipeline)
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+	}
+
+	diagnostic := diagnostics[0]
+	if diagnostic.Range.Start.Line != 11 {
+		t.Errorf("Expected duplicate reported on line 11, got %d", diagnostic.Range.Start.Line)
+	}
+	if len(diagnostic.RelatedInformation) != 1 {
+		t.Fatalf("Expected 1 related information entry, got %d", len(diagnostic.RelatedInformation))
+	}
+	if related := diagnostic.RelatedInformation[0]; related.Location.Range.Start.Line != 4 {
+		t.Errorf("Expected related location on line 4, got %d", related.Location.Range.Start.Line)
+	}
+}
+
+func TestServer_RetryManualRanges(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - command: "make deploy"
+    retry:
+      manual:
+        allowed: "no"
+        permit_on_pass: true`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var ranges []protocol.Range
+	for _, diagnostic := range server.validatePlugins(pipeline) {
+		if diagnostic.Code == "invalid-retry-manual" {
+			ranges = append(ranges, diagnostic.Range)
+		}
+	}
+
+	expected := []protocol.Range{
+		{Start: protocol.Position{Line: 4, Character: 17}, End: protocol.Position{Line: 4, Character: 21}},
+		{Start: protocol.Position{Line: 5, Character: 8}, End: protocol.Position{Line: 5, Character: 22}},
+	}
+	if len(ranges) != len(expected) {
+		t.Fatalf("Expected %d diagnostics, got %d", len(expected), len(ranges))
+	}
+	for i := range expected {
+		if ranges[i] != expected[i] {
+			t.Errorf("Diagnostic %d: expected range %v, got %v", i, expected[i], ranges[i])
+		}
+	}
+}
+
+func TestServer_SchemaDeprecatedKeys(t *testing.T) {
+	server := newTestServer()
+
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	schemaData := `{
+		"definitions": {
+			"commandStep": {
+				"properties": {
+					"priority": {"type": "integer", "deprecated": true, "description": "Use 'queue' instead"},
+					"id": {"type": "string", "deprecated": true}
+				}
+			},
+			"blockStep": {
+				"properties": {
+					"fields": {"type": "array", "deprecated": true, "description": "Use an input step instead"}
+				}
+			},
+			"notify": {
+				"properties": {
+					"label": {"type": "string", "deprecated": true}
+				}
+			}
+		}
+	}`
+	if err := os.WriteFile(schemaPath, []byte(schemaData), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	server.schemaLoader.SetURL(schemaPath)
+	if _, err := server.schemaLoader.GetSchemaData(); err != nil {
+		t.Fatalf("Failed to load schema: %v", err)
+	}
+
+	content := `steps:
+  - label: "Build"
+    command: "make"
+    priority: 1
+    id: "build"
+  - input: "Release"
+    fields:
+      - text: "Version"
+        key: "version"
+  - block: "Deploy?"
+    fields:
+      - text: "Reason"
+        key: "reason"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var messages []string
+	for _, diagnostic := range server.validatePlugins(pipeline) {
+		if diagnostic.Code != "deprecated-key" {
+			continue
+		}
+		messages = append(messages, diagnostic.Message)
+		if len(diagnostic.Tags) != 1 || diagnostic.Tags[0] != protocol.DiagnosticTagDeprecated {
+			t.Errorf("Expected a deprecated tag on %q", diagnostic.Message)
+		}
+	}
+
+	// id keeps its hand-written hint, label is only deprecated outside steps and
+	// fields only on block steps
+	expected := []string{
+		"'priority' is deprecated - Use 'queue' instead",
+		"'id' is deprecated - use 'key' instead",
+		"'fields' is deprecated - Use an input step instead",
+	}
+	if strings.Join(messages, "\n") != strings.Join(expected, "\n") {
+		t.Errorf("Expected %v, got %v", expected, messages)
+	}
+}
+
+func TestServer_DeprecatedKeyTags(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - label: "Build"
+    id: "build"
+    command: "make build"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	diagnostics := server.validatePlugins(pipeline)
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+	}
+
+	tags := diagnostics[0].Tags
+	if len(tags) != 1 || tags[0] != protocol.DiagnosticTagDeprecated {
+		t.Errorf("Expected deprecated tag, got %v", tags)
+	}
+
+	expected := protocol.Range{
+		Start: protocol.Position{Line: 2, Character: 4},
+		End:   protocol.Position{Line: 2, Character: 6},
+	}
+	if diagnostics[0].Range != expected {
+		t.Errorf("Expected range %v, got %v", expected, diagnostics[0].Range)
+	}
+}
+
+func TestServer_PluginEntriesHaveOneKey(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - label: "Build"
+    command: "make build"
+    plugins:
+      - docker#v5.13.0:
+          image: "node:18"
+        docker-compose#v4.0.0:
+          run: app
+      - artifacts#v1.9.0
+      - {}`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	diagnostics := server.validatePluginEntries(pipeline.PluginEntries(), splitLines(content))
+	if len(diagnostics) != 2 {
+		t.Fatalf("Expected 2 diagnostics, got %d", len(diagnostics))
+	}
+
+	merged := diagnostics[0]
+	if merged.Message != "Plugin 1 in step 1 has 2 keys - each plugin must be a single plugin reference (check the indentation of its configuration)" {
+		t.Errorf("Unexpected message: %q", merged.Message)
+	}
+	if merged.Range.Start.Line != 4 || merged.Range.End.Line != 7 {
+		t.Errorf("Expected range over lines 4-7, got %d-%d", merged.Range.Start.Line, merged.Range.End.Line)
+	}
+
+	empty := diagnostics[1]
+	if empty.Message != "Plugin 3 in step 1 is empty - each plugin must be a single plugin reference" {
+		t.Errorf("Unexpected message: %q", empty.Message)
+	}
+	if empty.Range.Start.Line != 9 {
+		t.Errorf("Expected empty plugin on line 9, got %d", empty.Range.Start.Line)
+	}
+}
+
+func TestServer_AllowedPlugins(t *testing.T) {
+	content := `steps:
+  - label: "Build"
+    command: "make build"
+    plugins:
+      - docker#v5.13.0:
+          image: "golang:1.22"
+      - "my-org/deploy#v1.0.0"
+      - other-org/untrusted#v2.0.0:
+          enabled: true`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+	references := pluginReferences(pipeline)
+
+	t.Run("no allowed list configured", func(t *testing.T) {
+		server := newTestServer()
+		if diagnostics := server.validateAllowedPlugins(references); len(diagnostics) != 0 {
+			t.Errorf("Expected no diagnostics without allowedPlugins, got %d", len(diagnostics))
+		}
+	})
+
+	t.Run("allowed list configured", func(t *testing.T) {
+		server := newTestServer()
+		server.settings.AllowedPlugins = []string{"buildkite-plugins/docker", "my-org/deploy"}
+
+		diagnostics := server.validateAllowedPlugins(references)
+		if len(diagnostics) != 1 {
+			t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+		}
+
+		d := diagnostics[0]
+		if d.Message != "Plugin 'other-org/untrusted#v2.0.0' is not in the list of allowed plugins" {
+			t.Errorf("Unexpected message: %q", d.Message)
+		}
+		if d.Severity != protocol.DiagnosticSeverityError || d.Code != "plugin-not-allowed" {
+			t.Errorf("Expected plugin-not-allowed error, got %v %v", d.Severity, d.Code)
+		}
+		expected := protocol.Range{
+			Start: protocol.Position{Line: 7, Character: 8},
+			End:   protocol.Position{Line: 7, Character: 34},
+		}
+		if d.Range != expected {
+			t.Errorf("Expected range %+v, got %+v", expected, d.Range)
+		}
+	})
+}
+
+func TestPluginIdentity(t *testing.T) {
+	tests := map[string]string{
+		"docker#v5.13.0":           "buildkite-plugins/docker",
+		"buildkite-plugins/docker": "buildkite-plugins/docker",
+		"my-org/deploy#main":       "my-org/deploy",
+		"./plugins/local#v1":       "./plugins/local",
+	}
+
+	for ref, expected := range tests {
+		if got := pluginIdentity(ref); got != expected {
+			t.Errorf("pluginIdentity(%q) = %q, want %q", ref, got, expected)
+		}
+	}
+}
+
+func TestServer_UnpinnedPlugins(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - label: "Build"
+    command: "make build"
+    plugins:
+      - docker#v5.13.0:
+          image: "golang:1.22"
+      - "my-org/deploy"
+      - ./.buildkite/plugins/local:
+          enabled: true
+      - docker-compose:
+          run: app
+  - group: "Deploy"
+    steps:
+      - command: "make deploy"
+        plugins:
+          - ecr: {login: true}`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	diagnostics := server.validateUnpinnedPlugins(pluginReferences(pipeline))
+	expected := []struct {
+		message string
+		rng     protocol.Range
+	}{
+		{
+			message: "Plugin 'my-org/deploy' isn't pinned to a version - add a version (e.g. 'my-org/deploy#v1.0.0') so builds are reproducible",
+			rng:     protocol.Range{Start: protocol.Position{Line: 6, Character: 9}, End: protocol.Position{Line: 6, Character: 22}},
+		},
+		{
+			message: "Plugin 'docker-compose' isn't pinned to a version - add a version (e.g. 'docker-compose#v1.0.0') so builds are reproducible",
+			rng:     protocol.Range{Start: protocol.Position{Line: 9, Character: 8}, End: protocol.Position{Line: 9, Character: 22}},
+		},
+		{
+			message: "Plugin 'ecr' isn't pinned to a version - add a version (e.g. 'ecr#v1.0.0') so builds are reproducible",
+			rng:     protocol.Range{Start: protocol.Position{Line: 15, Character: 12}, End: protocol.Position{Line: 15, Character: 15}},
+		},
+	}
+	if len(diagnostics) != len(expected) {
+		t.Fatalf("Expected %d diagnostics, got %d", len(expected), len(diagnostics))
+	}
+	for i, want := range expected {
+		d := diagnostics[i]
+		if d.Message != want.message {
+			t.Errorf("Diagnostic %d: expected message %q, got %q", i, want.message, d.Message)
+		}
+		if d.Range != want.rng {
+			t.Errorf("Diagnostic %d: expected range %+v, got %+v", i, want.rng, d.Range)
+		}
+		if d.Severity != protocol.DiagnosticSeverityInformation || d.Code != "unpinned-plugin" {
+			t.Errorf("Diagnostic %d: expected unpinned-plugin information, got %v %v", i, d.Severity, d.Code)
+		}
+	}
+}
+
+func TestServer_UnknownPluginOptions(t *testing.T) {
+	server := newTestServer()
+	server.pluginRegistry.SetCatalog([]plugins.CatalogEntry{{
+		Name: "acme/deploy",
+		Configuration: map[string]any{
+			"properties": map[string]any{
+				"dry_run": map[string]any{"type": "boolean"},
+				"region":  map[string]any{"type": "string"},
+			},
+			"additionalProperties": false,
+		},
+	}})
+
+	content := `steps:
+  - command: "make deploy"
+    plugins:
+      - acme/deploy#v1.0.0:
+          regoin: us-east-1
+          dry_run: true
+          zzz: true`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var unknown []protocol.Diagnostic
+	for _, d := range server.validatePlugins(pipeline) {
+		switch d.Code {
+		case "unknown-plugin-option":
+			unknown = append(unknown, d)
+		case "plugin-config-error":
+			t.Errorf("Expected unknown options to be reported once, got %q", d.Message)
+		}
+	}
+
+	expected := []struct {
+		message string
+		line    uint32
+	}{
+		{message: "Plugin 'acme/deploy#v1.0.0' doesn't have a 'regoin' option - did you mean 'region'?", line: 4},
+		{message: "Plugin 'acme/deploy#v1.0.0' doesn't have a 'zzz' option", line: 6},
+	}
+	if len(unknown) != len(expected) {
+		t.Fatalf("Expected %d diagnostics, got %d", len(expected), len(unknown))
+	}
+	for i, want := range expected {
+		d := unknown[i]
+		if d.Message != want.message {
+			t.Errorf("Diagnostic %d: expected message %q, got %q", i, want.message, d.Message)
+		}
+		if d.Range.Start.Line != want.line || d.Range.Start.Character != 10 {
+			t.Errorf("Diagnostic %d: expected range starting at %d:10, got %+v", i, want.line, d.Range)
+		}
+		if d.Severity != protocol.DiagnosticSeverityWarning {
+			t.Errorf("Diagnostic %d: expected warning, got %v", i, d.Severity)
+		}
+	}
+}
+
+func TestServer_UnknownPluginOptionsWithoutPluginsList(t *testing.T) {
+	server := newTestServer()
+	server.pluginRegistry.SetCatalog([]plugins.CatalogEntry{
+		{
+			Name: "acme/deploy",
+			Configuration: map[string]any{
+				"properties":           map[string]any{"region": map[string]any{"type": "string"}},
+				"additionalProperties": false,
+			},
+		},
+		{
+			Name:          "acme/notify",
+			Configuration: map[string]any{"additionalProperties": false},
+		},
+	})
+
+	// Steps at column 0, entries with several plugins, plugins given as a
+	// mapping, and options merged from an anchor are all checked
+	content := `defaults: &defaults
+  regoin: us-east-1
+steps:
+- command: "make deploy"
+  plugins:
+    - acme/deploy#v1.0.0:
+        zone: a
+      acme/notify#v1.0.0:
+        channel: ops
+- command: "make rollback"
+  plugins:
+    acme/deploy#v1.0.0:
+      <<: *defaults`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	diagnostics := server.validateUnknownPluginOptions(pluginReferences(pipeline))
+	expected := []struct {
+		message string
+		rng     protocol.Range
+	}{
+		{
+			message: "Plugin 'acme/deploy#v1.0.0' doesn't have a 'zone' option",
+			rng:     protocol.Range{Start: protocol.Position{Line: 6, Character: 8}, End: protocol.Position{Line: 6, Character: 12}},
+		},
+		{
+			message: "Plugin 'acme/notify#v1.0.0' doesn't have a 'channel' option",
+			rng:     protocol.Range{Start: protocol.Position{Line: 8, Character: 8}, End: protocol.Position{Line: 8, Character: 15}},
+		},
+		{
+			message: "Plugin 'acme/deploy#v1.0.0' doesn't have a 'regoin' option - did you mean 'region'?",
+			rng:     protocol.Range{Start: protocol.Position{Line: 11, Character: 4}, End: protocol.Position{Line: 11, Character: 22}},
+		},
+	}
+	if len(diagnostics) != len(expected) {
+		t.Fatalf("Expected %d diagnostics, got %d", len(expected), len(diagnostics))
+	}
+	for i, want := range expected {
+		d := diagnostics[i]
+		if d.Message != want.message {
+			t.Errorf("Diagnostic %d: expected message %q, got %q", i, want.message, d.Message)
+		}
+		if d.Range != want.rng {
+			t.Errorf("Diagnostic %d: expected range %+v, got %+v", i, want.rng, d.Range)
+		}
+	}
+}
+
+func TestServer_StepCountLimit(t *testing.T) {
+	content := `env:
+  FOO: bar
+steps:
+  - command: "make build"
+  - wait
+  - group: "Tests"
+    steps:
+      - command: "make unit"
+      - command: "make integration"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+	var pipelineData map[string]interface{}
+	if err := json.Unmarshal(pipeline.JSONBytes, &pipelineData); err != nil {
+		t.Fatalf("Failed to decode pipeline: %v", err)
+	}
+	lines := strings.Split(content, "\n")
+
+	server := newTestServer()
+	if diagnostics := server.validateStepCount(pipelineData, lines); len(diagnostics) != 0 {
+		t.Errorf("Expected no diagnostics under the default limit, got %d", len(diagnostics))
+	}
+
+	server.settings.MaxSteps = 4
+	diagnostics := server.validateStepCount(pipelineData, lines)
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+	}
+
+	d := diagnostics[0]
+	if d.Severity != protocol.DiagnosticSeverityInformation || d.Code != "too-many-steps" {
+		t.Errorf("Expected too-many-steps information, got %v %v", d.Severity, d.Code)
+	}
+	if !strings.HasPrefix(d.Message, "Pipeline has 5 steps, more than the limit of 4") {
+		t.Errorf("Unexpected message: %q", d.Message)
+	}
+	if d.Range.Start.Line != 2 || d.Range.End.Character != 5 {
+		t.Errorf("Expected range on the steps key, got %+v", d.Range)
+	}
+}
+
+func TestDescribeStep(t *testing.T) {
+	tests := []struct {
+		step     map[string]interface{}
+		expected string
+	}{
+		{step: map[string]interface{}{"label": "Deploy", "key": "deploy", "command": "make deploy"}, expected: "Step 'Deploy'"},
+		{step: map[string]interface{}{"group": "Tests", "steps": []interface{}{}}, expected: "Step 'Tests'"},
+		{step: map[string]interface{}{"block": "Release?"}, expected: "Step 'Release?'"},
+		{step: map[string]interface{}{"key": "build", "command": "make"}, expected: "Step 'build'"},
+		{step: map[string]interface{}{"label": " ", "command": "make"}, expected: "Step 3"},
+		{step: map[string]interface{}{"wait": nil}, expected: "Step 3"},
+	}
+
+	for _, tt := range tests {
+		if got := describeStep(tt.step, 2); got != tt.expected {
+			t.Errorf("describeStep(%v) = %q, want %q", tt.step, got, tt.expected)
+		}
+	}
+}
+
+func TestServer_NestedGroupRange(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - group: "Tests"
+    steps:
+      - label: "Unit"
+        command: "make unit"
+      - group: "Integration"  # not supported
+        steps:
+          - label: "API"
+            command: "make api"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var ranges []protocol.Range
+	for _, d := range server.validatePlugins(pipeline) {
+		if d.Code == "nested-group" {
+			ranges = append(ranges, d.Range)
+		}
+	}
+
+	expected := protocol.Range{
+		Start: protocol.Position{Line: 5, Character: 8},
+		End:   protocol.Position{Line: 5, Character: 45},
+	}
+	if len(ranges) != 1 || ranges[0] != expected {
+		t.Errorf("Expected nested group range %v, got %v", expected, ranges)
+	}
+}
+
+func TestServer_ConditionalMemberRange(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - label: "Test"
+    command: "make test"
+    if: 'build.pull_request_number == 1'
+  - label: "Deploy"
+    command: "make deploy"
+    if: "build.branch == \"main\" && pipeline.name == \"x\""`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var ranges []protocol.Range
+	for _, d := range server.validatePlugins(pipeline) {
+		if d.Code == "unknown-conditional-member" {
+			ranges = append(ranges, d.Range)
+		}
+	}
+
+	expected := []protocol.Range{
+		{Start: protocol.Position{Line: 3, Character: 15}, End: protocol.Position{Line: 3, Character: 34}},
+		{Start: protocol.Position{Line: 6, Character: 46}, End: protocol.Position{Line: 6, Character: 50}},
+	}
+	if !slices.Equal(ranges, expected) {
+		t.Errorf("Expected unknown member ranges %v, got %v", expected, ranges)
+	}
+}
+
+func TestServer_InvisibleCharacterRange(t *testing.T) {
+	server := newTestServer()
+
+	// The rocket is two UTF-16 code units wide
+	lines := []string{
+		"steps:",
+		"  - label: \"\U0001F680 Deploy\u200b\"",
+	}
+
+	diagnostics := server.validateInvisibleCharacters(lines)
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+	}
+
+	expected := protocol.Range{
+		Start: protocol.Position{Line: 1, Character: 21},
+		End:   protocol.Position{Line: 1, Character: 22},
+	}
+	if diagnostics[0].Range != expected {
+		t.Errorf("Expected range %v, got %v", expected, diagnostics[0].Range)
+	}
+}
+
+func TestServer_PluginOptionTypes(t *testing.T) {
+	server := newTestServer()
+
+	content := `steps:
+  - command: "make test"
+    plugins:
+      - docker#v5.13.0:
+          image: ["node:20"]
+          environment: "CI=true"
+          always-pull: true
+          volumes:
+            - ".:/app"
+          propagate-environment:
+            enabled: true
+      - docker-compose#v5.10.0:
+          run: app
+          config:
+            - docker-compose.yml
+      - my-org/docker#v1.0.0:
+          image: false`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var mismatches []protocol.Diagnostic
+	for _, d := range server.validatePlugins(pipeline) {
+		if d.Code == "plugin-option-type" {
+			mismatches = append(mismatches, d)
+		}
+	}
+
+	expected := []struct {
+		message string
+		rng     protocol.Range
+	}{
+		{
+			message: "Plugin 'docker#v5.13.0' option 'environment' should be a list, not a string",
+			rng:     protocol.Range{Start: protocol.Position{Line: 5, Character: 23}, End: protocol.Position{Line: 5, Character: 32}},
+		},
+		{
+			message: "Plugin 'docker#v5.13.0' option 'image' should be a string, not a list",
+			rng:     protocol.Range{Start: protocol.Position{Line: 4, Character: 17}, End: protocol.Position{Line: 4, Character: 28}},
+		},
+		{
+			message: "Plugin 'docker#v5.13.0' option 'propagate-environment' should be a boolean, not a mapping",
+			rng:     protocol.Range{Start: protocol.Position{Line: 9, Character: 10}, End: protocol.Position{Line: 9, Character: 31}},
+		},
+	}
+	if len(mismatches) != len(expected) {
+		t.Fatalf("Expected %d diagnostics, got %d: %v", len(expected), len(mismatches), mismatches)
+	}
+	for i, want := range expected {
+		if mismatches[i].Message != want.message {
+			t.Errorf("Diagnostic %d: expected message %q, got %q", i, want.message, mismatches[i].Message)
+		}
+		if mismatches[i].Range != want.rng {
+			t.Errorf("Diagnostic %d: expected range %v, got %v", i, want.rng, mismatches[i].Range)
+		}
+	}
+}
+
+func TestServer_AnchoredStepTemplates(t *testing.T) {
+	server := newTestServer()
+	templates := `x-tests: &tests
+  group: "Tests"
+  steps:
+    - label: "Unit"
+      command: "make unit"
+env:
+  CI: "true"
+`
+
+	t.Run("anchors outside steps aren't steps", func(t *testing.T) {
+		content := templates + `steps:
+  - label: "Build"
+    command: "make build"
+  - *tests`
+
+		pipeline, err := parser.ParseYAML([]byte(content))
+		if err != nil {
+			t.Fatalf("Failed to parse YAML: %v", err)
+		}
+		if diagnostics := server.validatePlugins(pipeline); len(diagnostics) != 0 {
+			t.Errorf("Expected no diagnostics, got %+v", diagnostics)
+		}
+	})
+
+	t.Run("bare anchor inside steps is still a step", func(t *testing.T) {
+		content := templates + `steps:
+  - label: "Build"
+    command: "make build"
+  - &linux
+    agents:
+      queue: "linux"`
+
+		pipeline, err := parser.ParseYAML([]byte(content))
+		if err != nil {
+			t.Fatalf("Failed to parse YAML: %v", err)
+		}
+
+		var lines []uint32
+		for _, d := range server.validatePlugins(pipeline) {
+			if d.Code == "missing-step-type" {
+				lines = append(lines, d.Range.Start.Line)
+			}
+		}
+		if len(lines) != 1 || lines[0] != 10 {
+			t.Errorf("Expected the bare anchor on line 10 to be missing a step type, got lines %v", lines)
+		}
+	})
+}
+
+func TestServer_DuplicateMappingKeys(t *testing.T) {
+	server := newTestServer()
+	content := `steps:
+  - label: "Build"
+    command: "make build"
+    command: "make test"
+    env:
+      FOO: "1"
+      "FOO": "2"
+  - label: "Deploy"
+    command: "make deploy"
+    plugins:
+      - docker#v5.13.0:
+          image: "node"
+      - docker#v5.13.0:
+          image: "ruby"
+    notes: |
+      label: one
+      label: two
+env:
+  FOO: "1"`
+
+	if _, err := parser.ParseYAML([]byte(content)); err == nil {
+		t.Fatal("Expected duplicate keys to fail the YAML parse")
+	}
+
+	diagnostics := server.validateDuplicateMappingKeys(strings.Split(content, "\n"))
+
+	expected := []struct {
+		message   string
+		rng       protocol.Range
+		firstLine uint32
+	}{
+		{
+			message:   "Key 'command' is already defined in this mapping - remove or rename the duplicate, as YAML doesn't allow a key to repeat",
+			rng:       protocol.Range{Start: protocol.Position{Line: 3, Character: 4}, End: protocol.Position{Line: 3, Character: 11}},
+			firstLine: 2,
+		},
+		{
+			message:   "Key 'FOO' is already defined in this mapping - remove or rename the duplicate, as YAML doesn't allow a key to repeat",
+			rng:       protocol.Range{Start: protocol.Position{Line: 6, Character: 6}, End: protocol.Position{Line: 6, Character: 11}},
+			firstLine: 5,
+		},
+	}
+	if len(diagnostics) != len(expected) {
+		t.Fatalf("Expected %d duplicate key diagnostics, got %+v", len(expected), diagnostics)
+	}
+	for i, want := range expected {
+		d := diagnostics[i]
+		if d.Code != "duplicate-key" || d.Message != want.message || d.Range != want.rng {
+			t.Errorf("Diagnostic %d: expected %q at %v, got %q at %v", i, want.message, want.rng, d.Message, d.Range)
+		}
+		if len(d.RelatedInformation) != 1 || d.RelatedInformation[0].Location.Range.Start.Line != want.firstLine {
+			t.Errorf("Diagnostic %d: expected related information on line %d, got %+v", i, want.firstLine, d.RelatedInformation)
+		}
+	}
+}
+
+func TestServer_MissingLabelRange(t *testing.T) {
+	server := newTestServer()
+	content := `steps:
+  - command: "make build"
+    artifact_paths: "dist/*"
+
+  - label: "Test"
+    command: "make test"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var ranges []protocol.Range
+	for _, d := range server.validatePlugins(pipeline) {
+		if d.Code == "missing-label" {
+			ranges = append(ranges, d.Range)
+		}
+	}
+
+	expected := protocol.Range{
+		Start: protocol.Position{Line: 1, Character: 2},
+		End:   protocol.Position{Line: 2, Character: 28},
+	}
+	if len(ranges) != 1 || ranges[0] != expected {
+		t.Errorf("Expected a single missing-label diagnostic over the first step %v, got %v", expected, ranges)
+	}
+}
+
+func TestServer_JSONPipeline(t *testing.T) {
+	server := newTestServer()
+	content := `{
+  "env": "production",
+  "steps": [
+    {"label": "Build", "command": "make build"},
+    {"command": "make test"}
+  ]
+}`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse JSON pipeline: %v", err)
+	}
+
+	var codes []string
+	for _, d := range server.validatePlugins(pipeline) {
+		code, _ := d.Code.(string)
+		codes = append(codes, code)
+	}
+
+	expected := []string{"invalid-env", "missing-label"}
+	if !slices.Equal(codes, expected) {
+		t.Errorf("Expected diagnostics %v for the JSON pipeline, got %v", expected, codes)
+	}
+}
+
+func TestServer_LabelLength(t *testing.T) {
+	content := `steps:
+  - label: ":rocket: Deploy the application to every production region"
+    command: "make deploy"
+  - label: "Test"
+    command: "make test"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+	var pipelineData map[string]interface{}
+	if err := json.Unmarshal(pipeline.JSONBytes, &pipelineData); err != nil {
+		t.Fatalf("Failed to decode pipeline: %v", err)
+	}
+	lines := strings.Split(content, "\n")
+
+	server := newTestServer()
+	if diagnostics := server.validateLabelLength(pipelineData, lines); len(diagnostics) != 0 {
+		t.Errorf("Expected no diagnostics under the default limit, got %d", len(diagnostics))
+	}
+
+	server.settings.MaxLabelLength = 40
+	diagnostics := server.validateLabelLength(pipelineData, lines)
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d", len(diagnostics))
+	}
+
+	d := diagnostics[0]
+	if d.Severity != protocol.DiagnosticSeverityInformation || d.Code != "label-too-long" {
+		t.Errorf("Expected label-too-long information, got %v %v", d.Severity, d.Code)
+	}
+	if !strings.HasPrefix(d.Message, "Step 1 label is 58 characters, more than the limit of 40") {
+		t.Errorf("Unexpected message: %q", d.Message)
+	}
+	if d.Range.Start.Line != 1 || d.Range.Start.Character != 11 || d.Range.End.Character != uint32(len(lines[1])) {
+		t.Errorf("Expected range on the label value, got %+v", d.Range)
+	}
+}
+
+func TestServer_EnvValueLength(t *testing.T) {
+	content := `env:
+  REGION: "us-east-1"
+  BANNER: "Deploying the application to every region"
+steps:
+  - label: "Deploy"
+    command: "make deploy"
+    env:
+      CERT: |
+        -----BEGIN CERTIFICATE-----
+        MIIBszCCAVmgAwIBAgIU
+        -----END CERTIFICATE-----`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+	var pipelineData map[string]interface{}
+	if err := json.Unmarshal(pipeline.JSONBytes, &pipelineData); err != nil {
+		t.Fatalf("Failed to decode pipeline: %v", err)
+	}
+	lines := strings.Split(content, "\n")
+
+	server := newTestServer()
+	if diagnostics := server.validateEnvValueLength(pipelineData, lines); len(diagnostics) != 0 {
+		t.Errorf("Expected no diagnostics under the default limit, got %d", len(diagnostics))
+	}
+
+	server.settings.MaxEnvValueLength = 32
+	diagnostics := server.validateEnvValueLength(pipelineData, lines)
+	if len(diagnostics) != 2 {
+		t.Fatalf("Expected 2 diagnostics, got %d", len(diagnostics))
+	}
+
+	d := diagnostics[0]
+	if d.Severity != protocol.DiagnosticSeverityWarning || d.Code != "env-value-too-long" {
+		t.Errorf("Expected env-value-too-long warning, got %v %v", d.Severity, d.Code)
+	}
+	if !strings.HasPrefix(d.Message, "Env 'BANNER' value is 41 bytes, more than the limit of 32") {
+		t.Errorf("Unexpected message: %q", d.Message)
+	}
+	if d.Range.Start.Line != 2 || d.Range.Start.Character != 10 || d.Range.End.Character != uint32(len(lines[2])) {
+		t.Errorf("Expected range on the BANNER value, got %+v", d.Range)
+	}
+
+	// A block scalar is covered from its indicator to the last line
+	d = diagnostics[1]
+	if !strings.HasPrefix(d.Message, "Env 'CERT' value is") {
+		t.Errorf("Unexpected message: %q", d.Message)
+	}
+	if d.Range.Start.Line != 7 || d.Range.Start.Character != 12 ||
+		d.Range.End.Line != 10 || d.Range.End.Character != uint32(len(lines[10])) {
+		t.Errorf("Expected range over the CERT block, got %+v", d.Range)
+	}
+}
+
+func TestServer_TopLevelKeyOrder(t *testing.T) {
+	content := `steps:
+  - label: "Build"
+    command: "make build"
+notify:
+  - email: "team@example.com"
+env:
+  CI: "true"
+agents:
+  queue: "default"`
+	lines := strings.Split(content, "\n")
+
+	server := newTestServer()
+	if diagnostics := server.validateTopLevelKeyOrder(lines); len(diagnostics) != 0 {
+		t.Errorf("Expected no diagnostics without a preferred order, got %+v", diagnostics)
+	}
+
+	server.settings.TopLevelKeyOrder = []string{"env", "agents", "steps"}
+	diagnostics := server.validateTopLevelKeyOrder(lines)
+
+	expected := []struct {
+		message string
+		line    uint32
+	}{
+		{message: "'env' should come before 'steps' - the preferred order of top-level keys is env, agents, steps", line: 5},
+		{message: "'agents' should come before 'steps' - the preferred order of top-level keys is env, agents, steps", line: 7},
+	}
+	if len(diagnostics) != len(expected) {
+		t.Fatalf("Expected %d diagnostics, got %+v", len(expected), diagnostics)
+	}
+	for i, want := range expected {
+		d := diagnostics[i]
+		if d.Code != "top-level-key-order" || d.Severity != protocol.DiagnosticSeverityInformation || d.Message != want.message {
+			t.Errorf("Diagnostic %d: expected %q, got %s %q", i, want.message, d.Code, d.Message)
+		}
+		if d.Range.Start.Line != want.line || d.Range.Start.Character != 0 || d.Range.End.Character != uint32(len(lines[want.line])-1) {
+			t.Errorf("Diagnostic %d: expected the key on line %d, got %+v", i, want.line, d.Range)
+		}
+	}
+}
+
+func TestServer_SlackChannelRange(t *testing.T) {
+	server := newTestServer()
+	content := `steps:
+  - command: "make deploy"
+    notify:
+      - slack:
+          channels: ["#deploys", "releases"]`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var found bool
+	for _, d := range server.validatePlugins(pipeline) {
+		if d.Code != "invalid-slack-channel" {
+			continue
+		}
+		found = true
+		expected := protocol.Range{
+			Start: protocol.Position{Line: 4, Character: 34},
+			End:   protocol.Position{Line: 4, Character: 42},
+		}
+		if d.Range != expected {
+			t.Errorf("Expected range %+v, got %+v", expected, d.Range)
+		}
+	}
+	if !found {
+		t.Error("Expected an invalid-slack-channel diagnostic")
+	}
+}
+
+func TestServer_NotifyWebhookRange(t *testing.T) {
+	server := newTestServer()
+	content := `notify:
+  - email: "team@example.com"
+  - webhook: "hooks.example.com/build" # deploys
+steps:
+  - command: "make deploy"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var found bool
+	for _, d := range server.validatePlugins(pipeline) {
+		if d.Code != "invalid-webhook-url" {
+			continue
+		}
+		found = true
+		expected := protocol.Range{
+			Start: protocol.Position{Line: 2, Character: 13},
+			End:   protocol.Position{Line: 2, Character: 38},
+		}
+		if d.Range != expected {
+			t.Errorf("Expected range %+v, got %+v", expected, d.Range)
+		}
+	}
+	if !found {
+		t.Error("Expected an invalid-webhook-url diagnostic")
+	}
+}
+
+func TestServer_ReservedEnvRange(t *testing.T) {
+	server := newTestServer()
+	content := `steps:
+  - command: "make build"
+    env:
+      "BUILDKITE_COMMIT": "HEAD"`
+
+	pipeline, err := parser.ParseYAML([]byte(content))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	var diagnostics []protocol.Diagnostic
+	for _, d := range server.validatePlugins(pipeline) {
+		if d.Code == "reserved-env-variable" {
+			diagnostics = append(diagnostics, d)
+		}
+	}
+	if len(diagnostics) != 1 {
+		t.Fatalf("Expected 1 diagnostic, got %d: %+v", len(diagnostics), diagnostics)
+	}
+
+	expected := protocol.Range{
+		Start: protocol.Position{Line: 3, Character: 6},
+		End:   protocol.Position{Line: 3, Character: 24},
+	}
+	if diagnostics[0].Range != expected {
+		t.Errorf("Expected range %+v, got %+v", expected, diagnostics[0].Range)
+	}
+}
+
+func TestServer_ForeignPipelineDiagnostic(t *testing.T) {
+	server := newTestServer()
+
+	tests := []struct {
+		name     string
+		content  string
+		expected string
+	}{
+		{
+			name: "github actions workflow",
+			content: `# CI
+name: CI
+on: [push]
+jobs:
+  build:
+    runs-on: ubuntu-latest`,
+			expected: "This doesn't look like a Buildkite pipeline - it has top-level 'on', 'jobs' but no 'steps', like a GitHub Actions workflow, so it hasn't been validated",
+		},
+		{
+			name: "buildkite pipeline",
+			content: `steps:
+  - command: make`,
+		},
+		{
+			name: "jobs alongside steps",
+			content: `jobs: 2
+steps:
+  - command: make`,
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			pipeline, err := parser.ParseYAML([]byte(tt.content))
+			if err != nil {
+				t.Fatalf("Failed to parse YAML: %v", err)
+			}
+			var pipelineData map[string]interface{}
+			if err := json.Unmarshal(pipeline.JSONBytes, &pipelineData); err != nil {
+				t.Fatalf("Failed to decode pipeline: %v", err)
+			}
+
+			diagnostic := server.foreignPipelineDiagnostic(pipelineData, splitLines(tt.content))
+			if tt.expected == "" {
+				if diagnostic != nil {
+					t.Errorf("Expected no diagnostic, got %q", diagnostic.Message)
+				}
+				return
+			}
+			if diagnostic == nil {
+				t.Fatal("Expected a diagnostic, got none")
+			}
+			if diagnostic.Message != tt.expected {
+				t.Errorf("Expected message %q, got %q", tt.expected, diagnostic.Message)
+			}
+			if diagnostic.Range.Start.Line != 1 {
+				t.Errorf("Expected diagnostic on line 1, got %d", diagnostic.Range.Start.Line)
+			}
+		})
+	}
 }
@@ -1,11 +1,16 @@
 package lsp
 
 import (
+	"hash/fnv"
+	"regexp"
+	"strings"
 	"sync"
 
 	"go.lsp.dev/protocol"
 
 	"github.com/mcncl/buildkite-ls/internal/context"
+	"github.com/mcncl/buildkite-ls/internal/parser"
+	"github.com/mcncl/buildkite-ls/internal/plugins"
 )
 
 // DocumentManager handles document content caching and state management
@@ -20,6 +25,132 @@ type Document struct {
 	Version int32
 	Content string
 	Lines   []string
+
+	// parsed is the content parsed on first use. Copies of the document share
+	// it, so a version is parsed at most once however many snapshots there are.
+	parsed *parsedContent
+}
+
+// parsedContent is a document's content parsed as a pipeline. pipeline is nil
+// if the content isn't valid YAML, in which case err says why.
+type parsedContent struct {
+	once     sync.Once
+	pipeline *parser.Pipeline
+	err      error
+}
+
+// newDocument creates a document. Its content is parsed when first needed, so
+// storing a document is cheap and validation and other features share one YAML
+// to JSON conversion per version.
+func newDocument(uri protocol.DocumentURI, version int32, content string) *Document {
+	return &Document{
+		URI:     uri,
+		Version: version,
+		Content: content,
+		Lines:   splitLines(content),
+		parsed:  &parsedContent{},
+	}
+}
+
+// Pipeline returns the parsed document, or the error from parsing it
+func (d *Document) Pipeline() (*parser.Pipeline, error) {
+	d.parsed.once.Do(func() {
+		d.parsed.pipeline, d.parsed.err = parser.ParseYAML([]byte(d.Content))
+	})
+	return d.parsed.pipeline, d.parsed.err
+}
+
+// JSONValue returns the document decoded as JSON data, or nil if it isn't
+// valid YAML or its top level isn't a mapping. Callers must not modify it.
+func (d *Document) JSONValue() map[string]interface{} {
+	pipeline, _ := d.Pipeline()
+	if pipeline == nil {
+		return nil
+	}
+	return pipeline.Data()
+}
+
+// PluginReference is a plugin used by a step, parsed from its reference, with
+// the reference's range in the document
+type PluginReference struct {
+	// Ref is the reference as written, e.g. "docker#v5.13.0"
+	Ref string
+	// Org is the GitHub organization, buildkite-plugins for official plugins.
+	// It's empty for local plugins and full repository URLs.
+	Org string
+	// Name is the plugin name, or the path or URL it's loaded from
+	Name string
+	// Version is empty when the reference isn't pinned
+	Version string
+	// Local is set for plugins loaded from a path in the repository
+	Local bool
+	Range protocol.Range
+	// Config is the plugin's configuration as JSON data, and Options the keys
+	// written at its top level. Both are empty for a bare reference.
+	Config  interface{}
+	Options []parser.PluginOption
+}
+
+// PluginReferences returns the plugins used by the document's steps, including
+// steps in groups, in document order. It returns nil if the document isn't valid YAML.
+func (d *Document) PluginReferences() []PluginReference {
+	pipeline, _ := d.Pipeline()
+	return pluginReferences(pipeline)
+}
+
+// pluginReferences parses the plugin references in a pipeline, so features
+// working from a parsed pipeline rather than a stored document share them
+func pluginReferences(pipeline *parser.Pipeline) []PluginReference {
+	if pipeline == nil {
+		return nil
+	}
+
+	var references []PluginReference
+	for _, node := range pipeline.PluginNodes() {
+		reference := parsePluginReference(node.Ref)
+		reference.Range = protocol.Range{
+			Start: protocol.Position{Line: uint32(node.Position.Line), Character: uint32(node.Position.Character)},
+			End:   protocol.Position{Line: uint32(node.Position.Line), Character: uint32(node.Position.Character + len(node.Ref))},
+		}
+		reference.Config = node.Config
+		reference.Options = node.Options
+		references = append(references, reference)
+	}
+	return references
+}
+
+// parsePluginReference splits a reference such as "my-org/deploy#v1.0.0" into
+// its parts. Local paths and full URLs are kept whole as the name.
+func parsePluginReference(ref string) PluginReference {
+	reference := PluginReference{Ref: ref}
+	location, version, _ := strings.Cut(ref, "#")
+	reference.Version = version
+
+	switch {
+	case strings.HasPrefix(location, ".") || strings.HasPrefix(location, "/"):
+		reference.Name = location
+		reference.Local = true
+	case strings.Contains(location, "://"):
+		reference.Name = location
+	default:
+		if parsed := plugins.ParsePluginReference(location); parsed != nil {
+			reference.Org = parsed.Org
+			reference.Name = parsed.Name
+		}
+	}
+
+	return reference
+}
+
+// pluginReferenceAt returns the reference whose range contains position
+func pluginReferenceAt(references []PluginReference, position protocol.Position) (PluginReference, bool) {
+	for _, reference := range references {
+		if position.Line == reference.Range.Start.Line &&
+			position.Character >= reference.Range.Start.Character && position.Character <= reference.Range.End.Character {
+			return reference, true
+		}
+	}
+	return PluginReference{}, false
 }
 
 // NewDocumentManager creates a new document manager
@@ -31,35 +162,24 @@ func NewDocumentManager() *DocumentManager {
 
 // OpenDocument stores a newly opened document
 func (dm *DocumentManager) OpenDocument(uri protocol.DocumentURI, version int32, content string) {
+	doc := newDocument(uri, version, content)
+
 	dm.mu.Lock()
 	defer dm.mu.Unlock()
 
-	dm.documents[uri] = &Document{
-		URI:     uri,
-		Version: version,
-		Content: content,
-		Lines:   splitLines(content),
-	}
+	dm.documents[uri] = doc
 }
 
-// UpdateDocument updates an existing document with new content
+// UpdateDocument updates an existing document with new content, creating it if
+// it isn't open. The document is replaced rather than modified, so callers
+// holding the previous version keep a consistent snapshot.
 func (dm *DocumentManager) UpdateDocument(uri protocol.DocumentURI, version int32, content string) {
+	doc := newDocument(uri, version, content)
+
 	dm.mu.Lock()
 	defer dm.mu.Unlock()
 
-	if doc, exists := dm.documents[uri]; exists {
-		doc.Version = version
-		doc.Content = content
-		doc.Lines = splitLines(content)
-	} else {
-		// Document doesn't exist, create it
-		dm.documents[uri] = &Document{
-			URI:     uri,
-			Version: version,
-			Content: content,
-			Lines:   splitLines(content),
-		}
-	}
+	dm.documents[uri] = doc
 }
 
 // CloseDocument removes a document from the cache
@@ -79,6 +199,18 @@ func (dm *DocumentManager) GetDocument(uri protocol.DocumentURI) (*Document, boo
 	return doc, exists
 }
 
+// GetAllDocuments returns a snapshot of every open document
+func (dm *DocumentManager) GetAllDocuments() []Document {
+	dm.mu.RLock()
+	defer dm.mu.RUnlock()
+
+	documents := make([]Document, 0, len(dm.documents))
+	for _, doc := range dm.documents {
+		documents = append(documents, *doc)
+	}
+	return documents
+}
+
 // GetContentAtPosition returns the content and line information at a specific position
 func (dm *DocumentManager) GetContentAtPosition(uri protocol.DocumentURI, position protocol.Position) (*context.PositionContext, error) {
 	dm.mu.RLock()
@@ -89,29 +221,35 @@ func (dm *DocumentManager) GetContentAtPosition(uri protocol.DocumentURI, positi
 		return nil, nil // Document not found
 	}
 
+	return doc.PositionContext(position), nil
+}
+
+// PositionContext returns the content and line information at position in
+// this version of the document, or nil if position is out of bounds
+func (d *Document) PositionContext(position protocol.Position) *context.PositionContext {
 	lineIndex := int(position.Line)
 	charIndex := int(position.Character)
 
-	if lineIndex >= len(doc.Lines) {
-		return nil, nil // Position out of bounds
+	if lineIndex >= len(d.Lines) {
+		return nil // Position out of bounds
 	}
 
-	currentLine := doc.Lines[lineIndex]
+	currentLine := d.Lines[lineIndex]
 
 	// Get surrounding context for analysis
-	contextLines := make([]string, 0, len(doc.Lines))
-	for i := 0; i < len(doc.Lines) && i <= lineIndex; i++ {
-		contextLines = append(contextLines, doc.Lines[i])
+	contextLines := make([]string, 0, len(d.Lines))
+	for i := 0; i < len(d.Lines) && i <= lineIndex; i++ {
+		contextLines = append(contextLines, d.Lines[i])
 	}
 
 	return &context.PositionContext{
-		URI:          uri,
+		URI:          d.URI,
 		Position:     position,
 		CurrentLine:  currentLine,
 		CharIndex:    charIndex,
 		ContextLines: contextLines,
-		FullContent:  doc.Content,
-	}, nil
+		FullContent:  d.Content,
+	}
 }
 
 // splitLines splits content into lines, preserving empty lines
@@ -139,3 +277,114 @@ func splitLines(content string) []string {
 
 	return lines
 }
+
+// stepBlock is the line span of a top-level step and a hash of its text
+type stepBlock struct {
+	Start int
+	End   int
+	Hash  uint64
+}
+
+// stepBlocks splits the top-level steps list into per-step line spans.
+// Trailing blank lines are not part of a step.
+func stepBlocks(lines []string) []stepBlock {
+	var blocks []stepBlock
+	inSteps := false
+
+	for i, line := range lines {
+		trimmed := strings.TrimSpace(line)
+		if trimmed == "steps:" && !strings.HasPrefix(line, " ") {
+			inSteps = true
+			continue
+		}
+		if !inSteps || trimmed == "" {
+			continue
+		}
+
+		// Another top-level key ends the steps list
+		if line[0] != ' ' && line[0] != '\t' {
+			break
+		}
+
+		if strings.HasPrefix(line, "  - ") || line == "  -" {
+			blocks = append(blocks, stepBlock{Start: i, End: i})
+		} else if len(blocks) > 0 {
+			blocks[len(blocks)-1].End = i
+		}
+	}
+
+	for i := range blocks {
+		hash := fnv.New64a()
+		for _, line := range lines[blocks[i].Start : blocks[i].End+1] {
+			_, _ = hash.Write([]byte(line))
+			_, _ = hash.Write([]byte{'\n'})
+		}
+		blocks[i].Hash = hash.Sum64()
+	}
+
+	return blocks
+}
+
+// ChangedStepRanges returns the ranges of the top-level steps whose text differs
+// from the step at the same position in previous. When steps are inserted or
+// removed, every step after the edit is reported as changed.
+func (d *Document) ChangedStepRanges(previous *Document) []protocol.Range {
+	var ranges []protocol.Range
+
+	current := stepBlocks(d.Lines)
+	var before []stepBlock
+	if previous != nil {
+		before = stepBlocks(previous.Lines)
+	}
+
+	for i, block := range current {
+		if i < len(before) && before[i].Hash == block.Hash {
+			continue
+		}
+		ranges = append(ranges, protocol.Range{
+			Start: protocol.Position{Line: uint32(block.Start), Character: 0},
+			End:   protocol.Position{Line: uint32(block.End), Character: uint32(len(d.Lines[block.End]))},
+		})
+	}
+
+	return ranges
+}
+
+// anchorPattern matches a YAML anchor definition such as `&defaults`
+var anchorPattern = regexp.MustCompile(`(?:^|[\s\[,-])&([A-Za-z0-9_][A-Za-z0-9_.-]*)(?:\s|$)`)
+
+// anchor is a YAML anchor defined in a document
+type anchor struct {
+	Name string
+	Line int
+}
+
+// findAnchors returns the anchors defined in lines, in order of first definition.
+// When a name is defined more than once the later line wins, matching how YAML
+// resolves aliases after a redefinition.
+func findAnchors(lines []string) []anchor {
+	var anchors []anchor
+	index := make(map[string]int)
+
+	for i, line := range lines {
+		content := line
+		if comment := strings.Index(content, " #"); comment >= 0 {
+			content = content[:comment]
+		}
+		if strings.HasPrefix(strings.TrimSpace(content), "#") {
+			continue
+		}
+
+		for _, match := range anchorPattern.FindAllStringSubmatch(content, -1) {
+			name := match[1]
+			if existing, ok := index[name]; ok {
+				anchors[existing].Line = i
+				continue
+			}
+			index[name] = len(anchors)
+			anchors = append(anchors, anchor{Name: name, Line: i})
+		}
+	}
+
+	return anchors
+}
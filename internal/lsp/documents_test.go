@@ -95,6 +95,101 @@ func TestDocumentManager_UpdateNonExistentDocument(t *testing.T) {
 	}
 }
 
+func TestDocument_JSONValue(t *testing.T) {
+	dm := NewDocumentManager()
+	uri := protocol.DocumentURI("file:///tmp/test.yml")
+
+	dm.OpenDocument(uri, 1, "steps:\n  - command: make")
+	doc, _ := dm.GetDocument(uri)
+	if _, err := doc.Pipeline(); err != nil {
+		t.Fatalf("Expected document to parse, got %v", err)
+	}
+	if steps, ok := doc.JSONValue()["steps"].([]interface{}); !ok || len(steps) != 1 {
+		t.Errorf("Expected one step in the JSON value, got %v", doc.JSONValue())
+	}
+
+	// Updating replaces the parsed value along with the content
+	dm.UpdateDocument(uri, 2, "steps: [")
+	updated, _ := dm.GetDocument(uri)
+	if _, err := updated.Pipeline(); err == nil {
+		t.Error("Expected a parse error for invalid YAML")
+	}
+	if updated.JSONValue() != nil {
+		t.Errorf("Expected no JSON value for invalid YAML, got %v", updated.JSONValue())
+	}
+	if doc.JSONValue() == nil {
+		t.Error("Expected the previous version to keep its JSON value")
+	}
+}
+
+func TestDocument_ParsesOnFirstUse(t *testing.T) {
+	doc := newDocument("file:///tmp/test.yml", 1, "steps:\n  - command: make")
+	if doc.parsed.pipeline != nil || doc.parsed.err != nil {
+		t.Fatal("Expected the document not to be parsed until it's needed")
+	}
+
+	// A snapshot shares the parse with the stored document
+	snapshot := *doc
+	first, err := snapshot.Pipeline()
+	if err != nil || first == nil {
+		t.Fatalf("Expected the document to parse, got %v", err)
+	}
+	if second, _ := doc.Pipeline(); second != first {
+		t.Error("Expected the document to be parsed only once")
+	}
+}
+
+func TestDocument_PluginReferences(t *testing.T) {
+	doc := newDocument("file:///tmp/test.yml", 1, `steps:
+  - command: "make build"
+    plugins:
+      - docker#v5.13.0:
+          image: "golang:1.22"
+      - "my-org/deploy"
+      - ./.buildkite/plugins/local#v1:
+          enabled: true
+      - https://github.com/acme/cache-buildkite-plugin.git#v2.0.0: ~`)
+
+	ranged := func(line, start, end uint32) protocol.Range {
+		return protocol.Range{
+			Start: protocol.Position{Line: line, Character: start},
+			End:   protocol.Position{Line: line, Character: end},
+		}
+	}
+	expected := []PluginReference{
+		{Ref: "docker#v5.13.0", Org: "buildkite-plugins", Name: "docker", Version: "v5.13.0", Range: ranged(3, 8, 22)},
+		{Ref: "my-org/deploy", Org: "my-org", Name: "deploy", Range: ranged(5, 9, 22)},
+		{Ref: "./.buildkite/plugins/local#v1", Name: "./.buildkite/plugins/local", Version: "v1", Local: true, Range: ranged(6, 8, 37)},
+		{
+			Ref:     "https://github.com/acme/cache-buildkite-plugin.git#v2.0.0",
+			Name:    "https://github.com/acme/cache-buildkite-plugin.git",
+			Version: "v2.0.0",
+			Range:   ranged(8, 8, 65),
+		},
+	}
+
+	references := doc.PluginReferences()
+	if len(references) != len(expected) {
+		t.Fatalf("Expected %d references, got %+v", len(expected), references)
+	}
+	for i, want := range expected {
+		if references[i] != want {
+			t.Errorf("Reference %d: expected %+v, got %+v", i, want, references[i])
+		}
+	}
+
+	if reference, ok := pluginReferenceAt(references, protocol.Position{Line: 5, Character: 12}); !ok || reference.Name != "deploy" {
+		t.Errorf("Expected the deploy plugin under the cursor, got %+v", reference)
+	}
+	if _, ok := pluginReferenceAt(references, protocol.Position{Line: 4, Character: 12}); ok {
+		t.Error("Did not expect a reference on a configuration line")
+	}
+
+	if invalid := newDocument("file:///tmp/test.yml", 1, "steps: ["); invalid.PluginReferences() != nil {
+		t.Error("Expected no references for invalid YAML")
+	}
+}
+
 func TestDocumentManager_CloseDocument(t *testing.T) {
 	dm := NewDocumentManager()
 
@@ -303,3 +398,96 @@ func TestDocumentManager_ConcurrentAccess(t *testing.T) {
 		t.Errorf("Expected final version 100, got %d", doc.Version)
 	}
 }
+
+func TestDocumentManager_GetAllDocuments(t *testing.T) {
+	dm := NewDocumentManager()
+
+	dm.OpenDocument("file:///tmp/a.yml", 1, "steps:")
+	dm.OpenDocument("file:///tmp/b.yml", 1, "env:")
+
+	documents := dm.GetAllDocuments()
+	if len(documents) != 2 {
+		t.Fatalf("Expected 2 documents, got %d", len(documents))
+	}
+
+	// The snapshot must not change when the document is updated afterwards
+	dm.UpdateDocument("file:///tmp/a.yml", 2, "agents:")
+	for _, doc := range documents {
+		if doc.URI == "file:///tmp/a.yml" && doc.Content != "steps:" {
+			t.Errorf("Expected snapshot content 'steps:', got %q", doc.Content)
+		}
+	}
+}
+
+func TestDocument_ChangedStepRanges(t *testing.T) {
+	previous := &Document{Lines: splitLines(`steps:
+  - label: "Build"
+    command: "make build"
+
+  - label: "Test"
+    command: "make test"
+
+  - wait
+
+env:
+  FOO: bar`)}
+
+	t.Run("one step edited", func(t *testing.T) {
+		current := &Document{Lines: splitLines(`steps:
+  - label: "Build"
+    command: "make build"
+
+  - label: "Test"
+    command: "make test -v"
+
+  - wait
+
+env:
+  FOO: baz`)}
+
+		ranges := current.ChangedStepRanges(previous)
+		expected := []protocol.Range{{
+			Start: protocol.Position{Line: 4, Character: 0},
+			End:   protocol.Position{Line: 5, Character: 27},
+		}}
+		if fmt.Sprint(ranges) != fmt.Sprint(expected) {
+			t.Errorf("Expected %v, got %v", expected, ranges)
+		}
+	})
+
+	t.Run("unchanged", func(t *testing.T) {
+		if ranges := previous.ChangedStepRanges(previous); len(ranges) != 0 {
+			t.Errorf("Expected no changed steps, got %v", ranges)
+		}
+	})
+
+	t.Run("no previous version", func(t *testing.T) {
+		if ranges := previous.ChangedStepRanges(nil); len(ranges) != 3 {
+			t.Errorf("Expected every step to be reported, got %v", ranges)
+		}
+	})
+}
+
+func TestFindAnchors(t *testing.T) {
+	lines := splitLines(`defaults: &defaults
+  timeout_in_minutes: 10
+# &commented isn't an anchor
+steps:
+  - &build
+    command: "make build && make test" # &inline
+  - label: "Retry"
+    retry: &retry { automatic: true }
+  - command: "make lint"
+defaults: &defaults
+  timeout_in_minutes: 20`)
+
+	anchors := findAnchors(lines)
+	expected := []anchor{
+		{Name: "defaults", Line: 9},
+		{Name: "build", Line: 4},
+		{Name: "retry", Line: 7},
+	}
+	if fmt.Sprint(anchors) != fmt.Sprint(expected) {
+		t.Errorf("Expected %v, got %v", expected, anchors)
+	}
+}
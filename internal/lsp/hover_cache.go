@@ -0,0 +1,94 @@
+package lsp
+
+import (
+	"container/list"
+	"sync"
+
+	"go.lsp.dev/protocol"
+)
+
+// defaultHoverCacheSize is how many hover results are kept across all documents
+const defaultHoverCacheSize = 256
+
+// hoverKey identifies a hover request on one version of a document
+type hoverKey struct {
+	URI      protocol.DocumentURI
+	Position protocol.Position
+	Version  int32
+}
+
+// hoverEntry is a cached hover result, which may be nil when there was nothing to show
+type hoverEntry struct {
+	key   hoverKey
+	hover *protocol.Hover
+}
+
+// hoverCache is a least recently used cache of hover results
+type hoverCache struct {
+	mu       sync.Mutex
+	capacity int
+	order    *list.List // most recently used at the front
+	entries  map[hoverKey]*list.Element
+}
+
+func newHoverCache(capacity int) *hoverCache {
+	return &hoverCache{
+		capacity: capacity,
+		order:    list.New(),
+		entries:  make(map[hoverKey]*list.Element),
+	}
+}
+
+// get returns the cached hover for key and whether there was one
+func (c *hoverCache) get(key hoverKey) (*protocol.Hover, bool) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	element, ok := c.entries[key]
+	if !ok {
+		return nil, false
+	}
+	c.order.MoveToFront(element)
+	return element.Value.(*hoverEntry).hover, true
+}
+
+// put caches hover for key, evicting the least recently used entry when full
+func (c *hoverCache) put(key hoverKey, hover *protocol.Hover) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	if element, ok := c.entries[key]; ok {
+		element.Value.(*hoverEntry).hover = hover
+		c.order.MoveToFront(element)
+		return
+	}
+
+	c.entries[key] = c.order.PushFront(&hoverEntry{key: key, hover: hover})
+	if c.order.Len() > c.capacity {
+		oldest := c.order.Back()
+		c.order.Remove(oldest)
+		delete(c.entries, oldest.Value.(*hoverEntry).key)
+	}
+}
+
+// invalidate drops every cached hover for uri
+func (c *hoverCache) invalidate(uri protocol.DocumentURI) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	for key, element := range c.entries {
+		if key.URI == uri {
+			c.order.Remove(element)
+			delete(c.entries, key)
+		}
+	}
+}
+
+// clear drops every cached hover, for when something all hovers draw on changes
+func (c *hoverCache) clear() {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+
+	c.order.Init()
+	clear(c.entries)
+}
@@ -0,0 +1,137 @@
+package lsp
+
+import (
+	"context"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+	"time"
+
+	"go.lsp.dev/protocol"
+)
+
+func TestHoverCache_EvictsLeastRecentlyUsed(t *testing.T) {
+	cache := newHoverCache(2)
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	key := func(line uint32) hoverKey {
+		return hoverKey{URI: uri, Position: protocol.Position{Line: line}, Version: 1}
+	}
+
+	cache.put(key(0), &protocol.Hover{})
+	cache.put(key(1), nil)
+	cache.get(key(0))
+	cache.put(key(2), &protocol.Hover{})
+
+	if _, ok := cache.get(key(1)); ok {
+		t.Error("Expected the least recently used entry to be evicted")
+	}
+	if _, ok := cache.get(key(0)); !ok {
+		t.Error("Expected a recently used entry to be kept")
+	}
+	if hover, ok := cache.get(key(2)); !ok || hover == nil {
+		t.Error("Expected the newest entry to be cached")
+	}
+
+	cache.invalidate(uri)
+	if _, ok := cache.get(key(2)); ok {
+		t.Error("Expected invalidate to drop every entry for the URI")
+	}
+
+	cache.put(key(0), &protocol.Hover{})
+	cache.put(hoverKey{URI: "file:///test/.buildkite/other.yml", Version: 1}, nil)
+	cache.clear()
+	if len(cache.entries) != 0 || cache.order.Len() != 0 {
+		t.Errorf("Expected clear to drop every entry, %d left", len(cache.entries))
+	}
+}
+
+func TestServer_HoverCacheInvalidatedOnChange(t *testing.T) {
+	server := newTestServer()
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+
+	err := server.DidOpen(context.Background(), &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{URI: uri, LanguageID: "yaml", Version: 1, Text: "steps:\n  - label: \"Build\""},
+	})
+	if err != nil {
+		t.Fatalf("DidOpen failed: %v", err)
+	}
+
+	hoverAt := func() *protocol.Hover {
+		t.Helper()
+		hover, err := server.Hover(context.Background(), &protocol.HoverParams{
+			TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+				TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+				Position:     protocol.Position{Line: 1, Character: 6},
+			},
+		})
+		if err != nil {
+			t.Fatalf("Hover failed: %v", err)
+		}
+		return hover
+	}
+
+	first := hoverAt()
+	if first == nil || !strings.Contains(first.Contents.Value, "**label**") {
+		t.Fatalf("Expected label documentation, got %+v", first)
+	}
+	if second := hoverAt(); second != first {
+		t.Error("Expected a repeated hover to be served from the cache")
+	}
+
+	err = server.DidChange(context.Background(), &protocol.DidChangeTextDocumentParams{
+		TextDocument: protocol.VersionedTextDocumentIdentifier{
+			TextDocumentIdentifier: protocol.TextDocumentIdentifier{URI: uri},
+			Version:                2,
+		},
+		ContentChanges: []protocol.TextDocumentContentChangeEvent{{Text: "steps:\n  - command: \"make\""}},
+	})
+	if err != nil {
+		t.Fatalf("DidChange failed: %v", err)
+	}
+
+	changed := hoverAt()
+	if changed == nil || !strings.Contains(changed.Contents.Value, "**command**") {
+		t.Errorf("Expected command documentation after the change, got %+v", changed)
+	}
+}
+
+func TestServer_HoverCacheClearedWhenSchemaLoads(t *testing.T) {
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	data := `{"definitions": {"commandStep": {"properties": {"label": {"type": "string"}}}}}`
+	if err := os.WriteFile(schemaPath, []byte(data), 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	server := newTestServer()
+	server.validationDelay = 10 * time.Millisecond
+	server.schemaLoader.SetURL(schemaPath)
+
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	server.documentManager.OpenDocument(uri, 1, "steps:\n  - label: \"Build\"")
+
+	hoverAt := func() *protocol.Hover {
+		t.Helper()
+		hover, err := server.Hover(context.Background(), &protocol.HoverParams{
+			TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+				TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+				Position:     protocol.Position{Line: 1, Character: 6},
+			},
+		})
+		if err != nil {
+			t.Fatalf("Hover failed: %v", err)
+		}
+		return hover
+	}
+
+	if before := hoverAt(); before == nil || strings.Contains(before.Contents.Value, "**Type:**") {
+		t.Fatalf("Expected label documentation without a schema type, got %+v", before)
+	}
+
+	server.loadSchemaInBackground()
+	server.tasks.Wait()
+
+	if after := hoverAt(); after == nil || !strings.Contains(after.Contents.Value, "**Type:** `string`") {
+		t.Errorf("Expected the hover to pick up the schema once loaded, got %+v", after)
+	}
+}
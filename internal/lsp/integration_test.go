@@ -0,0 +1,178 @@
+package lsp
+
+import (
+	"context"
+	"net"
+	"strings"
+	"testing"
+	"time"
+
+	"go.lsp.dev/jsonrpc2"
+	"go.lsp.dev/protocol"
+
+	"github.com/mcncl/buildkite-ls/internal/schema"
+)
+
+// testSession is a client connected to the server over an in-memory pipe. The
+// two ends exchange Content-Length framed JSON-RPC, as an editor does over stdio,
+// so requests go through the server's Handler rather than calling methods directly.
+type testSession struct {
+	t        *testing.T
+	conn     jsonrpc2.Conn
+	recorder *diagnosticsRecorder
+}
+
+// newTestSession starts server on one end of a pipe and returns a client for the other
+func newTestSession(t *testing.T, server *Server) *testSession {
+	t.Helper()
+
+	serverPipe, clientPipe := net.Pipe()
+	ctx, cancel := context.WithCancel(context.Background())
+
+	serverConn := jsonrpc2.NewConn(jsonrpc2.NewStream(serverPipe))
+	server.SetConnection(serverConn)
+	serverConn.Go(ctx, server.Handler())
+
+	recorder := &diagnosticsRecorder{}
+	clientConn := jsonrpc2.NewConn(jsonrpc2.NewStream(clientPipe))
+	clientConn.Go(ctx, recorder.handler)
+
+	t.Cleanup(func() {
+		cancel()
+		_ = serverConn.Close()
+		_ = clientConn.Close()
+	})
+
+	return &testSession{t: t, conn: clientConn, recorder: recorder}
+}
+
+// call sends a request and decodes its result, failing the test on an error response
+func (ts *testSession) call(method string, params, result interface{}) {
+	ts.t.Helper()
+
+	ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+	defer cancel()
+
+	if _, err := ts.conn.Call(ctx, method, params, result); err != nil {
+		ts.t.Fatalf("%s failed: %v", method, err)
+	}
+}
+
+// notify sends a notification, failing the test if it can't be written
+func (ts *testSession) notify(method string, params interface{}) {
+	ts.t.Helper()
+
+	if err := ts.conn.Notify(context.Background(), method, params); err != nil {
+		ts.t.Fatalf("%s notification failed: %v", method, err)
+	}
+}
+
+// waitForDiagnostics returns the first diagnostics published for uri
+func (ts *testSession) waitForDiagnostics(uri protocol.DocumentURI) protocol.PublishDiagnosticsParams {
+	ts.t.Helper()
+
+	deadline := time.Now().Add(2 * time.Second)
+	for time.Now().Before(deadline) {
+		for _, params := range ts.recorder.snapshot() {
+			if params.URI == uri {
+				return params
+			}
+		}
+		time.Sleep(10 * time.Millisecond)
+	}
+
+	ts.t.Fatalf("No diagnostics were published for %s", uri)
+	return protocol.PublishDiagnosticsParams{}
+}
+
+func TestIntegration_MessageLoop(t *testing.T) {
+	session := newTestSession(t, newTestServer())
+
+	var initResult protocol.InitializeResult
+	session.call("initialize", &protocol.InitializeParams{
+		ClientInfo: &protocol.ClientInfo{Name: "integration-test"},
+	}, &initResult)
+	if initResult.Capabilities.HoverProvider == nil {
+		t.Error("Expected the hover capability to be advertised")
+	}
+	session.notify("initialized", &protocol.InitializedParams{})
+
+	uri := protocol.DocumentURI("file:///project/.buildkite/pipeline.yml")
+	session.notify("textDocument/didOpen", &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{
+			URI:        uri,
+			LanguageID: "yaml",
+			Version:    1,
+			Text: `steps:
+  - label: "Deploy"
+    command: "make deploy"
+    if: build.tag != null
+    branches: "main"`,
+		},
+	})
+
+	// An empty pipeline to complete top-level keys in
+	rootURI := protocol.DocumentURI("file:///project/.buildkite/release.yml")
+	session.notify("textDocument/didOpen", &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{URI: rootURI, LanguageID: "yaml", Version: 1, Text: "\n"},
+	})
+
+	published := session.waitForDiagnostics(uri)
+	var codes []string
+	for _, diagnostic := range published.Diagnostics {
+		code, _ := diagnostic.Code.(string)
+		codes = append(codes, code)
+	}
+	if len(codes) != 1 || codes[0] != "if-and-branches" {
+		t.Errorf("Expected the if-and-branches diagnostic, got %v", codes)
+	}
+
+	var hover protocol.Hover
+	session.call("textDocument/hover", &protocol.HoverParams{
+		TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+			TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+			Position:     protocol.Position{Line: 2, Character: 6},
+		},
+	}, &hover)
+	if !strings.Contains(hover.Contents.Value, "command") {
+		t.Errorf("Expected hover documentation for command, got %q", hover.Contents.Value)
+	}
+
+	var completions protocol.CompletionList
+	session.call("textDocument/completion", &protocol.CompletionParams{
+		TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+			TextDocument: protocol.TextDocumentIdentifier{URI: rootURI},
+			Position:     protocol.Position{Line: 0, Character: 0},
+		},
+	}, &completions)
+	labels := make(map[string]bool)
+	for _, item := range completions.Items {
+		labels[item.Label] = true
+	}
+	for _, expected := range []string{"steps", "env", "agents"} {
+		if !labels[expected] {
+			t.Errorf("Expected a top-level completion for %s", expected)
+		}
+	}
+
+	// Custom methods are routed through the same handler
+	var info schema.Info
+	session.call("buildkite/schemaInfo", nil, &info)
+	if info.Source == "" {
+		t.Error("Expected the schema info to report its source")
+	}
+
+	session.call("shutdown", nil, nil)
+	session.notify("exit", nil)
+}
+
+func TestIntegration_UnknownMethod(t *testing.T) {
+	session := newTestSession(t, newTestServer())
+
+	ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+	defer cancel()
+
+	if _, err := session.conn.Call(ctx, "buildkite/doesNotExist", nil, nil); err == nil {
+		t.Error("Expected an error response for an unknown method")
+	}
+}
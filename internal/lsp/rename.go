@@ -0,0 +1,258 @@
+package lsp
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"strings"
+
+	"go.lsp.dev/protocol"
+)
+
+// stepKeyReference is a step key written in the document, either where a step
+// defines it or where a depends_on entry refers to it
+type stepKeyReference struct {
+	Key   string
+	Range protocol.Range
+	// Definition is true where a step defines the key rather than depends on it
+	Definition bool
+}
+
+// errNotStepKey is returned to the editor when a rename starts anywhere other than a step key
+var errNotStepKey = errors.New("only step keys can be renamed - place the cursor on a step's key or a depends_on entry")
+
+// PrepareRename checks that the cursor is on a step key and returns the range of the key token
+func (s *Server) PrepareRename(ctx context.Context, params *protocol.PrepareRenameParams) (*protocol.Range, error) {
+	s.logger.Printf("PrepareRename requested for URI: %s, Position: %d:%d", params.TextDocument.URI, params.Position.Line, params.Position.Character)
+
+	if !s.isBuildkiteFile(string(params.TextDocument.URI)) {
+		s.logger.Printf("File is not a Buildkite file, skipping rename")
+		return nil, nil
+	}
+
+	reference, err := s.stepKeyAtPosition(params.TextDocument.URI, params.Position)
+	if err != nil {
+		return nil, err
+	}
+
+	return &reference.Range, nil
+}
+
+// Rename renames a step key along with every depends_on entry that refers to it
+func (s *Server) Rename(ctx context.Context, params *protocol.RenameParams) (*protocol.WorkspaceEdit, error) {
+	s.logger.Printf("Rename requested for URI: %s, Position: %d:%d, New name: %s", params.TextDocument.URI, params.Position.Line, params.Position.Character, params.NewName)
+
+	if !s.isBuildkiteFile(string(params.TextDocument.URI)) {
+		s.logger.Printf("File is not a Buildkite file, skipping rename")
+		return nil, nil
+	}
+
+	reference, err := s.stepKeyAtPosition(params.TextDocument.URI, params.Position)
+	if err != nil {
+		return nil, err
+	}
+
+	newName := params.NewName
+	if newName == "" {
+		return nil, errors.New("step key cannot be empty")
+	}
+	if strings.ContainsAny(newName, " \t\r\n\"'#") {
+		return nil, fmt.Errorf("step key %q cannot contain whitespace, quotes or '#'", newName)
+	}
+
+	doc, _ := s.documentManager.GetDocument(params.TextDocument.URI)
+	references, unreadable := s.findStepKeyReferences(doc.Lines)
+
+	// A dependency rename can't read might refer to the key, and renaming
+	// around it would quietly break the pipeline
+	for _, text := range unreadable {
+		if strings.Contains(text, reference.Key) {
+			return nil, fmt.Errorf("can't rename step key %q: it may be used by a depends_on written in a form rename can't update (%s)", reference.Key, strings.TrimSpace(text))
+		}
+	}
+
+	if newName != reference.Key {
+		for _, occurrence := range references {
+			if occurrence.Definition && occurrence.Key == newName {
+				return nil, fmt.Errorf("a step with key %q already exists on line %d", newName, occurrence.Range.Start.Line+1)
+			}
+		}
+	}
+
+	var edits []protocol.TextEdit
+	for _, occurrence := range references {
+		if occurrence.Key == reference.Key {
+			edits = append(edits, protocol.TextEdit{
+				Range:   occurrence.Range,
+				NewText: newName,
+			})
+		}
+	}
+
+	s.logger.Printf("Renaming step key '%s' to '%s' in %d places", reference.Key, newName, len(edits))
+	return &protocol.WorkspaceEdit{
+		Changes: map[protocol.DocumentURI][]protocol.TextEdit{
+			params.TextDocument.URI: edits,
+		},
+	}, nil
+}
+
+// stepKeyAtPosition returns the step key under the cursor, or errNotStepKey
+func (s *Server) stepKeyAtPosition(uri protocol.DocumentURI, position protocol.Position) (*stepKeyReference, error) {
+	doc, exists := s.documentManager.GetDocument(uri)
+	if !exists {
+		return nil, fmt.Errorf("document not found: %s", uri)
+	}
+
+	references, _ := s.findStepKeyReferences(doc.Lines)
+	for _, reference := range references {
+		rng := reference.Range
+		if rng.Start.Line == position.Line &&
+			rng.Start.Character <= position.Character && position.Character <= rng.End.Character {
+			return &reference, nil
+		}
+	}
+
+	return nil, errNotStepKey
+}
+
+// findStepKeyReferences returns every step key definition and depends_on entry in
+// the document, including those of steps inside groups. It also returns the text of
+// each depends_on value it can't read, such as a flow list spanning several lines.
+func (s *Server) findStepKeyReferences(lines []string) ([]stepKeyReference, []string) {
+	var references []stepKeyReference
+	var unreadable []string
+
+	var walk func(start, end int)
+	walk = func(start, end int) {
+		for _, field := range []string{"key", "identifier", "id"} {
+			if loc := s.findStepProperty(field, lines, start, end); loc != nil {
+				if reference, ok := scalarStepKey(lines, loc); ok {
+					reference.Definition = true
+					references = append(references, reference)
+				}
+			}
+		}
+
+		if loc := s.findStepProperty("depends_on", lines, start, end); loc != nil {
+			dependencies, ok := s.dependsOnStepKeys(lines, loc, end)
+			references = append(references, dependencies...)
+			if !ok {
+				blockEnd := s.findBlockEnd(lines, loc.Line, end)
+				unreadable = append(unreadable, strings.Join(lines[loc.Line:blockEnd+1], "\n"))
+			}
+		}
+
+		if loc := s.findStepProperty("steps", lines, start, end); loc != nil {
+			for _, itemLine := range s.findListItemLines(lines, loc.Line, end) {
+				walk(itemLine, s.findListItemEnd(lines, itemLine, end))
+			}
+		}
+	}
+
+	stepLines := s.findStepLines(lines)
+	for i := range stepLines {
+		walk(s.stepLineRange(stepLines, i, lines))
+	}
+
+	return references, unreadable
+}
+
+// dependsOnStepKeys returns the step keys the depends_on at loc refers to, whether
+// written as a single key, a flow list or a block list. It returns false if any
+// entry couldn't be read.
+func (s *Server) dependsOnStepKeys(lines []string, loc *keyLocation, endLine int) ([]stepKeyReference, bool) {
+	rng := valueRange(lines, loc)
+	value := lines[loc.Line][rng.Start.Character:rng.End.Character]
+
+	switch {
+	case strings.HasPrefix(value, "["):
+		return flowListStepKeys(lines, loc)
+	case value != "" && !strings.HasPrefix(value, "#"):
+		reference, ok := scalarStepKey(lines, loc)
+		if !ok {
+			return nil, false
+		}
+		return []stepKeyReference{reference}, true
+	}
+
+	var references []stepKeyReference
+	readable := true
+	for _, itemLine := range s.findListItemLines(lines, loc.Line, endLine) {
+		itemLoc := s.findKeyInLines("step", lines, itemLine, itemLine)
+		if itemLoc == nil {
+			// valueRange skips one character after the key for the colon, which
+			// for a plain list item is the "-" marker itself
+			marker := strings.Index(lines[itemLine], "-")
+			itemLoc = &keyLocation{Line: itemLine, Column: marker, Length: 0}
+		}
+		if reference, ok := scalarStepKey(lines, itemLoc); ok {
+			references = append(references, reference)
+		} else {
+			readable = false
+		}
+	}
+
+	return references, readable
+}
+
+// flowListStepKeys reads the step keys in a flow list written on one line, such as
+// [test, "build"]. It returns false for a list spanning lines or holding anything
+// other than plain keys.
+func flowListStepKeys(lines []string, loc *keyLocation) ([]stepKeyReference, bool) {
+	rng := valueRange(lines, loc)
+	value := lines[loc.Line][rng.Start.Character:rng.End.Character]
+	if !strings.HasPrefix(value, "[") || !strings.HasSuffix(value, "]") || strings.ContainsAny(value[1:len(value)-1], "[]{}") {
+		return nil, false
+	}
+
+	var references []stepKeyReference
+	column := int(rng.Start.Character) + 1
+	for _, entry := range strings.Split(value[1:len(value)-1], ",") {
+		entryStart := column
+		column += len(entry) + 1
+
+		key := strings.TrimSpace(entry)
+		if key == "" {
+			continue
+		}
+		start := entryStart + strings.Index(entry, key)
+
+		if len(key) >= 2 && (key[0] == '"' || key[0] == '\'') && key[len(key)-1] == key[0] {
+			key = key[1 : len(key)-1]
+			start++
+		}
+		if key == "" || strings.ContainsAny(key[:1], "&*|>#\"'") {
+			return nil, false
+		}
+
+		references = append(references, stepKeyReference{
+			Key: key,
+			Range: protocol.Range{
+				Start: protocol.Position{Line: uint32(loc.Line), Character: uint32(start)},
+				End:   protocol.Position{Line: uint32(loc.Line), Character: uint32(start + len(key))},
+			},
+		})
+	}
+
+	return references, true
+}
+
+// scalarStepKey reads the scalar value after the key at loc, returning the range of the key text
+// without its quotes. Flow collections, anchors, block scalars and comments are not step keys.
+func scalarStepKey(lines []string, loc *keyLocation) (stepKeyReference, bool) {
+	rng := valueRange(lines, loc)
+	value := lines[loc.Line][rng.Start.Character:rng.End.Character]
+
+	if len(value) >= 2 && (value[0] == '"' || value[0] == '\'') && value[len(value)-1] == value[0] {
+		value = value[1 : len(value)-1]
+		rng.Start.Character++
+		rng.End.Character--
+	}
+
+	if value == "" || strings.ContainsAny(value[:1], "[{&*|>#\"'") {
+		return stepKeyReference{}, false
+	}
+
+	return stepKeyReference{Key: value, Range: rng}, true
+}
@@ -0,0 +1,286 @@
+package lsp
+
+import (
+	"context"
+	"strings"
+	"testing"
+
+	"go.lsp.dev/protocol"
+)
+
+const renamePipeline = `steps:
+  - label: "Build"
+    key: "build"
+    command: "make build"
+
+  - label: "Test"
+    key: test
+    depends_on: build
+    command: "make test"
+
+  - label: "Deploy"
+    depends_on:
+      - "build"
+      - step: test
+        allow_failure: true
+    command: "make deploy"
+`
+
+func openRenameDocument(t *testing.T, server *Server, uri protocol.DocumentURI) {
+	t.Helper()
+
+	err := server.DidOpen(context.Background(), &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{
+			URI:        uri,
+			LanguageID: "yaml",
+			Version:    1,
+			Text:       renamePipeline,
+		},
+	})
+	if err != nil {
+		t.Fatalf("DidOpen failed: %v", err)
+	}
+}
+
+func TestServer_PrepareRename(t *testing.T) {
+	server := newTestServer()
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	openRenameDocument(t, server, uri)
+
+	tests := []struct {
+		name          string
+		line          uint32
+		char          uint32
+		expectedRange *protocol.Range
+	}{
+		{
+			name: "quoted key definition",
+			line: 2,
+			char: 11,
+			expectedRange: &protocol.Range{
+				Start: protocol.Position{Line: 2, Character: 10},
+				End:   protocol.Position{Line: 2, Character: 15},
+			},
+		},
+		{
+			name: "unquoted key definition",
+			line: 6,
+			char: 9,
+			expectedRange: &protocol.Range{
+				Start: protocol.Position{Line: 6, Character: 9},
+				End:   protocol.Position{Line: 6, Character: 13},
+			},
+		},
+		{
+			name: "scalar depends_on",
+			line: 7,
+			char: 18,
+			expectedRange: &protocol.Range{
+				Start: protocol.Position{Line: 7, Character: 16},
+				End:   protocol.Position{Line: 7, Character: 21},
+			},
+		},
+		{
+			name: "depends_on list item",
+			line: 12,
+			char: 10,
+			expectedRange: &protocol.Range{
+				Start: protocol.Position{Line: 12, Character: 9},
+				End:   protocol.Position{Line: 12, Character: 14},
+			},
+		},
+		{
+			name: "depends_on step entry",
+			line: 13,
+			char: 15,
+			expectedRange: &protocol.Range{
+				Start: protocol.Position{Line: 13, Character: 14},
+				End:   protocol.Position{Line: 13, Character: 18},
+			},
+		},
+		{
+			name: "label value",
+			line: 1,
+			char: 14,
+		},
+		{
+			name: "key property name",
+			line: 2,
+			char: 5,
+		},
+		{
+			name: "command value",
+			line: 3,
+			char: 16,
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			result, err := server.PrepareRename(context.Background(), &protocol.PrepareRenameParams{
+				TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+					TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+					Position:     protocol.Position{Line: tt.line, Character: tt.char},
+				},
+			})
+
+			if tt.expectedRange == nil {
+				if err == nil {
+					t.Errorf("Expected an error, got range %+v", result)
+				}
+				return
+			}
+
+			if err != nil {
+				t.Fatalf("PrepareRename failed: %v", err)
+			}
+			if result == nil || *result != *tt.expectedRange {
+				t.Errorf("Expected range %+v, got %+v", tt.expectedRange, result)
+			}
+		})
+	}
+}
+
+func TestServer_Rename(t *testing.T) {
+	server := newTestServer()
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	openRenameDocument(t, server, uri)
+
+	renameParams := func(line, char uint32, newName string) *protocol.RenameParams {
+		return &protocol.RenameParams{
+			TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+				TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+				Position:     protocol.Position{Line: line, Character: char},
+			},
+			NewName: newName,
+		}
+	}
+
+	t.Run("renames definition and references", func(t *testing.T) {
+		result, err := server.Rename(context.Background(), renameParams(12, 10, "compile"))
+		if err != nil {
+			t.Fatalf("Rename failed: %v", err)
+		}
+
+		edits := result.Changes[uri]
+		expectedLines := []uint32{2, 7, 12}
+		if len(edits) != len(expectedLines) {
+			t.Fatalf("Expected %d edits, got %d: %+v", len(expectedLines), len(edits), edits)
+		}
+		for i, edit := range edits {
+			if edit.Range.Start.Line != expectedLines[i] {
+				t.Errorf("Edit %d: expected line %d, got %d", i, expectedLines[i], edit.Range.Start.Line)
+			}
+			if edit.NewText != "compile" {
+				t.Errorf("Edit %d: expected new text 'compile', got %q", i, edit.NewText)
+			}
+		}
+	})
+
+	t.Run("rejects invalid names", func(t *testing.T) {
+		for _, newName := range []string{"", "has space", `quo"te`} {
+			if _, err := server.Rename(context.Background(), renameParams(2, 11, newName)); err == nil {
+				t.Errorf("Expected an error renaming to %q", newName)
+			}
+		}
+	})
+
+	t.Run("rejects positions outside step keys", func(t *testing.T) {
+		if _, err := server.Rename(context.Background(), renameParams(1, 14, "compile")); err == nil {
+			t.Error("Expected an error renaming a label")
+		}
+	})
+}
+
+func TestServer_RenameGroupsAndFlowLists(t *testing.T) {
+	server := newTestServer()
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+
+	content := `steps:
+  - key: build
+    command: "make build"
+
+  - group: "Checks"
+    key: checks
+    steps:
+      - key: lint
+        depends_on: [build, "setup"]
+        command: "make lint"
+      - key: test
+        depends_on: build
+        command: "make test"
+
+  - key: deploy
+    depends_on: [checks, build]
+    command: "make deploy"
+
+  - key: notify
+    depends_on: [
+      deploy,
+    ]
+    command: "make notify"
+`
+	err := server.DidOpen(context.Background(), &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{URI: uri, LanguageID: "yaml", Version: 1, Text: content},
+	})
+	if err != nil {
+		t.Fatalf("DidOpen failed: %v", err)
+	}
+
+	rename := func(line, char uint32, newName string) (*protocol.WorkspaceEdit, error) {
+		return server.Rename(context.Background(), &protocol.RenameParams{
+			TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+				TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+				Position:     protocol.Position{Line: line, Character: char},
+			},
+			NewName: newName,
+		})
+	}
+
+	t.Run("updates references inside groups and flow lists", func(t *testing.T) {
+		result, err := rename(1, 9, "compile")
+		if err != nil {
+			t.Fatalf("Rename failed: %v", err)
+		}
+
+		expected := []protocol.Range{
+			{Start: protocol.Position{Line: 1, Character: 9}, End: protocol.Position{Line: 1, Character: 14}},
+			{Start: protocol.Position{Line: 8, Character: 21}, End: protocol.Position{Line: 8, Character: 26}},
+			{Start: protocol.Position{Line: 11, Character: 20}, End: protocol.Position{Line: 11, Character: 25}},
+			{Start: protocol.Position{Line: 15, Character: 25}, End: protocol.Position{Line: 15, Character: 30}},
+		}
+		edits := result.Changes[uri]
+		if len(edits) != len(expected) {
+			t.Fatalf("Expected %d edits, got %+v", len(expected), edits)
+		}
+		for i, edit := range edits {
+			if edit.Range != expected[i] {
+				t.Errorf("Edit %d: expected range %v, got %v", i, expected[i], edit.Range)
+			}
+		}
+	})
+
+	t.Run("renames a key defined inside a group", func(t *testing.T) {
+		if _, err := server.PrepareRename(context.Background(), &protocol.PrepareRenameParams{
+			TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+				TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+				Position:     protocol.Position{Line: 7, Character: 14},
+			},
+		}); err != nil {
+			t.Errorf("Expected a group's step key to be renameable, got %v", err)
+		}
+	})
+
+	t.Run("refuses when an unreadable depends_on may refer to the key", func(t *testing.T) {
+		if _, err := rename(14, 9, "ship"); err == nil {
+			t.Error("Expected an error renaming a key used by a multi-line flow list")
+		}
+	})
+
+	t.Run("refuses a name another step already uses", func(t *testing.T) {
+		_, err := rename(1, 9, "lint")
+		if err == nil || !strings.Contains(err.Error(), "already exists") {
+			t.Errorf("Expected a collision error, got %v", err)
+		}
+	})
+}
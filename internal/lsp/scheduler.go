@@ -0,0 +1,108 @@
+package lsp
+
+import (
+	"context"
+	"time"
+
+	"go.lsp.dev/protocol"
+)
+
+// defaultValidationDelay is how long to wait after the last change before validating
+const defaultValidationDelay = 300 * time.Millisecond
+
+// shutdownTimeout bounds how long shutdown waits for background tasks, since a
+// schema download in flight can't be interrupted
+const shutdownTimeout = 5 * time.Second
+
+// pendingValidation is a scheduled or running validation for one document
+type pendingValidation struct {
+	timer  *time.Timer
+	cancel context.CancelFunc
+}
+
+// scheduleValidation validates the document after a quiet period, cancelling any
+// earlier validation for the same URI so the last edit always wins
+func (s *Server) scheduleValidation(uri protocol.DocumentURI) {
+	ctx, cancel := context.WithCancel(s.lifetime)
+
+	s.validationMu.Lock()
+	defer s.validationMu.Unlock()
+
+	s.cancelValidationLocked(uri)
+	if !s.startTask() {
+		cancel()
+		return
+	}
+
+	timer := time.AfterFunc(s.validationDelay, func() {
+		defer s.tasks.Done()
+
+		doc, exists := s.documentManager.GetDocument(uri)
+		if !exists || ctx.Err() != nil {
+			return
+		}
+		s.validateDocument(ctx, doc)
+	})
+
+	s.pendingValidations[uri] = &pendingValidation{timer: timer, cancel: cancel}
+}
+
+// cancelValidation stops any scheduled or running validation for uri
+func (s *Server) cancelValidation(uri protocol.DocumentURI) {
+	s.validationMu.Lock()
+	defer s.validationMu.Unlock()
+	s.cancelValidationLocked(uri)
+}
+
+func (s *Server) cancelValidationLocked(uri protocol.DocumentURI) {
+	if pending, exists := s.pendingValidations[uri]; exists {
+		if pending.timer.Stop() {
+			// The validation never started, so it won't mark itself done
+			s.tasks.Done()
+		}
+		pending.cancel()
+		delete(s.pendingValidations, uri)
+	}
+}
+
+// startTask registers a background task, returning false once shutdown has begun
+func (s *Server) startTask() bool {
+	s.tasksMu.Lock()
+	defer s.tasksMu.Unlock()
+
+	if s.stopping {
+		return false
+	}
+	s.tasks.Add(1)
+	return true
+}
+
+// stopBackgroundTasks cancels scheduled validations and waits for running
+// background work to finish, giving up after shutdownTimeout or when ctx ends
+func (s *Server) stopBackgroundTasks(ctx context.Context) {
+	s.tasksMu.Lock()
+	s.stopping = true
+	s.tasksMu.Unlock()
+	s.stop()
+
+	s.validationMu.Lock()
+	for uri := range s.pendingValidations {
+		s.cancelValidationLocked(uri)
+	}
+	s.validationMu.Unlock()
+
+	done := make(chan struct{})
+	go func() {
+		s.tasks.Wait()
+		close(done)
+	}()
+
+	select {
+	case <-done:
+		s.logger.Printf("Background tasks stopped")
+	case <-time.After(shutdownTimeout):
+		s.logger.Printf("Gave up waiting for background tasks after %s", shutdownTimeout)
+	case <-ctx.Done():
+		s.logger.Printf("Shutdown cancelled while waiting for background tasks: %v", ctx.Err())
+	}
+}
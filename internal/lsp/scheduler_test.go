@@ -0,0 +1,278 @@
+package lsp
+
+import (
+	"context"
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"sync"
+	"testing"
+	"time"
+
+	"go.lsp.dev/jsonrpc2"
+	"go.lsp.dev/protocol"
+)
+
+// diagnosticsRecorder collects publishDiagnostics and diagnostic summary
+// notifications received by a fake client
+type diagnosticsRecorder struct {
+	mu        sync.Mutex
+	published []protocol.PublishDiagnosticsParams
+	summaries []diagnosticSummary
+}
+
+func (r *diagnosticsRecorder) handler(ctx context.Context, reply jsonrpc2.Replier, req jsonrpc2.Request) error {
+	switch req.Method() {
+	case "textDocument/publishDiagnostics":
+		var params protocol.PublishDiagnosticsParams
+		if err := json.Unmarshal(req.Params(), &params); err == nil {
+			r.mu.Lock()
+			r.published = append(r.published, params)
+			r.mu.Unlock()
+		}
+	case diagnosticSummaryMethod:
+		var summary diagnosticSummary
+		if err := json.Unmarshal(req.Params(), &summary); err == nil {
+			r.mu.Lock()
+			r.summaries = append(r.summaries, summary)
+			r.mu.Unlock()
+		}
+	}
+	return reply(ctx, nil, nil)
+}
+
+func (r *diagnosticsRecorder) snapshot() []protocol.PublishDiagnosticsParams {
+	r.mu.Lock()
+	defer r.mu.Unlock()
+	return append([]protocol.PublishDiagnosticsParams{}, r.published...)
+}
+
+func (r *diagnosticsRecorder) summarySnapshot() []diagnosticSummary {
+	r.mu.Lock()
+	defer r.mu.Unlock()
+	return append([]diagnosticSummary{}, r.summaries...)
+}
+
+// connectTestClient wires the server to an in-memory client connection
+func connectTestClient(t *testing.T, server *Server) *diagnosticsRecorder {
+	t.Helper()
+	return newTestSession(t, server).recorder
+}
+
+func TestServer_RapidChangesPublishOnlyFinalState(t *testing.T) {
+	server := newTestServer()
+	server.validationDelay = 20 * time.Millisecond
+	recorder := connectTestClient(t, server)
+
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	server.documentManager.OpenDocument(uri, 1, "steps:\n  - wait: ~")
+
+	// Every intermediate version is valid; only the final one has a problem
+	for version := int32(2); version < 12; version++ {
+		content := "steps:\n  - wait: ~"
+		if version == 11 {
+			content = "steps:\n  - wait: true"
+		}
+
+		err := server.DidChange(context.Background(), &protocol.DidChangeTextDocumentParams{
+			TextDocument: protocol.VersionedTextDocumentIdentifier{
+				TextDocumentIdentifier: protocol.TextDocumentIdentifier{URI: uri},
+				Version:                version,
+			},
+			ContentChanges: []protocol.TextDocumentContentChangeEvent{{Text: content}},
+		})
+		if err != nil {
+			t.Fatalf("DidChange failed: %v", err)
+		}
+	}
+
+	deadline := time.Now().Add(2 * time.Second)
+	for len(recorder.snapshot()) == 0 && time.Now().Before(deadline) {
+		time.Sleep(10 * time.Millisecond)
+	}
+	// Leave time for any stale validation to (incorrectly) publish
+	time.Sleep(5 * server.validationDelay)
+
+	published := recorder.snapshot()
+	if len(published) == 0 {
+		t.Fatal("Expected diagnostics to be published")
+	}
+
+	// Intermediate versions would publish an empty list, so every publish must carry errors
+	for i, params := range published {
+		if len(params.Diagnostics) == 0 {
+			t.Errorf("Publish %d has no diagnostics, so it came from a stale version", i)
+		}
+	}
+}
+
+func TestServer_SchemaLoadDoesNotOverwriteNewerEdits(t *testing.T) {
+	requested := make(chan struct{})
+	release := make(chan struct{})
+	schemaServer := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		close(requested)
+		<-release
+		_, _ = w.Write([]byte(`{"type": "object"}`))
+	}))
+	defer schemaServer.Close()
+
+	server := newTestServer()
+	server.validationDelay = 100 * time.Millisecond
+	server.schemaLoader.SetURL(schemaServer.URL)
+	recorder := connectTestClient(t, server)
+
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	server.documentManager.OpenDocument(uri, 1, "steps:\n  - wait: true")
+	server.loadSchemaInBackground()
+
+	select {
+	case <-requested:
+	case <-time.After(2 * time.Second):
+		t.Fatal("Expected the schema to be requested")
+	}
+
+	// The fix lands as the download finishes, so re-validating the version
+	// that was open when it started would publish a stale error
+	close(release)
+	err := server.DidChange(context.Background(), &protocol.DidChangeTextDocumentParams{
+		TextDocument: protocol.VersionedTextDocumentIdentifier{
+			TextDocumentIdentifier: protocol.TextDocumentIdentifier{URI: uri},
+			Version:                2,
+		},
+		ContentChanges: []protocol.TextDocumentContentChangeEvent{{Text: "steps:\n  - wait: ~"}},
+	})
+	if err != nil {
+		t.Fatalf("DidChange failed: %v", err)
+	}
+
+	deadline := time.Now().Add(2 * time.Second)
+	for (len(recorder.snapshot()) == 0 || !server.schemaLoader.IsLoaded()) && time.Now().Before(deadline) {
+		time.Sleep(10 * time.Millisecond)
+	}
+	// Leave time for the re-validation after the schema load to publish
+	time.Sleep(3 * server.validationDelay)
+
+	published := recorder.snapshot()
+	if len(published) == 0 {
+		t.Fatal("Expected diagnostics to be published")
+	}
+	for i, params := range published {
+		if len(params.Diagnostics) != 0 {
+			t.Errorf("Publish %d has diagnostics %v, so it came from the stale version", i, params.Diagnostics)
+		}
+	}
+}
+
+func TestServer_ValidationDoesNotWaitForSchema(t *testing.T) {
+	requested := make(chan struct{})
+	release := make(chan struct{})
+	schemaServer := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		close(requested)
+		<-release
+		_, _ = w.Write([]byte(`{"type": "object"}`))
+	}))
+	defer schemaServer.Close()
+	defer close(release)
+
+	server := newTestServer()
+	server.schemaLoader.SetURL(schemaServer.URL)
+	recorder := connectTestClient(t, server)
+
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	doc := newDocument(uri, 1, "steps:\n  - wait: true")
+	server.loadSchemaInBackground()
+
+	select {
+	case <-requested:
+	case <-time.After(2 * time.Second):
+		t.Fatal("Expected the schema to be requested")
+	}
+
+	// Both run while the download is blocked, so they must not wait for it
+	validated := make(chan bool)
+	go func() {
+		server.validateDocument(context.Background(), doc)
+		validated <- server.schemaLoader.IsLoaded()
+	}()
+	select {
+	case loaded := <-validated:
+		if loaded {
+			t.Error("Expected the schema not to be loaded during the download")
+		}
+	case <-time.After(time.Second):
+		t.Fatal("Expected validation not to wait for the schema download")
+	}
+
+	deadline := time.Now().Add(2 * time.Second)
+	for len(recorder.snapshot()) == 0 && time.Now().Before(deadline) {
+		time.Sleep(10 * time.Millisecond)
+	}
+	if len(recorder.snapshot()) == 0 {
+		t.Error("Expected the structural checks to be published")
+	}
+}
+
+func TestServer_ShutdownCancelsPendingValidations(t *testing.T) {
+	server := newTestServer()
+	server.validationDelay = 50 * time.Millisecond
+	recorder := connectTestClient(t, server)
+
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	server.documentManager.OpenDocument(uri, 1, "steps:\n  - wait: true")
+	server.scheduleValidation(uri)
+
+	start := time.Now()
+	if err := server.Shutdown(context.Background()); err != nil {
+		t.Fatalf("Shutdown failed: %v", err)
+	}
+	if elapsed := time.Since(start); elapsed >= shutdownTimeout {
+		t.Errorf("Shutdown waited %s for background tasks", elapsed)
+	}
+
+	// Validations scheduled after shutdown are ignored
+	server.scheduleValidation(uri)
+	server.validationMu.Lock()
+	pending := len(server.pendingValidations)
+	server.validationMu.Unlock()
+	if pending != 0 {
+		t.Errorf("Expected no pending validations after shutdown, got %d", pending)
+	}
+
+	time.Sleep(3 * server.validationDelay)
+	if published := recorder.snapshot(); len(published) != 0 {
+		t.Errorf("Expected no diagnostics after shutdown, got %d publishes", len(published))
+	}
+}
+
+func TestServer_PublishesDiagnosticSummary(t *testing.T) {
+	server := newTestServer()
+	recorder := connectTestClient(t, server)
+
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	err := server.DidOpen(context.Background(), &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{URI: uri, Version: 1, Text: "steps:\n  - wait: true"},
+	})
+	if err != nil {
+		t.Fatalf("DidOpen failed: %v", err)
+	}
+
+	deadline := time.Now().Add(2 * time.Second)
+	for len(recorder.summarySnapshot()) == 0 && time.Now().Before(deadline) {
+		time.Sleep(10 * time.Millisecond)
+	}
+
+	summaries := recorder.summarySnapshot()
+	published := recorder.snapshot()
+	if len(summaries) == 0 || len(published) == 0 {
+		t.Fatal("Expected diagnostics and a summary to be published")
+	}
+
+	// Each summary follows the publish it describes
+	expected := summarizeDiagnostics(uri, published[0].Diagnostics)
+	if got := summaries[0]; got != expected {
+		t.Errorf("Expected summary %+v, got %+v", expected, got)
+	}
+	if expected.Errors == 0 {
+		t.Error("Expected the invalid wait step to be counted as an error")
+	}
+}
@@ -5,10 +5,16 @@ import (
 	"encoding/json"
 	"fmt"
 	"log"
+	"maps"
 	"os"
 	"path/filepath"
+	"regexp"
+	"slices"
 	"strconv"
 	"strings"
+	"sync"
+	"time"
+	"unicode/utf8"
 
 	"go.lsp.dev/jsonrpc2"
 	"go.lsp.dev/protocol"
@@ -27,6 +33,38 @@ type Server struct {
 	documentManager    *DocumentManager
 	completionProvider *CompletionProvider
 	conn               jsonrpc2.Conn
+	traceMu            sync.RWMutex
+	traceLevel         string
+	settings           Settings
+	schemaLoadMu       sync.Mutex
+	schemaLoading      bool
+	validationMu       sync.Mutex
+	pendingValidations map[protocol.DocumentURI]*pendingValidation
+	validationDelay    time.Duration
+	hoverCache         *hoverCache
+
+	// workspaceFolders are the roots the client has open, kept current by
+	// workspace/didChangeWorkspaceFolders
+	workspaceMu      sync.Mutex
+	workspaceFolders []protocol.WorkspaceFolder
+	// metaDataKeys are the meta-data keys each workspace pipeline uses, indexed
+	// on initialized and refreshed when a pipeline is saved
+	metaDataKeys map[protocol.DocumentURI][]string
+
+	// lifetime is cancelled on shutdown to stop validations that are running
+	lifetime context.Context
+	stop     context.CancelFunc
+	// tasks tracks scheduled validations and background schema loads; once
+	// stopping is set no new tasks are started
+	tasksMu  sync.Mutex
+	tasks    sync.WaitGroup
+	stopping bool
+
+	// commitCharactersSupport records whether the client accepts completion commit characters
+	commitCharactersSupport bool
+	// hierarchicalDocumentSymbolSupport records whether the client accepts nested
+	// DocumentSymbols; otherwise the outline is sent as flat SymbolInformation
+	hierarchicalDocumentSymbolSupport bool
 }
 
 func NewServer() *Server {
@@ -39,14 +77,26 @@ func NewServer() *Server {
 	}
 
 	logger := log.New(debugFile, "[buildkite-ls] ", log.LstdFlags|log.Lshortfile)
+	lifetime, stop := context.WithCancel(context.Background())
 
-	return &Server{
+	schemaLoader := schema.NewLoader()
+	completionProvider := NewCompletionProvider(pluginRegistry, logger)
+	completionProvider.SetSchemaLoader(schemaLoader)
+
+	server := &Server{
 		logger:             logger,
-		schemaLoader:       schema.NewLoader(),
+		schemaLoader:       schemaLoader,
 		pluginRegistry:     pluginRegistry,
 		documentManager:    NewDocumentManager(),
-		completionProvider: NewCompletionProvider(pluginRegistry, logger),
+		completionProvider: completionProvider,
+		pendingValidations: make(map[protocol.DocumentURI]*pendingValidation),
+		validationDelay:    defaultValidationDelay,
+		hoverCache:         newHoverCache(defaultHoverCacheSize),
+		lifetime:           lifetime,
+		stop:               stop,
 	}
+	completionProvider.SetWorkspaceMetaDataKeys(server.WorkspaceMetaDataKeys)
+	return server
 }
 
 func (s *Server) SetClient(client protocol.Client) {
@@ -63,9 +113,51 @@ func (s *Server) Logger() *log.Logger {
 
 func (s *Server) Initialize(ctx context.Context, params *protocol.InitializeParams) (*protocol.InitializeResult, error) {
 	s.logger.Printf("Initializing buildkite-ls server")
+	s.setTraceLevel(string(params.Trace))
+
+	settings, err := parseSettings(params.InitializationOptions)
+	if err != nil {
+		s.logger.Printf("Ignoring invalid initialization options: %v", err)
+	}
+	s.settings = settings
+	s.completionProvider.SetInsertRequiredFields(settings.InsertRequiredFields)
+
+	if settings.SchemaURL != "" {
+		s.logger.Printf("Loading pipeline schema from %s", settings.SchemaURL)
+		s.schemaLoader.SetURL(settings.SchemaURL)
+	} else if ref := settings.SchemaRef; ref != "" {
+		if schema.IsValidSchemaRef(ref) {
+			s.logger.Printf("Pinning pipeline schema to %s", ref)
+			s.schemaLoader.SetURL(schema.URLForRef(ref))
+		} else {
+			s.showMessage(ctx, protocol.MessageTypeWarning,
+				fmt.Sprintf("buildkite-ls: schemaRef %q is not a tag or commit SHA, using the latest schema from main", ref))
+		}
+	}
+
+	registryLocation := settings.PluginRegistryPath
+	if registryLocation == "" {
+		registryLocation = settings.PluginRegistryURL
+	}
+	if registryLocation != "" {
+		s.loadCatalogInBackground(registryLocation)
+	}
+
+	s.workspaceMu.Lock()
+	s.workspaceFolders = initialWorkspaceFolders(params)
+	s.workspaceMu.Unlock()
+
+	if textDocument := params.Capabilities.TextDocument; textDocument != nil {
+		if textDocument.Completion != nil && textDocument.Completion.CompletionItem != nil {
+			s.commitCharactersSupport = textDocument.Completion.CompletionItem.CommitCharactersSupport
+		}
+		if textDocument.DocumentSymbol != nil {
+			s.hierarchicalDocumentSymbolSupport = textDocument.DocumentSymbol.HierarchicalDocumentSymbolSupport
+		}
+	}
 
 	completionOptions := &protocol.CompletionOptions{
-		TriggerCharacters: []string{" ", ":", "-"},
+		TriggerCharacters: []string{" ", ":", "-", ".", "<"},
 	}
 
 	s.logger.Printf("Advertising completion capabilities with triggers: %v", completionOptions.TriggerCharacters)
@@ -75,11 +167,15 @@ func (s *Server) Initialize(ctx context.Context, params *protocol.InitializePara
 			TextDocumentSync: &protocol.TextDocumentSyncOptions{
 				OpenClose: true,
 				Change:    protocol.TextDocumentSyncKindFull,
+				Save:      &protocol.SaveOptions{IncludeText: true},
 			},
 			HoverProvider:          true,
 			CompletionProvider:     completionOptions,
 			DocumentSymbolProvider: true,
 			DefinitionProvider:     true,
+			RenameProvider: &protocol.RenameOptions{
+				PrepareProvider: true,
+			},
 			CodeActionProvider: &protocol.CodeActionOptions{
 				CodeActionKinds: []protocol.CodeActionKind{
 					protocol.QuickFix,
@@ -87,6 +183,17 @@ func (s *Server) Initialize(ctx context.Context, params *protocol.InitializePara
 					protocol.RefactorRewrite,
 				},
 			},
+			Workspace: &protocol.ServerCapabilitiesWorkspace{
+				WorkspaceFolders: &protocol.ServerCapabilitiesWorkspaceFolders{
+					Supported:           true,
+					ChangeNotifications: true,
+				},
+				FileOperations: &protocol.ServerCapabilitiesWorkspaceFileOperations{
+					WillRename: &protocol.FileOperationRegistrationOptions{
+						Filters: []protocol.FileOperationFilter{pipelineFileFilter},
+					},
+				},
+			},
 			SemanticTokensProvider: map[string]interface{}{
 				"legend": map[string]interface{}{
 					"tokenTypes": []string{
@@ -118,11 +225,13 @@ func (s *Server) Initialize(ctx context.Context, params *protocol.InitializePara
 
 func (s *Server) Initialized(ctx context.Context, params *protocol.InitializedParams) error {
 	s.logger.Printf("Server initialized - ready to receive document events")
+	s.indexWorkspaceMetaData()
 	return nil
 }
 
 func (s *Server) Shutdown(ctx context.Context) error {
 	s.logger.Printf("Server shutting down")
+	s.stopBackgroundTasks(ctx)
 	return nil
 }
 
@@ -138,9 +247,13 @@ func (s *Server) DidOpen(ctx context.Context, params *protocol.DidOpenTextDocume
 
 	// Store document content
 	s.documentManager.OpenDocument(params.TextDocument.URI, params.TextDocument.Version, params.TextDocument.Text)
+	s.hoverCache.invalidate(params.TextDocument.URI)
+	s.cancelValidation(params.TextDocument.URI)
 
 	// Validate the document
-	s.validateDocument(ctx, params.TextDocument.URI, params.TextDocument.Text)
+	if doc, exists := s.documentManager.GetDocument(params.TextDocument.URI); exists {
+		s.validateDocument(ctx, doc)
+	}
 	return nil
 }
 
@@ -152,9 +265,10 @@ func (s *Server) DidChange(ctx context.Context, params *protocol.DidChangeTextDo
 
 		// Update document content
 		s.documentManager.UpdateDocument(params.TextDocument.URI, params.TextDocument.Version, lastChange.Text)
+		s.hoverCache.invalidate(params.TextDocument.URI)
 
-		// Validate the updated document
-		s.validateDocument(ctx, params.TextDocument.URI, lastChange.Text)
+		// Validate once typing pauses, superseding any earlier validation
+		s.scheduleValidation(params.TextDocument.URI)
 	}
 	return nil
 }
@@ -163,7 +277,37 @@ func (s *Server) DidClose(ctx context.Context, params *protocol.DidCloseTextDocu
 	s.logger.Printf("Document closed: %s", params.TextDocument.URI)
 
 	// Remove document from cache
+	s.cancelValidation(params.TextDocument.URI)
 	s.documentManager.CloseDocument(params.TextDocument.URI)
+	s.hoverCache.invalidate(params.TextDocument.URI)
+	return nil
+}
+
+// DidSave revalidates the saved pipeline and refreshes the workspace meta-data
+// keys from it. The saved text, when the client sends it, replaces the stored
+// document in case incremental changes left it out of sync.
+func (s *Server) DidSave(ctx context.Context, params *protocol.DidSaveTextDocumentParams) error {
+	uri := params.TextDocument.URI
+	if !s.isBuildkiteFile(string(uri)) {
+		return nil
+	}
+
+	doc, exists := s.documentManager.GetDocument(uri)
+	if !exists {
+		return nil
+	}
+	if params.Text != "" && params.Text != doc.Content {
+		s.logger.Printf("Document %s was out of sync on save, using the saved text", uri)
+		s.documentManager.UpdateDocument(uri, doc.Version, params.Text)
+		s.hoverCache.invalidate(uri)
+		if refreshed, exists := s.documentManager.GetDocument(uri); exists {
+			doc = refreshed
+		}
+	}
+
+	s.cancelValidation(uri)
+	s.validateDocument(ctx, doc)
+	s.setMetaDataKeys(uri, collectMetaDataKeys(doc.Lines))
 	return nil
 }
 
@@ -172,24 +316,34 @@ func (s *Server) Hover(ctx context.Context, params *protocol.HoverParams) (*prot
 		return nil, nil
 	}
 
-	// Get position context to provide smart hover
-	posCtx, err := s.documentManager.GetContentAtPosition(params.TextDocument.URI, params.Position)
-	if err != nil {
-		s.logger.Printf("Failed to get position context for hover: %v", err)
+	doc, exists := s.documentManager.GetDocument(params.TextDocument.URI)
+	if !exists {
+		s.logger.Printf("Failed to get position context for hover: document not found: %s", params.TextDocument.URI)
 		return nil, nil
 	}
 
-	hoverContent := s.getContextualHoverContent(posCtx)
-	if hoverContent == "" {
-		return nil, nil // No hover content available
+	key := hoverKey{URI: params.TextDocument.URI, Position: params.Position, Version: doc.Version}
+	if hover, ok := s.hoverCache.get(key); ok {
+		return hover, nil
 	}
 
-	return &protocol.Hover{
-		Contents: protocol.MarkupContent{
-			Kind:  protocol.Markdown,
-			Value: hoverContent,
-		},
-	}, nil
+	// Get position context to provide smart hover. It's read from the same
+	// version of the document as the cache key, so an edit made meanwhile
+	// can't be cached under the old version.
+	posCtx := doc.PositionContext(params.Position)
+
+	var hover *protocol.Hover
+	if hoverContent := s.getContextualHoverContent(posCtx); hoverContent != "" {
+		hover = &protocol.Hover{
+			Contents: protocol.MarkupContent{
+				Kind:  protocol.Markdown,
+				Value: hoverContent,
+			},
+		}
+	}
+
+	s.hoverCache.put(key, hover)
+	return hover, nil
 }
 
 func (s *Server) getContextualHoverContent(posCtx *bkcontext.PositionContext) string {
@@ -200,21 +354,56 @@ func (s *Server) getContextualHoverContent(posCtx *bkcontext.PositionContext) st
 	// Analyze context to determine what we're hovering over
 	contextInfo := s.completionProvider.GetContextAnalyzer().AnalyzeContext(posCtx)
 
+	if variable, ok := escapedDollarAt(posCtx.CurrentLine, posCtx.CharIndex); ok {
+		return escapedDollarHoverContent(variable)
+	}
+
 	// Extract the word/property at cursor position
 	currentWord := s.extractWordAtPosition(posCtx)
 	if currentWord == "" {
 		return ""
 	}
 
-	// Check if hovering over a plugin reference
-	if strings.Contains(currentWord, "#") && contextInfo.IsInPluginsArray() {
-		return s.getPluginHoverContent(currentWord)
+	// Check if hovering over a plugin reference. Local plugins and full git
+	// URLs have no published plugin.yml to describe them.
+	if reference, ok := s.pluginReferenceAtCursor(posCtx); ok && reference.Org != "" {
+		return s.getPluginHoverContent(reference.Ref)
 	}
 
 	// Provide property-specific documentation
 	return s.getPropertyHoverContent(currentWord, contextInfo)
 }
 
+// escapedDollarPattern matches a $$ escape along with the variable name written
+// after it, if any. Matches are leftmost, so "$$$HOME" is an escape followed by
+// an interpolated $HOME, as Buildkite reads it.
+var escapedDollarPattern = regexp.MustCompile(`\$\$(\{[A-Za-z_][A-Za-z0-9_]*\}|[A-Za-z_][A-Za-z0-9_]*)?`)
+
+// escapedDollarAt reports whether index falls on a $$ escape in line, returning
+// the variable name written after it
+func escapedDollarAt(line string, index int) (string, bool) {
+	for _, match := range escapedDollarPattern.FindAllStringSubmatchIndex(line, -1) {
+		if match[0] <= index && index < match[1] {
+			if match[2] == -1 {
+				return "", true
+			}
+			return line[match[2]:match[3]], true
+		}
+	}
+	return "", false
+}
+
+// escapedDollarHoverContent explains that $$ becomes a literal $ rather than
+// starting an interpolation
+func escapedDollarHoverContent(variable string) string {
+	content := "**`$$`** - escaped dollar sign\n\n" +
+		"Buildkite replaces `$$` with a literal `$` when the pipeline is uploaded, without interpolating anything."
+	if variable != "" {
+		content += fmt.Sprintf(" The job receives `$%s`, so it's expanded by the shell at runtime rather than when the pipeline is uploaded.", variable)
+	}
+	return content
+}
+
 func (s *Server) extractWordAtPosition(posCtx *bkcontext.PositionContext) string {
 	currentLine := posCtx.CurrentLine
 	charIndex := posCtx.CharIndex
@@ -278,45 +467,54 @@ func (s *Server) getPluginHoverContent(pluginName string) string {
 	return content
 }
 
-func (s *Server) getPropertyHoverContent(property string, contextInfo *bkcontext.ContextInfo) string {
-	// Create comprehensive documentation for Buildkite properties
-	propertyDocs := map[string]string{
-		// Pipeline-level properties
-		"steps":  "**steps** - Array of build steps to be executed\n\nDefines the sequence of operations for your build pipeline. Each step can be a command step, wait step, block step, input step, or trigger step.\n\n[Steps Documentation](https://buildkite.com/docs/pipelines/defining-steps)",
-		"env":    "**env** - Environment variables for the pipeline\n\nDefines environment variables that will be available to all steps in the pipeline unless overridden at the step level.\n\nExample:\n```yaml\nenv:\n  NODE_ENV: production\n  DEBUG: \"false\"\n```",
-		"agents": "**agents** - Agent requirements for running steps\n\nSpecifies which agents can run this pipeline or step using key-value pairs for targeting.\n\nExample:\n```yaml\nagents:\n  queue: \"default\"\n  os: \"linux\"\n```",
-
-		// Step properties
-		"label":   "**label** - Human-readable name for the step\n\nDisplayed in the Buildkite UI and used to identify the step. Supports emoji and can include environment variable substitutions.\n\nExample: `label: \":rocket: Deploy to production\"`",
-		"command": "**command** - Shell command(s) to execute\n\nCan be a single command or multiple commands. Supports multiline YAML syntax for complex scripts.\n\nExample:\n```yaml\ncommand: |\n  echo \"Building...\"\n  make build\n  make test\n```",
-		"plugins": "**plugins** - List of plugins to enhance the step\n\nEach plugin provides additional functionality like Docker support, caching, or artifact management. Plugins are specified with their name and version.\n\n[Plugin Directory](https://buildkite.com/plugins)",
-
-		// Advanced step properties
-		"depends_on":         "**depends_on** - Step dependencies\n\nSpecifies which steps must complete before this step runs. Can reference steps by label or use step keys.\n\nExample:\n```yaml\ndepends_on:\n  - \"build\"\n  - step: \"test\"\n    allow_failure: true\n```",
-		"if":                 "**if** - Conditional execution\n\nStep will only run if the condition evaluates to true. Supports environment variables and build metadata.\n\nExample: `if: build.branch == \"main\"`",
-		"retry":              "**retry** - Automatic and manual retry configuration\n\nDefines how the step should be retried on failure.\n\nExample:\n```yaml\nretry:\n  automatic:\n    - exit_status: -1\n      limit: 2\n  manual:\n    allowed: true\n```",
-		"timeout_in_minutes": "**timeout_in_minutes** - Step timeout\n\nMaximum time the step can run before being cancelled. Defaults to no timeout.\n\nExample: `timeout_in_minutes: 30`",
-		"artifact_paths":     "**artifact_paths** - Glob patterns for build artifacts\n\nSpecifies which files/directories to upload as build artifacts after the step completes.\n\nExample: `artifact_paths: \"dist/**/*\"`",
-		"branches":           "**branches** - Branch filtering\n\nControls which branches this step runs on. Supports glob patterns and negation.\n\nExample: `branches: \"main release/*\"`",
-		"concurrency":        "**concurrency** - Parallel execution limit\n\nLimits how many instances of this step can run simultaneously across all agents.\n\nExample: `concurrency: 1`",
-		"concurrency_group":  "**concurrency_group** - Concurrency grouping\n\nGroups steps together for concurrency limiting. Steps in the same group share concurrency limits.\n\nExample: `concurrency_group: \"deploy\"`",
-
-		// Special step types
-		"wait":    "**wait** - Wait step\n\nPauses the pipeline until all previous steps have completed. Useful for creating pipeline phases.\n\nExample: `wait: ~` or `wait: \"Continue to deploy?\"`",
-		"block":   "**block** - Manual approval step\n\nPauses the pipeline and waits for manual approval before continuing.\n\nExample: `block: \"Deploy to production?\"`",
-		"input":   "**input** - Input step\n\nCollects input from users before continuing the pipeline.\n\nExample:\n```yaml\ninput: \"Release details\"\nfields:\n  - text: \"version\"\n    required: true\n```",
-		"trigger": "**trigger** - Trigger another pipeline\n\nTriggers another pipeline and optionally waits for it to complete.\n\nExample:\n```yaml\ntrigger: \"my-deployment-pipeline\"\nbuild:\n  message: \"Triggered from ${BUILDKITE_MESSAGE}\"\n```",
-
-		// Plugin-specific (common ones)
-		"image":   "**image** - Docker image to use\n\nSpecifies the Docker image for the docker plugin.\n\nExample: `image: \"node:18\"`",
-		"volumes": "**volumes** - Docker volume mounts\n\nMounts host directories or volumes into the Docker container.\n\nExample:\n```yaml\nvolumes:\n  - \".:/app\"\n  - \"./cache:/cache\"\n```",
-		"key":     "**key** - Cache key\n\nUnique identifier for the cache entry in the cache plugin.\n\nExample: `key: \"v1-{{ checksum 'package-lock.json' }}\"`",
-		"paths":   "**paths** - Cache paths\n\nDirectories or files to cache.\n\nExample:\n```yaml\npaths:\n  - \"node_modules\"\n  - \".cache\"\n```",
-	}
+// propertyDocs is the hover documentation for Buildkite properties, keyed by property name
+var propertyDocs = map[string]string{
+	// Pipeline-level properties
+	"steps":  "**steps** - Array of build steps to be executed\n\nDefines the sequence of operations for your build pipeline. Each step can be a command step, wait step, block step, input step, or trigger step.\n\n[Steps Documentation](https://buildkite.com/docs/pipelines/defining-steps)",
+	"env":    "**env** - Environment variables for the pipeline\n\nDefines environment variables that will be available to all steps in the pipeline unless overridden at the step level.\n\nExample:\n```yaml\nenv:\n  NODE_ENV: production\n  DEBUG: \"false\"\n```",
+	"agents": "**agents** - Agent requirements for running steps\n\nSpecifies which agents can run this pipeline or step using key-value pairs for targeting.\n\nExample:\n```yaml\nagents:\n  queue: \"default\"\n  os: \"linux\"\n```",
+
+	// Step properties
+	"label":   "**label** - Human-readable name for the step\n\nDisplayed in the Buildkite UI and used to identify the step. Supports emoji and can include environment variable substitutions.\n\nExample: `label: \":rocket: Deploy to production\"`",
+	"command": "**command** - Shell command(s) to execute\n\nCan be a single command or multiple commands. Supports multiline YAML syntax for complex scripts.\n\nExample:\n```yaml\ncommand: |\n  echo \"Building...\"\n  make build\n  make test\n```",
+	"plugins": "**plugins** - List of plugins to enhance the step\n\nEach plugin provides additional functionality like Docker support, caching, or artifact management. Plugins are specified with their name and version.\n\n[Plugin Directory](https://buildkite.com/plugins)",
+
+	// Advanced step properties
+	"cache":              "**cache** - Paths to cache between builds\n\nCaches the listed paths on the agent so later builds can restore them. Use `key` to control when the cache is invalidated.\n\nExample:\n```yaml\ncache:\n  paths:\n    - \"node_modules\"\n  key: \"v1-deps\"\n```",
+	"depends_on":         "**depends_on** - Step dependencies\n\nSpecifies which steps must complete before this step runs. Can reference steps by label or use step keys.\n\nExample:\n```yaml\ndepends_on:\n  - \"build\"\n  - step: \"test\"\n    allow_failure: true\n```",
+	"if":                 "**if** - Conditional execution\n\nStep will only run if the condition evaluates to true. Supports environment variables and build metadata.\n\nExample: `if: build.branch == \"main\"`",
+	"retry":              "**retry** - Automatic and manual retry configuration\n\nDefines how the step should be retried on failure.\n\nExample:\n```yaml\nretry:\n  automatic:\n    - exit_status: -1\n      limit: 2\n  manual:\n    allowed: true\n```",
+	"timeout_in_minutes": "**timeout_in_minutes** - Step timeout\n\nMaximum time the step can run before being cancelled. Defaults to no timeout.\n\nExample: `timeout_in_minutes: 30`",
+	"artifact_paths":     "**artifact_paths** - Glob patterns for build artifacts\n\nSpecifies which files/directories to upload as build artifacts after the step completes.\n\nExample: `artifact_paths: \"dist/**/*\"`",
+	"branches":           "**branches** - Branch filtering\n\nControls which branches this step runs on. Supports glob patterns and negation.\n\nExample: `branches: \"main release/*\"`",
+	"concurrency":        "**concurrency** - Parallel execution limit\n\nLimits how many instances of this step can run simultaneously across all agents.\n\nExample: `concurrency: 1`",
+	"concurrency_group":  "**concurrency_group** - Concurrency grouping\n\nGroups steps together for concurrency limiting. Steps in the same group share concurrency limits.\n\nExample: `concurrency_group: \"deploy\"`",
+
+	// Special step types
+	"wait":    "**wait** - Wait step\n\nPauses the pipeline until all previous steps have completed. Useful for creating pipeline phases.\n\nExample: `wait: ~` or `wait: \"Continue to deploy?\"`",
+	"block":   "**block** - Manual approval step\n\nPauses the pipeline and waits for manual approval before continuing.\n\nExample: `block: \"Deploy to production?\"`",
+	"input":   "**input** - Input step\n\nCollects input from users before continuing the pipeline.\n\nExample:\n```yaml\ninput: \"Release details\"\nfields:\n  - text: \"version\"\n    required: true\n```",
+	"trigger": "**trigger** - Trigger another pipeline\n\nTriggers another pipeline and optionally waits for it to complete.\n\nExample:\n```yaml\ntrigger: \"my-deployment-pipeline\"\nbuild:\n  message: \"Triggered from ${BUILDKITE_MESSAGE}\"\n```",
+
+	// Plugin-specific (common ones)
+	"image":   "**image** - Docker image to use\n\nSpecifies the Docker image for the docker plugin.\n\nExample: `image: \"node:18\"`",
+	"volumes": "**volumes** - Docker volume mounts\n\nMounts host directories or volumes into the Docker container.\n\nExample:\n```yaml\nvolumes:\n  - \".:/app\"\n  - \"./cache:/cache\"\n```",
+	"key":     "**key** - Cache key\n\nUnique identifier for the cache entry in the cache plugin.\n\nExample: `key: \"v1-{{ checksum 'package-lock.json' }}\"`",
+	"paths":   "**paths** - Cache paths\n\nDirectories or files to cache.\n\nExample:\n```yaml\npaths:\n  - \"node_modules\"\n  - \".cache\"\n```",
+}
+
+// PropertyDocumentation returns a copy of the hover documentation for
+// Buildkite properties, keyed by property name, for reuse by editor
+// extensions and documentation generators
+func PropertyDocumentation() map[string]string {
+	return maps.Clone(propertyDocs)
+}
 
+func (s *Server) getPropertyHoverContent(property string, contextInfo *bkcontext.ContextInfo) string {
 	// Get documentation for the property
 	if doc, exists := propertyDocs[property]; exists {
-		return doc
+		// Step type keywords also list the fields that type accepts
+		return doc + s.getStepTypeFieldsContent(property) + s.getPropertyTypeContent(property) + s.getPropertyDefaultContent(property)
 	}
 
 	// For unknown properties, provide basic context-aware help
@@ -329,7 +527,65 @@ func (s *Server) getPropertyHoverContent(property string, contextInfo *bkcontext
 		contextType = "plugin"
 	}
 
-	return fmt.Sprintf("**%s** - %s property\n\nNo specific documentation available for this property.\n\n[Buildkite Documentation](https://buildkite.com/docs)", property, contextType)
+	return fmt.Sprintf("**%s** - %s property\n\nNo specific documentation available for this property.%s%s\n\n[Buildkite Documentation](https://buildkite.com/docs)",
+		property, contextType, s.getPropertyTypeContent(property), s.getPropertyDefaultContent(property))
+}
+
+// getPropertyTypeContent renders the type the pipeline schema gives a property
+func (s *Server) getPropertyTypeContent(property string) string {
+	summary, ok := s.schemaLoader.PropertyType(property)
+	if !ok {
+		return ""
+	}
+	return fmt.Sprintf("\n\n**Type:** `%s`", summary)
+}
+
+// getPropertyDefaultContent renders the default the pipeline schema gives a
+// property, so users know what happens when they leave it out
+func (s *Server) getPropertyDefaultContent(property string) string {
+	value, ok := s.schemaLoader.PropertyDefault(property)
+	if !ok {
+		return ""
+	}
+
+	rendered, isString := value.(string)
+	if !isString || rendered == "" {
+		encoded, err := json.Marshal(value)
+		if err != nil {
+			return ""
+		}
+		rendered = string(encoded)
+	}
+	return fmt.Sprintf("\n\n**Default:** `%s`", rendered)
+}
+
+// maxHoverFields bounds the field list shown when hovering a step type
+const maxHoverFields = 8
+
+// getStepTypeFieldsContent renders the common fields of a step type as a Markdown list
+func (s *Server) getStepTypeFieldsContent(stepType string) string {
+	signature := s.getStepTypeSignature(stepType)
+	if signature == nil || len(signature.Parameters) == 0 {
+		return ""
+	}
+
+	var content strings.Builder
+	content.WriteString("\n\n**Fields**\n")
+
+	for i, param := range signature.Parameters {
+		if i == maxHoverFields {
+			content.WriteString(fmt.Sprintf("- ...and %d more\n", len(signature.Parameters)-maxHoverFields))
+			break
+		}
+
+		description := ""
+		if doc, ok := param.Documentation.(*protocol.MarkupContent); ok {
+			description = " - " + doc.Value
+		}
+		content.WriteString(fmt.Sprintf("- `%s`%s\n", param.Label, description))
+	}
+
+	return content.String()
 }
 
 func (s *Server) Completion(ctx context.Context, params *protocol.CompletionParams) (*protocol.CompletionList, error) {
@@ -349,15 +605,20 @@ func (s *Server) Completion(ctx context.Context, params *protocol.CompletionPara
 
 	s.logger.Printf("Position context - Current line: '%s', Char index: %d", positionContext.CurrentLine, positionContext.CharIndex)
 
+	// "." only triggers completion for member access in `if` expressions
+	if params.Context != nil && params.Context.TriggerCharacter == "." && !s.completionProvider.IsInConditional(positionContext) {
+		return &protocol.CompletionList{IsIncomplete: false, Items: []protocol.CompletionItem{}}, nil
+	}
+
 	// Get context-aware completions
-	items := s.completionProvider.GetCompletions(positionContext)
+	list := s.completionProvider.GetCompletionList(positionContext)
+	if s.commitCharactersSupport {
+		addPropertyCommitCharacters(list.Items)
+	}
 
-	s.logger.Printf("Generated %d completion items", len(items))
+	s.logger.Printf("Generated %d completion items (incomplete: %t)", len(list.Items), list.IsIncomplete)
 
-	return &protocol.CompletionList{
-		IsIncomplete: false,
-		Items:        items,
-	}, nil
+	return list, nil
 }
 
 func (s *Server) SignatureHelp(ctx context.Context, params *protocol.SignatureHelpParams) (*protocol.SignatureHelp, error) {
@@ -407,7 +668,7 @@ func (s *Server) getSignatureHelp(ctx *bkcontext.PositionContext) []protocol.Sig
 func (s *Server) isInPluginContext(ctx *bkcontext.PositionContext) bool {
 	// Check if we're in a plugin configuration context
 	// Look for "plugins:" section and check if we're inside it
-	lines := strings.Split(ctx.FullContent, "\n")
+	lines := splitLines(ctx.FullContent)
 	currentLine := int(ctx.Position.Line)
 
 	// Go backwards to find if we're in a plugins section
@@ -442,7 +703,7 @@ func (s *Server) isInPluginContext(ctx *bkcontext.PositionContext) bool {
 
 func (s *Server) isInStepContext(ctx *bkcontext.PositionContext) bool {
 	// Check if we're configuring step properties
-	lines := strings.Split(ctx.FullContent, "\n")
+	lines := splitLines(ctx.FullContent)
 	currentLine := int(ctx.Position.Line)
 
 	// Go backwards to find if we're in a step
@@ -510,7 +771,7 @@ func (s *Server) getStepSignatures(ctx *bkcontext.PositionContext) []protocol.Si
 }
 
 func (s *Server) detectPluginName(ctx *bkcontext.PositionContext) string {
-	lines := strings.Split(ctx.FullContent, "\n")
+	lines := splitLines(ctx.FullContent)
 	currentLine := int(ctx.Position.Line)
 
 	// Go backwards to find the plugin name
@@ -536,7 +797,7 @@ func (s *Server) detectPluginName(ctx *bkcontext.PositionContext) string {
 }
 
 func (s *Server) detectStepType(ctx *bkcontext.PositionContext) string {
-	lines := strings.Split(ctx.FullContent, "\n")
+	lines := splitLines(ctx.FullContent)
 	currentLine := int(ctx.Position.Line)
 
 	// Look for step type in current step
@@ -798,7 +1059,7 @@ func (s *Server) isStepReference(ctx *bkcontext.PositionContext, word string) bo
 func (s *Server) isPluginReference(ctx *bkcontext.PositionContext, word string) bool {
 	// Check if we're in a plugin configuration context
 	// This could be in plugins array or plugin references
-	lines := strings.Split(ctx.FullContent, "\n")
+	lines := splitLines(ctx.FullContent)
 	currentLine := int(ctx.Position.Line)
 
 	// Check if we're in a plugins section
@@ -825,7 +1086,7 @@ func (s *Server) isPluginReference(ctx *bkcontext.PositionContext, word string)
 }
 
 func (s *Server) findStepDefinition(ctx *bkcontext.PositionContext, stepKey string) *protocol.Location {
-	lines := strings.Split(ctx.FullContent, "\n")
+	lines := splitLines(ctx.FullContent)
 
 	// Find all step definitions and look for one with matching key
 	inSteps := false
@@ -863,78 +1124,73 @@ func (s *Server) findStepDefinition(ctx *bkcontext.PositionContext, stepKey stri
 	return nil
 }
 
+// findStepKey returns the key of the step starting on stepStartLine, whatever
+// its type, or one generated from its label when it has none. Only the step's
+// own properties are read, so block fields and depends_on entries are skipped.
 func (s *Server) findStepKey(lines []string, stepStartLine int) string {
-	// Look through the step for a "key:" property
-	for i := stepStartLine; i < len(lines); i++ {
-		line := strings.TrimSpace(lines[i])
-
-		// Stop if we hit another step or top-level property
-		if i > stepStartLine && (strings.HasPrefix(strings.TrimLeft(lines[i], " \t"), "- ") || (len(lines[i]) > 0 && lines[i][0] != ' ' && lines[i][0] != '\t')) {
-			break
-		}
+	stepEndLine := s.findListItemEnd(lines, stepStartLine, len(lines)-1)
 
-		// Look for key: property
-		if strings.Contains(line, "key:") {
-			// Find the key: part and extract the value
-			keyStart := strings.Index(line, "key:")
-			if keyStart != -1 {
-				keyPart := line[keyStart:]
-				parts := strings.SplitN(keyPart, ":", 2)
-				if len(parts) == 2 {
-					keyValue := strings.TrimSpace(parts[1])
-					// Remove quotes
-					keyValue = strings.Trim(keyValue, `"'`)
-					return keyValue
-				}
+	for _, field := range []string{"key", "identifier", "id"} {
+		if loc := s.findStepProperty(field, lines, stepStartLine, stepEndLine); loc != nil {
+			if reference, ok := scalarStepKey(lines, loc); ok {
+				return reference.Key
 			}
 		}
 	}
 
-	// If no explicit key, try to generate one from label
-	for i := stepStartLine; i < len(lines); i++ {
-		line := strings.TrimSpace(lines[i])
-
-		// Stop if we hit another step or top-level property
-		if i > stepStartLine && (strings.HasPrefix(strings.TrimLeft(lines[i], " \t"), "- ") || (len(lines[i]) > 0 && lines[i][0] != ' ' && lines[i][0] != '\t')) {
-			break
-		}
-
-		// Look for label: property and generate key from it
-		if strings.Contains(line, "label:") {
-			// Find the label: part and extract the value
-			labelStart := strings.Index(line, "label:")
-			if labelStart != -1 {
-				labelPart := line[labelStart:]
-				parts := strings.SplitN(labelPart, ":", 2)
-				if len(parts) == 2 {
-					labelValue := strings.TrimSpace(parts[1])
-					// Remove quotes
-					labelValue = strings.Trim(labelValue, `"'`)
-					// Convert to key format (lowercase, replace spaces with dashes)
-					key := strings.ToLower(labelValue)
-					key = strings.ReplaceAll(key, " ", "-")
-					key = strings.ReplaceAll(key, ":", "")
-					return key
-				}
-			}
-		}
+	// If no explicit key, generate one from the label
+	loc := s.findStepProperty("label", lines, stepStartLine, stepEndLine)
+	if loc == nil {
+		return ""
 	}
+	rng := valueRange(lines, loc)
+	labelValue := strings.Trim(lines[loc.Line][rng.Start.Character:rng.End.Character], `"'`)
 
-	return ""
+	// Convert to key format (lowercase, replace spaces with dashes)
+	key := strings.ToLower(labelValue)
+	key = strings.ReplaceAll(key, " ", "-")
+	key = strings.ReplaceAll(key, ":", "")
+	return key
 }
 
+// findPluginDefinitions resolves a plugin reference to its GitHub repository.
+// The location is external, so clients open it in a browser.
 func (s *Server) findPluginDefinitions(ctx *bkcontext.PositionContext, pluginName string) []protocol.Location {
 	var locations []protocol.Location
 
-	// For now, return empty - this could be extended to:
-	// 1. Find other uses of the same plugin in the pipeline
-	// 2. Link to external plugin repositories
-	// 3. Show plugin schema definitions
+	reference, ok := s.pluginReferenceAtCursor(ctx)
+	if !ok || !strings.Contains(reference.Ref, pluginName) {
+		return locations
+	}
+
+	// Local plugins and full git URLs don't map to a GitHub repository
+	if reference.Org == "" {
+		s.logger.Printf("Plugin '%s' has no repository to link to", reference.Ref)
+		return locations
+	}
+
+	repositoryURL := s.pluginRegistry.RepositoryURL(reference.Ref)
+	if repositoryURL == "" {
+		return locations
+	}
+
+	locations = append(locations, protocol.Location{
+		URI: protocol.DocumentURI(repositoryURL),
+	})
 
-	s.logger.Printf("Plugin definition search for '%s' not yet implemented", pluginName)
 	return locations
 }
 
+// pluginReferenceAtCursor returns the plugin reference under the cursor, using
+// the open document's references when it has the same content
+func (s *Server) pluginReferenceAtCursor(ctx *bkcontext.PositionContext) (PluginReference, bool) {
+	doc, ok := s.documentManager.GetDocument(ctx.URI)
+	if !ok || doc.Content != ctx.FullContent {
+		doc = newDocument(ctx.URI, 0, ctx.FullContent)
+	}
+	return pluginReferenceAt(doc.PluginReferences(), ctx.Position)
+}
+
 func isAlphaNumeric(c byte) bool {
 	return (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || (c >= '0' && c <= '9')
 }
@@ -1029,6 +1285,11 @@ func (s *Server) getRefactorActions(params *protocol.CodeActionParams, doc *Docu
 		actions = append(actions, s.createExtractStepAction(params.TextDocument.URI, stepInfo))
 	}
 
+	// Refactor: Hoist the keys this step shares with others into a YAML anchor
+	if action := s.createExtractToAnchorAction(params.TextDocument.URI, lines, stepInfo.StartLine); action != nil {
+		actions = append(actions, *action)
+	}
+
 	return actions
 }
 
@@ -1361,6 +1622,192 @@ func (s *Server) createExtractStepAction(uri protocol.DocumentURI, stepInfo *Ste
 	}
 }
 
+// anchorStepBlock is one property of a step, with the step's list marker replaced by
+// spaces so every block is indented to the step's content column
+type anchorStepBlock struct {
+	Key   string
+	Lines []string
+}
+
+// anchorStepBlocks splits the step spanning start to last into its properties. It
+// returns nil if the step's layout is too irregular to rewrite safely, such as
+// tab indentation or a step that isn't a block mapping.
+func anchorStepBlocks(lines []string, start, last int) ([]anchorStepBlock, int) {
+	first := lines[start]
+	indent := len(first) - len(strings.TrimLeft(first, " "))
+	content := first[indent:]
+	if !strings.HasPrefix(content, "- ") || strings.HasPrefix(content, "-  ") {
+		return nil, 0
+	}
+	column := indent + 2
+
+	var blocks []anchorStepBlock
+	for i := start; i <= last; i++ {
+		line := lines[i]
+		if i == start {
+			line = strings.Repeat(" ", column) + content[2:]
+		}
+
+		trimmed := strings.TrimSpace(line)
+		if trimmed == "" {
+			if len(blocks) > 0 {
+				blocks[len(blocks)-1].Lines = append(blocks[len(blocks)-1].Lines, "")
+			}
+			continue
+		}
+
+		lineIndent := len(line) - len(strings.TrimLeft(line, " "))
+		if lineIndent < column || line[lineIndent] == '\t' {
+			return nil, 0
+		}
+
+		// A new key starts a block; anything else belongs to the one before it
+		isKey := lineIndent == column && !strings.HasPrefix(trimmed, "#") && !strings.HasPrefix(trimmed, "-")
+		if isKey {
+			key, _, found := strings.Cut(trimmed, ":")
+			if !found {
+				return nil, 0
+			}
+			blocks = append(blocks, anchorStepBlock{Key: strings.Trim(key, `"'`), Lines: []string{line}})
+			continue
+		}
+		if len(blocks) == 0 {
+			return nil, 0
+		}
+		blocks[len(blocks)-1].Lines = append(blocks[len(blocks)-1].Lines, line)
+	}
+
+	return blocks, column
+}
+
+// createExtractToAnchorAction offers to move the keys a step shares with other
+// steps into an anchored top-level block that each of them merges. To stay
+// conservative it only applies to steps that are identical apart from label.
+func (s *Server) createExtractToAnchorAction(uri protocol.DocumentURI, lines []string, stepStart int) *protocol.CodeAction {
+	stepsLine := -1
+	for i, line := range lines {
+		if strings.TrimRight(line, " \t") == "steps:" {
+			stepsLine = i
+			break
+		}
+	}
+	if stepsLine == -1 {
+		return nil
+	}
+
+	type extractableStep struct {
+		start, last int
+		column      int
+		label       []string
+		shared      []string
+	}
+
+	var steps []extractableStep
+	currentIndex := -1
+	stepLines := s.findStepLines(lines)
+	for i := range stepLines {
+		start, end := s.stepLineRange(stepLines, i, lines)
+		last := end
+		for last > start && strings.TrimSpace(lines[last]) == "" {
+			last--
+		}
+
+		blocks, column := anchorStepBlocks(lines, start, last)
+		if blocks == nil {
+			continue
+		}
+
+		step := extractableStep{start: start, last: last, column: column}
+		for _, block := range blocks {
+			if block.Key == "label" {
+				step.label = block.Lines
+			} else {
+				step.shared = append(step.shared, block.Lines...)
+			}
+		}
+		// Blank lines at the end of the shared keys aren't worth comparing
+		for len(step.shared) > 0 && step.shared[len(step.shared)-1] == "" {
+			step.shared = step.shared[:len(step.shared)-1]
+		}
+		if len(step.shared) == 0 {
+			continue
+		}
+
+		if start == stepStart {
+			currentIndex = len(steps)
+		}
+		steps = append(steps, step)
+	}
+	if currentIndex == -1 {
+		return nil
+	}
+	current := steps[currentIndex]
+
+	var matches []extractableStep
+	for _, step := range steps {
+		if step.column == current.column && slices.Equal(step.shared, current.shared) {
+			matches = append(matches, step)
+		}
+	}
+	if len(matches) < 2 {
+		return nil
+	}
+
+	// Pick an anchor name that isn't already in use
+	name := "shared"
+	existing := make(map[string]bool)
+	for _, a := range findAnchors(lines) {
+		existing[a.Name] = true
+	}
+	for n := 2; existing[name]; n++ {
+		name = fmt.Sprintf("shared_%d", n)
+	}
+
+	hoisted := []string{fmt.Sprintf("x-%s: &%s", name, name)}
+	for _, line := range current.shared {
+		if line == "" {
+			hoisted = append(hoisted, "")
+			continue
+		}
+		hoisted = append(hoisted, line[current.column-2:])
+	}
+
+	edits := []protocol.TextEdit{{
+		Range: protocol.Range{
+			Start: protocol.Position{Line: uint32(stepsLine), Character: 0},
+			End:   protocol.Position{Line: uint32(stepsLine), Character: 0},
+		},
+		NewText: strings.Join(hoisted, "\n") + "\n\n",
+	}}
+	for _, step := range matches {
+		rewritten := append([]string{strings.Repeat(" ", step.column-2) + "- <<: *" + name}, step.label...)
+		for len(rewritten) > 1 && rewritten[len(rewritten)-1] == "" {
+			rewritten = rewritten[:len(rewritten)-1]
+		}
+
+		rng := protocol.Range{
+			Start: protocol.Position{Line: uint32(step.start), Character: 0},
+			End:   protocol.Position{Line: uint32(step.last + 1), Character: 0},
+		}
+		newText := strings.Join(rewritten, "\n") + "\n"
+		if step.last+1 >= len(lines) {
+			rng.End = protocol.Position{Line: uint32(step.last), Character: uint32(len(lines[step.last]))}
+			newText = strings.TrimSuffix(newText, "\n")
+		}
+		edits = append(edits, protocol.TextEdit{Range: rng, NewText: newText})
+	}
+
+	return &protocol.CodeAction{
+		Title: "Extract to YAML anchor",
+		Kind:  protocol.RefactorExtract,
+		Edit: &protocol.WorkspaceEdit{
+			Changes: map[protocol.DocumentURI][]protocol.TextEdit{
+				uri: edits,
+			},
+		},
+	}
+}
+
 func (s *Server) DocumentSymbol(ctx context.Context, params *protocol.DocumentSymbolParams) ([]protocol.DocumentSymbol, error) {
 	if !s.isBuildkiteFile(string(params.TextDocument.URI)) {
 		return nil, nil
@@ -1373,7 +1820,7 @@ func (s *Server) DocumentSymbol(ctx context.Context, params *protocol.DocumentSy
 	}
 
 	// Parse YAML to extract symbols
-	symbols, err := s.extractDocumentSymbols(doc.Content, doc.Lines)
+	symbols, err := s.extractDocumentSymbols(doc)
 	if err != nil {
 		s.logger.Printf("Failed to extract document symbols: %v", err)
 		return nil, nil // Return nil instead of error to avoid disrupting the user
@@ -1382,12 +1829,32 @@ func (s *Server) DocumentSymbol(ctx context.Context, params *protocol.DocumentSy
 	return symbols, nil
 }
 
-func (s *Server) extractDocumentSymbols(content string, lines []string) ([]protocol.DocumentSymbol, error) {
-	// Parse YAML first to validate it
-	_, err := parser.ParseYAML([]byte(content))
-	if err != nil {
-		return nil, fmt.Errorf("failed to parse YAML: %w", err)
+// flattenDocumentSymbols converts nested symbols to the flat SymbolInformation
+// form for clients without hierarchical document symbol support, naming each
+// symbol's parent as its container
+func flattenDocumentSymbols(symbols []protocol.DocumentSymbol, uri protocol.DocumentURI, container string) []protocol.SymbolInformation {
+	var flat []protocol.SymbolInformation
+	for _, symbol := range symbols {
+		flat = append(flat, protocol.SymbolInformation{
+			Name: symbol.Name,
+			Kind: symbol.Kind,
+			Location: protocol.Location{
+				URI:   uri,
+				Range: symbol.Range,
+			},
+			ContainerName: container,
+		})
+		flat = append(flat, flattenDocumentSymbols(symbol.Children, uri, symbol.Name)...)
 	}
+	return flat
+}
+
+func (s *Server) extractDocumentSymbols(doc *Document) ([]protocol.DocumentSymbol, error) {
+	// Only documents that parse get an outline
+	if _, err := doc.Pipeline(); err != nil {
+		return nil, err
+	}
+	lines := doc.Lines
 
 	var symbols []protocol.DocumentSymbol
 
@@ -1646,34 +2113,76 @@ func extractQuotedValue(line string) string {
 	return ""
 }
 
-func (s *Server) validateDocument(ctx context.Context, uri protocol.DocumentURI, content string) {
+func (s *Server) validateDocument(ctx context.Context, doc *Document) {
+	uri := doc.URI
 	if !s.isBuildkiteFile(string(uri)) {
 		return
 	}
 
-	pipeline, err := parser.ParseYAML([]byte(content))
+	if !utf8.ValidString(doc.Content) {
+		s.sendDiagnostics(ctx, uri, []protocol.Diagnostic{
+			{
+				Range:    documentRange(doc.Lines),
+				Severity: protocol.DiagnosticSeverityError,
+				Message:  "File is not valid UTF-8 - save it with UTF-8 encoding so it can be parsed",
+				Source:   "buildkite-ls",
+				Code:     "invalid-encoding",
+			},
+		})
+		return
+	}
+
+	pipeline, err := doc.Pipeline()
 	if err != nil {
+		// A repeated key fails the whole parse, so point at each duplicate instead
+		if duplicates := s.validateDuplicateMappingKeys(doc.Lines); len(duplicates) > 0 && !parser.IsJSON([]byte(doc.Content)) {
+			setRelatedInformationURI(duplicates, uri)
+			s.sendDiagnostics(ctx, uri, duplicates)
+			return
+		}
+
+		format := "YAML"
+		if parser.IsJSON([]byte(doc.Content)) {
+			format = "JSON"
+		}
+		rng := documentRange(doc.Lines)
+		if line, ok := parser.ErrorLine(err); ok {
+			rng = lineRange(doc.Lines, line, 0)
+		}
 		s.sendDiagnostics(ctx, uri, []protocol.Diagnostic{
 			{
-				Range: protocol.Range{
-					Start: protocol.Position{Line: 0, Character: 0},
-					End:   protocol.Position{Line: 0, Character: 0},
-				},
+				Range:    rng,
 				Severity: protocol.DiagnosticSeverityError,
-				Message:  "YAML parse error: " + err.Error(),
+				Message:  format + " parse error: " + err.Error(),
 			},
 		})
 		return
 	}
 
-	validationErr, err := s.schemaLoader.ValidateJSON(pipeline.JSONBytes)
+	// A workflow for another CI system would only produce a flood of schema
+	// errors, so report it once instead
+	if pipelineData := pipeline.Data(); pipelineData != nil {
+		if diagnostic := s.foreignPipelineDiagnostic(pipelineData, doc.Lines); diagnostic != nil {
+			s.sendDiagnostics(ctx, uri, []protocol.Diagnostic{*diagnostic})
+			return
+		}
+	}
+
+	if !s.schemaLoader.IsLoaded() {
+		// Don't block on the download; structural checks don't need the schema
+		s.logger.Printf("Schema not loaded yet, running structural checks only for %s", uri)
+		diagnostics := s.validatePlugins(pipeline)
+		setRelatedInformationURI(diagnostics, uri)
+		s.sendDiagnostics(ctx, uri, diagnostics)
+		s.loadSchemaInBackground()
+		return
+	}
+
+	validationErr, err := s.schemaLoader.ValidateJSON(pipeline.NormalizedJSON())
 	if err != nil {
 		s.sendDiagnostics(ctx, uri, []protocol.Diagnostic{
 			{
-				Range: protocol.Range{
-					Start: protocol.Position{Line: 0, Character: 0},
-					End:   protocol.Position{Line: 0, Character: 0},
-				},
+				Range:    documentRange(doc.Lines),
 				Severity: protocol.DiagnosticSeverityError,
 				Message:  "Schema loading error: " + err.Error(),
 			},
@@ -1682,13 +2191,17 @@ func (s *Server) validateDocument(ctx context.Context, uri protocol.DocumentURI,
 	}
 
 	if validationErr != nil {
-		line := pipeline.GetLineForError(validationErr.Message)
+		// Point at the node the error is about, such as the step missing a
+		// required key, and at the whole document if it can't be found
+		rng := documentRange(doc.Lines)
+		if position, ok := pipeline.PathPosition(validationErr.Path); ok {
+			rng = lineRange(doc.Lines, position.Line, position.Character)
+		} else if line := pipeline.GetLineForError(validationErr.Message); line > 1 {
+			rng = lineRange(doc.Lines, line-1, 0)
+		}
 		s.sendDiagnostics(ctx, uri, []protocol.Diagnostic{
 			{
-				Range: protocol.Range{
-					Start: protocol.Position{Line: uint32(line - 1), Character: 0},
-					End:   protocol.Position{Line: uint32(line - 1), Character: 999},
-				},
+				Range:    rng,
 				Severity: protocol.DiagnosticSeverityError,
 				Message:  "Schema validation error: " + validationErr.Message,
 			},
@@ -1698,25 +2211,166 @@ func (s *Server) validateDocument(ctx context.Context, uri protocol.DocumentURI,
 
 	// All basic schema validation passed, now validate plugins
 	diagnostics := s.validatePlugins(pipeline)
+	setRelatedInformationURI(diagnostics, uri)
 
 	// If no validation errors, send empty diagnostics to clear any existing ones
 	s.sendDiagnostics(ctx, uri, diagnostics)
 }
 
+// lineRange covers line from column, or from its indentation if that's
+// further in, to the end of its content. Lines past the end of the document
+// are clamped to the last one.
+func lineRange(lines []string, line, column int) protocol.Range {
+	if len(lines) == 0 {
+		return protocol.Range{}
+	}
+	line = min(max(line, 0), len(lines)-1)
+
+	content := strings.TrimRight(lines[line], " \t")
+	start := max(column, len(content)-len(strings.TrimLeft(content, " \t")))
+	end := max(len(content), start)
+
+	return protocol.Range{
+		Start: protocol.Position{Line: uint32(line), Character: uint32(start)},
+		End:   protocol.Position{Line: uint32(line), Character: uint32(end)},
+	}
+}
+
+// documentRange covers the whole document, for problems that can't be placed
+// more precisely. A zero-width range at the start is dropped by many editors.
+func documentRange(lines []string) protocol.Range {
+	if len(lines) == 0 {
+		return protocol.Range{}
+	}
+	last := len(lines) - 1
+	return protocol.Range{
+		Start: protocol.Position{Line: 0, Character: 0},
+		End:   protocol.Position{Line: uint32(last), Character: uint32(len(lines[last]))},
+	}
+}
+
+// loadSchemaInBackground fetches the schema and re-validates open documents once it is available
+func (s *Server) loadSchemaInBackground() {
+	s.schemaLoadMu.Lock()
+	if s.schemaLoading || !s.startTask() {
+		s.schemaLoadMu.Unlock()
+		return
+	}
+	s.schemaLoading = true
+	s.schemaLoadMu.Unlock()
+
+	go func() {
+		defer s.tasks.Done()
+
+		_, err := s.schemaLoader.GetSchemaData()
+
+		s.schemaLoadMu.Lock()
+		s.schemaLoading = false
+		s.schemaLoadMu.Unlock()
+
+		if err != nil {
+			s.logger.Printf("Failed to load schema, will retry on a later validation: %v", err)
+			return
+		}
+
+		if s.lifetime.Err() != nil {
+			return
+		}
+
+		// Hovers cached before now are missing the schema's types and defaults
+		s.hoverCache.clear()
+
+		// Scheduling reads each document when it runs and is superseded by any
+		// later edit, so a change made during the download is never overwritten
+		s.logger.Printf("Schema loaded, re-validating open documents")
+		for _, doc := range s.documentManager.GetAllDocuments() {
+			s.scheduleValidation(doc.URI)
+		}
+	}()
+}
+
+// loadCatalogInBackground loads the plugin catalog without holding up
+// initialization, and re-validates open documents once it is in use
+func (s *Server) loadCatalogInBackground(location string) {
+	if !s.startTask() {
+		return
+	}
+
+	go func() {
+		defer s.tasks.Done()
+
+		if err := s.pluginRegistry.LoadCatalog(location); err != nil {
+			s.logger.Printf("Failed to load plugin registry: %v", err)
+			s.showMessage(s.lifetime, protocol.MessageTypeWarning,
+				fmt.Sprintf("buildkite-ls: %v - using the bundled plugin list", err))
+			return
+		}
+		s.hoverCache.clear()
+
+		if s.lifetime.Err() != nil {
+			return
+		}
+
+		s.logger.Printf("Loaded plugin registry from %s, re-validating open documents", location)
+		for _, doc := range s.documentManager.GetAllDocuments() {
+			s.scheduleValidation(doc.URI)
+		}
+	}()
+}
+
 func (s *Server) validatePlugins(pipeline *parser.Pipeline) []protocol.Diagnostic {
 	var diagnostics []protocol.Diagnostic
 
-	// Parse the pipeline JSON to extract steps with plugins
-	var pipelineData map[string]interface{}
-	if err := json.Unmarshal(pipeline.JSONBytes, &pipelineData); err != nil {
+	pipelineData := pipeline.Data()
+	if pipelineData == nil {
 		return diagnostics
 	}
 
 	// Enhanced validation with multiple checks
-	lines := strings.Split(string(pipeline.Content), "\n")
+	lines := splitLines(string(pipeline.Content))
+	if len(lines) == 0 {
+		// An empty document still has a single empty line to report against
+		lines = []string{""}
+	}
+	references := pluginReferences(pipeline)
 	diagnostics = append(diagnostics, s.validatePipelineStructure(pipelineData, lines)...)
 	diagnostics = append(diagnostics, s.validateSteps(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateMetaDataKeys(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateSkippedSteps(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateCommandAndCommands(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateAgentQueues(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateAgentTags(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateCacheSettings(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateDuplicateStepKeys(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateTimeouts(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateGroupLabels(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateDeprecatedKeys(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateStepTypeKeys(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateGroupKeys(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateNestedGroups(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateTriggerAsync(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateRetryManual(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateSoftFailRetry(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validatePluginEntries(pipeline.PluginEntries(), lines)...)
+	diagnostics = append(diagnostics, s.validateAllowedPlugins(references)...)
+	diagnostics = append(diagnostics, s.validateUnpinnedPlugins(references)...)
+	diagnostics = append(diagnostics, s.validateStepCount(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateLabelLength(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateNotifySlack(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateNotifyWebhooks(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateReservedEnv(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateEnvShadowing(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateEnvValueLength(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateAllowDependencyFailure(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateIfAndBranches(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateConditionalMembers(lines)...)
+	diagnostics = append(diagnostics, s.validateDuplicateFieldKeys(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateIndentation(lines)...)
+	diagnostics = append(diagnostics, s.validateInvisibleCharacters(lines)...)
+	diagnostics = append(diagnostics, s.validateTopLevelKeyOrder(lines)...)
 	diagnostics = append(diagnostics, s.validatePluginConfigurations(pipelineData, lines)...)
+	diagnostics = append(diagnostics, s.validateUnknownPluginOptions(references)...)
+	diagnostics = append(diagnostics, s.validatePluginOptionTypes(references, lines)...)
 
 	return diagnostics
 }
@@ -1742,7 +2396,36 @@ func (s *Server) validatePipelineStructure(pipelineData map[string]interface{},
 
 	// Validate common properties
 	if env, hasEnv := pipelineData["env"]; hasEnv {
-		if _, ok := env.(map[string]interface{}); !ok {
+		if entries, isList := env.([]interface{}); isList {
+			// Agent-style KEY=value lists aren't accepted at the pipeline level
+			message := "Pipeline 'env' must be a map of KEY: value pairs, not a list"
+			if len(entries) > 0 {
+				if entry, ok := entries[0].(string); ok {
+					if name, value, found := strings.Cut(entry, "="); found {
+						message += fmt.Sprintf(" - write '- %s' as '%s: %s'", entry, name, value)
+					}
+				}
+			}
+
+			rng := protocol.Range{}
+			for i, line := range lines {
+				if strings.HasPrefix(line, "env:") {
+					rng = protocol.Range{
+						Start: protocol.Position{Line: uint32(i), Character: 0},
+						End:   protocol.Position{Line: uint32(i), Character: uint32(len("env"))},
+					}
+					break
+				}
+			}
+
+			diagnostics = append(diagnostics, protocol.Diagnostic{
+				Range:    rng,
+				Severity: protocol.DiagnosticSeverityError,
+				Message:  message,
+				Source:   "buildkite-ls",
+				Code:     "invalid-env",
+			})
+		} else if _, ok := env.(map[string]interface{}); !ok {
 			lineNum := s.findLineForProperty("env", lines)
 			diagnostics = append(diagnostics, protocol.Diagnostic{
 				Range: protocol.Range{
@@ -1776,20 +2459,32 @@ func (s *Server) validateSteps(pipelineData map[string]interface{}, lines []stri
 			continue
 		}
 
-		// Get the actual line number for this step
+		// Get the actual line number and extent of this step
 		lineNum := uint32(stepIndex)
+		stepRange := protocol.Range{
+			Start: protocol.Position{Line: lineNum, Character: 2},
+			End:   protocol.Position{Line: lineNum, Character: 999},
+		}
 		if stepIndex < len(stepLines) {
-			lineNum = uint32(stepLines[stepIndex])
+			start, end := s.stepLineRange(stepLines, stepIndex, lines)
+			for end > start && strings.TrimSpace(lines[end]) == "" {
+				end--
+			}
+			lineNum = uint32(start)
+			stepRange = protocol.Range{
+				Start: protocol.Position{Line: uint32(start), Character: uint32(s.getIndentLevel(lines[start]))},
+				End:   protocol.Position{Line: uint32(end), Character: uint32(len(lines[end]))},
+			}
 		}
 
 		// Validate step structure
-		diagnostics = append(diagnostics, s.validateSingleStep(stepData, lineNum, stepIndex+1)...)
+		diagnostics = append(diagnostics, s.validateSingleStep(stepData, lineNum, stepRange, describeStep(stepData, stepIndex))...)
 	}
 
 	return diagnostics
 }
 
-func (s *Server) validateSingleStep(stepData map[string]interface{}, lineNum uint32, stepNumber int) []protocol.Diagnostic {
+func (s *Server) validateSingleStep(stepData map[string]interface{}, lineNum uint32, stepRange protocol.Range, stepName string) []protocol.Diagnostic {
 	var diagnostics []protocol.Diagnostic
 
 	// Check for step type - must have one of: command, wait, block, input, trigger, group
@@ -1798,7 +2493,7 @@ func (s *Server) validateSingleStep(stepData map[string]interface{}, lineNum uin
 	hasBlock := stepData["block"] != nil
 	hasInput := stepData["input"] != nil
 	hasTrigger := stepData["trigger"] != nil
-	hasGroup := stepData["group"] != nil
+	_, hasGroup := stepData["group"] // group key exists (null is an unlabeled group)
 
 	stepTypeCount := 0
 	if hasCommand {
@@ -1832,7 +2527,7 @@ func (s *Server) validateSingleStep(stepData map[string]interface{}, lineNum uin
 					End:   protocol.Position{Line: lineNum, Character: 999},
 				},
 				Severity: protocol.DiagnosticSeverityInformation,
-				Message:  fmt.Sprintf("Step %d has no explicit step type, but plugins may provide command execution via hooks", stepNumber),
+				Message:  fmt.Sprintf("%s has no explicit step type, but plugins may provide command execution via hooks", stepName),
 				Source:   "buildkite-ls",
 				Code:     "no-step-type-with-plugins",
 			})
@@ -1844,7 +2539,7 @@ func (s *Server) validateSingleStep(stepData map[string]interface{}, lineNum uin
 					End:   protocol.Position{Line: lineNum, Character: 999},
 				},
 				Severity: protocol.DiagnosticSeverityError,
-				Message:  fmt.Sprintf("Step %d must specify a step type: command, wait, block, input, trigger, or group", stepNumber),
+				Message:  fmt.Sprintf("%s must specify a step type: command, wait, block, input, trigger, or group", stepName),
 				Source:   "buildkite-ls",
 				Code:     "missing-step-type",
 			})
@@ -1856,7 +2551,7 @@ func (s *Server) validateSingleStep(stepData map[string]interface{}, lineNum uin
 				End:   protocol.Position{Line: lineNum, Character: 999},
 			},
 			Severity: protocol.DiagnosticSeverityError,
-			Message:  fmt.Sprintf("Step %d has multiple step types - only one is allowed per step", stepNumber),
+			Message:  fmt.Sprintf("%s has multiple step types - only one is allowed per step", stepName),
 			Source:   "buildkite-ls",
 			Code:     "multiple-step-types",
 		})
@@ -1909,15 +2604,13 @@ func (s *Server) validateSingleStep(stepData map[string]interface{}, lineNum uin
 			})
 		}
 
-		// Warn about missing labels (only if no name field exists)
-		if stepData["label"] == nil && stepData["name"] == nil {
+		// Suggest a label or key when the step has neither, since it's then shown
+		// by its command alone and can't be referenced from depends_on
+		if stepData["label"] == nil && stepData["name"] == nil && stepKey(stepData) == "" {
 			diagnostics = append(diagnostics, protocol.Diagnostic{
-				Range: protocol.Range{
-					Start: protocol.Position{Line: lineNum, Character: 2},
-					End:   protocol.Position{Line: lineNum, Character: 999},
-				},
+				Range:    stepRange,
 				Severity: protocol.DiagnosticSeverityInformation,
-				Message:  "Consider adding a 'label' to make this step easier to identify in the UI",
+				Message:  "Consider adding a 'label' or 'key' to make this step easier to identify in the UI and to reference from depends_on",
 				Source:   "buildkite-ls",
 				Code:     "missing-label",
 			})
@@ -2062,8 +2755,9 @@ func (s *Server) findStepLines(lines []string) []int {
 	for i, line := range lines {
 		trimmed := strings.TrimSpace(line)
 
-		// Look for the steps: section
-		if trimmed == "steps:" {
+		// Look for the top-level steps: section. A steps: key nested in an
+		// anchored template, such as a shared group, isn't part of the pipeline.
+		if trimmed == "steps:" && !strings.HasPrefix(line, " ") && !strings.HasPrefix(line, "\t") {
 			inSteps = true
 			continue
 		}
@@ -2093,15 +2787,33 @@ func (s *Server) isBuildkiteFile(uri string) bool {
 		filePath = strings.TrimPrefix(uri, "file://")
 	}
 
-	// Check if file is in .buildkite directory and is YAML
+	// Check if file is in .buildkite directory and is YAML or JSON
 	if strings.Contains(filePath, ".buildkite/") {
-		return strings.HasSuffix(filePath, ".yml") || strings.HasSuffix(filePath, ".yaml")
+		return strings.HasSuffix(filePath, ".yml") || strings.HasSuffix(filePath, ".yaml") ||
+			strings.HasSuffix(filePath, ".json")
 	}
 
 	// Check for standalone pipeline files (common pattern)
 	fileName := filepath.Base(filePath)
-	return fileName == "pipeline.yml" || fileName == "pipeline.yaml" ||
-		fileName == "buildkite.yml" || fileName == "buildkite.yaml"
+	return fileName == "pipeline.yml" || fileName == "pipeline.yaml" || fileName == "pipeline.json" ||
+		fileName == "buildkite.yml" || fileName == "buildkite.yaml" || fileName == "buildkite.json"
+}
+
+// showMessage asks the client to display a message to the user
+func (s *Server) showMessage(ctx context.Context, messageType protocol.MessageType, message string) {
+	s.logger.Printf("%s", message)
+
+	if s.conn == nil {
+		return
+	}
+
+	params := protocol.ShowMessageParams{
+		Type:    messageType,
+		Message: message,
+	}
+	if err := s.conn.Notify(ctx, "window/showMessage", params); err != nil {
+		s.logger.Printf("Failed to show message: %v", err)
+	}
 }
 
 func (s *Server) sendDiagnostics(ctx context.Context, uri protocol.DocumentURI, diagnostics []protocol.Diagnostic) {
@@ -2112,6 +2824,17 @@ func (s *Server) sendDiagnostics(ctx context.Context, uri protocol.DocumentURI,
 		return
 	}
 
+	// Publishing is serialized with cancellation so a superseded validation
+	// can never publish after a newer one has been scheduled
+	s.validationMu.Lock()
+	defer s.validationMu.Unlock()
+	if ctx.Err() != nil {
+		s.logger.Printf("Skipping stale diagnostics for %s", uri)
+		return
+	}
+
+	diagnostics = applySeverityOverrides(diagnostics, s.settings.SeverityOverrides)
+
 	// Ensure diagnostics is never nil
 	if diagnostics == nil {
 		diagnostics = []protocol.Diagnostic{}
@@ -2128,6 +2851,43 @@ func (s *Server) sendDiagnostics(ctx context.Context, uri protocol.DocumentURI,
 	if err != nil {
 		s.logger.Printf("Failed to send diagnostics: %v", err)
 	}
+
+	// Clients that don't handle the summary ignore it
+	if err := s.conn.Notify(ctx, diagnosticSummaryMethod, summarizeDiagnostics(uri, diagnostics)); err != nil {
+		s.logger.Printf("Failed to send diagnostic summary: %v", err)
+	}
+}
+
+// diagnosticSummaryMethod is the custom notification sent after diagnostics
+// are published, so dashboards can show per-file counts
+const diagnosticSummaryMethod = "$/buildkite/diagnosticSummary"
+
+// diagnosticSummary counts the diagnostics published for a document by severity
+type diagnosticSummary struct {
+	URI         protocol.DocumentURI `json:"uri"`
+	Errors      int                  `json:"errors"`
+	Warnings    int                  `json:"warnings"`
+	Information int                  `json:"information"`
+	Hints       int                  `json:"hints"`
+}
+
+// summarizeDiagnostics counts diagnostics by severity. Diagnostics without a
+// severity are counted as errors, as most clients display them that way.
+func summarizeDiagnostics(uri protocol.DocumentURI, diagnostics []protocol.Diagnostic) diagnosticSummary {
+	summary := diagnosticSummary{URI: uri}
+	for _, diagnostic := range diagnostics {
+		switch diagnostic.Severity {
+		case protocol.DiagnosticSeverityWarning:
+			summary.Warnings++
+		case protocol.DiagnosticSeverityInformation:
+			summary.Information++
+		case protocol.DiagnosticSeverityHint:
+			summary.Hints++
+		default:
+			summary.Errors++
+		}
+	}
+	return summary
 }
 
 func (s *Server) SemanticTokensFull(ctx context.Context, params *protocol.SemanticTokensParams) (*protocol.SemanticTokens, error) {
@@ -2486,9 +3246,18 @@ func (s *Server) getTokenModifierBits(modifiers []string) int {
 	return bits
 }
 
+// SchemaInfo handles the custom buildkite/schemaInfo request, letting clients
+// show which pipeline schema the server is validating against
+func (s *Server) SchemaInfo(ctx context.Context) (*schema.Info, error) {
+	info := s.schemaLoader.Info()
+	return &info, nil
+}
+
 func (s *Server) Handler() jsonrpc2.Handler {
 	return func(ctx context.Context, reply jsonrpc2.Replier, req jsonrpc2.Request) error {
-		s.logger.Printf("Received method: %s", req.Method())
+		s.verbosef("Received method: %s", req.Method())
+		defer s.timeRequest(req.Method(), time.Now())
+		s.logTrace(ctx, fmt.Sprintf("Received '%s'", req.Method()), string(req.Params()))
 		switch req.Method() {
 		case "initialize":
 			var params protocol.InitializeParams
@@ -2496,12 +3265,12 @@ func (s *Server) Handler() jsonrpc2.Handler {
 				s.logger.Printf("Error unmarshaling params: %v", err)
 				return reply(ctx, nil, err)
 			}
-			s.logger.Printf("Calling Initialize")
+			s.verbosef("Calling Initialize")
 
 			// Client will be set up when needed for diagnostics
 
 			result, err := s.Initialize(ctx, &params)
-			s.logger.Printf("Initialize result: %+v, err: %v", result, err)
+			s.verbosef("Initialize result: %+v, err: %v", result, err)
 			return reply(ctx, result, err)
 
 		case "initialized":
@@ -2520,6 +3289,34 @@ func (s *Server) Handler() jsonrpc2.Handler {
 			_ = s.Exit(ctx)
 			return nil
 
+		case "$/setTrace":
+			var params SetTraceParams
+			if err := json.Unmarshal(req.Params(), &params); err != nil {
+				return reply(ctx, nil, err)
+			}
+			err := s.SetTrace(ctx, &params)
+			return reply(ctx, nil, err)
+
+		case "buildkite/schemaInfo":
+			result, err := s.SchemaInfo(ctx)
+			return reply(ctx, result, err)
+
+		case "workspace/didChangeWorkspaceFolders":
+			var params protocol.DidChangeWorkspaceFoldersParams
+			if err := json.Unmarshal(req.Params(), &params); err != nil {
+				return reply(ctx, nil, err)
+			}
+			err := s.DidChangeWorkspaceFolders(ctx, &params)
+			return reply(ctx, nil, err)
+
+		case "workspace/willRenameFiles":
+			var params protocol.RenameFilesParams
+			if err := json.Unmarshal(req.Params(), &params); err != nil {
+				return reply(ctx, nil, err)
+			}
+			result, err := s.WillRenameFiles(ctx, &params)
+			return reply(ctx, result, err)
+
 		case "textDocument/didOpen":
 			var params protocol.DidOpenTextDocumentParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
@@ -2544,6 +3341,14 @@ func (s *Server) Handler() jsonrpc2.Handler {
 			err := s.DidClose(ctx, &params)
 			return reply(ctx, nil, err)
 
+		case "textDocument/didSave":
+			var params protocol.DidSaveTextDocumentParams
+			if err := json.Unmarshal(req.Params(), &params); err != nil {
+				return reply(ctx, nil, err)
+			}
+			err := s.DidSave(ctx, &params)
+			return reply(ctx, nil, err)
+
 		case "textDocument/hover":
 			var params protocol.HoverParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
@@ -2553,106 +3358,136 @@ func (s *Server) Handler() jsonrpc2.Handler {
 			return reply(ctx, result, err)
 
 		case "textDocument/completion":
-			s.logger.Printf("Received textDocument/completion request")
+			s.verbosef("Received textDocument/completion request")
 			var params protocol.CompletionParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
 				s.logger.Printf("Error unmarshaling completion params: %v", err)
 				return reply(ctx, nil, err)
 			}
-			s.logger.Printf("Processing completion for URI: %s, Position: %d:%d",
+			s.verbosef("Processing completion for URI: %s, Position: %d:%d",
 				params.TextDocument.URI, params.Position.Line, params.Position.Character)
 			result, err := s.Completion(ctx, &params)
-			s.logger.Printf("Completion result: %d items, error: %v",
+			s.verbosef("Completion result: %d items, error: %v",
 				len(result.Items), err)
 			return reply(ctx, result, err)
 
 		case "textDocument/documentSymbol":
-			s.logger.Printf("Received textDocument/documentSymbol request")
+			s.verbosef("Received textDocument/documentSymbol request")
 			var params protocol.DocumentSymbolParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
 				s.logger.Printf("Error unmarshaling document symbol params: %v", err)
 				return reply(ctx, nil, err)
 			}
-			s.logger.Printf("Processing document symbols for URI: %s", params.TextDocument.URI)
+			s.verbosef("Processing document symbols for URI: %s", params.TextDocument.URI)
 			result, err := s.DocumentSymbol(ctx, &params)
-			s.logger.Printf("DocumentSymbol result: %d symbols, error: %v",
+			s.verbosef("DocumentSymbol result: %d symbols, error: %v",
 				len(result), err)
+			if !s.hierarchicalDocumentSymbolSupport {
+				return reply(ctx, flattenDocumentSymbols(result, params.TextDocument.URI, ""), err)
+			}
 			return reply(ctx, result, err)
 
 		case "textDocument/signatureHelp":
-			s.logger.Printf("Received textDocument/signatureHelp request")
+			s.verbosef("Received textDocument/signatureHelp request")
 			var params protocol.SignatureHelpParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
 				s.logger.Printf("Error unmarshaling signature help params: %v", err)
 				return reply(ctx, nil, err)
 			}
-			s.logger.Printf("Processing signature help for URI: %s", params.TextDocument.URI)
+			s.verbosef("Processing signature help for URI: %s", params.TextDocument.URI)
 			result, err := s.SignatureHelp(ctx, &params)
 			if result != nil {
-				s.logger.Printf("SignatureHelp result: %d signatures, error: %v",
+				s.verbosef("SignatureHelp result: %d signatures, error: %v",
 					len(result.Signatures), err)
 			} else {
-				s.logger.Printf("SignatureHelp result: nil, error: %v", err)
+				s.verbosef("SignatureHelp result: nil, error: %v", err)
 			}
 			return reply(ctx, result, err)
 
 		case "textDocument/definition":
-			s.logger.Printf("Received textDocument/definition request")
+			s.verbosef("Received textDocument/definition request")
 			var params protocol.DefinitionParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
 				s.logger.Printf("Error unmarshaling definition params: %v", err)
 				return reply(ctx, nil, err)
 			}
-			s.logger.Printf("Processing definition for URI: %s", params.TextDocument.URI)
+			s.verbosef("Processing definition for URI: %s", params.TextDocument.URI)
 			result, err := s.Definition(ctx, &params)
-			s.logger.Printf("Definition result: %d locations, error: %v",
+			s.verbosef("Definition result: %d locations, error: %v",
 				len(result), err)
 			return reply(ctx, result, err)
 
+		case "textDocument/prepareRename":
+			s.verbosef("Received textDocument/prepareRename request")
+			var params protocol.PrepareRenameParams
+			if err := json.Unmarshal(req.Params(), &params); err != nil {
+				s.logger.Printf("Error unmarshaling prepare rename params: %v", err)
+				return reply(ctx, nil, err)
+			}
+			result, err := s.PrepareRename(ctx, &params)
+			s.verbosef("PrepareRename result: %+v, error: %v", result, err)
+			return reply(ctx, result, err)
+
+		case "textDocument/rename":
+			s.verbosef("Received textDocument/rename request")
+			var params protocol.RenameParams
+			if err := json.Unmarshal(req.Params(), &params); err != nil {
+				s.logger.Printf("Error unmarshaling rename params: %v", err)
+				return reply(ctx, nil, err)
+			}
+			result, err := s.Rename(ctx, &params)
+			if result != nil {
+				s.verbosef("Rename result: %d edits, error: %v",
+					len(result.Changes[params.TextDocument.URI]), err)
+			} else {
+				s.verbosef("Rename result: nil, error: %v", err)
+			}
+			return reply(ctx, result, err)
+
 		case "textDocument/codeAction":
-			s.logger.Printf("Received textDocument/codeAction request")
+			s.verbosef("Received textDocument/codeAction request")
 			var params protocol.CodeActionParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
 				s.logger.Printf("Error unmarshaling code action params: %v", err)
 				return reply(ctx, nil, err)
 			}
-			s.logger.Printf("Processing code actions for URI: %s", params.TextDocument.URI)
+			s.verbosef("Processing code actions for URI: %s", params.TextDocument.URI)
 			result, err := s.CodeAction(ctx, &params)
-			s.logger.Printf("CodeAction result: %d actions, error: %v",
+			s.verbosef("CodeAction result: %d actions, error: %v",
 				len(result), err)
 			return reply(ctx, result, err)
 
 		case "textDocument/semanticTokens/full":
-			s.logger.Printf("Received textDocument/semanticTokens/full request")
+			s.verbosef("Received textDocument/semanticTokens/full request")
 			var params protocol.SemanticTokensParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
 				s.logger.Printf("Error unmarshaling semantic tokens params: %v", err)
 				return reply(ctx, nil, err)
 			}
-			s.logger.Printf("Processing semantic tokens for URI: %s", params.TextDocument.URI)
+			s.verbosef("Processing semantic tokens for URI: %s", params.TextDocument.URI)
 			result, err := s.SemanticTokensFull(ctx, &params)
 			if result != nil {
-				s.logger.Printf("SemanticTokensFull result: %d tokens, error: %v",
+				s.verbosef("SemanticTokensFull result: %d tokens, error: %v",
 					len(result.Data)/5, err)
 			} else {
-				s.logger.Printf("SemanticTokensFull result: nil, error: %v", err)
+				s.verbosef("SemanticTokensFull result: nil, error: %v", err)
 			}
 			return reply(ctx, result, err)
 
 		case "textDocument/semanticTokens/range":
-			s.logger.Printf("Received textDocument/semanticTokens/range request")
+			s.verbosef("Received textDocument/semanticTokens/range request")
 			var params protocol.SemanticTokensRangeParams
 			if err := json.Unmarshal(req.Params(), &params); err != nil {
 				s.logger.Printf("Error unmarshaling semantic tokens range params: %v", err)
 				return reply(ctx, nil, err)
 			}
-			s.logger.Printf("Processing semantic tokens range for URI: %s", params.TextDocument.URI)
+			s.verbosef("Processing semantic tokens range for URI: %s", params.TextDocument.URI)
 			result, err := s.SemanticTokensRange(ctx, &params)
 			if result != nil {
-				s.logger.Printf("SemanticTokensRange result: %d tokens, error: %v",
+				s.verbosef("SemanticTokensRange result: %d tokens, error: %v",
 					len(result.Data)/5, err)
 			} else {
-				s.logger.Printf("SemanticTokensRange result: nil, error: %v", err)
+				s.verbosef("SemanticTokensRange result: nil, error: %v", err)
 			}
 			return reply(ctx, result, err)
 
@@ -1,11 +1,20 @@
 package lsp
 
 import (
+	"bytes"
 	"context"
+	"fmt"
+	"log"
+	"os"
+	"path/filepath"
 	"strings"
 	"testing"
+	"time"
 
 	"go.lsp.dev/protocol"
+
+	bkcontext "github.com/mcncl/buildkite-ls/internal/context"
+	"github.com/mcncl/buildkite-ls/internal/parser"
 )
 
 func newTestServer() *Server {
@@ -36,6 +45,9 @@ func TestServer_Initialize(t *testing.T) {
 	if caps.TextDocumentSync == nil {
 		t.Error("TextDocumentSync capability missing")
 	}
+	if sync, ok := caps.TextDocumentSync.(*protocol.TextDocumentSyncOptions); !ok || sync.Save == nil || !sync.Save.IncludeText {
+		t.Errorf("Expected the saved text to be requested on didSave, got %+v", caps.TextDocumentSync)
+	}
 
 	if caps.CompletionProvider == nil {
 		t.Error("CompletionProvider capability missing")
@@ -167,6 +179,47 @@ func TestServer_DidChange(t *testing.T) {
 	}
 }
 
+func TestServer_DidSave(t *testing.T) {
+	server := newTestServer()
+	uri := protocol.DocumentURI("file:///project/.buildkite/pipeline.yml")
+
+	err := server.DidOpen(context.Background(), &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{URI: uri, LanguageID: "yaml", Version: 3, Text: "steps:\n  - label: \"stale\""},
+	})
+	if err != nil {
+		t.Fatalf("DidOpen failed: %v", err)
+	}
+
+	// Without text the stored document is kept
+	if err := server.DidSave(context.Background(), &protocol.DidSaveTextDocumentParams{
+		TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+	}); err != nil {
+		t.Fatalf("DidSave failed: %v", err)
+	}
+	if doc, _ := server.documentManager.GetDocument(uri); !strings.Contains(doc.Content, "stale") {
+		t.Errorf("Expected the stored document to be kept, got %q", doc.Content)
+	}
+
+	// The saved text replaces a document that has drifted out of sync
+	if err := server.DidSave(context.Background(), &protocol.DidSaveTextDocumentParams{
+		TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+		Text:         "steps:\n  - label: \"saved\"",
+	}); err != nil {
+		t.Fatalf("DidSave failed: %v", err)
+	}
+
+	doc, exists := server.documentManager.GetDocument(uri)
+	if !exists {
+		t.Fatal("Document not found after DidSave")
+	}
+	if !strings.Contains(doc.Content, "saved") || len(doc.Lines) != 2 {
+		t.Errorf("Expected the saved text to replace the document, got %q", doc.Content)
+	}
+	if doc.Version != 3 {
+		t.Errorf("Expected the document version to be kept, got %d", doc.Version)
+	}
+}
+
 func TestServer_DidClose(t *testing.T) {
 	server := newTestServer()
 
@@ -336,6 +389,42 @@ func TestServer_Hover_StepProperty(t *testing.T) {
 	}
 }
 
+func TestServer_HoverSchemaDefault(t *testing.T) {
+	server := newTestServer()
+
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	data := `{"definitions": {"commandStep": {"properties": {
+		"parallelism": {"type": "integer", "default": 1},
+		"concurrency_method": {"enum": ["ordered", "eager"], "default": "ordered"}
+	}}}}`
+	if err := os.WriteFile(schemaPath, []byte(data), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	server.schemaLoader.SetURL(schemaPath)
+	if _, err := server.schemaLoader.GetSchemaData(); err != nil {
+		t.Fatalf("Failed to load schema: %v", err)
+	}
+
+	contextInfo := &bkcontext.ContextInfo{}
+	tests := map[string]string{
+		"parallelism":        "**Default:** `1`",
+		"concurrency_method": "**Default:** `ordered`",
+	}
+	for property, expected := range tests {
+		if content := server.getPropertyHoverContent(property, contextInfo); !strings.Contains(content, expected) {
+			t.Errorf("Expected hover for %s to contain %q, got %q", property, expected, content)
+		}
+	}
+
+	if content := server.getPropertyHoverContent("label", contextInfo); strings.Contains(content, "**Default:**") {
+		t.Errorf("Expected no default for label, got %q", content)
+	}
+
+	if content := server.getPropertyHoverContent("parallelism", contextInfo); !strings.Contains(content, "**Type:** `integer`") {
+		t.Errorf("Expected hover for parallelism to show its type, got %q", content)
+	}
+}
+
 func TestServer_Hover_EnhancedFeatures(t *testing.T) {
 	server := newTestServer()
 	uri := "file:///test/.buildkite/pipeline.yml"
@@ -394,6 +483,15 @@ func TestServer_Hover_EnhancedFeatures(t *testing.T) {
 			contains:    "Plugin",
 			description: "hover on plugin reference",
 		},
+		{
+			name:        "step_type_fields",
+			content:     "steps:\n  - trigger: \"deploy\"",
+			line:        1,
+			character:   6, // On "trigger"
+			expectHover: true,
+			contains:    "- `async` - Don't wait for completion",
+			description: "hover on step type lists its fields",
+		},
 		{
 			name:        "unknown_property",
 			content:     "steps:\n  - label: \"test\"\n    unknown_prop: \"value\"",
@@ -403,6 +501,15 @@ func TestServer_Hover_EnhancedFeatures(t *testing.T) {
 			contains:    "step-level property",
 			description: "hover on unknown property",
 		},
+		{
+			name:        "escaped_dollar",
+			content:     "steps:\n  - command: \"echo $$HOME\"",
+			line:        1,
+			character:   21, // On "HOME" after "$$"
+			expectHover: true,
+			contains:    "The job receives `$HOME`",
+			description: "hover on an escaped dollar sign",
+		},
 		{
 			name:        "empty_space",
 			content:     "steps:\n  - label: \"test\"\n    ",
@@ -479,6 +586,46 @@ func TestServer_Hover_EnhancedFeatures(t *testing.T) {
 	}
 }
 
+func TestEscapedDollarAt(t *testing.T) {
+	tests := []struct {
+		line     string
+		index    int
+		escaped  bool
+		variable string
+	}{
+		{line: "echo $$HOME", index: 5, escaped: true, variable: "HOME"},
+		{line: "echo $$HOME", index: 8, escaped: true, variable: "HOME"},
+		{line: "echo $${HOME}", index: 6, escaped: true, variable: "{HOME}"},
+		{line: "echo $$ done", index: 6, escaped: true},
+		{line: "echo $$$HOME", index: 5, escaped: true},
+		{line: "echo $$$HOME", index: 7},
+		{line: "echo $HOME", index: 6},
+	}
+
+	for _, tt := range tests {
+		variable, escaped := escapedDollarAt(tt.line, tt.index)
+		if escaped != tt.escaped || variable != tt.variable {
+			t.Errorf("escapedDollarAt(%q, %d) = %q, %v, want %q, %v", tt.line, tt.index, variable, escaped, tt.variable, tt.escaped)
+		}
+	}
+}
+
+func TestPropertyDocumentation(t *testing.T) {
+	docs := PropertyDocumentation()
+
+	for _, property := range []string{"steps", "command", "depends_on", "wait"} {
+		if !strings.HasPrefix(docs[property], "**"+property+"**") {
+			t.Errorf("Expected documentation for '%s', got %q", property, docs[property])
+		}
+	}
+
+	// Callers get a copy, so changes don't leak into hover content
+	docs["steps"] = "changed"
+	if PropertyDocumentation()["steps"] == "changed" {
+		t.Error("Expected PropertyDocumentation to return a copy")
+	}
+}
+
 func TestServer_Hover_NonBuildkiteFile(t *testing.T) {
 	server := newTestServer()
 
@@ -538,6 +685,8 @@ func TestServer_isBuildkiteFile(t *testing.T) {
 		{"file:///project/buildkite.yaml", true},
 		{"file:///project/pipeline.yml", true}, // This should be true - standalone pipeline files are valid
 		{"file:///project/other.yml", false},
+		{"file:///project/.buildkite/pipeline.json", true},
+		{"file:///project/pipeline.json", true},
 		{"file:///project/test.json", false},
 	}
 
@@ -765,6 +914,65 @@ func TestServer_DocumentSymbol_SimpleSteps(t *testing.T) {
 	}
 }
 
+func TestServer_HierarchicalDocumentSymbolSupport(t *testing.T) {
+	server := newTestServer()
+
+	params := &protocol.InitializeParams{
+		Capabilities: protocol.ClientCapabilities{
+			TextDocument: &protocol.TextDocumentClientCapabilities{
+				DocumentSymbol: &protocol.DocumentSymbolClientCapabilities{
+					HierarchicalDocumentSymbolSupport: true,
+				},
+			},
+		},
+	}
+	if _, err := server.Initialize(context.Background(), params); err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	if !server.hierarchicalDocumentSymbolSupport {
+		t.Error("Expected hierarchical document symbol support to be recorded")
+	}
+
+	server = newTestServer()
+	if _, err := server.Initialize(context.Background(), &protocol.InitializeParams{}); err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	if server.hierarchicalDocumentSymbolSupport {
+		t.Error("Expected flat document symbols for a client without the capability")
+	}
+}
+
+func TestFlattenDocumentSymbols(t *testing.T) {
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	stepRange := protocol.Range{
+		Start: protocol.Position{Line: 1, Character: 0},
+		End:   protocol.Position{Line: 2, Character: 0},
+	}
+	symbols := []protocol.DocumentSymbol{
+		{
+			Name: "steps (1)",
+			Kind: protocol.SymbolKindArray,
+			Children: []protocol.DocumentSymbol{
+				{Name: "Build", Kind: protocol.SymbolKindObject, Range: stepRange},
+			},
+		},
+	}
+
+	flat := flattenDocumentSymbols(symbols, uri, "")
+	if len(flat) != 2 {
+		t.Fatalf("Expected 2 symbols, got %d", len(flat))
+	}
+	if flat[0].Name != "steps (1)" || flat[0].ContainerName != "" {
+		t.Errorf("Expected top-level steps symbol, got %q in %q", flat[0].Name, flat[0].ContainerName)
+	}
+	if flat[1].Name != "Build" || flat[1].ContainerName != "steps (1)" {
+		t.Errorf("Expected Build inside steps, got %q in %q", flat[1].Name, flat[1].ContainerName)
+	}
+	if flat[1].Location.URI != uri || flat[1].Location.Range != stepRange {
+		t.Errorf("Expected Build location %v, got %v", stepRange, flat[1].Location)
+	}
+}
+
 func TestServer_DocumentSymbol_SpecialSteps(t *testing.T) {
 	server := newTestServer()
 	uri := "file:///test/.buildkite/pipeline.yml"
@@ -958,3 +1166,286 @@ func TestServer_DocumentSymbol_InvalidYAML(t *testing.T) {
 		t.Error("Expected no symbols for invalid YAML")
 	}
 }
+
+func TestServer_SetTrace(t *testing.T) {
+	server := newTestServer()
+	ctx := context.Background()
+
+	if server.getTraceLevel() != TraceOff {
+		t.Errorf("Expected default trace level %q, got %q", TraceOff, server.getTraceLevel())
+	}
+
+	tests := []struct {
+		value    string
+		expected string
+		verbose  bool
+	}{
+		{value: "verbose", expected: TraceVerbose, verbose: true},
+		{value: "messages", expected: TraceMessages, verbose: false},
+		{value: "off", expected: TraceOff, verbose: false},
+		{value: "bogus", expected: TraceOff, verbose: false},
+	}
+
+	for _, tt := range tests {
+		if err := server.SetTrace(ctx, &SetTraceParams{Value: tt.value}); err != nil {
+			t.Fatalf("SetTrace(%q) returned error: %v", tt.value, err)
+		}
+		if server.getTraceLevel() != tt.expected {
+			t.Errorf("SetTrace(%q): expected level %q, got %q", tt.value, tt.expected, server.getTraceLevel())
+		}
+		if server.isVerbose() != tt.verbose {
+			t.Errorf("SetTrace(%q): expected verbose %t", tt.value, tt.verbose)
+		}
+	}
+}
+
+func TestServer_SchemaInfo(t *testing.T) {
+	server := newTestServer()
+
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	if err := os.WriteFile(schemaPath, []byte(`{"title": "Buildkite pipeline"}`), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	server.schemaLoader.SetURL(schemaPath)
+	if _, err := server.schemaLoader.GetSchemaData(); err != nil {
+		t.Fatalf("Failed to load schema: %v", err)
+	}
+
+	info, err := server.SchemaInfo(context.Background())
+	if err != nil {
+		t.Fatalf("SchemaInfo failed: %v", err)
+	}
+	if info.Title != "Buildkite pipeline" || info.Source != schemaPath || info.IsStub || info.FetchedAt.IsZero() {
+		t.Errorf("Unexpected schema info %+v", info)
+	}
+}
+
+func TestServer_TimeRequest(t *testing.T) {
+	server := newTestServer()
+	var output bytes.Buffer
+	server.logger = log.New(&output, "", 0)
+
+	server.timeRequest("textDocument/hover", time.Now())
+	if output.Len() != 0 {
+		t.Errorf("Expected no timing output when not verbose, got %q", output.String())
+	}
+
+	server.setTraceLevel(TraceVerbose)
+	server.timeRequest("textDocument/hover", time.Now().Add(-time.Second))
+	if !strings.Contains(output.String(), "Handled 'textDocument/hover' in 1") {
+		t.Errorf("Expected timing output for the request, got %q", output.String())
+	}
+}
+
+func TestServer_CRLFMatchesLF(t *testing.T) {
+	server := newTestServer()
+	ctx := context.Background()
+
+	lf := "steps:\n  - label: \"Build\"\n    command: \"make build\"\n    commands:\n      - \"make test\"\n    timeout_in_minutes: 0\n"
+	crlf := strings.ReplaceAll(lf, "\n", "\r\n")
+
+	lfURI := protocol.DocumentURI("file:///test/.buildkite/lf.yml")
+	crlfURI := protocol.DocumentURI("file:///test/.buildkite/crlf.yml")
+	server.documentManager.OpenDocument(lfURI, 1, lf)
+	server.documentManager.OpenDocument(crlfURI, 1, crlf)
+
+	for _, position := range []protocol.Position{{Line: 1, Character: 6}, {Line: 2, Character: 6}, {Line: 5, Character: 10}} {
+		hover := func(uri protocol.DocumentURI) string {
+			result, err := server.Hover(ctx, &protocol.HoverParams{
+				TextDocumentPositionParams: protocol.TextDocumentPositionParams{
+					TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+					Position:     position,
+				},
+			})
+			if err != nil {
+				t.Fatalf("Hover failed: %v", err)
+			}
+			if result == nil {
+				return ""
+			}
+			return result.Contents.Value
+		}
+
+		if lfHover, crlfHover := hover(lfURI), hover(crlfURI); lfHover != crlfHover {
+			t.Errorf("Hover at %d:%d differs between LF and CRLF:\nLF:   %q\nCRLF: %q", position.Line, position.Character, lfHover, crlfHover)
+		}
+	}
+
+	diagnose := func(content string) []protocol.Diagnostic {
+		pipeline, err := parser.ParseYAML([]byte(content))
+		if err != nil {
+			t.Fatalf("Failed to parse YAML: %v", err)
+		}
+		return server.validatePlugins(pipeline)
+	}
+
+	lfDiagnostics, crlfDiagnostics := diagnose(lf), diagnose(crlf)
+	if len(lfDiagnostics) == 0 || len(lfDiagnostics) != len(crlfDiagnostics) {
+		t.Fatalf("Expected matching diagnostics, got %d for LF and %d for CRLF", len(lfDiagnostics), len(crlfDiagnostics))
+	}
+	for i := range lfDiagnostics {
+		if lfDiagnostics[i].Range != crlfDiagnostics[i].Range || lfDiagnostics[i].Message != crlfDiagnostics[i].Message {
+			t.Errorf("Diagnostic %d differs between LF and CRLF:\nLF:   %+v\nCRLF: %+v", i, lfDiagnostics[i], crlfDiagnostics[i])
+		}
+	}
+}
+
+func TestServer_ValidateEmptyDocument(t *testing.T) {
+	server := newTestServer()
+
+	pipeline, err := parser.ParseYAML([]byte(""))
+	if err != nil {
+		t.Fatalf("Failed to parse YAML: %v", err)
+	}
+
+	diagnostics := server.validatePlugins(pipeline)
+	if len(diagnostics) != 1 || diagnostics[0].Code != "missing-steps" {
+		t.Fatalf("Expected a single missing-steps diagnostic, got %+v", diagnostics)
+	}
+	if diagnostics[0].Range.Start.Line != 0 {
+		t.Errorf("Expected the diagnostic on line 0, got %d", diagnostics[0].Range.Start.Line)
+	}
+}
+
+func TestSummarizeDiagnostics(t *testing.T) {
+	diagnostics := []protocol.Diagnostic{
+		{Severity: protocol.DiagnosticSeverityError},
+		{Severity: protocol.DiagnosticSeverityWarning},
+		{Severity: protocol.DiagnosticSeverityWarning},
+		{Severity: protocol.DiagnosticSeverityInformation},
+		{Severity: protocol.DiagnosticSeverityHint},
+		{},
+	}
+
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	expected := diagnosticSummary{URI: uri, Errors: 2, Warnings: 2, Information: 1, Hints: 1}
+	if got := summarizeDiagnostics(uri, diagnostics); got != expected {
+		t.Errorf("Expected %+v, got %+v", expected, got)
+	}
+}
+
+func TestServer_ValidateDocumentRanges(t *testing.T) {
+	server := newTestServer()
+	recorder := connectTestClient(t, server)
+
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	data := `{
+		"type": "object",
+		"required": ["steps"],
+		"properties": {
+			"steps": {"type": "array", "items": {"type": "object", "required": ["command"]}}
+		}
+	}`
+	if err := os.WriteFile(schemaPath, []byte(data), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	server.schemaLoader.SetURL(schemaPath)
+	if _, err := server.schemaLoader.GetSchemaData(); err != nil {
+		t.Fatalf("Failed to load schema: %v", err)
+	}
+
+	malformed := "steps:\n  - label: \"Build\"\n    command: [make\n"
+	_, parseErr := parser.ParseYAML([]byte(malformed))
+	if parseErr == nil {
+		t.Fatal("Expected the malformed document not to parse")
+	}
+	parseLine, ok := parser.ErrorLine(parseErr)
+	if !ok {
+		t.Fatalf("Expected the parse error to report a line: %v", parseErr)
+	}
+
+	tests := []struct {
+		name    string
+		content string
+		line    uint32
+		rng     *protocol.Range
+	}{
+		{
+			name: "step missing a required key",
+			content: `steps:
+  - command: "make build"
+  - label: "Broken"
+  - command: "make test"`,
+			rng: &protocol.Range{
+				Start: protocol.Position{Line: 2, Character: 4},
+				End:   protocol.Position{Line: 2, Character: 19},
+			},
+		},
+		{
+			name:    "malformed YAML",
+			content: malformed,
+			line:    uint32(parseLine),
+		},
+		{
+			name:    "error that can't be placed",
+			content: "env:\n  CI: \"true\"",
+			rng: &protocol.Range{
+				Start: protocol.Position{Line: 0, Character: 0},
+				End:   protocol.Position{Line: 1, Character: 12},
+			},
+		},
+	}
+
+	for i, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			uri := protocol.DocumentURI(fmt.Sprintf("file:///project/.buildkite/pipeline-%d.yml", i))
+			err := server.DidOpen(context.Background(), &protocol.DidOpenTextDocumentParams{
+				TextDocument: protocol.TextDocumentItem{URI: uri, LanguageID: "yaml", Version: 1, Text: tt.content},
+			})
+			if err != nil {
+				t.Fatalf("DidOpen failed: %v", err)
+			}
+
+			var published []protocol.Diagnostic
+			deadline := time.Now().Add(2 * time.Second)
+			for published == nil && time.Now().Before(deadline) {
+				for _, params := range recorder.snapshot() {
+					if params.URI == uri {
+						published = params.Diagnostics
+					}
+				}
+				time.Sleep(10 * time.Millisecond)
+			}
+			if len(published) != 1 {
+				t.Fatalf("Expected 1 diagnostic, got %v", published)
+			}
+
+			rng := published[0].Range
+			if tt.rng != nil && rng != *tt.rng {
+				t.Errorf("Expected range %v, got %v", *tt.rng, rng)
+			}
+			if tt.rng == nil && (rng.Start.Line != tt.line || rng.End.Character <= rng.Start.Character) {
+				t.Errorf("Expected a non-empty range on line %d, got %v", tt.line, rng)
+			}
+		})
+	}
+}
+
+func TestServer_ValidateDocumentInvalidEncoding(t *testing.T) {
+	server := newTestServer()
+	recorder := connectTestClient(t, server)
+
+	uri := protocol.DocumentURI("file:///project/.buildkite/pipeline.yml")
+	server.validateDocument(context.Background(), newDocument(uri, 1, "steps:\n  - label: \"caf\xe9\"\n"))
+
+	var published []protocol.Diagnostic
+	deadline := time.Now().Add(2 * time.Second)
+	for published == nil && time.Now().Before(deadline) {
+		for _, params := range recorder.snapshot() {
+			if params.URI == uri {
+				published = params.Diagnostics
+			}
+		}
+		time.Sleep(10 * time.Millisecond)
+	}
+
+	if len(published) != 1 || published[0].Code != "invalid-encoding" {
+		t.Fatalf("Expected a single invalid-encoding diagnostic, got %v", published)
+	}
+	if published[0].Severity != protocol.DiagnosticSeverityError {
+		t.Errorf("Expected an error, got severity %v", published[0].Severity)
+	}
+	if end := published[0].Range.End; end.Line != 2 {
+		t.Errorf("Expected the diagnostic to cover the whole document, got %v", published[0].Range)
+	}
+}
@@ -0,0 +1,106 @@
+package lsp
+
+import (
+	"encoding/json"
+
+	"go.lsp.dev/protocol"
+)
+
+// Settings holds user configuration passed by the client as initializationOptions
+type Settings struct {
+	// AllowedQueues restricts agents.queue to a fixed set of names when non-empty
+	AllowedQueues []string `json:"allowedQueues"`
+
+	// AllowedPlugins restricts plugin references to a fixed set of org/name pairs
+	// (e.g. "docker" or "my-org/deploy") when non-empty
+	AllowedPlugins []string `json:"allowedPlugins"`
+
+	// InsertRequiredFields adds the fields the pipeline schema requires for a
+	// step type to that type's completion snippet
+	InsertRequiredFields bool `json:"insertRequiredFields"`
+
+	// MaxEnvValueLength is the size in bytes above which a pipeline or step env
+	// value is reported as too large. Defaults to 131072 (128 KiB).
+	MaxEnvValueLength int `json:"maxEnvValueLength"`
+
+	// MaxLabelLength is the number of characters above which a step label is
+	// reported as too long. Defaults to 100.
+	MaxLabelLength int `json:"maxLabelLength"`
+
+	// MaxSteps is the step count, including steps in groups, above which a
+	// pipeline is reported as large. Defaults to 500.
+	MaxSteps int `json:"maxSteps"`
+
+	// PluginRegistryPath loads a JSON catalog of plugins from a local file in
+	// place of the bundled plugin list
+	PluginRegistryPath string `json:"pluginRegistryPath"`
+
+	// PluginRegistryURL loads the plugin catalog from an http(s) URL when
+	// PluginRegistryPath isn't set
+	PluginRegistryURL string `json:"pluginRegistryUrl"`
+
+	// SchemaRef pins the pipeline schema to a release tag or commit instead of main
+	SchemaRef string `json:"schemaRef"`
+
+	// SchemaURL loads the pipeline schema from an http(s) URL, a file:// URL or a
+	// local path, taking precedence over SchemaRef
+	SchemaURL string `json:"schemaUrl"`
+
+	// SeverityOverrides changes the severity of diagnostics by code, for example
+	// {"if-and-branches": "off"}. Values are error, warning, information, hint or off.
+	SeverityOverrides map[string]string `json:"severityOverrides"`
+
+	// TopLevelKeyOrder is a preferred order for top-level keys, such as
+	// ["env", "agents", "steps"]. When set, keys out of that order are noted;
+	// keys missing from the list can go anywhere.
+	TopLevelKeyOrder []string `json:"topLevelKeyOrder"`
+}
+
+// severityNames maps the severityOverrides values to LSP severities
+var severityNames = map[string]protocol.DiagnosticSeverity{
+	"error":       protocol.DiagnosticSeverityError,
+	"warning":     protocol.DiagnosticSeverityWarning,
+	"information": protocol.DiagnosticSeverityInformation,
+	"hint":        protocol.DiagnosticSeverityHint,
+}
+
+// applySeverityOverrides changes the severity of diagnostics whose code has an
+// override, dropping those set to "off". Unrecognised values are ignored.
+func applySeverityOverrides(diagnostics []protocol.Diagnostic, overrides map[string]string) []protocol.Diagnostic {
+	if len(overrides) == 0 {
+		return diagnostics
+	}
+
+	adjusted := make([]protocol.Diagnostic, 0, len(diagnostics))
+	for _, diagnostic := range diagnostics {
+		code, _ := diagnostic.Code.(string)
+		override, ok := overrides[code]
+		if ok && override == "off" {
+			continue
+		}
+		if severity, known := severityNames[override]; ok && known {
+			diagnostic.Severity = severity
+		}
+		adjusted = append(adjusted, diagnostic)
+	}
+	return adjusted
+}
+
+// parseSettings decodes initializationOptions, ignoring anything it doesn't recognise
+func parseSettings(options interface{}) (Settings, error) {
+	var settings Settings
+	if options == nil {
+		return settings, nil
+	}
+
+	data, err := json.Marshal(options)
+	if err != nil {
+		return settings, err
+	}
+
+	if err := json.Unmarshal(data, &settings); err != nil {
+		return Settings{}, err
+	}
+
+	return settings, nil
+}
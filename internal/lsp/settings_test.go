@@ -0,0 +1,199 @@
+package lsp
+
+import (
+	"context"
+	"net/http"
+	"net/http/httptest"
+	"os"
+	"path/filepath"
+	"testing"
+	"time"
+
+	"go.lsp.dev/protocol"
+
+	"github.com/mcncl/buildkite-ls/internal/plugins"
+)
+
+func TestParseSettings(t *testing.T) {
+	settings, err := parseSettings(map[string]interface{}{
+		"allowedQueues": []interface{}{"default", "deploy"},
+		"unknownOption": true,
+	})
+	if err != nil {
+		t.Fatalf("Unexpected error: %v", err)
+	}
+
+	if len(settings.AllowedQueues) != 2 || settings.AllowedQueues[1] != "deploy" {
+		t.Errorf("Unexpected allowed queues: %v", settings.AllowedQueues)
+	}
+
+	settings, err = parseSettings(nil)
+	if err != nil {
+		t.Fatalf("Unexpected error for nil options: %v", err)
+	}
+	if len(settings.AllowedQueues) != 0 {
+		t.Errorf("Expected no allowed queues, got %v", settings.AllowedQueues)
+	}
+
+	settings, err = parseSettings(map[string]interface{}{"schemaUrl": "file:///etc/buildkite/schema.json"})
+	if err != nil {
+		t.Fatalf("Unexpected error: %v", err)
+	}
+	if settings.SchemaURL != "file:///etc/buildkite/schema.json" {
+		t.Errorf("Unexpected schema URL: %q", settings.SchemaURL)
+	}
+
+	settings, err = parseSettings(map[string]interface{}{"topLevelKeyOrder": []interface{}{"env", "steps"}})
+	if err != nil {
+		t.Fatalf("Unexpected error: %v", err)
+	}
+	if len(settings.TopLevelKeyOrder) != 2 || settings.TopLevelKeyOrder[0] != "env" {
+		t.Errorf("Unexpected top-level key order: %v", settings.TopLevelKeyOrder)
+	}
+
+	if _, err := parseSettings(map[string]interface{}{"allowedQueues": "default"}); err == nil {
+		t.Error("Expected error for wrongly typed allowedQueues")
+	}
+}
+
+func TestServer_InitializeReadsSettings(t *testing.T) {
+	server := newTestServer()
+
+	_, err := server.Initialize(context.Background(), &protocol.InitializeParams{
+		InitializationOptions: map[string]interface{}{
+			"allowedQueues": []interface{}{"default"},
+		},
+	})
+	if err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+
+	if len(server.settings.AllowedQueues) != 1 || server.settings.AllowedQueues[0] != "default" {
+		t.Errorf("Expected allowed queues from initialization options, got %v", server.settings.AllowedQueues)
+	}
+}
+
+func TestServer_InitializeLoadsPluginRegistry(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "plugins.json")
+	catalog := `[{"name": "my-org/deploy", "description": "Deploy the app", "versions": ["v2.0.0"]}]`
+	if err := os.WriteFile(path, []byte(catalog), 0o644); err != nil {
+		t.Fatalf("Failed to write catalog: %v", err)
+	}
+
+	server := newTestServer()
+	_, err := server.Initialize(context.Background(), &protocol.InitializeParams{
+		InitializationOptions: map[string]interface{}{
+			"pluginRegistryPath": path,
+			"pluginRegistryUrl":  "https://example.com/ignored.json",
+		},
+	})
+	if err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	server.tasks.Wait()
+
+	popular := server.pluginRegistry.PopularPlugins()
+	if len(popular) != 1 || popular[0].Name != "my-org/deploy" || popular[0].Version != "v2.0.0" {
+		t.Errorf("Expected plugins from the registry file, got %+v", popular)
+	}
+
+	// A registry that can't be loaded leaves the bundled list in place
+	server = newTestServer()
+	_, err = server.Initialize(context.Background(), &protocol.InitializeParams{
+		InitializationOptions: map[string]interface{}{
+			"pluginRegistryPath": filepath.Join(t.TempDir(), "missing.json"),
+		},
+	})
+	if err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	server.tasks.Wait()
+	if len(server.pluginRegistry.PopularPlugins()) != len(plugins.GetPopularPlugins()) {
+		t.Error("Expected the bundled plugin list when the registry fails to load")
+	}
+}
+
+func TestServer_InitializeLoadsPluginRegistryInBackground(t *testing.T) {
+	release := make(chan struct{})
+	registry := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		<-release
+		_, _ = w.Write([]byte(`[{"name": "my-org/deploy", "versions": ["v2.0.0"]}]`))
+	}))
+	defer registry.Close()
+
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	if err := os.WriteFile(schemaPath, []byte(`{"type": "object"}`), 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	server := newTestServer()
+	server.validationDelay = 10 * time.Millisecond
+	server.schemaLoader.SetURL(schemaPath)
+	recorder := connectTestClient(t, server)
+
+	uri := protocol.DocumentURI("file:///test/.buildkite/pipeline.yml")
+	server.documentManager.OpenDocument(uri, 1, "steps:\n  - command: make\n")
+
+	initialized := make(chan error, 1)
+	go func() {
+		_, err := server.Initialize(context.Background(), &protocol.InitializeParams{
+			InitializationOptions: map[string]interface{}{"pluginRegistryUrl": registry.URL},
+		})
+		initialized <- err
+	}()
+
+	select {
+	case err := <-initialized:
+		if err != nil {
+			t.Fatalf("Initialize failed: %v", err)
+		}
+	case <-time.After(2 * time.Second):
+		close(release)
+		t.Fatal("Expected Initialize not to wait for the plugin registry")
+	}
+
+	if len(server.pluginRegistry.PopularPlugins()) != len(plugins.GetPopularPlugins()) {
+		t.Error("Expected the bundled plugin list while the registry loads")
+	}
+
+	close(release)
+	deadline := time.Now().Add(2 * time.Second)
+	for len(recorder.snapshot()) == 0 && time.Now().Before(deadline) {
+		time.Sleep(10 * time.Millisecond)
+	}
+
+	if popular := server.pluginRegistry.PopularPlugins(); len(popular) != 1 || popular[0].Name != "my-org/deploy" {
+		t.Errorf("Expected plugins from the registry once loaded, got %+v", popular)
+	}
+	if published := recorder.snapshot(); len(published) == 0 || published[0].URI != uri {
+		t.Errorf("Expected the open document to be re-validated, got %+v", published)
+	}
+}
+
+func TestApplySeverityOverrides(t *testing.T) {
+	diagnostics := []protocol.Diagnostic{
+		{Code: "if-and-branches", Severity: protocol.DiagnosticSeverityInformation},
+		{Code: "missing-label", Severity: protocol.DiagnosticSeverityInformation},
+		{Code: "empty-command", Severity: protocol.DiagnosticSeverityWarning},
+		{Severity: protocol.DiagnosticSeverityError, Message: "YAML parse error"},
+	}
+
+	adjusted := applySeverityOverrides(diagnostics, map[string]string{
+		"if-and-branches": "off",
+		"missing-label":   "hint",
+		"empty-command":   "loud",
+	})
+
+	if len(adjusted) != 3 {
+		t.Fatalf("Expected the disabled diagnostic to be dropped, got %d diagnostics", len(adjusted))
+	}
+	if adjusted[0].Code != "missing-label" || adjusted[0].Severity != protocol.DiagnosticSeverityHint {
+		t.Errorf("Expected missing-label as a hint, got %v %v", adjusted[0].Code, adjusted[0].Severity)
+	}
+	if adjusted[1].Severity != protocol.DiagnosticSeverityWarning {
+		t.Errorf("Expected an unrecognised override to be ignored, got %v", adjusted[1].Severity)
+	}
+	if adjusted[2].Severity != protocol.DiagnosticSeverityError {
+		t.Errorf("Expected diagnostics without a code to be unchanged, got %v", adjusted[2].Severity)
+	}
+}
@@ -0,0 +1,47 @@
+package lsp
+
+// levenshtein returns the edit distance between two strings
+func levenshtein(a, b string) int {
+	ra, rb := []rune(a), []rune(b)
+	previous := make([]int, len(rb)+1)
+	current := make([]int, len(rb)+1)
+
+	for j := range previous {
+		previous[j] = j
+	}
+
+	for i := 1; i <= len(ra); i++ {
+		current[0] = i
+		for j := 1; j <= len(rb); j++ {
+			cost := 1
+			if ra[i-1] == rb[j-1] {
+				cost = 0
+			}
+			current[j] = min(previous[j]+1, current[j-1]+1, previous[j-1]+cost)
+		}
+		previous, current = current, previous
+	}
+
+	return previous[len(rb)]
+}
+
+// closestMatch returns the candidate nearest to value, or "" if none is close enough to be a likely typo
+func closestMatch(value string, candidates []string) string {
+	best := ""
+	bestDistance := -1
+
+	for _, candidate := range candidates {
+		distance := levenshtein(value, candidate)
+		if bestDistance == -1 || distance < bestDistance {
+			best = candidate
+			bestDistance = distance
+		}
+	}
+
+	// Allow roughly one edit per three characters
+	if bestDistance == -1 || bestDistance > max(1, len(value)/3) {
+		return ""
+	}
+
+	return best
+}
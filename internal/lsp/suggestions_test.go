@@ -0,0 +1,33 @@
+package lsp
+
+import "testing"
+
+func TestLevenshtein(t *testing.T) {
+	tests := []struct {
+		a, b     string
+		expected int
+	}{
+		{"", "", 0},
+		{"deploy", "deploy", 0},
+		{"deloy", "deploy", 1},
+		{"default", "deploy", 5},
+		{"", "abc", 3},
+	}
+
+	for _, tt := range tests {
+		if got := levenshtein(tt.a, tt.b); got != tt.expected {
+			t.Errorf("levenshtein(%q, %q) = %d, expected %d", tt.a, tt.b, got, tt.expected)
+		}
+	}
+}
+
+func TestClosestMatch(t *testing.T) {
+	candidates := []string{"default", "deploy", "macos"}
+
+	if got := closestMatch("deloy", candidates); got != "deploy" {
+		t.Errorf("Expected 'deploy', got %q", got)
+	}
+	if got := closestMatch("windows", candidates); got != "" {
+		t.Errorf("Expected no suggestion, got %q", got)
+	}
+}
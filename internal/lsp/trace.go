@@ -0,0 +1,89 @@
+package lsp
+
+import (
+	"context"
+	"time"
+)
+
+// Trace levels sent by the client in initialize and $/setTrace
+const (
+	TraceOff      = "off"
+	TraceMessages = "messages"
+	TraceVerbose  = "verbose"
+)
+
+// SetTraceParams are the parameters of a $/setTrace notification
+type SetTraceParams struct {
+	Value string `json:"value"`
+}
+
+// logTraceParams are the parameters of a $/logTrace notification
+type logTraceParams struct {
+	Message string `json:"message"`
+	Verbose string `json:"verbose,omitempty"`
+}
+
+// SetTrace updates the trace level requested by the client
+func (s *Server) SetTrace(ctx context.Context, params *SetTraceParams) error {
+	s.setTraceLevel(params.Value)
+	s.logger.Printf("Trace level set to %s", s.getTraceLevel())
+	return nil
+}
+
+func (s *Server) setTraceLevel(level string) {
+	switch level {
+	case TraceMessages, TraceVerbose:
+	default:
+		level = TraceOff
+	}
+
+	s.traceMu.Lock()
+	defer s.traceMu.Unlock()
+	s.traceLevel = level
+}
+
+func (s *Server) getTraceLevel() string {
+	s.traceMu.RLock()
+	defer s.traceMu.RUnlock()
+
+	if s.traceLevel == "" {
+		return TraceOff
+	}
+	return s.traceLevel
+}
+
+// isVerbose reports whether detailed request logging is enabled
+func (s *Server) isVerbose() bool {
+	return s.getTraceLevel() == TraceVerbose
+}
+
+// verbosef writes to the server log only when the trace level is verbose
+func (s *Server) verbosef(format string, args ...interface{}) {
+	if s.isVerbose() {
+		s.logger.Printf(format, args...)
+	}
+}
+
+// timeRequest logs how long a request took when the trace level is verbose.
+// Call it deferred with the start time: defer s.timeRequest(method, time.Now())
+func (s *Server) timeRequest(method string, start time.Time) {
+	s.verbosef("Handled '%s' in %s", method, time.Since(start))
+}
+
+// logTrace sends a $/logTrace notification to the client, including the
+// verbose detail only when the trace level asks for it
+func (s *Server) logTrace(ctx context.Context, message, verbose string) {
+	level := s.getTraceLevel()
+	if level == TraceOff || s.conn == nil {
+		return
+	}
+
+	params := logTraceParams{Message: message}
+	if level == TraceVerbose {
+		params.Verbose = verbose
+	}
+
+	if err := s.conn.Notify(ctx, "$/logTrace", params); err != nil {
+		s.logger.Printf("Failed to send trace: %v", err)
+	}
+}
@@ -0,0 +1,245 @@
+package lsp
+
+import (
+	"context"
+	"io/fs"
+	"net/url"
+	"os"
+	"path"
+	"path/filepath"
+	"slices"
+	"strings"
+
+	"go.lsp.dev/protocol"
+)
+
+// pipelineFileFilter selects the files whose renames the server wants to hear about
+var pipelineFileFilter = protocol.FileOperationFilter{
+	Scheme:  "file",
+	Pattern: protocol.FileOperationPattern{Glob: "**/.buildkite/**/*.{yml,yaml,json}"},
+}
+
+// initialWorkspaceFolders returns the workspace roots from initialize, falling
+// back to rootUri for clients that don't send workspaceFolders
+func initialWorkspaceFolders(params *protocol.InitializeParams) []protocol.WorkspaceFolder {
+	if len(params.WorkspaceFolders) > 0 {
+		return slices.Clone(params.WorkspaceFolders)
+	}
+	if params.RootURI == "" {
+		return nil
+	}
+
+	root := string(params.RootURI)
+	return []protocol.WorkspaceFolder{{URI: root, Name: path.Base(strings.TrimSuffix(root, "/"))}}
+}
+
+// WorkspaceFolders returns the roots of the workspace the client has open
+func (s *Server) WorkspaceFolders() []protocol.WorkspaceFolder {
+	s.workspaceMu.Lock()
+	defer s.workspaceMu.Unlock()
+	return slices.Clone(s.workspaceFolders)
+}
+
+// DidChangeWorkspaceFolders keeps the set of workspace roots, and the meta-data
+// keys indexed from them, current as the client adds and removes folders
+func (s *Server) DidChangeWorkspaceFolders(ctx context.Context, params *protocol.DidChangeWorkspaceFoldersParams) error {
+	s.workspaceMu.Lock()
+	for _, removed := range params.Event.Removed {
+		s.workspaceFolders = slices.DeleteFunc(s.workspaceFolders, func(folder protocol.WorkspaceFolder) bool {
+			return folder.URI == removed.URI
+		})
+
+		root, ok := fileURIPath(removed.URI)
+		if !ok {
+			continue
+		}
+		prefix := filepath.Clean(root) + string(filepath.Separator)
+		for uri := range s.metaDataKeys {
+			if filePath, ok := fileURIPath(string(uri)); ok && strings.HasPrefix(filePath, prefix) {
+				delete(s.metaDataKeys, uri)
+			}
+		}
+	}
+	for _, added := range params.Event.Added {
+		if !slices.ContainsFunc(s.workspaceFolders, func(folder protocol.WorkspaceFolder) bool {
+			return folder.URI == added.URI
+		}) {
+			s.workspaceFolders = append(s.workspaceFolders, added)
+		}
+	}
+	s.logger.Printf("Workspace folders changed: %d open", len(s.workspaceFolders))
+	s.workspaceMu.Unlock()
+
+	for _, added := range params.Event.Added {
+		s.indexFolderMetaData(added)
+	}
+	return nil
+}
+
+// pipelineSlugForFile derives the pipeline slug a monorepo would use for a
+// pipeline file from its name, e.g. ".buildkite/pipeline.deploy.yml" and
+// ".buildkite/deploy.yml" both give "deploy". The default pipeline.yml gives no
+// slug, since its pipeline is named after the repository.
+func pipelineSlugForFile(uri string) string {
+	name := path.Base(uri)
+	if unescaped, err := url.PathUnescape(name); err == nil {
+		name = unescaped
+	}
+	name = strings.TrimSuffix(strings.TrimSuffix(strings.TrimSuffix(name, ".yml"), ".yaml"), ".json")
+	name = strings.TrimSuffix(strings.TrimPrefix(name, "pipeline."), ".pipeline")
+
+	slug := slugify(name)
+	if slug == "pipeline" {
+		return ""
+	}
+	return slug
+}
+
+// WillRenameFiles updates trigger steps in open pipelines that trigger the
+// pipeline derived from a renamed file, so the reference follows the rename
+func (s *Server) WillRenameFiles(ctx context.Context, params *protocol.RenameFilesParams) (*protocol.WorkspaceEdit, error) {
+	changes := make(map[protocol.DocumentURI][]protocol.TextEdit)
+
+	for _, file := range params.Files {
+		// Only pipeline files give the slugs other pipelines trigger
+		if !s.isBuildkiteFile(file.OldURI) {
+			continue
+		}
+
+		oldSlug, newSlug := pipelineSlugForFile(file.OldURI), pipelineSlugForFile(file.NewURI)
+		if oldSlug == "" || newSlug == "" || oldSlug == newSlug {
+			continue
+		}
+
+		for _, doc := range s.documentManager.GetAllDocuments() {
+			if !s.isBuildkiteFile(string(doc.URI)) {
+				continue
+			}
+			for _, rng := range s.findTriggerReferences(doc.Lines, oldSlug) {
+				changes[doc.URI] = append(changes[doc.URI], protocol.TextEdit{Range: rng, NewText: newSlug})
+			}
+		}
+
+		s.logger.Printf("Renaming triggers of '%s' to '%s'", oldSlug, newSlug)
+	}
+
+	if len(changes) == 0 {
+		return nil, nil
+	}
+	return &protocol.WorkspaceEdit{Changes: changes}, nil
+}
+
+// findTriggerReferences returns the range of each trigger step's pipeline slug
+// that matches slug, including trigger steps inside groups
+func (s *Server) findTriggerReferences(lines []string, slug string) []protocol.Range {
+	var ranges []protocol.Range
+
+	var checkStep func(start, end int)
+	checkStep = func(start, end int) {
+		if loc := s.findStepProperty("trigger", lines, start, end); loc != nil {
+			if reference, ok := scalarStepKey(lines, loc); ok && reference.Key == slug {
+				ranges = append(ranges, reference.Range)
+			}
+			return
+		}
+
+		stepsLoc := s.findStepProperty("steps", lines, start, end)
+		if stepsLoc == nil {
+			return
+		}
+		for _, itemLine := range s.findListItemLines(lines, stepsLoc.Line, end) {
+			checkStep(itemLine, s.findListItemEnd(lines, itemLine, end))
+		}
+	}
+
+	stepLines := s.findStepLines(lines)
+	for i := range stepLines {
+		checkStep(s.stepLineRange(stepLines, i, lines))
+	}
+
+	return ranges
+}
+
+// indexWorkspaceMetaData records the meta-data keys used by the pipelines in
+// every workspace folder
+func (s *Server) indexWorkspaceMetaData() {
+	for _, folder := range s.WorkspaceFolders() {
+		s.indexFolderMetaData(folder)
+	}
+}
+
+// indexFolderMetaData reads the pipelines anywhere under a workspace folder's
+// .buildkite directory and records the meta-data keys they use
+func (s *Server) indexFolderMetaData(folder protocol.WorkspaceFolder) {
+	root, ok := fileURIPath(folder.URI)
+	if !ok {
+		return
+	}
+
+	dir := filepath.Join(root, ".buildkite")
+	_ = filepath.WalkDir(dir, func(filePath string, entry fs.DirEntry, err error) error {
+		if err != nil {
+			if filePath == dir {
+				// The folder has no .buildkite directory
+				return err
+			}
+			s.logger.Printf("Skipping %s when indexing meta-data keys: %v", filePath, err)
+			return nil
+		}
+
+		uri := protocol.DocumentURI((&url.URL{Scheme: "file", Path: filepath.ToSlash(filePath)}).String())
+		if entry.IsDir() || !s.isBuildkiteFile(string(uri)) {
+			return nil
+		}
+
+		data, err := os.ReadFile(filePath)
+		if err != nil {
+			s.logger.Printf("Skipping %s when indexing meta-data keys: %v", filePath, err)
+			return nil
+		}
+		s.setMetaDataKeys(uri, collectMetaDataKeys(splitLines(string(data))))
+		return nil
+	})
+}
+
+// fileURIPath returns the local path of a file:// URI
+func fileURIPath(uri string) (string, bool) {
+	parsed, err := url.Parse(uri)
+	if err != nil || parsed.Scheme != "file" {
+		return "", false
+	}
+	return filepath.FromSlash(parsed.Path), true
+}
+
+// setMetaDataKeys records the meta-data keys a workspace pipeline uses
+func (s *Server) setMetaDataKeys(uri protocol.DocumentURI, keys []string) {
+	s.workspaceMu.Lock()
+	defer s.workspaceMu.Unlock()
+
+	if len(keys) == 0 {
+		delete(s.metaDataKeys, uri)
+		return
+	}
+	if s.metaDataKeys == nil {
+		s.metaDataKeys = make(map[protocol.DocumentURI][]string)
+	}
+	s.metaDataKeys[uri] = keys
+}
+
+// WorkspaceMetaDataKeys returns the meta-data keys used across the workspace's
+// pipelines, sorted
+func (s *Server) WorkspaceMetaDataKeys() []string {
+	s.workspaceMu.Lock()
+	defer s.workspaceMu.Unlock()
+
+	var keys []string
+	for _, fileKeys := range s.metaDataKeys {
+		for _, key := range fileKeys {
+			if !slices.Contains(keys, key) {
+				keys = append(keys, key)
+			}
+		}
+	}
+	slices.Sort(keys)
+	return keys
+}
@@ -0,0 +1,267 @@
+package lsp
+
+import (
+	"context"
+	"net/url"
+	"os"
+	"path/filepath"
+	"slices"
+	"testing"
+
+	"go.lsp.dev/protocol"
+)
+
+func workspaceFolderURIs(folders []protocol.WorkspaceFolder) []string {
+	var uris []string
+	for _, folder := range folders {
+		uris = append(uris, folder.URI)
+	}
+	return uris
+}
+
+func TestServer_WorkspaceFolders(t *testing.T) {
+	server := newTestServer()
+
+	result, err := server.Initialize(context.Background(), &protocol.InitializeParams{
+		RootURI: "file:///repo/api",
+		WorkspaceFolders: []protocol.WorkspaceFolder{
+			{URI: "file:///repo/api", Name: "api"},
+			{URI: "file:///repo/web", Name: "web"},
+		},
+	})
+	if err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	if workspace := result.Capabilities.Workspace; workspace == nil || workspace.WorkspaceFolders == nil || !workspace.WorkspaceFolders.Supported {
+		t.Error("Expected workspace folder support to be advertised")
+	}
+
+	if got := workspaceFolderURIs(server.WorkspaceFolders()); len(got) != 2 || got[1] != "file:///repo/web" {
+		t.Fatalf("Expected both workspace folders, got %v", got)
+	}
+
+	err = server.DidChangeWorkspaceFolders(context.Background(), &protocol.DidChangeWorkspaceFoldersParams{
+		Event: protocol.WorkspaceFoldersChangeEvent{
+			Added: []protocol.WorkspaceFolder{
+				{URI: "file:///repo/infra", Name: "infra"},
+				{URI: "file:///repo/web", Name: "web"},
+			},
+			Removed: []protocol.WorkspaceFolder{{URI: "file:///repo/api", Name: "api"}},
+		},
+	})
+	if err != nil {
+		t.Fatalf("DidChangeWorkspaceFolders failed: %v", err)
+	}
+
+	got := workspaceFolderURIs(server.WorkspaceFolders())
+	expected := []string{"file:///repo/web", "file:///repo/infra"}
+	if len(got) != len(expected) || got[0] != expected[0] || got[1] != expected[1] {
+		t.Errorf("Expected workspace folders %v, got %v", expected, got)
+	}
+}
+
+func TestServer_WorkspaceFoldersFromRootURI(t *testing.T) {
+	server := newTestServer()
+
+	if _, err := server.Initialize(context.Background(), &protocol.InitializeParams{RootURI: "file:///repo/api/"}); err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+
+	folders := server.WorkspaceFolders()
+	if len(folders) != 1 || folders[0].URI != "file:///repo/api/" || folders[0].Name != "api" {
+		t.Errorf("Expected a single folder for the root URI, got %v", folders)
+	}
+
+	if _, err := server.Initialize(context.Background(), &protocol.InitializeParams{}); err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	if folders := server.WorkspaceFolders(); len(folders) != 0 {
+		t.Errorf("Expected no workspace folders without a root, got %v", folders)
+	}
+}
+
+func TestPipelineSlugForFile(t *testing.T) {
+	tests := map[string]string{
+		"file:///repo/.buildkite/pipeline.deploy.yml": "deploy",
+		"file:///repo/.buildkite/deploy.yaml":         "deploy",
+		"file:///repo/.buildkite/Release%20App.yml":   "release-app",
+		"file:///repo/.buildkite/pipeline.test.json":  "test",
+		"file:///repo/.buildkite/pipeline.yml":        "",
+	}
+
+	for uri, expected := range tests {
+		if got := pipelineSlugForFile(uri); got != expected {
+			t.Errorf("pipelineSlugForFile(%q) = %q, want %q", uri, got, expected)
+		}
+	}
+}
+
+func TestServer_WillRenameFiles(t *testing.T) {
+	server := newTestServer()
+	uri := protocol.DocumentURI("file:///repo/.buildkite/pipeline.yml")
+
+	content := `steps:
+  - trigger: "deploy"
+    build:
+      branch: main
+  - group: "Release"
+    steps:
+      - trigger: deploy
+      - trigger: "deploy-docs"`
+	err := server.DidOpen(context.Background(), &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{URI: uri, LanguageID: "yaml", Version: 1, Text: content},
+	})
+	if err != nil {
+		t.Fatalf("DidOpen failed: %v", err)
+	}
+
+	edit, err := server.WillRenameFiles(context.Background(), &protocol.RenameFilesParams{
+		Files: []protocol.FileRename{
+			{OldURI: "file:///repo/.buildkite/pipeline.deploy.yml", NewURI: "file:///repo/.buildkite/pipeline.release.yml"},
+		},
+	})
+	if err != nil {
+		t.Fatalf("WillRenameFiles failed: %v", err)
+	}
+	if edit == nil {
+		t.Fatal("Expected edits for the renamed pipeline")
+	}
+
+	edits := edit.Changes[uri]
+	expected := []protocol.Range{
+		{Start: protocol.Position{Line: 1, Character: 14}, End: protocol.Position{Line: 1, Character: 20}},
+		{Start: protocol.Position{Line: 6, Character: 17}, End: protocol.Position{Line: 6, Character: 23}},
+	}
+	if len(edits) != len(expected) {
+		t.Fatalf("Expected %d edits, got %+v", len(expected), edits)
+	}
+	for i, rng := range expected {
+		if edits[i].Range != rng || edits[i].NewText != "release" {
+			t.Errorf("Edit %d: expected 'release' at %v, got %+v", i, rng, edits[i])
+		}
+	}
+
+	edit, err = server.WillRenameFiles(context.Background(), &protocol.RenameFilesParams{
+		Files: []protocol.FileRename{
+			{OldURI: "file:///repo/.buildkite/pipeline.yml", NewURI: "file:///repo/.buildkite/pipeline.main.yml"},
+		},
+	})
+	if err != nil || edit != nil {
+		t.Errorf("Expected no edits when renaming the default pipeline, got %+v, %v", edit, err)
+	}
+
+	edit, err = server.WillRenameFiles(context.Background(), &protocol.RenameFilesParams{
+		Files: []protocol.FileRename{
+			{OldURI: "file:///repo/config/deploy.yml", NewURI: "file:///repo/config/release.yml"},
+		},
+	})
+	if err != nil || edit != nil {
+		t.Errorf("Expected no edits when renaming a file that isn't a pipeline, got %+v, %v", edit, err)
+	}
+}
+
+func TestServer_WorkspaceMetaDataKeys(t *testing.T) {
+	root := t.TempDir()
+	buildkiteDir := filepath.Join(root, ".buildkite")
+	if err := os.Mkdir(buildkiteDir, 0o755); err != nil {
+		t.Fatal(err)
+	}
+	pipeline := `steps:
+  - trigger: "deploy"
+    build:
+      meta_data:
+        release-version: "1.2.3"`
+	if err := os.WriteFile(filepath.Join(buildkiteDir, "pipeline.yml"), []byte(pipeline), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.WriteFile(filepath.Join(buildkiteDir, "notes.txt"), []byte("meta_data:\n  ignored: true"), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	nestedDir := filepath.Join(buildkiteDir, "pipelines")
+	if err := os.Mkdir(nestedDir, 0o755); err != nil {
+		t.Fatal(err)
+	}
+	nested := `steps:
+  - command: "buildkite-agent meta-data get release-notes"`
+	if err := os.WriteFile(filepath.Join(nestedDir, "release.yml"), []byte(nested), 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	server := newTestServer()
+	rootURI := (&url.URL{Scheme: "file", Path: filepath.ToSlash(root)}).String()
+	if _, err := server.Initialize(context.Background(), &protocol.InitializeParams{RootURI: protocol.DocumentURI(rootURI)}); err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	if err := server.Initialized(context.Background(), &protocol.InitializedParams{}); err != nil {
+		t.Fatalf("Initialized failed: %v", err)
+	}
+
+	if got := server.WorkspaceMetaDataKeys(); !slices.Equal(got, []string{"release-notes", "release-version"}) {
+		t.Fatalf("Expected the meta-data keys of the top-level and nested pipelines, got %v", got)
+	}
+
+	uri := protocol.DocumentURI(rootURI + "/.buildkite/deploy.yml")
+	content := `steps:
+  - command: "buildkite-agent meta-data set deploy-target prod"`
+	err := server.DidOpen(context.Background(), &protocol.DidOpenTextDocumentParams{
+		TextDocument: protocol.TextDocumentItem{URI: uri, LanguageID: "yaml", Version: 1, Text: content},
+	})
+	if err != nil {
+		t.Fatalf("DidOpen failed: %v", err)
+	}
+	if got := server.WorkspaceMetaDataKeys(); !slices.Equal(got, []string{"release-notes", "release-version"}) {
+		t.Errorf("Expected unsaved changes not to be indexed, got %v", got)
+	}
+
+	if err := server.DidSave(context.Background(), &protocol.DidSaveTextDocumentParams{
+		TextDocument: protocol.TextDocumentIdentifier{URI: uri},
+	}); err != nil {
+		t.Fatalf("DidSave failed: %v", err)
+	}
+	if got := server.WorkspaceMetaDataKeys(); !slices.Equal(got, []string{"deploy-target", "release-notes", "release-version"}) {
+		t.Errorf("Expected the saved pipeline's keys to be added, got %v", got)
+	}
+}
+
+func TestServer_DidChangeWorkspaceFoldersIndexesMetaData(t *testing.T) {
+	writePipeline := func(key string) string {
+		root := t.TempDir()
+		if err := os.Mkdir(filepath.Join(root, ".buildkite"), 0o755); err != nil {
+			t.Fatal(err)
+		}
+		pipeline := "steps:\n  - command: \"buildkite-agent meta-data set " + key + " 1\""
+		if err := os.WriteFile(filepath.Join(root, ".buildkite", "pipeline.yml"), []byte(pipeline), 0o644); err != nil {
+			t.Fatal(err)
+		}
+		return (&url.URL{Scheme: "file", Path: filepath.ToSlash(root)}).String()
+	}
+	first, second := writePipeline("first-key"), writePipeline("second-key")
+
+	server := newTestServer()
+	if _, err := server.Initialize(context.Background(), &protocol.InitializeParams{RootURI: protocol.DocumentURI(first)}); err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	if err := server.Initialized(context.Background(), &protocol.InitializedParams{}); err != nil {
+		t.Fatalf("Initialized failed: %v", err)
+	}
+
+	err := server.DidChangeWorkspaceFolders(context.Background(), &protocol.DidChangeWorkspaceFoldersParams{
+		Event: protocol.WorkspaceFoldersChangeEvent{Added: []protocol.WorkspaceFolder{{URI: second, Name: "second"}}},
+	})
+	if err != nil {
+		t.Fatalf("DidChangeWorkspaceFolders failed: %v", err)
+	}
+	if got := server.WorkspaceMetaDataKeys(); !slices.Equal(got, []string{"first-key", "second-key"}) {
+		t.Errorf("Expected the added folder's keys to be indexed, got %v", got)
+	}
+
+	err = server.DidChangeWorkspaceFolders(context.Background(), &protocol.DidChangeWorkspaceFoldersParams{
+		Event: protocol.WorkspaceFoldersChangeEvent{Removed: []protocol.WorkspaceFolder{{URI: first, Name: "first"}}},
+	})
+	if err != nil {
+		t.Fatalf("DidChangeWorkspaceFolders failed: %v", err)
+	}
+	if got := server.WorkspaceMetaDataKeys(); !slices.Equal(got, []string{"second-key"}) {
+		t.Errorf("Expected the removed folder's keys to be dropped, got %v", got)
+	}
+}
@@ -0,0 +1,85 @@
+package parser
+
+import (
+	"regexp"
+	"strings"
+)
+
+// blockScalarPattern matches a line that opens a literal or folded block scalar
+var blockScalarPattern = regexp.MustCompile(`(?:^|[:\s-])[|>][-+0-9]*\s*(?:#.*)?$`)
+
+// Indentation records the leading spaces of each line and the indentation unit
+// the document settled on. Indents holds -1 for blank lines, comments and the
+// body of block scalars, which don't take part in nesting. InconsistentLine is
+// the first line whose indentation step differs from Unit, or -1 when every
+// step matches.
+type Indentation struct {
+	Indents          []int
+	Unit             int
+	InconsistentLine int
+	Step             int
+}
+
+// AnalyzeIndentation works out the indentation unit from the first nested line
+// and finds the first line that nests by a different number of spaces. Block
+// sequence items count their content column as a level, so `- label:` followed
+// by `  command:` is consistent.
+func AnalyzeIndentation(lines []string) Indentation {
+	result := Indentation{Indents: make([]int, len(lines)), InconsistentLine: -1}
+
+	levels := []int{0}
+	blockIndent := -1
+
+	for i, line := range lines {
+		result.Indents[i] = -1
+
+		trimmed := strings.TrimLeft(line, " ")
+		indent := len(line) - len(trimmed)
+		content := strings.TrimSpace(trimmed)
+		if content == "" {
+			continue
+		}
+
+		// Block scalar bodies are free-form text
+		if blockIndent >= 0 {
+			if indent > blockIndent {
+				continue
+			}
+			blockIndent = -1
+		}
+		if strings.HasPrefix(content, "#") || content == "---" || content == "..." {
+			continue
+		}
+		result.Indents[i] = indent
+
+		for len(levels) > 1 && levels[len(levels)-1] > indent {
+			levels = levels[:len(levels)-1]
+		}
+
+		if top := levels[len(levels)-1]; indent > top {
+			step := indent - top
+			if result.Unit == 0 {
+				result.Unit = step
+			} else if step != result.Unit && result.InconsistentLine == -1 {
+				result.InconsistentLine = i
+				result.Step = step
+			}
+			levels = append(levels, indent)
+		}
+
+		// Each `- ` opens a level at the column its content starts
+		column := indent
+		for strings.HasPrefix(content, "- ") {
+			rest := content[2:]
+			content = strings.TrimLeft(rest, " ")
+			column += 2 + len(rest) - len(content)
+			levels = append(levels, column)
+		}
+
+		if blockScalarPattern.MatchString(content) {
+			blockIndent = indent
+		}
+	}
+
+	return result
+}
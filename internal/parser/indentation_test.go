@@ -0,0 +1,95 @@
+package parser
+
+import (
+	"strings"
+	"testing"
+)
+
+func TestAnalyzeIndentation(t *testing.T) {
+	tests := []struct {
+		name             string
+		content          string
+		wantUnit         int
+		wantInconsistent int
+		wantStep         int
+	}{
+		{
+			name: "two spaces with list items",
+			content: `steps:
+  - label: "Build"
+    plugins:
+      - docker#v5.13.0:
+          image: "golang"
+  - wait`,
+			wantUnit:         2,
+			wantInconsistent: -1,
+		},
+		{
+			name: "four spaces",
+			content: `env:
+    FOO: bar
+agents:
+    queue: default`,
+			wantUnit:         4,
+			wantInconsistent: -1,
+		},
+		{
+			name: "unindented sequence",
+			content: `steps:
+- label: "Build"
+  agents:
+    queue: default`,
+			wantUnit:         2,
+			wantInconsistent: -1,
+		},
+		{
+			name: "block scalar body is ignored",
+			content: `steps:
+  - command: |
+        echo "deeply indented script"
+          echo "even deeper"
+    label: "Script"`,
+			wantUnit:         2,
+			wantInconsistent: -1,
+		},
+		{
+			name: "mixed widths",
+			content: `env:
+  FOO: bar
+steps:
+  - label: "Build"
+    agents:
+        queue: default
+    env:
+      BAR: baz`,
+			wantUnit:         2,
+			wantInconsistent: 5,
+			wantStep:         4,
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			result := AnalyzeIndentation(strings.Split(tt.content, "\n"))
+			if result.Unit != tt.wantUnit {
+				t.Errorf("Expected unit %d, got %d", tt.wantUnit, result.Unit)
+			}
+			if result.InconsistentLine != tt.wantInconsistent {
+				t.Errorf("Expected inconsistent line %d, got %d", tt.wantInconsistent, result.InconsistentLine)
+			}
+			if result.Step != tt.wantStep {
+				t.Errorf("Expected step %d, got %d", tt.wantStep, result.Step)
+			}
+		})
+	}
+}
+
+func TestAnalyzeIndentation_RecordsIndents(t *testing.T) {
+	result := AnalyzeIndentation([]string{"steps:", "", "  # comment", "  - wait"})
+	expected := []int{0, -1, -1, 2}
+	for i, indent := range expected {
+		if result.Indents[i] != indent {
+			t.Errorf("Line %d: expected indent %d, got %d", i, indent, result.Indents[i])
+		}
+	}
+}
@@ -1,8 +1,12 @@
 package parser
 
 import (
+	"bytes"
 	"encoding/json"
+	"errors"
 	"fmt"
+	"regexp"
+	"strconv"
 	"strings"
 
 	"gopkg.in/yaml.v3"
@@ -12,6 +16,10 @@ type Pipeline struct {
 	Content   []byte
 	JSONBytes []byte
 	YAMLNode  *yaml.Node
+
+	// data is JSONBytes decoded once by ParseYAML, or nil if the top level
+	// isn't a mapping
+	data map[string]interface{}
 }
 
 type Position struct {
@@ -19,15 +27,44 @@ type Position struct {
 	Character int
 }
 
-func ParseYAML(content []byte) (*Pipeline, error) {
+// utf8BOM is the byte order mark some Windows editors prepend to UTF-8 files
+var utf8BOM = []byte{0xEF, 0xBB, 0xBF}
+
+// ParseYAML parses a pipeline document. Malformed input is reported as an
+// error and never panics, whatever bytes the editor sends.
+func ParseYAML(content []byte) (pipeline *Pipeline, err error) {
+	defer func() {
+		if r := recover(); r != nil {
+			pipeline = nil
+			err = fmt.Errorf("failed to parse YAML: %v", r)
+		}
+	}()
+
+	content = bytes.TrimPrefix(content, utf8BOM)
+
+	// JSON pipelines are decoded as JSON, and JSON is also YAML, which gives them
+	// the same node tree. A top-level YAML flow collection looks like JSON too,
+	// so the JSON error is only reported if the content isn't valid YAML either.
+	var jsonData interface{}
+	var jsonErr error
+	isJSON := IsJSON(content)
+	if isJSON {
+		jsonErr = json.Unmarshal(content, &jsonData)
+	}
+
 	var yamlNode yaml.Node
 	if err := yaml.Unmarshal(content, &yamlNode); err != nil {
+		if isJSON && jsonErr != nil {
+			return nil, jsonSyntaxError(content, jsonErr)
+		}
 		return nil, fmt.Errorf("failed to parse YAML: %w", err)
 	}
 
-	var yamlData interface{}
-	if err := yaml.Unmarshal(content, &yamlData); err != nil {
-		return nil, fmt.Errorf("failed to parse YAML data: %w", err)
+	yamlData := jsonData
+	if !isJSON || jsonErr != nil {
+		if err := yaml.Unmarshal(content, &yamlData); err != nil {
+			return nil, fmt.Errorf("failed to parse YAML data: %w", err)
+		}
 	}
 
 	jsonBytes, err := json.Marshal(yamlData)
@@ -35,13 +72,98 @@ func ParseYAML(content []byte) (*Pipeline, error) {
 		return nil, fmt.Errorf("failed to convert YAML to JSON: %w", err)
 	}
 
+	// An empty document decodes as an empty mapping so it's still validated
+	var data map[string]interface{}
+	if err := json.Unmarshal(jsonBytes, &data); err == nil && data == nil {
+		data = map[string]interface{}{}
+	}
+
 	return &Pipeline{
 		Content:   content,
 		JSONBytes: jsonBytes,
 		YAMLNode:  &yamlNode,
+		data:      data,
 	}, nil
 }
 
+// IsJSON reports whether content is a JSON pipeline, going by its first
+// non-whitespace character
+func IsJSON(content []byte) bool {
+	trimmed := bytes.TrimLeft(bytes.TrimPrefix(content, utf8BOM), " \t\r\n")
+	return len(trimmed) > 0 && (trimmed[0] == '{' || trimmed[0] == '[')
+}
+
+// jsonSyntaxError adds the line of a JSON syntax error to its message, since
+// encoding/json only reports a byte offset
+func jsonSyntaxError(content []byte, err error) error {
+	var syntaxErr *json.SyntaxError
+	if errors.As(err, &syntaxErr) {
+		offset := min(int(syntaxErr.Offset), len(content))
+		line := bytes.Count(content[:offset], []byte("\n")) + 1
+		return fmt.Errorf("failed to parse JSON: line %d: %w", line, err)
+	}
+	return fmt.Errorf("failed to parse JSON: %w", err)
+}
+
+// Data returns the pipeline decoded as JSON data, with numbers as float64, or
+// nil if its top level isn't a mapping. Callers must not modify it.
+func (p *Pipeline) Data() map[string]interface{} {
+	return p.data
+}
+
+// stringStepTypes are the step types that can be written as a bare string.
+// Any other string in a steps list is shorthand for a command step.
+var stringStepTypes = map[string]bool{
+	"block":  true,
+	"input":  true,
+	"manual": true,
+	"wait":   true,
+	"waiter": true,
+}
+
+// NormalizedJSON returns the pipeline as JSON with command shorthand steps (e.g.
+// `- "make test"`) expanded to `{"command": ...}`, so schema validation sees
+// them as the command steps Buildkite runs them as
+func (p *Pipeline) NormalizedJSON() []byte {
+	var data map[string]interface{}
+	if err := json.Unmarshal(p.JSONBytes, &data); err != nil {
+		return p.JSONBytes
+	}
+
+	steps, ok := data["steps"].([]interface{})
+	if !ok || !normalizeStringSteps(steps) {
+		return p.JSONBytes
+	}
+
+	normalized, err := json.Marshal(data)
+	if err != nil {
+		return p.JSONBytes
+	}
+	return normalized
+}
+
+// normalizeStringSteps expands command shorthand in steps and nested group
+// steps in place, reporting whether anything changed
+func normalizeStringSteps(steps []interface{}) bool {
+	changed := false
+
+	for i, step := range steps {
+		switch value := step.(type) {
+		case string:
+			if !stringStepTypes[value] {
+				steps[i] = map[string]interface{}{"command": value}
+				changed = true
+			}
+		case map[string]interface{}:
+			if nested, ok := value["steps"].([]interface{}); ok && normalizeStringSteps(nested) {
+				changed = true
+			}
+		}
+	}
+
+	return changed
+}
+
 func (p *Pipeline) FindNodeByPath(path []string) *yaml.Node {
 	if p.YAMLNode == nil || len(p.YAMLNode.Content) == 0 {
 		return nil
@@ -69,6 +191,293 @@ func findNodeRecursive(node *yaml.Node, path []string, depth int) *yaml.Node {
 	return nil
 }
 
+// PathPosition returns the zero-based position of the node at a dotted JSON
+// schema field path such as "steps.1.command". Mapping values are reported at
+// their key and list items at their content. It returns false for the root and
+// for paths that aren't in the document.
+func (p *Pipeline) PathPosition(field string) (Position, bool) {
+	if p.YAMLNode == nil || len(p.YAMLNode.Content) == 0 || field == "" || field == "(root)" {
+		return Position{}, false
+	}
+
+	node := p.YAMLNode.Content[0]
+	var position Position
+	for _, segment := range strings.Split(field, ".") {
+		if node.Kind == yaml.AliasNode && node.Alias != nil {
+			node = node.Alias
+		}
+
+		switch node.Kind {
+		case yaml.MappingNode:
+			var value *yaml.Node
+			for i := 0; i+1 < len(node.Content); i += 2 {
+				if key := node.Content[i]; key.Value == segment {
+					position = Position{Line: key.Line - 1, Character: key.Column - 1}
+					value = node.Content[i+1]
+					break
+				}
+			}
+			if value == nil {
+				return Position{}, false
+			}
+			node = value
+		case yaml.SequenceNode:
+			index, err := strconv.Atoi(segment)
+			if err != nil || index < 0 || index >= len(node.Content) {
+				return Position{}, false
+			}
+			node = node.Content[index]
+			position = Position{Line: node.Line - 1, Character: node.Column - 1}
+		default:
+			return Position{}, false
+		}
+	}
+
+	return position, true
+}
+
+// PluginNode is a plugin reference in a step's plugins and the zero-based
+// position of its first character
+type PluginNode struct {
+	Ref      string
+	Position Position
+	// Config is the plugin's configuration as JSON data, and Options the keys
+	// written at its top level. Both are empty for a bare reference.
+	Config  interface{}
+	Options []PluginOption
+}
+
+// PluginOption is a key at the top level of a plugin's configuration
+type PluginOption struct {
+	Name string
+	// Position is the zero-based position of the key, and Length its length as
+	// written, including any quotes
+	Position Position
+	Length   int
+}
+
+// PluginEntry is an item in a step's plugins list, or one reference and its
+// configuration when the step gives its plugins as a mapping
+type PluginEntry struct {
+	// Step and Index are the zero-based positions of the step in its steps list
+	// and of the entry in the step's plugins
+	Step  int
+	Index int
+	// Position is where the entry's content starts, after any "- " marker
+	Position Position
+	// Value is the entry as JSON data, which should be a reference or a mapping
+	// of one reference to its configuration
+	Value interface{}
+	// Plugins are the references written in the entry
+	Plugins []PluginNode
+}
+
+// PluginNodes returns the plugin references of every step, including steps in
+// groups, in document order. Plugins can be listed as bare references or as
+// single-key mappings, or given as a mapping of references to configuration.
+func (p *Pipeline) PluginNodes() []PluginNode {
+	var found []PluginNode
+	for _, entry := range p.PluginEntries() {
+		found = append(found, entry.Plugins...)
+	}
+	return found
+}
+
+// PluginEntries returns the entries in the plugins of every step, including
+// steps in groups, in document order, whether or not they're valid
+func (p *Pipeline) PluginEntries() []PluginEntry {
+	if p.YAMLNode == nil || len(p.YAMLNode.Content) == 0 {
+		return nil
+	}
+
+	var found []PluginEntry
+	collectPluginEntries(mappingValue(p.YAMLNode.Content[0], "steps", 0), &found, make(map[*yaml.Node]bool), 0)
+	return found
+}
+
+// maxNodeDepth guards the walks over aliases and nested steps against documents
+// whose aliases refer back to themselves
+const maxNodeDepth = 10
+
+// collectPluginEntries records the plugin entries of a list of steps. A
+// plugins node reached from several steps, such as one in an anchor merged
+// into each of them, is only recorded the first time.
+func collectPluginEntries(steps *yaml.Node, found *[]PluginEntry, seen map[*yaml.Node]bool, depth int) {
+	steps = resolveAlias(steps)
+	if steps == nil || steps.Kind != yaml.SequenceNode || depth > maxNodeDepth {
+		return
+	}
+
+	// Steps that are aliases are skipped, as their references are written
+	// where the anchor is rather than at each use
+	for stepIndex, step := range steps.Content {
+		if step.Kind != yaml.MappingNode {
+			continue
+		}
+
+		switch plugins := resolveAlias(mappingValue(step, "plugins", 0)); {
+		case plugins == nil || seen[plugins]:
+		case plugins.Kind == yaml.SequenceNode:
+			seen[plugins] = true
+			for index, item := range plugins.Content {
+				entry := PluginEntry{Step: stepIndex, Index: index, Position: nodePosition(item), Value: nodeData(item)}
+				switch item.Kind {
+				case yaml.ScalarNode:
+					entry.Plugins = appendPluginNode(entry.Plugins, item, nil)
+				case yaml.MappingNode:
+					for i := 0; i+1 < len(item.Content); i += 2 {
+						entry.Plugins = appendPluginNode(entry.Plugins, item.Content[i], item.Content[i+1])
+					}
+				}
+				*found = append(*found, entry)
+			}
+		case plugins.Kind == yaml.MappingNode:
+			seen[plugins] = true
+			for i := 0; i+1 < len(plugins.Content); i += 2 {
+				ref, config := plugins.Content[i], plugins.Content[i+1]
+				entry := PluginEntry{
+					Step:     stepIndex,
+					Index:    i / 2,
+					Position: nodePosition(ref),
+					Value:    map[string]interface{}{ref.Value: nodeData(config)},
+					Plugins:  appendPluginNode(nil, ref, config),
+				}
+				*found = append(*found, entry)
+			}
+		}
+
+		collectPluginEntries(mappingValue(step, "steps", 0), found, seen, depth+1)
+	}
+}
+
+// appendPluginNode records the plugin reference in ref, configured by config,
+// which is nil for a bare reference
+func appendPluginNode(nodes []PluginNode, ref, config *yaml.Node) []PluginNode {
+	if ref.Kind != yaml.ScalarNode || ref.Value == "" {
+		return nodes
+	}
+
+	position := nodePosition(ref)
+	if ref.Style&(yaml.DoubleQuotedStyle|yaml.SingleQuotedStyle) != 0 {
+		position.Character++
+	}
+
+	node := PluginNode{Ref: ref.Value, Position: position}
+	if config != nil {
+		node.Config = nodeData(config)
+		node.Options = pluginOptions(config)
+	}
+	return append(nodes, node)
+}
+
+// pluginOptions returns the keys written at the top level of a plugin's
+// configuration. Keys merged in with "<<" are written elsewhere, so aren't included.
+func pluginOptions(config *yaml.Node) []PluginOption {
+	config = resolveAlias(config)
+	if config == nil || config.Kind != yaml.MappingNode {
+		return nil
+	}
+
+	var options []PluginOption
+	for i := 0; i+1 < len(config.Content); i += 2 {
+		key := config.Content[i]
+		if key.Kind != yaml.ScalarNode || key.Value == "<<" {
+			continue
+		}
+
+		length := len(key.Value)
+		if key.Style&(yaml.DoubleQuotedStyle|yaml.SingleQuotedStyle) != 0 {
+			length += 2
+		}
+		options = append(options, PluginOption{Name: key.Value, Position: nodePosition(key), Length: length})
+	}
+	return options
+}
+
+// nodePosition returns the zero-based position of a node's first character
+func nodePosition(node *yaml.Node) Position {
+	return Position{Line: node.Line - 1, Character: node.Column - 1}
+}
+
+// nodeData decodes a node as JSON data, with numbers as float64 as in Data, or
+// returns nil if it can't be represented as JSON
+func nodeData(node *yaml.Node) interface{} {
+	var value interface{}
+	if err := node.Decode(&value); err != nil {
+		return nil
+	}
+
+	encoded, err := json.Marshal(value)
+	if err != nil {
+		return nil
+	}
+
+	var data interface{}
+	if err := json.Unmarshal(encoded, &data); err != nil {
+		return nil
+	}
+	return data
+}
+
+// mappingValue returns the value of key in a mapping, looking in mappings
+// merged with "<<" when the mapping doesn't set it itself
+func mappingValue(node *yaml.Node, key string, depth int) *yaml.Node {
+	node = resolveAlias(node)
+	if node == nil || node.Kind != yaml.MappingNode || depth > maxNodeDepth {
+		return nil
+	}
+
+	var merged []*yaml.Node
+	for i := 0; i+1 < len(node.Content); i += 2 {
+		switch node.Content[i].Value {
+		case key:
+			return node.Content[i+1]
+		case "<<":
+			merged = append(merged, node.Content[i+1])
+		}
+	}
+
+	for _, merge := range merged {
+		merge = resolveAlias(merge)
+		sources := []*yaml.Node{merge}
+		if merge != nil && merge.Kind == yaml.SequenceNode {
+			sources = merge.Content
+		}
+		for _, source := range sources {
+			if value := mappingValue(source, key, depth+1); value != nil {
+				return value
+			}
+		}
+	}
+
+	return nil
+}
+
+// resolveAlias returns the node an alias refers to, or node itself
+func resolveAlias(node *yaml.Node) *yaml.Node {
+	if node != nil && node.Kind == yaml.AliasNode {
+		return node.Alias
+	}
+	return node
+}
+
+// errorLinePattern finds the line number in YAML and JSON parse errors
+var errorLinePattern = regexp.MustCompile(`\bline (\d+)\b`)
+
+// ErrorLine returns the zero-based line a ParseYAML error points at, if the
+// parser reported one
+func ErrorLine(err error) (int, bool) {
+	match := errorLinePattern.FindStringSubmatch(err.Error())
+	if match == nil {
+		return 0, false
+	}
+	line, convErr := strconv.Atoi(match[1])
+	if convErr != nil || line < 1 {
+		return 0, false
+	}
+	return line - 1, true
+}
+
 func (p *Pipeline) GetLineForError(errorMsg string) int {
 	lines := strings.Split(string(p.Content), "\n")
 
@@ -1,6 +1,9 @@
 package parser
 
 import (
+	"encoding/json"
+	"reflect"
+	"slices"
 	"strings"
 	"testing"
 
@@ -77,6 +80,86 @@ func TestParseYAML_EmptyContent(t *testing.T) {
 	}
 }
 
+func TestParseYAML_StripsByteOrderMark(t *testing.T) {
+	content := append([]byte{0xEF, 0xBB, 0xBF}, []byte("steps:\n  - command: \"echo hello\"")...)
+
+	pipeline, err := ParseYAML(content)
+	if err != nil {
+		t.Fatalf("ParseYAML failed on BOM-prefixed content: %v", err)
+	}
+
+	if strings.HasPrefix(string(pipeline.Content), "\uFEFF") {
+		t.Error("Content should not start with a byte order mark")
+	}
+
+	if !strings.Contains(string(pipeline.JSONBytes), `"steps"`) {
+		t.Errorf("Expected 'steps' key in JSON, got %s", pipeline.JSONBytes)
+	}
+}
+
+func TestParseYAML_JSONPipeline(t *testing.T) {
+	content := []byte(`{
+  "env": {"DEBUG": "true"},
+  "steps": [
+    {"label": "Test step", "command": "echo hello", "parallelism": 2},
+    "wait"
+  ]
+}`)
+
+	if !IsJSON(content) {
+		t.Fatal("Expected content to be detected as JSON")
+	}
+
+	pipeline, err := ParseYAML(content)
+	if err != nil {
+		t.Fatalf("ParseYAML failed on a JSON pipeline: %v", err)
+	}
+
+	steps, ok := pipeline.Data()["steps"].([]interface{})
+	if !ok || len(steps) != 2 {
+		t.Fatalf("Expected two steps, got %v", pipeline.Data()["steps"])
+	}
+	if step, _ := steps[0].(map[string]interface{}); step["parallelism"] != float64(2) {
+		t.Errorf("Expected parallelism 2, got %v", step["parallelism"])
+	}
+
+	if node := pipeline.FindNodeByPath([]string{"steps"}); node == nil || node.Line != 3 {
+		t.Errorf("Expected the steps node on line 3, got %+v", node)
+	}
+}
+
+func TestParseYAML_InvalidJSON(t *testing.T) {
+	content := []byte(`{
+  "steps": [
+    {"command": "echo hello"},,
+  ]
+}`)
+
+	_, err := ParseYAML(content)
+	if err == nil {
+		t.Fatal("Expected error for invalid JSON")
+	}
+	if !strings.Contains(err.Error(), "failed to parse JSON: line 3") {
+		t.Errorf("Error should give the JSON error's line, got: %v", err)
+	}
+}
+
+func TestParseYAML_FlowMapping(t *testing.T) {
+	content := []byte(`{steps: [wait]}`)
+
+	if !IsJSON(content) {
+		t.Fatal("Expected a flow mapping to look like JSON")
+	}
+
+	pipeline, err := ParseYAML(content)
+	if err != nil {
+		t.Fatalf("ParseYAML should accept a YAML flow mapping, got: %v", err)
+	}
+	if steps, ok := pipeline.Data()["steps"].([]interface{}); !ok || len(steps) != 1 {
+		t.Errorf("Expected one step, got %v", pipeline.Data()["steps"])
+	}
+}
+
 func TestParseYAML_ComplexPipeline(t *testing.T) {
 	content := []byte(`env:
   NODE_ENV: production
@@ -124,6 +207,97 @@ steps:
 	}
 }
 
+func TestPipeline_NormalizedJSON(t *testing.T) {
+	content := []byte(`steps:
+  - "make build"
+  - wait
+  - group: "Tests"
+    steps:
+      - "make test"
+      - block
+  - command: "make deploy"`)
+
+	pipeline, err := ParseYAML(content)
+	if err != nil {
+		t.Fatalf("ParseYAML failed: %v", err)
+	}
+
+	var data struct {
+		Steps []interface{} `json:"steps"`
+	}
+	if err := json.Unmarshal(pipeline.NormalizedJSON(), &data); err != nil {
+		t.Fatalf("Failed to decode normalized JSON: %v", err)
+	}
+
+	if step, ok := data.Steps[0].(map[string]interface{}); !ok || step["command"] != "make build" {
+		t.Errorf("Expected command shorthand to become a command step, got %#v", data.Steps[0])
+	}
+	if data.Steps[1] != "wait" {
+		t.Errorf("Expected the wait keyword to stay a string, got %#v", data.Steps[1])
+	}
+
+	nested := data.Steps[2].(map[string]interface{})["steps"].([]interface{})
+	if step, ok := nested[0].(map[string]interface{}); !ok || step["command"] != "make test" {
+		t.Errorf("Expected group shorthand to become a command step, got %#v", nested[0])
+	}
+	if nested[1] != "block" {
+		t.Errorf("Expected the block keyword to stay a string, got %#v", nested[1])
+	}
+
+	plain, err := ParseYAML([]byte("steps:\n  - wait\n"))
+	if err != nil {
+		t.Fatalf("ParseYAML failed: %v", err)
+	}
+	if string(plain.NormalizedJSON()) != string(plain.JSONBytes) {
+		t.Error("Expected JSON without shorthand steps to be returned unchanged")
+	}
+}
+
+func TestPipeline_Data(t *testing.T) {
+	tests := []struct {
+		name     string
+		content  string
+		expected map[string]interface{}
+	}{
+		{
+			name:     "mapping",
+			content:  "steps:\n  - command: make\ntimeout_in_minutes: 10",
+			expected: map[string]interface{}{"steps": []interface{}{map[string]interface{}{"command": "make"}}, "timeout_in_minutes": float64(10)},
+		},
+		{
+			name:     "empty document",
+			content:  "",
+			expected: map[string]interface{}{},
+		},
+		{
+			name:    "top-level list",
+			content: "- command: make",
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			pipeline, err := ParseYAML([]byte(tt.content))
+			if err != nil {
+				t.Fatalf("ParseYAML failed: %v", err)
+			}
+
+			data := pipeline.Data()
+			if tt.expected == nil {
+				if data != nil {
+					t.Errorf("Expected no data, got %v", data)
+				}
+				return
+			}
+			got, _ := json.Marshal(data)
+			want, _ := json.Marshal(tt.expected)
+			if data == nil || string(got) != string(want) {
+				t.Errorf("Expected %s, got %s", want, got)
+			}
+		})
+	}
+}
+
 func TestPipeline_FindNodeByPath_TopLevel(t *testing.T) {
 	content := []byte(`steps:
   - label: "test"
@@ -261,6 +435,166 @@ func TestFindNodeRecursive_DifferentNodeTypes(t *testing.T) {
 	}
 }
 
+func TestPipeline_PathPosition(t *testing.T) {
+	content := []byte(`env:
+  CI: "true"
+steps:
+  - label: "Build"
+    command: "make"
+  - group: "Tests"
+    steps:
+      - wait: ~
+      -   label: "Lint"`)
+
+	pipeline, err := ParseYAML(content)
+	if err != nil {
+		t.Fatalf("ParseYAML failed: %v", err)
+	}
+
+	tests := []struct {
+		field    string
+		expected Position
+		found    bool
+	}{
+		{field: "steps.1", expected: Position{Line: 5, Character: 4}, found: true},
+		{field: "steps.0.command", expected: Position{Line: 4, Character: 4}, found: true},
+		{field: "steps.1.steps.1", expected: Position{Line: 8, Character: 10}, found: true},
+		{field: "env.CI", expected: Position{Line: 1, Character: 2}, found: true},
+		{field: "steps.5"},
+		{field: "steps.0.missing"},
+		{field: "(root)"},
+	}
+
+	for _, tt := range tests {
+		position, found := pipeline.PathPosition(tt.field)
+		if found != tt.found || position != tt.expected {
+			t.Errorf("PathPosition(%q): expected %+v (found %v), got %+v (found %v)", tt.field, tt.expected, tt.found, position, found)
+		}
+	}
+}
+
+func TestPipeline_PluginNodes(t *testing.T) {
+	content := []byte(`defaults: &defaults
+  plugins:
+    - "my-org/cache#v1.0.0"
+steps:
+  - label: "Build"
+    plugins:
+      - docker#v5.13.0:
+          image: "golang:1.22"
+      - ./.buildkite/plugins/local
+  - group: "Tests"
+    steps:
+      - command: "make test"
+        plugins:
+          docker-compose#v4.7.0:
+            run: app
+  - <<: *defaults
+    command: "make lint"
+  - <<: *defaults
+    command: "make vet"
+  - wait`)
+
+	pipeline, err := ParseYAML(content)
+	if err != nil {
+		t.Fatalf("ParseYAML failed: %v", err)
+	}
+
+	type located struct {
+		Ref      string
+		Position Position
+	}
+	expected := []located{
+		{Ref: "docker#v5.13.0", Position: Position{Line: 6, Character: 8}},
+		{Ref: "./.buildkite/plugins/local", Position: Position{Line: 8, Character: 8}},
+		{Ref: "docker-compose#v4.7.0", Position: Position{Line: 13, Character: 10}},
+		// Recorded once however many steps merge the anchor
+		{Ref: "my-org/cache#v1.0.0", Position: Position{Line: 2, Character: 7}},
+	}
+
+	nodes := pipeline.PluginNodes()
+	var got []located
+	for _, node := range nodes {
+		got = append(got, located{Ref: node.Ref, Position: node.Position})
+	}
+	if !slices.Equal(got, expected) {
+		t.Errorf("Expected %+v, got %+v", expected, got)
+	}
+
+	docker := nodes[0]
+	if !reflect.DeepEqual(docker.Config, map[string]interface{}{"image": "golang:1.22"}) {
+		t.Errorf("Expected the docker configuration, got %#v", docker.Config)
+	}
+	expectedOptions := []PluginOption{{Name: "image", Position: Position{Line: 7, Character: 10}, Length: 5}}
+	if !slices.Equal(docker.Options, expectedOptions) {
+		t.Errorf("Expected options %+v, got %+v", expectedOptions, docker.Options)
+	}
+	if nodes[1].Config != nil || nodes[1].Options != nil {
+		t.Errorf("Expected a bare reference to have no configuration, got %+v", nodes[1])
+	}
+}
+
+func TestPipeline_PluginEntries(t *testing.T) {
+	// Steps written at column 0 are found like any others
+	content := []byte(`steps:
+- command: "make"
+  plugins:
+    - docker#v5.13.0:
+        "image": "node:18"
+      docker-compose#v4.0.0:
+        run: app
+    - {}
+- label: "Upload"
+  plugins:
+    - artifacts#v1.9.0`)
+
+	pipeline, err := ParseYAML(content)
+	if err != nil {
+		t.Fatalf("ParseYAML failed: %v", err)
+	}
+
+	entries := pipeline.PluginEntries()
+	if len(entries) != 3 {
+		t.Fatalf("Expected 3 entries, got %+v", entries)
+	}
+
+	merged := entries[0]
+	if merged.Step != 0 || merged.Index != 0 || merged.Position != (Position{Line: 3, Character: 6}) || len(merged.Plugins) != 2 {
+		t.Errorf("Unexpected entry for the merged plugins: %+v", merged)
+	}
+	if option := merged.Plugins[0].Options[0]; option.Name != "image" || option.Length != 7 {
+		t.Errorf("Expected the quoted option's length to include its quotes, got %+v", option)
+	}
+
+	empty := entries[1]
+	if value, ok := empty.Value.(map[string]interface{}); !ok || len(value) != 0 || len(empty.Plugins) != 0 {
+		t.Errorf("Expected an empty entry with no plugins, got %+v", empty)
+	}
+
+	if bare := entries[2]; bare.Step != 1 || bare.Value != "artifacts#v1.9.0" || bare.Position.Line != 10 {
+		t.Errorf("Unexpected entry for the unindented step: %+v", bare)
+	}
+}
+
+func TestErrorLine(t *testing.T) {
+	_, err := ParseYAML([]byte("steps:\n  - label: \"Build\"\n    command: [make\n"))
+	if err == nil {
+		t.Fatal("Expected a parse error")
+	}
+	if line, ok := ErrorLine(err); !ok || line < 2 {
+		t.Errorf("Expected the error to point at the unterminated list, got line %d (ok %v) from %v", line, ok, err)
+	}
+
+	// The object is never closed, so the error is at the end of the input
+	_, err = ParseYAML([]byte("{\n  \"steps\": [\n    {\"command\": \"make\"}\n"))
+	if err == nil {
+		t.Fatal("Expected a JSON parse error")
+	}
+	if line, ok := ErrorLine(err); !ok || line != 3 {
+		t.Errorf("Expected the JSON error on line 3, got %d (ok %v) from %v", line, ok, err)
+	}
+}
+
 func TestGetLineForError_KnownPatterns(t *testing.T) {
 	content := []byte(`steps:
   - label: "test"
@@ -395,3 +729,43 @@ func TestPosition_Structure(t *testing.T) {
 		t.Errorf("Expected Character 25, got %d", pos.Character)
 	}
 }
+
+func FuzzParseYAML(f *testing.F) {
+	seeds := []string{
+		"",
+		"steps:\n  - command: \"echo hello\"\n",
+		"steps:\n  - label: test\n    command: [unclosed",
+		"\xef\xbb\xbfsteps: []\n",
+		"? [complex, key]\n: value\n",
+		"1: numeric key\n",
+		"anchor: &a [*a]\n",
+		"steps:\n  - plugins:\n      - docker#v5.13.0: {image: alpine}\n",
+		"steps:\n\t- tab indented\n",
+		"key: \"\\xff\"\n",
+		"- - - -\n",
+		"{{{{",
+		"\x00\xff\xfe",
+	}
+	for _, seed := range seeds {
+		f.Add([]byte(seed))
+	}
+
+	f.Fuzz(func(t *testing.T, content []byte) {
+		pipeline, err := ParseYAML(content)
+		if err != nil {
+			if pipeline != nil {
+				t.Errorf("Expected nil pipeline alongside error %v", err)
+			}
+			return
+		}
+
+		if !json.Valid(pipeline.JSONBytes) {
+			t.Errorf("Expected valid JSON, got %q", pipeline.JSONBytes)
+		}
+
+		pipeline.FindNodeByPath([]string{"steps", "label"})
+		pipeline.GetLineForError("evaluation failed")
+		pipeline.PluginNodes()
+		pipeline.PluginEntries()
+	})
+}
@@ -0,0 +1,174 @@
+package plugins
+
+import (
+	"encoding/json"
+	"fmt"
+	"io"
+	"net/http"
+	"os"
+	"strings"
+	"time"
+
+	"github.com/mcncl/buildkite-ls/internal/schema"
+)
+
+// catalogTimeout bounds how long fetching a remote plugin catalog may take
+const catalogTimeout = 10 * time.Second
+
+// CatalogEntry describes one plugin in an external plugin catalog. Name is the
+// plugin reference without a version (e.g. "docker" or "my-org/deploy"),
+// Versions lists releases newest first and Configuration is the plugin's
+// configuration JSON schema, as in plugin.yml.
+type CatalogEntry struct {
+	Name          string         `json:"name"`
+	Description   string         `json:"description"`
+	Repository    string         `json:"repository"`
+	Versions      []string       `json:"versions"`
+	Configuration map[string]any `json:"configuration"`
+}
+
+// ParseCatalog decodes a plugin catalog, a JSON array of CatalogEntry
+func ParseCatalog(data []byte) ([]CatalogEntry, error) {
+	var entries []CatalogEntry
+	if err := json.Unmarshal(data, &entries); err != nil {
+		return nil, fmt.Errorf("failed to parse plugin catalog: %w", err)
+	}
+
+	for i, entry := range entries {
+		if strings.TrimSpace(entry.Name) == "" {
+			return nil, fmt.Errorf("plugin catalog entry %d has no name", i+1)
+		}
+		if strings.Contains(entry.Name, "#") {
+			return nil, fmt.Errorf("plugin catalog entry %q should not include a version", entry.Name)
+		}
+	}
+
+	return entries, nil
+}
+
+// LoadCatalog reads a plugin catalog from an http(s) URL, a file:// URL or a
+// local path and uses it in place of the bundled plugin list
+func (r *Registry) LoadCatalog(location string) error {
+	var data []byte
+	path, isLocal, err := schema.LocalPath(location)
+	if !isLocal {
+		data, err = fetchCatalog(location)
+	} else if err == nil {
+		data, err = os.ReadFile(path)
+	}
+	if err != nil {
+		return fmt.Errorf("failed to load plugin catalog from %s: %w", location, err)
+	}
+
+	entries, err := ParseCatalog(data)
+	if err != nil {
+		return err
+	}
+
+	r.SetCatalog(entries)
+	return nil
+}
+
+func fetchCatalog(url string) ([]byte, error) {
+	client := &http.Client{Timeout: catalogTimeout}
+	resp, err := client.Get(url)
+	if err != nil {
+		return nil, err
+	}
+	defer func() { _ = resp.Body.Close() }()
+
+	if resp.StatusCode != http.StatusOK {
+		return nil, fmt.Errorf("HTTP %d from %s", resp.StatusCode, url)
+	}
+
+	return io.ReadAll(resp.Body)
+}
+
+// SetCatalog replaces the bundled plugin list with entries. Plugins with a
+// configuration schema are validated against it instead of fetching plugin.yml.
+func (r *Registry) SetCatalog(entries []CatalogEntry) {
+	r.mu.Lock()
+	defer r.mu.Unlock()
+	r.catalog = entries
+}
+
+// PopularPlugins returns the plugins offered in completion, from the loaded
+// catalog when there is one and the bundled list otherwise
+func (r *Registry) PopularPlugins() []PopularPlugin {
+	r.mu.RLock()
+	defer r.mu.RUnlock()
+
+	if r.catalog == nil {
+		return GetPopularPlugins()
+	}
+
+	popular := make([]PopularPlugin, 0, len(r.catalog))
+	for _, entry := range r.catalog {
+		version := "latest"
+		if len(entry.Versions) > 0 {
+			version = entry.Versions[0]
+		}
+		popular = append(popular, PopularPlugin{
+			Name:        entry.Name,
+			Version:     version,
+			Description: entry.Description,
+		})
+	}
+	return popular
+}
+
+// RepositoryURL returns the repository of the plugin named by ref, preferring
+// the loaded catalog over the GitHub naming convention
+func (r *Registry) RepositoryURL(ref string) string {
+	r.mu.RLock()
+	entry := r.catalogEntryLocked(ref)
+	r.mu.RUnlock()
+
+	if entry != nil && entry.Repository != "" {
+		return entry.Repository
+	}
+
+	parsed := ParsePluginReference(ref)
+	if parsed == nil {
+		return ""
+	}
+	return parsed.GetRepositoryURL()
+}
+
+// catalogEntryLocked finds the catalog entry for ref, ignoring its version.
+// The caller must hold r.mu.
+func (r *Registry) catalogEntryLocked(ref string) *CatalogEntry {
+	parsed := ParsePluginReference(ref)
+	if parsed == nil {
+		return nil
+	}
+
+	for i := range r.catalog {
+		candidate := ParsePluginReference(r.catalog[i].Name)
+		if candidate != nil && candidate.Org == parsed.Org && candidate.Name == parsed.Name {
+			return &r.catalog[i]
+		}
+	}
+	return nil
+}
+
+// catalogSchemaLocked builds a plugin schema from the catalog entry for ref.
+// The caller must hold r.mu.
+func (r *Registry) catalogSchemaLocked(ref string) (*PluginSchema, bool) {
+	entry := r.catalogEntryLocked(ref)
+	if entry == nil || entry.Configuration == nil {
+		return nil, false
+	}
+
+	schemaData, err := json.Marshal(entry.Configuration)
+	if err != nil {
+		return nil, false
+	}
+
+	return &PluginSchema{
+		Name:          entry.Name,
+		Description:   entry.Description,
+		Configuration: entry.Configuration,
+		SchemaData:    schemaData,
+	}, true
+}
@@ -0,0 +1,116 @@
+package plugins
+
+import (
+	"net/url"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+)
+
+const testCatalog = `[
+  {
+    "name": "docker",
+    "description": "Run steps in Docker",
+    "repository": "https://git.example.com/plugins/docker",
+    "versions": ["v6.0.0", "v5.13.0"],
+    "configuration": {
+      "properties": {"image": {"type": "string"}},
+      "required": ["image"]
+    }
+  },
+  {
+    "name": "my-org/deploy",
+    "description": "Deploy the app"
+  }
+]`
+
+func TestParseCatalog(t *testing.T) {
+	entries, err := ParseCatalog([]byte(testCatalog))
+	if err != nil {
+		t.Fatalf("ParseCatalog failed: %v", err)
+	}
+	if len(entries) != 2 || entries[1].Name != "my-org/deploy" {
+		t.Errorf("Unexpected entries: %+v", entries)
+	}
+
+	for _, invalid := range []string{`{"name": "docker"}`, `[{"description": "no name"}]`, `[{"name": "docker#v1.0.0"}]`} {
+		if _, err := ParseCatalog([]byte(invalid)); err == nil {
+			t.Errorf("Expected an error parsing %s", invalid)
+		}
+	}
+}
+
+func TestRegistry_LoadCatalog(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "plugins.json")
+	if err := os.WriteFile(path, []byte(testCatalog), 0o644); err != nil {
+		t.Fatalf("Failed to write catalog: %v", err)
+	}
+
+	registry := NewRegistry()
+	if err := registry.LoadCatalog(path); err != nil {
+		t.Fatalf("LoadCatalog failed: %v", err)
+	}
+
+	popular := registry.PopularPlugins()
+	if len(popular) != 2 {
+		t.Fatalf("Expected the catalog to replace the bundled list, got %+v", popular)
+	}
+	if popular[0].Version != "v6.0.0" || popular[1].Version != "latest" {
+		t.Errorf("Unexpected versions: %+v", popular)
+	}
+
+	// Catalog schemas are used without fetching plugin.yml
+	schema, err := registry.GetPluginSchema("docker#v5.13.0")
+	if err != nil {
+		t.Fatalf("GetPluginSchema failed: %v", err)
+	}
+	if schema.Description != "Run steps in Docker" || !strings.Contains(string(schema.SchemaData), "image") {
+		t.Errorf("Unexpected schema: %+v", schema)
+	}
+	if err := registry.ValidatePluginConfig("docker#v6.0.0", map[string]interface{}{}); err == nil {
+		t.Error("Expected the catalog schema to require 'image'")
+	}
+
+	if url := registry.RepositoryURL("docker#v6.0.0"); url != "https://git.example.com/plugins/docker" {
+		t.Errorf("Expected the catalog repository, got %s", url)
+	}
+	if url := registry.RepositoryURL("my-org/deploy#v1.0.0"); url != "https://github.com/my-org/deploy-buildkite-plugin" {
+		t.Errorf("Expected the conventional repository, got %s", url)
+	}
+}
+
+func TestRegistry_LoadCatalogFileURL(t *testing.T) {
+	dir := filepath.Join(t.TempDir(), "plugin catalogs")
+	if err := os.Mkdir(dir, 0o755); err != nil {
+		t.Fatal(err)
+	}
+	path := filepath.Join(dir, "plugins.json")
+	if err := os.WriteFile(path, []byte(testCatalog), 0o644); err != nil {
+		t.Fatalf("Failed to write catalog: %v", err)
+	}
+
+	fileURL := (&url.URL{Scheme: "file", Path: "/" + strings.TrimPrefix(filepath.ToSlash(path), "/")}).String()
+	if !strings.Contains(fileURL, "%20") {
+		t.Fatalf("Expected the file URL to be percent-encoded, got %s", fileURL)
+	}
+
+	registry := NewRegistry()
+	if err := registry.LoadCatalog(fileURL); err != nil {
+		t.Fatalf("LoadCatalog failed: %v", err)
+	}
+	if len(registry.PopularPlugins()) != 2 {
+		t.Errorf("Expected the catalog from %s, got %+v", fileURL, registry.PopularPlugins())
+	}
+}
+
+func TestRegistry_LoadCatalogFailureKeepsBundledList(t *testing.T) {
+	registry := NewRegistry()
+
+	if err := registry.LoadCatalog(filepath.Join(t.TempDir(), "missing.json")); err == nil {
+		t.Fatal("Expected an error loading a missing catalog")
+	}
+	if len(registry.PopularPlugins()) != len(GetPopularPlugins()) {
+		t.Error("Expected the bundled plugin list after a failed load")
+	}
+}
@@ -63,6 +63,7 @@ type Registry struct {
 	plugins    map[string]*CachedPluginSchema // Cache with expiration
 	cacheTTL   time.Duration                  // How long to cache schemas
 	maxRetries int                            // Maximum retry attempts for failed requests
+	catalog    []CatalogEntry                 // Plugin catalog replacing the bundled list, if loaded
 }
 
 func NewRegistry() *Registry {
@@ -84,6 +85,10 @@ func NewRegistryWithTTL(ttl time.Duration) *Registry {
 
 func (r *Registry) GetPluginSchema(pluginName string) (*PluginSchema, error) {
 	r.mu.RLock()
+	if schema, ok := r.catalogSchemaLocked(pluginName); ok {
+		r.mu.RUnlock()
+		return schema, nil
+	}
 	if cached, exists := r.plugins[pluginName]; exists {
 		// Check if cache is still valid
 		if !cached.IsExpired() {
@@ -232,16 +237,37 @@ func (r *Registry) ValidatePluginConfig(pluginName string, config interface{}) e
 	}
 
 	if !result.Valid() {
-		// Return the first validation error
-		if len(result.Errors()) > 0 {
-			return fmt.Errorf("plugin %s configuration error: %s", pluginName, result.Errors()[0].Description())
+		// Return the first validation error. Unknown top-level options are
+		// reported individually by the language server, with suggestions.
+		for _, resultErr := range result.Errors() {
+			if resultErr.Type() == "additional_property_not_allowed" && resultErr.Context().String() == "(root)" {
+				continue
+			}
+			return fmt.Errorf("plugin %s configuration error: %s", pluginName, resultErr.Description())
+		}
+		if len(result.Errors()) == 0 {
+			return fmt.Errorf("plugin %s configuration is invalid", pluginName)
 		}
-		return fmt.Errorf("plugin %s configuration is invalid", pluginName)
 	}
 
 	return nil
 }
 
+// LoadedPluginSchema returns the schema for ref from the catalog or the cache,
+// without fetching it, or nil if it isn't available yet
+func (r *Registry) LoadedPluginSchema(ref string) *PluginSchema {
+	r.mu.RLock()
+	defer r.mu.RUnlock()
+
+	if schema, ok := r.catalogSchemaLocked(ref); ok {
+		return schema
+	}
+	if cached, exists := r.plugins[ref]; exists && !cached.IsExpired() {
+		return cached.Schema
+	}
+	return nil
+}
+
 // ParsePluginFromStep extracts plugin information from a pipeline step
 func ParsePluginFromStep(stepData map[string]interface{}) []PluginReference {
 	var plugins []PluginReference
@@ -195,6 +195,62 @@ func TestRegistry_InvalidateCache(t *testing.T) {
 	}
 }
 
+func TestRegistry_LoadedPluginSchema(t *testing.T) {
+	registry := NewRegistry()
+
+	if schema := registry.LoadedPluginSchema("docker#v5.13.0"); schema != nil {
+		t.Errorf("Expected no schema before one is loaded, got %+v", schema)
+	}
+
+	now := time.Now()
+	registry.plugins["docker#v5.13.0"] = &CachedPluginSchema{
+		Schema:    &PluginSchema{Name: "docker"},
+		CachedAt:  now,
+		ExpiresAt: now.Add(time.Hour),
+	}
+	registry.plugins["cache#v1.7.0"] = &CachedPluginSchema{
+		Schema:    &PluginSchema{Name: "cache"},
+		CachedAt:  now.Add(-2 * time.Hour),
+		ExpiresAt: now.Add(-time.Hour),
+	}
+	registry.SetCatalog([]CatalogEntry{{
+		Name:          "acme/deploy",
+		Configuration: map[string]any{"properties": map[string]any{}},
+	}})
+
+	if schema := registry.LoadedPluginSchema("docker#v5.13.0"); schema == nil || schema.Name != "docker" {
+		t.Errorf("Expected the cached docker schema, got %+v", schema)
+	}
+	if schema := registry.LoadedPluginSchema("cache#v1.7.0"); schema != nil {
+		t.Errorf("Expected an expired schema to be ignored, got %+v", schema)
+	}
+	if schema := registry.LoadedPluginSchema("acme/deploy#v2.0.0"); schema == nil || schema.Name != "acme/deploy" {
+		t.Errorf("Expected the catalog schema for acme/deploy, got %+v", schema)
+	}
+}
+
+func TestRegistry_ValidatePluginConfigSkipsUnknownOptions(t *testing.T) {
+	registry := NewRegistry()
+	registry.SetCatalog([]CatalogEntry{{
+		Name: "acme/deploy",
+		Configuration: map[string]any{
+			"properties": map[string]any{
+				"region": map[string]any{"type": "string"},
+			},
+			"additionalProperties": false,
+		},
+	}})
+
+	// Unknown options are reported separately, so only other errors remain
+	if err := registry.ValidatePluginConfig("acme/deploy#v1.0.0", map[string]any{"regoin": "us-east-1"}); err != nil {
+		t.Errorf("Expected unknown options to be skipped, got %v", err)
+	}
+	err := registry.ValidatePluginConfig("acme/deploy#v1.0.0", map[string]any{"regoin": "us-east-1", "region": 1})
+	if err == nil || !strings.Contains(err.Error(), "Invalid type") {
+		t.Errorf("Expected a type error for region, got %v", err)
+	}
+}
+
 func TestParsePluginFromStep(t *testing.T) {
 	tests := []struct {
 		name     string
@@ -0,0 +1,20 @@
+package plugins
+
+// configSnippets holds a ready-made configuration for the most common official
+// plugins, as snippet text relative to the plugin's configuration block
+var configSnippets = map[string]string{
+	"docker-compose": "run: ${1:app}\nconfig: ${2:docker-compose.yml}\nenv:\n  - ${3:BUILDKITE_BUILD_NUMBER}",
+	"artifacts":      "upload: \"${1:build/**/*}\"\ndownload: \"${2:dist/*}\"",
+}
+
+// ConfigSnippet returns the configuration snippet for a plugin reference such
+// as "docker-compose#v5.10.0", if the plugin has one
+func ConfigSnippet(ref string) (string, bool) {
+	parsed := ParsePluginReference(ref)
+	if parsed == nil || parsed.Org != "buildkite-plugins" {
+		return "", false
+	}
+
+	snippet, ok := configSnippets[parsed.Name]
+	return snippet, ok
+}
@@ -0,0 +1,28 @@
+package plugins
+
+import (
+	"strings"
+	"testing"
+)
+
+func TestConfigSnippet(t *testing.T) {
+	snippet, ok := ConfigSnippet("docker-compose#v5.10.0")
+	if !ok {
+		t.Fatal("Expected a snippet for docker-compose")
+	}
+	for _, key := range []string{"run:", "config:", "env:"} {
+		if !strings.Contains(snippet, key) {
+			t.Errorf("Expected the docker-compose snippet to contain %q, got %q", key, snippet)
+		}
+	}
+
+	if snippet, ok := ConfigSnippet("artifacts"); !ok || !strings.Contains(snippet, "upload:") || !strings.Contains(snippet, "download:") {
+		t.Errorf("Expected an upload/download snippet for artifacts, got %q", snippet)
+	}
+
+	for _, ref := range []string{"", "shellcheck#v1.4.0", "my-org/docker-compose#v1.0.0"} {
+		if _, ok := ConfigSnippet(ref); ok {
+			t.Errorf("Expected no snippet for %q", ref)
+		}
+	}
+}
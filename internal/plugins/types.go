@@ -0,0 +1,125 @@
+package plugins
+
+import "math"
+
+// optionTypes is a mini-schema of the official plugins' options, giving the
+// JSON schema types each option accepts. Options that take several shapes that
+// a type alone can't describe are left out.
+var optionTypes = map[string]map[string][]string{
+	"docker": {
+		"add-caps":              {"array"},
+		"add-host":              {"array"},
+		"always-pull":           {"boolean"},
+		"command":               {"array"},
+		"cpus":                  {"string"},
+		"debug":                 {"boolean"},
+		"devices":               {"array"},
+		"entrypoint":            {"string"},
+		"environment":           {"array"},
+		"image":                 {"string"},
+		"init":                  {"boolean"},
+		"interactive":           {"boolean"},
+		"leave-container":       {"boolean"},
+		"memory":                {"string"},
+		"mount-buildkite-agent": {"boolean"},
+		"mount-checkout":        {"boolean"},
+		"mount-ssh-agent":       {"boolean"},
+		"network":               {"string"},
+		"platform":              {"string"},
+		"privileged":            {"boolean"},
+		"propagate-environment": {"boolean"},
+		"propagate-uid-gid":     {"boolean"},
+		"publish":               {"array"},
+		"pull-retries":          {"integer"},
+		"run-labels":            {"boolean"},
+		"runtime":               {"string"},
+		"skip-checkout":         {"boolean"},
+		"tmpfs":                 {"array"},
+		"tty":                   {"boolean"},
+		"ulimits":               {"array"},
+		"user":                  {"string"},
+		"volumes":               {"array"},
+		"workdir":               {"string"},
+	},
+	"docker-compose": {
+		"args":                  {"array"},
+		"build":                 {"string", "array"},
+		"buildkit":              {"boolean"},
+		"cache-from":            {"array"},
+		"command":               {"array"},
+		"config":                {"string", "array"},
+		"dependencies":          {"boolean"},
+		"entrypoint":            {"string"},
+		"env":                   {"array"},
+		"environment":           {"array"},
+		"graceful-shutdown":     {"boolean"},
+		"image-name":            {"string"},
+		"image-repository":      {"string"},
+		"leave-volumes":         {"boolean"},
+		"mount-buildkite-agent": {"boolean"},
+		"propagate-environment": {"boolean"},
+		"pull":                  {"string", "array"},
+		"push":                  {"string", "array"},
+		"run":                   {"string"},
+		"run-labels":            {"boolean"},
+		"secrets":               {"array"},
+		"skip-checkout":         {"boolean"},
+		"skip-pull":             {"boolean"},
+		"target":                {"string"},
+		"tty":                   {"boolean"},
+		"user":                  {"string"},
+		"volumes":               {"array"},
+		"wait":                  {"boolean"},
+		"workdir":               {"string"},
+	},
+}
+
+// OptionTypes returns the JSON schema types accepted by each option of a plugin
+// reference such as "docker#v5.13.0", if the plugin's options are bundled
+func OptionTypes(ref string) (map[string][]string, bool) {
+	parsed := ParsePluginReference(ref)
+	if parsed == nil || parsed.Org != "buildkite-plugins" {
+		return nil, false
+	}
+
+	types, ok := optionTypes[parsed.Name]
+	return types, ok
+}
+
+// HasType reports whether a value decoded from YAML has one of the given JSON
+// schema types
+func HasType(value any, types []string) bool {
+	for _, expected := range types {
+		switch v := value.(type) {
+		case string:
+			if expected == "string" {
+				return true
+			}
+		case bool:
+			if expected == "boolean" {
+				return true
+			}
+		case int, int64, uint64:
+			if expected == "integer" || expected == "number" {
+				return true
+			}
+		case float64:
+			if expected == "number" || (expected == "integer" && v == math.Trunc(v)) {
+				return true
+			}
+		case []any:
+			if expected == "array" {
+				return true
+			}
+		case map[string]any:
+			if expected == "object" {
+				return true
+			}
+		case nil:
+			if expected == "null" {
+				return true
+			}
+		}
+	}
+	return false
+}
@@ -0,0 +1,47 @@
+package plugins
+
+import "testing"
+
+func TestOptionTypes(t *testing.T) {
+	types, ok := OptionTypes("docker#v5.13.0")
+	if !ok {
+		t.Fatal("Expected bundled option types for docker")
+	}
+	if got := types["environment"]; len(got) != 1 || got[0] != "array" {
+		t.Errorf("Expected docker environment to be an array, got %v", got)
+	}
+
+	if types, ok := OptionTypes("buildkite-plugins/docker-compose#v5.10.0"); !ok || len(types["config"]) != 2 {
+		t.Errorf("Expected docker-compose config to accept a string or an array, got %v", types["config"])
+	}
+
+	for _, ref := range []string{"", "cache#v1.7.0", "my-org/docker#v1.0.0"} {
+		if _, ok := OptionTypes(ref); ok {
+			t.Errorf("Expected no option types for %q", ref)
+		}
+	}
+}
+
+func TestHasType(t *testing.T) {
+	tests := []struct {
+		value    any
+		types    []string
+		expected bool
+	}{
+		{value: "node:20", types: []string{"string"}, expected: true},
+		{value: []any{"CI"}, types: []string{"string", "array"}, expected: true},
+		{value: "CI=true", types: []string{"array"}, expected: false},
+		{value: true, types: []string{"boolean"}, expected: true},
+		{value: "true", types: []string{"boolean"}, expected: false},
+		{value: 3, types: []string{"integer"}, expected: true},
+		{value: 3.5, types: []string{"integer"}, expected: false},
+		{value: map[string]any{"a": 1}, types: []string{"array"}, expected: false},
+		{value: nil, types: []string{"string"}, expected: false},
+	}
+
+	for _, tt := range tests {
+		if got := HasType(tt.value, tt.types); got != tt.expected {
+			t.Errorf("HasType(%#v, %v): expected %v, got %v", tt.value, tt.types, tt.expected, got)
+		}
+	}
+}
@@ -0,0 +1,271 @@
+package schema
+
+import (
+	"encoding/json"
+	"fmt"
+	"maps"
+	"reflect"
+	"slices"
+)
+
+// Stats summarizes the contents of a pipeline schema
+type Stats struct {
+	Definitions     int
+	DocumentedPaths int
+}
+
+// Inspect parses schema data and counts its definitions and documented properties
+func Inspect(data []byte) (*Stats, error) {
+	var root map[string]interface{}
+	if err := json.Unmarshal(data, &root); err != nil {
+		return nil, fmt.Errorf("failed to parse schema: %w", err)
+	}
+
+	stats := &Stats{}
+	for _, key := range []string{"definitions", "$defs"} {
+		if definitions, ok := root[key].(map[string]interface{}); ok {
+			stats.Definitions += len(definitions)
+		}
+	}
+
+	stats.DocumentedPaths = countDocumentedProperties(root)
+	return stats, nil
+}
+
+// countDocumentedProperties counts properties with a description anywhere in the schema
+func countDocumentedProperties(node interface{}) int {
+	count := 0
+
+	switch value := node.(type) {
+	case map[string]interface{}:
+		if properties, ok := value["properties"].(map[string]interface{}); ok {
+			for _, property := range properties {
+				if propertyMap, ok := property.(map[string]interface{}); ok {
+					if _, ok := propertyMap["description"].(string); ok {
+						count++
+					}
+				}
+			}
+		}
+		for _, child := range value {
+			count += countDocumentedProperties(child)
+		}
+	case []interface{}:
+		for _, child := range value {
+			count += countDocumentedProperties(child)
+		}
+	}
+
+	return count
+}
+
+// DeprecatedProperty is a property the schema marks with `deprecated: true`.
+// Path is a JSON pointer to it, such as "#/definitions/commandStep/properties/name".
+type DeprecatedProperty struct {
+	Name        string
+	Path        string
+	Description string
+}
+
+// DeprecatedProperties parses schema data and returns every property marked
+// deprecated, ordered by path
+func DeprecatedProperties(data []byte) ([]DeprecatedProperty, error) {
+	var root map[string]interface{}
+	if err := json.Unmarshal(data, &root); err != nil {
+		return nil, fmt.Errorf("failed to parse schema: %w", err)
+	}
+
+	var found []DeprecatedProperty
+	collectDeprecatedProperties(root, "#", &found)
+	return found, nil
+}
+
+// collectDeprecatedProperties walks the schema in key order, recording deprecated properties
+func collectDeprecatedProperties(node interface{}, path string, found *[]DeprecatedProperty) {
+	switch value := node.(type) {
+	case map[string]interface{}:
+		if properties, ok := value["properties"].(map[string]interface{}); ok {
+			for _, name := range slices.Sorted(maps.Keys(properties)) {
+				property, ok := properties[name].(map[string]interface{})
+				if !ok || property["deprecated"] != true {
+					continue
+				}
+				description, _ := property["description"].(string)
+				*found = append(*found, DeprecatedProperty{
+					Name:        name,
+					Path:        path + "/properties/" + name,
+					Description: description,
+				})
+			}
+		}
+		for _, key := range slices.Sorted(maps.Keys(value)) {
+			collectDeprecatedProperties(value[key], path+"/"+key, found)
+		}
+	case []interface{}:
+		for i, child := range value {
+			collectDeprecatedProperties(child, fmt.Sprintf("%s/%d", path, i), found)
+		}
+	}
+}
+
+// PropertyDefaults parses schema data and returns the default of each property
+// that declares one, keyed by property name. A property given different defaults
+// in different places is left out, since its name alone doesn't say which applies.
+func PropertyDefaults(data []byte) (map[string]interface{}, error) {
+	var root map[string]interface{}
+	if err := json.Unmarshal(data, &root); err != nil {
+		return nil, fmt.Errorf("failed to parse schema: %w", err)
+	}
+
+	defaults := make(map[string]interface{})
+	conflicting := make(map[string]bool)
+	collectPropertyDefaults(root, root, defaults, conflicting)
+	for name := range conflicting {
+		delete(defaults, name)
+	}
+	return defaults, nil
+}
+
+// collectPropertyDefaults walks the schema recording property defaults, following
+// a property's $ref when the default is declared on the referenced definition
+func collectPropertyDefaults(root map[string]interface{}, node interface{}, defaults map[string]interface{}, conflicting map[string]bool) {
+	switch value := node.(type) {
+	case map[string]interface{}:
+		if properties, ok := value["properties"].(map[string]interface{}); ok {
+			for name, property := range properties {
+				propertyNode, ok := property.(map[string]interface{})
+				if !ok {
+					continue
+				}
+				def, ok := propertyNode["default"]
+				if !ok {
+					ref, _ := propertyNode["$ref"].(string)
+					resolved := ResolveRef(root, ref)
+					if resolved == nil {
+						continue
+					}
+					if def, ok = resolved["default"]; !ok {
+						continue
+					}
+				}
+
+				if existing, seen := defaults[name]; seen && !reflect.DeepEqual(existing, def) {
+					conflicting[name] = true
+				}
+				defaults[name] = def
+			}
+		}
+		for _, child := range value {
+			collectPropertyDefaults(root, child, defaults, conflicting)
+		}
+	case []interface{}:
+		for _, child := range value {
+			collectPropertyDefaults(root, child, defaults, conflicting)
+		}
+	}
+}
+
+// ArrayProperties parses schema data and returns the names of properties whose
+// type is an array, following a property's $ref. A name that is an array in one
+// place but not in another is left out, as with PropertyDefaults.
+func ArrayProperties(data []byte) (map[string]bool, error) {
+	var root map[string]interface{}
+	if err := json.Unmarshal(data, &root); err != nil {
+		return nil, fmt.Errorf("failed to parse schema: %w", err)
+	}
+
+	arrays := make(map[string]bool)
+	collectArrayProperties(root, root, arrays)
+	for name, isArray := range arrays {
+		if !isArray {
+			delete(arrays, name)
+		}
+	}
+	return arrays, nil
+}
+
+// collectArrayProperties walks the schema recording whether each property is an
+// array. Once a name has been seen as something else it stays false.
+func collectArrayProperties(root map[string]interface{}, node interface{}, arrays map[string]bool) {
+	switch value := node.(type) {
+	case map[string]interface{}:
+		if properties, ok := value["properties"].(map[string]interface{}); ok {
+			for name, property := range properties {
+				propertyNode, ok := property.(map[string]interface{})
+				if !ok {
+					continue
+				}
+				for depth := 0; propertyNode != nil && depth <= maxRefDepth; depth++ {
+					ref, ok := propertyNode["$ref"].(string)
+					if !ok {
+						break
+					}
+					propertyNode = ResolveRef(root, ref)
+				}
+
+				isArray := propertyNode != nil && propertyNode["type"] == "array"
+				if seen, ok := arrays[name]; ok && !seen {
+					continue
+				}
+				arrays[name] = isArray
+			}
+		}
+		for _, child := range value {
+			collectArrayProperties(root, child, arrays)
+		}
+	case []interface{}:
+		for _, child := range value {
+			collectArrayProperties(root, child, arrays)
+		}
+	}
+}
+
+// PropertyTypes parses schema data and returns a PropertyTypeSummary of each
+// property, keyed by property name. A name summarized differently in different
+// places is left out, as with PropertyDefaults.
+func PropertyTypes(data []byte) (map[string]string, error) {
+	var root map[string]interface{}
+	if err := json.Unmarshal(data, &root); err != nil {
+		return nil, fmt.Errorf("failed to parse schema: %w", err)
+	}
+
+	types := make(map[string]string)
+	conflicting := make(map[string]bool)
+	collectPropertyTypes(root, root, types, conflicting)
+	for name := range conflicting {
+		delete(types, name)
+	}
+	return types, nil
+}
+
+// collectPropertyTypes walks the schema recording the type summary of each
+// property, skipping those it can't say anything about
+func collectPropertyTypes(root map[string]interface{}, node interface{}, types map[string]string, conflicting map[string]bool) {
+	switch value := node.(type) {
+	case map[string]interface{}:
+		if properties, ok := value["properties"].(map[string]interface{}); ok {
+			for name, property := range properties {
+				propertyNode, ok := property.(map[string]interface{})
+				if !ok {
+					continue
+				}
+
+				summary := PropertyTypeSummary(root, propertyNode)
+				if summary == "any" {
+					continue
+				}
+				if existing, seen := types[name]; seen && existing != summary {
+					conflicting[name] = true
+				}
+				types[name] = summary
+			}
+		}
+		for _, child := range value {
+			collectPropertyTypes(root, child, types, conflicting)
+		}
+	case []interface{}:
+		for _, child := range value {
+			collectPropertyTypes(root, child, types, conflicting)
+		}
+	}
+}
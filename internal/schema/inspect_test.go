@@ -0,0 +1,217 @@
+package schema
+
+import (
+	"reflect"
+	"testing"
+)
+
+func TestInspect(t *testing.T) {
+	data := []byte(`{
+		"definitions": {
+			"label": {"type": "string", "description": "The step label"},
+			"commandStep": {
+				"type": "object",
+				"properties": {
+					"command": {"type": "string", "description": "The command to run"},
+					"label": {"$ref": "#/definitions/label"}
+				}
+			}
+		},
+		"properties": {
+			"steps": {"type": "array", "description": "The pipeline steps"},
+			"env": {"type": "object"}
+		}
+	}`)
+
+	stats, err := Inspect(data)
+	if err != nil {
+		t.Fatalf("Inspect failed: %v", err)
+	}
+
+	if stats.Definitions != 2 {
+		t.Errorf("Expected 2 definitions, got %d", stats.Definitions)
+	}
+
+	if stats.DocumentedPaths != 2 {
+		t.Errorf("Expected 2 documented paths, got %d", stats.DocumentedPaths)
+	}
+}
+
+func TestInspect_InvalidJSON(t *testing.T) {
+	if _, err := Inspect([]byte(`{"definitions": `)); err == nil {
+		t.Error("Expected error for invalid schema JSON")
+	}
+}
+
+func TestDeprecatedProperties(t *testing.T) {
+	data := []byte(`{
+		"definitions": {
+			"commandStep": {
+				"properties": {
+					"command": {"type": "string"},
+					"retired": {"type": "string", "deprecated": true, "description": "Use 'label' instead"}
+				}
+			},
+			"blockStep": {
+				"properties": {
+					"old_prompt": {"deprecated": true}
+				}
+			}
+		}
+	}`)
+
+	deprecated, err := DeprecatedProperties(data)
+	if err != nil {
+		t.Fatalf("DeprecatedProperties failed: %v", err)
+	}
+
+	expected := []DeprecatedProperty{
+		{Name: "old_prompt", Path: "#/definitions/blockStep/properties/old_prompt"},
+		{Name: "retired", Path: "#/definitions/commandStep/properties/retired", Description: "Use 'label' instead"},
+	}
+	if len(deprecated) != len(expected) {
+		t.Fatalf("Expected %d deprecated properties, got %v", len(expected), deprecated)
+	}
+	for i := range expected {
+		if deprecated[i] != expected[i] {
+			t.Errorf("Property %d: expected %+v, got %+v", i, expected[i], deprecated[i])
+		}
+	}
+}
+
+func TestPropertyDefaults(t *testing.T) {
+	data := []byte(`{
+		"definitions": {
+			"cancelOnBuildFailing": {"type": "boolean", "default": false},
+			"commandStep": {
+				"properties": {
+					"cancel_on_build_failing": {"$ref": "#/definitions/cancelOnBuildFailing"},
+					"concurrency_method": {"enum": ["ordered", "eager"], "default": "ordered"},
+					"parallelism": {"type": "integer", "default": 1},
+					"skip": {"type": "boolean", "default": false},
+					"label": {"type": "string"}
+				}
+			},
+			"blockStep": {
+				"properties": {
+					"skip": {"type": "boolean", "default": false},
+					"blocked_state": {"enum": ["passed", "failed"], "default": "passed"}
+				}
+			},
+			"triggerStep": {
+				"properties": {
+					"blocked_state": {"enum": ["passed", "running"], "default": "running"}
+				}
+			}
+		}
+	}`)
+
+	defaults, err := PropertyDefaults(data)
+	if err != nil {
+		t.Fatalf("PropertyDefaults failed: %v", err)
+	}
+
+	expected := map[string]interface{}{
+		"cancel_on_build_failing": false,
+		"concurrency_method":      "ordered",
+		"parallelism":             float64(1),
+		"skip":                    false,
+	}
+	if !reflect.DeepEqual(defaults, expected) {
+		t.Errorf("Expected %v, got %v", expected, defaults)
+	}
+
+	if _, err := PropertyDefaults([]byte(`{not json`)); err == nil {
+		t.Error("Expected an error for invalid JSON")
+	}
+}
+
+func TestArrayProperties(t *testing.T) {
+	data := []byte(`{
+		"properties": {
+			"steps": {"$ref": "#/definitions/pipelineSteps"},
+			"env": {"type": "object"}
+		},
+		"definitions": {
+			"pipelineSteps": {"type": "array", "items": {"type": "object"}},
+			"commandStep": {
+				"properties": {
+					"artifact_paths": {"anyOf": [{"type": "string"}, {"type": "array"}]},
+					"fields": {"type": "array"},
+					"label": {"type": "string"}
+				}
+			},
+			"groupStep": {
+				"properties": {
+					"steps": {"$ref": "#/definitions/pipelineSteps"}
+				}
+			},
+			"triggerStep": {
+				"properties": {
+					"fields": {"type": "object"}
+				}
+			}
+		}
+	}`)
+
+	arrays, err := ArrayProperties(data)
+	if err != nil {
+		t.Fatalf("ArrayProperties failed: %v", err)
+	}
+
+	// fields is an object in triggerStep, so its name alone doesn't say which applies
+	expected := map[string]bool{"steps": true}
+	if !reflect.DeepEqual(arrays, expected) {
+		t.Errorf("Expected %v, got %v", expected, arrays)
+	}
+
+	if _, err := ArrayProperties([]byte(`{not json`)); err == nil {
+		t.Error("Expected an error for invalid JSON")
+	}
+}
+
+func TestPropertyTypes(t *testing.T) {
+	data := []byte(`{
+		"properties": {
+			"steps": {"$ref": "#/definitions/pipelineSteps"},
+			"env": {"type": "object"}
+		},
+		"definitions": {
+			"pipelineSteps": {"type": "array", "items": {"type": "object"}},
+			"commandStep": {
+				"properties": {
+					"artifact_paths": {"anyOf": [{"type": "string"}, {"type": "array", "items": {"type": "string"}}]},
+					"fields": {"type": "array"},
+					"label": {"type": "string"},
+					"matrix": {}
+				}
+			},
+			"triggerStep": {
+				"properties": {
+					"fields": {"type": "object"},
+					"label": {"type": "string"}
+				}
+			}
+		}
+	}`)
+
+	types, err := PropertyTypes(data)
+	if err != nil {
+		t.Fatalf("PropertyTypes failed: %v", err)
+	}
+
+	// fields differs between steps and matrix says nothing, so both are left out
+	expected := map[string]string{
+		"steps":          "array of object",
+		"env":            "object",
+		"artifact_paths": "string | array of string",
+		"label":          "string",
+	}
+	if !reflect.DeepEqual(types, expected) {
+		t.Errorf("Expected %v, got %v", expected, types)
+	}
+
+	if _, err := PropertyTypes([]byte(`{not json`)); err == nil {
+		t.Error("Expected an error for invalid JSON")
+	}
+}
@@ -1,58 +1,416 @@
 package schema
 
 import (
+	"encoding/json"
 	"fmt"
 	"io"
 	"net/http"
+	"net/url"
+	"os"
+	"path/filepath"
+	"regexp"
 	"strings"
 	"sync"
+	"time"
 
 	"github.com/xeipuuv/gojsonschema"
 )
 
 const SchemaURL = "https://raw.githubusercontent.com/buildkite/pipeline-schema/refs/heads/main/schema.json"
 
+// schemaRefURL is the schema location for a pinned tag or commit
+const schemaRefURL = "https://raw.githubusercontent.com/buildkite/pipeline-schema/%s/schema.json"
+
+// validSchemaRef matches release tags (v1.2.3) and commit SHAs
+var validSchemaRef = regexp.MustCompile(`^(v?\d+\.\d+\.\d+([-+.][0-9A-Za-z.-]+)?|[0-9a-f]{7,40})$`)
+
+// IsValidSchemaRef reports whether ref looks like a release tag or commit SHA
+func IsValidSchemaRef(ref string) bool {
+	return validSchemaRef.MatchString(ref)
+}
+
+// URLForRef returns the schema URL pinned to a tag or commit
+func URLForRef(ref string) string {
+	return fmt.Sprintf(schemaRefURL, ref)
+}
+
+// LocalPath returns the filesystem path for a file:// URL or a plain path. It
+// reports false for http and https URLs, which are downloaded instead.
+func LocalPath(location string) (string, bool, error) {
+	if strings.HasPrefix(location, "http://") || strings.HasPrefix(location, "https://") {
+		return "", false, nil
+	}
+
+	if !strings.HasPrefix(location, "file:") {
+		return location, true, nil
+	}
+
+	// Parsing decodes percent-encoded characters such as %20 in the path
+	u, err := url.Parse(location)
+	if err != nil {
+		return "", true, fmt.Errorf("invalid file URL %q: %w", location, err)
+	}
+	if u.Host != "" && u.Host != "localhost" {
+		return "", true, fmt.Errorf("invalid file URL %q: remote file hosts are not supported", location)
+	}
+
+	path := u.Path
+	if path == "" {
+		path = u.Opaque
+	}
+	// Windows paths arrive as /C:/path/schema.json
+	if len(path) >= 3 && path[0] == '/' && path[2] == ':' {
+		path = path[1:]
+	}
+
+	return filepath.FromSlash(path), true, nil
+}
+
+const (
+	// fetchTimeout bounds a single schema request, including redirects
+	fetchTimeout = 15 * time.Second
+	// fetchRetries is the number of additional attempts after a failed request
+	fetchRetries = 2
+	// fetchBackoff is the delay before the first retry, doubled on each attempt
+	fetchBackoff = 500 * time.Millisecond
+	// failureCooldown is how long a failed load is reported before fetching again,
+	// so an offline client doesn't start a download on every validation
+	failureCooldown = 30 * time.Second
+)
+
 type Loader struct {
 	mu         sync.RWMutex
 	schemaData []byte
+	url        string
+	client     *http.Client
+	backoff    time.Duration
+	deprecated []DeprecatedProperty
+	root       map[string]interface{}
+	defaults   map[string]interface{}
+	arrays     map[string]bool
+	types      map[string]string
+	fetchedAt  time.Time
+	// loading is closed when the fetch in progress finishes, and nil when
+	// there isn't one
+	loading chan struct{}
+	// generation counts URL changes, so a fetch for a replaced URL isn't stored
+	generation int
+	// loadErr is the last failed load, returned until cooldown has passed since failedAt
+	loadErr  error
+	failedAt time.Time
+	cooldown time.Duration
+}
+
+// Info describes the schema a loader is using, for clients to display
+type Info struct {
+	Title     string    `json:"title"`
+	Source    string    `json:"source"`
+	FetchedAt time.Time `json:"fetchedAt"`
+	// IsStub is true until a schema has been loaded, while only the server's
+	// built-in checks apply
+	IsStub bool `json:"isStub"`
 }
 
 func NewLoader() *Loader {
-	return &Loader{}
+	return &Loader{
+		url: SchemaURL,
+		// The default redirect policy follows up to 10 redirects
+		client:   &http.Client{Timeout: fetchTimeout},
+		backoff:  fetchBackoff,
+		cooldown: failureCooldown,
+	}
 }
 
-func (l *Loader) GetSchemaData() ([]byte, error) {
+// NewLoaderFromFile reads and parses the schema at path synchronously, returning
+// a loader that is already loaded. Read failures wrap the underlying error, so
+// errors.Is(err, fs.ErrNotExist) reports a missing file.
+func NewLoaderFromFile(path string) (*Loader, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return nil, fmt.Errorf("failed to read schema: %w", err)
+	}
+
+	var parsed map[string]interface{}
+	if err := json.Unmarshal(data, &parsed); err != nil {
+		return nil, fmt.Errorf("failed to parse schema %s: %w", path, err)
+	}
+
+	loader := NewLoader()
+	loader.url = path
+	loader.schemaData = data
+	loader.fetchedAt = time.Now()
+	return loader, nil
+}
+
+// SetURL changes where the schema is fetched from, discarding any schema already loaded.
+// The location can be an http(s) URL, a file:// URL or a local path.
+func (l *Loader) SetURL(url string) {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	l.url = url
+	l.schemaData = nil
+	l.deprecated = nil
+	l.root = nil
+	l.defaults = nil
+	l.arrays = nil
+	l.types = nil
+	l.fetchedAt = time.Time{}
+	l.generation++
+	l.loading = nil
+	l.loadErr = nil
+	l.failedAt = time.Time{}
+}
+
+// IsLoaded reports whether the schema has been fetched
+func (l *Loader) IsLoaded() bool {
 	l.mu.RLock()
-	if l.schemaData != nil {
-		defer l.mu.RUnlock()
-		return l.schemaData, nil
+	defer l.mu.RUnlock()
+	return l.schemaData != nil
+}
+
+// DeprecatedProperties returns the properties the loaded schema marks deprecated.
+// It returns nil until the schema has been loaded and never starts a fetch.
+func (l *Loader) DeprecatedProperties() []DeprecatedProperty {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+
+	if l.schemaData == nil {
+		return nil
+	}
+	if l.deprecated == nil {
+		deprecated, err := DeprecatedProperties(l.schemaData)
+		if err != nil || deprecated == nil {
+			deprecated = []DeprecatedProperty{}
+		}
+		l.deprecated = deprecated
 	}
-	l.mu.RUnlock()
+	return l.deprecated
+}
+
+// PropertyDefault returns the default the loaded schema gives a property, if any.
+// Like DeprecatedProperties it reports nothing until the schema has been loaded.
+func (l *Loader) PropertyDefault(name string) (interface{}, bool) {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+
+	if l.schemaData == nil {
+		return nil, false
+	}
+	if l.defaults == nil {
+		defaults, err := PropertyDefaults(l.schemaData)
+		if err != nil || defaults == nil {
+			defaults = map[string]interface{}{}
+		}
+		l.defaults = defaults
+	}
+
+	value, ok := l.defaults[name]
+	return value, ok
+}
 
+// IsArrayProperty reports whether the loaded schema declares a property as an
+// array. Like DeprecatedProperties it reports nothing until the schema has been loaded.
+func (l *Loader) IsArrayProperty(name string) bool {
 	l.mu.Lock()
 	defer l.mu.Unlock()
 
+	if l.schemaData == nil {
+		return false
+	}
+	if l.arrays == nil {
+		arrays, err := ArrayProperties(l.schemaData)
+		if err != nil || arrays == nil {
+			arrays = map[string]bool{}
+		}
+		l.arrays = arrays
+	}
+	return l.arrays[name]
+}
+
+// PropertyType returns a short summary of a property's type in the loaded schema,
+// such as "array of string". Like DeprecatedProperties it reports nothing until
+// the schema has been loaded.
+func (l *Loader) PropertyType(name string) (string, bool) {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+
+	if l.schemaData == nil {
+		return "", false
+	}
+	if l.types == nil {
+		types, err := PropertyTypes(l.schemaData)
+		if err != nil || types == nil {
+			types = map[string]string{}
+		}
+		l.types = types
+	}
+
+	summary, ok := l.types[name]
+	return summary, ok
+}
+
+// DefinitionRequired returns the required properties of a named definition, such
+// as "triggerStep", in the loaded schema. Like DeprecatedProperties it returns nil
+// until the schema has been loaded.
+func (l *Loader) DefinitionRequired(definition string) []string {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+
+	if l.schemaData == nil {
+		return nil
+	}
+
+	root := l.rootLocked()
+	for _, key := range []string{"definitions", "$defs"} {
+		if definitions, ok := root[key].(map[string]interface{}); ok {
+			if node, ok := definitions[definition].(map[string]interface{}); ok {
+				return RequiredProperties(root, node)
+			}
+		}
+	}
+	return nil
+}
+
+// Info reports where the schema comes from and, once it has been loaded, its
+// title and when it was fetched. Like DeprecatedProperties it never starts a fetch.
+func (l *Loader) Info() Info {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+
+	info := Info{Source: l.url, IsStub: l.schemaData == nil}
 	if l.schemaData != nil {
-		return l.schemaData, nil
+		info.Title, _ = l.rootLocked()["title"].(string)
+		info.FetchedAt = l.fetchedAt
+	}
+	return info
+}
+
+// rootLocked returns the parsed schema, parsing it on first use. The caller
+// must hold the write lock and have loaded the schema.
+func (l *Loader) rootLocked() map[string]interface{} {
+	if l.root == nil {
+		if err := json.Unmarshal(l.schemaData, &l.root); err != nil || l.root == nil {
+			l.root = map[string]interface{}{}
+		}
+	}
+	return l.root
+}
+
+// GetSchemaData returns the schema, fetching it if it hasn't been loaded.
+// Concurrent callers share a single fetch, which runs without holding the
+// loader's lock so that readers such as IsLoaded never wait on the network.
+// After a failed load the error is returned without fetching again until the
+// cooldown has passed.
+func (l *Loader) GetSchemaData() ([]byte, error) {
+	for {
+		l.mu.Lock()
+		if l.schemaData != nil {
+			schemaBytes := l.schemaData
+			l.mu.Unlock()
+			return schemaBytes, nil
+		}
+		if l.loadErr != nil && time.Since(l.failedAt) < l.cooldown {
+			err := l.loadErr
+			l.mu.Unlock()
+			return nil, err
+		}
+		if loading := l.loading; loading != nil {
+			l.mu.Unlock()
+			<-loading
+			continue
+		}
+
+		done := make(chan struct{})
+		l.loading = done
+		location, generation := l.url, l.generation
+		l.mu.Unlock()
+
+		schemaBytes, err := l.fetchSchema(location)
+
+		l.mu.Lock()
+		stale := l.generation != generation
+		if !stale {
+			l.loading = nil
+			if err != nil {
+				l.loadErr = err
+				l.failedAt = time.Now()
+			} else {
+				l.schemaData = schemaBytes
+				l.fetchedAt = time.Now()
+			}
+		}
+		l.mu.Unlock()
+		close(done)
+
+		// The URL changed during the fetch, so load from the new one instead
+		if stale {
+			continue
+		}
+		if err != nil {
+			return nil, err
+		}
+		return schemaBytes, nil
 	}
+}
 
-	resp, err := http.Get(SchemaURL)
+// fetchSchema reads a local schema or downloads a remote one, retrying with
+// backoff on network errors, rate limiting and server errors
+func (l *Loader) fetchSchema(location string) ([]byte, error) {
+	if path, isLocal, err := LocalPath(location); isLocal {
+		if err != nil {
+			return nil, err
+		}
+		schemaBytes, err := os.ReadFile(path)
+		if err != nil {
+			return nil, fmt.Errorf("failed to read schema: %w", err)
+		}
+		return schemaBytes, nil
+	}
+
+	var lastErr error
+	delay := l.backoff
+
+	for attempt := 0; attempt <= fetchRetries; attempt++ {
+		if attempt > 0 {
+			time.Sleep(delay)
+			delay *= 2
+		}
+
+		schemaBytes, retry, err := l.fetchOnce(location)
+		if err == nil {
+			return schemaBytes, nil
+		}
+		lastErr = err
+		if !retry {
+			break
+		}
+	}
+
+	return nil, lastErr
+}
+
+// fetchOnce performs a single request and reports whether a failure is worth retrying
+func (l *Loader) fetchOnce(location string) ([]byte, bool, error) {
+	resp, err := l.client.Get(location)
 	if err != nil {
-		return nil, fmt.Errorf("failed to fetch schema: %w", err)
+		return nil, true, fmt.Errorf("failed to fetch schema: %w", err)
 	}
 	defer func() { _ = resp.Body.Close() }()
 
 	if resp.StatusCode != http.StatusOK {
-		return nil, fmt.Errorf("failed to fetch schema: HTTP %d", resp.StatusCode)
+		// GitHub reports rate limiting as 403 or 429
+		retry := resp.StatusCode == http.StatusForbidden ||
+			resp.StatusCode == http.StatusTooManyRequests ||
+			resp.StatusCode >= http.StatusInternalServerError
+		return nil, retry, fmt.Errorf("failed to fetch schema: HTTP %d", resp.StatusCode)
 	}
 
 	schemaBytes, err := io.ReadAll(resp.Body)
 	if err != nil {
-		return nil, fmt.Errorf("failed to read schema: %w", err)
+		return nil, true, fmt.Errorf("failed to read schema: %w", err)
 	}
 
-	l.schemaData = schemaBytes
-	return schemaBytes, nil
+	return schemaBytes, false, nil
 }
 
 type ValidationError struct {
@@ -1,7 +1,17 @@
 package schema
 
 import (
+	"errors"
+	"io/fs"
+	"net/http"
+	"net/http/httptest"
+	"net/url"
+	"os"
+	"path/filepath"
+	"strings"
+	"sync/atomic"
 	"testing"
+	"time"
 )
 
 func TestValidateJSON_ValidPipeline(t *testing.T) {
@@ -310,3 +320,363 @@ func containsSubstring(text, substring string) bool {
 
 	return false
 }
+
+func TestGetSchemaData_RetriesTransientFailures(t *testing.T) {
+	var requests int32
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		switch atomic.AddInt32(&requests, 1) {
+		case 1:
+			w.WriteHeader(http.StatusTooManyRequests)
+		case 2:
+			w.WriteHeader(http.StatusBadGateway)
+		default:
+			_, _ = w.Write([]byte(`{"type": "object"}`))
+		}
+	}))
+	defer server.Close()
+
+	loader := NewLoader()
+	loader.url = server.URL
+	loader.backoff = 0
+
+	data, err := loader.GetSchemaData()
+	if err != nil {
+		t.Fatalf("Expected schema after retries, got error: %v", err)
+	}
+	if string(data) != `{"type": "object"}` {
+		t.Errorf("Unexpected schema data: %s", data)
+	}
+	if requests != 3 {
+		t.Errorf("Expected 3 requests, got %d", requests)
+	}
+}
+
+func TestGetSchemaData_FollowsRedirects(t *testing.T) {
+	mux := http.NewServeMux()
+	mux.HandleFunc("/old", func(w http.ResponseWriter, r *http.Request) {
+		http.Redirect(w, r, "/schema.json", http.StatusMovedPermanently)
+	})
+	mux.HandleFunc("/schema.json", func(w http.ResponseWriter, r *http.Request) {
+		_, _ = w.Write([]byte(`{}`))
+	})
+	server := httptest.NewServer(mux)
+	defer server.Close()
+
+	loader := NewLoader()
+	loader.url = server.URL + "/old"
+	loader.backoff = 0
+
+	if _, err := loader.GetSchemaData(); err != nil {
+		t.Fatalf("Expected redirect to be followed, got error: %v", err)
+	}
+}
+
+func TestGetSchemaData_DoesNotRetryNotFound(t *testing.T) {
+	var requests int32
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		atomic.AddInt32(&requests, 1)
+		w.WriteHeader(http.StatusNotFound)
+	}))
+	defer server.Close()
+
+	loader := NewLoader()
+	loader.url = server.URL
+	loader.backoff = 0
+
+	if _, err := loader.GetSchemaData(); err == nil {
+		t.Fatal("Expected error for missing schema")
+	}
+	if requests != 1 {
+		t.Errorf("Expected a single request, got %d", requests)
+	}
+}
+
+func TestLoader_IsLoaded(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		_, _ = w.Write([]byte(`{}`))
+	}))
+	defer server.Close()
+
+	loader := NewLoader()
+	loader.url = server.URL
+
+	if loader.IsLoaded() {
+		t.Fatal("Expected schema not to be loaded before fetching")
+	}
+
+	if _, err := loader.GetSchemaData(); err != nil {
+		t.Fatalf("GetSchemaData failed: %v", err)
+	}
+
+	if !loader.IsLoaded() {
+		t.Error("Expected schema to be loaded after fetching")
+	}
+}
+
+func TestGetSchemaData_DoesNotBlockReaders(t *testing.T) {
+	var requests int32
+	requested := make(chan struct{})
+	release := make(chan struct{})
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if atomic.AddInt32(&requests, 1) == 1 {
+			close(requested)
+		}
+		<-release
+		_, _ = w.Write([]byte(`{"title": "Buildkite"}`))
+	}))
+	defer server.Close()
+
+	loader := NewLoader()
+	loader.url = server.URL
+
+	results := make(chan error, 2)
+	for range 2 {
+		go func() {
+			_, err := loader.GetSchemaData()
+			results <- err
+		}()
+	}
+
+	select {
+	case <-requested:
+	case <-time.After(2 * time.Second):
+		t.Fatal("Expected the schema to be requested")
+	}
+
+	// Readers answer from what's loaded while the download is in progress
+	read := make(chan bool)
+	go func() {
+		loaded := loader.IsLoaded()
+		_ = loader.Info()
+		read <- loaded
+	}()
+	select {
+	case loaded := <-read:
+		if loaded {
+			t.Error("Expected the schema not to be loaded during the fetch")
+		}
+	case <-time.After(time.Second):
+		t.Fatal("Expected readers not to wait for the fetch")
+	}
+
+	close(release)
+	for range 2 {
+		if err := <-results; err != nil {
+			t.Errorf("GetSchemaData failed: %v", err)
+		}
+	}
+	if !loader.IsLoaded() {
+		t.Error("Expected the schema to be loaded after the fetch")
+	}
+	if requests != 1 {
+		t.Errorf("Expected concurrent loads to share one request, got %d", requests)
+	}
+}
+
+func TestGetSchemaData_CoolsDownAfterFailure(t *testing.T) {
+	var requests int32
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		atomic.AddInt32(&requests, 1)
+		w.WriteHeader(http.StatusNotFound)
+	}))
+	defer server.Close()
+
+	loader := NewLoader()
+	loader.url = server.URL
+	loader.backoff = 0
+
+	for range 3 {
+		if _, err := loader.GetSchemaData(); err == nil {
+			t.Fatal("Expected error for missing schema")
+		}
+	}
+	if requests != 1 {
+		t.Errorf("Expected failures to be reused during the cooldown, got %d requests", requests)
+	}
+
+	loader.cooldown = 0
+	if _, err := loader.GetSchemaData(); err == nil {
+		t.Fatal("Expected error for missing schema")
+	}
+	if requests != 2 {
+		t.Errorf("Expected a new request after the cooldown, got %d requests", requests)
+	}
+
+	// A new location is fetched straight away
+	loader.cooldown = time.Hour
+	loader.SetURL(server.URL + "/other")
+	if _, err := loader.GetSchemaData(); err == nil {
+		t.Fatal("Expected error for missing schema")
+	}
+	if requests != 3 {
+		t.Errorf("Expected changing the URL to clear the failure, got %d requests", requests)
+	}
+}
+
+func TestSchemaRef(t *testing.T) {
+	valid := []string{"v1.2.3", "1.0.0", "v2.0.0-beta.1", "a1b2c3d", "0123456789abcdef0123456789abcdef01234567"}
+	for _, ref := range valid {
+		if !IsValidSchemaRef(ref) {
+			t.Errorf("Expected %q to be a valid schema ref", ref)
+		}
+	}
+
+	invalid := []string{"", "main", "../etc", "v1.2", "feature/branch", "ABCDEF1"}
+	for _, ref := range invalid {
+		if IsValidSchemaRef(ref) {
+			t.Errorf("Expected %q to be rejected", ref)
+		}
+	}
+
+	expected := "https://raw.githubusercontent.com/buildkite/pipeline-schema/v1.2.3/schema.json"
+	if got := URLForRef("v1.2.3"); got != expected {
+		t.Errorf("Expected %s, got %s", expected, got)
+	}
+}
+
+func TestLocalPath(t *testing.T) {
+	tests := []struct {
+		location string
+		path     string
+		isLocal  bool
+		wantErr  bool
+	}{
+		{location: "https://example.com/schema.json"},
+		{location: "http://localhost:8080/schema.json"},
+		{location: "/etc/buildkite/schema.json", path: "/etc/buildkite/schema.json", isLocal: true},
+		{location: "file:///etc/buildkite/schema.json", path: "/etc/buildkite/schema.json", isLocal: true},
+		{location: "file://localhost/etc/schema.json", path: "/etc/schema.json", isLocal: true},
+		{location: "file:///home/me/my%20schemas/schema.json", path: "/home/me/my schemas/schema.json", isLocal: true},
+		{location: "file://server/share/schema.json", isLocal: true, wantErr: true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.location, func(t *testing.T) {
+			path, isLocal, err := LocalPath(tt.location)
+			if isLocal != tt.isLocal {
+				t.Errorf("Expected isLocal %v, got %v", tt.isLocal, isLocal)
+			}
+			if (err != nil) != tt.wantErr {
+				t.Errorf("Expected error %v, got %v", tt.wantErr, err)
+			}
+			if path != filepath.FromSlash(tt.path) {
+				t.Errorf("Expected path %q, got %q", tt.path, path)
+			}
+		})
+	}
+}
+
+func TestGetSchemaData_FileURL(t *testing.T) {
+	dir := filepath.Join(t.TempDir(), "my schemas")
+	if err := os.Mkdir(dir, 0o755); err != nil {
+		t.Fatal(err)
+	}
+	schemaPath := filepath.Join(dir, "schema.json")
+	if err := os.WriteFile(schemaPath, []byte(`{"type": "object"}`), 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	fileURL := (&url.URL{Scheme: "file", Path: "/" + strings.TrimPrefix(filepath.ToSlash(schemaPath), "/")}).String()
+	if !strings.Contains(fileURL, "%20") {
+		t.Fatalf("Expected the file URL to be percent-encoded, got %s", fileURL)
+	}
+
+	for _, location := range []string{fileURL, schemaPath} {
+		loader := NewLoader()
+		loader.SetURL(location)
+
+		data, err := loader.GetSchemaData()
+		if err != nil {
+			t.Fatalf("Expected schema from %s, got error: %v", location, err)
+		}
+		if string(data) != `{"type": "object"}` {
+			t.Errorf("Unexpected schema data from %s: %s", location, data)
+		}
+	}
+
+	loader := NewLoader()
+	loader.SetURL(filepath.Join(dir, "missing.json"))
+	if _, err := loader.GetSchemaData(); err == nil {
+		t.Error("Expected error for a missing schema file")
+	}
+}
+
+func TestLoader_DeprecatedProperties(t *testing.T) {
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	data := `{"properties": {"old": {"deprecated": true}}}`
+	if err := os.WriteFile(schemaPath, []byte(data), 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	loader := NewLoader()
+	loader.SetURL(schemaPath)
+	if deprecated := loader.DeprecatedProperties(); deprecated != nil {
+		t.Errorf("Expected no deprecated properties before loading, got %v", deprecated)
+	}
+	if loader.IsLoaded() {
+		t.Error("Expected DeprecatedProperties not to load the schema")
+	}
+
+	if _, err := loader.GetSchemaData(); err != nil {
+		t.Fatalf("GetSchemaData failed: %v", err)
+	}
+	deprecated := loader.DeprecatedProperties()
+	if len(deprecated) != 1 || deprecated[0].Path != "#/properties/old" {
+		t.Errorf("Expected the deprecated property from the schema, got %v", deprecated)
+	}
+}
+
+func TestNewLoaderFromFile(t *testing.T) {
+	dir := t.TempDir()
+	schemaPath := filepath.Join(dir, "schema.json")
+	if err := os.WriteFile(schemaPath, []byte(`{"type": "object"}`), 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	loader, err := NewLoaderFromFile(schemaPath)
+	if err != nil {
+		t.Fatalf("NewLoaderFromFile failed: %v", err)
+	}
+	if !loader.IsLoaded() {
+		t.Error("Expected the loader to be loaded")
+	}
+	if _, err := loader.ValidateJSON([]byte(`{"steps": []}`)); err != nil {
+		t.Errorf("Expected validation against the file schema, got %v", err)
+	}
+
+	if _, err := NewLoaderFromFile(filepath.Join(dir, "missing.json")); !errors.Is(err, fs.ErrNotExist) {
+		t.Errorf("Expected a not-exist error for a missing file, got %v", err)
+	}
+
+	invalidPath := filepath.Join(dir, "invalid.json")
+	if err := os.WriteFile(invalidPath, []byte(`{not json`), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	if _, err := NewLoaderFromFile(invalidPath); err == nil || !strings.Contains(err.Error(), "failed to parse schema") {
+		t.Errorf("Expected a parse error, got %v", err)
+	}
+}
+
+func TestLoader_Info(t *testing.T) {
+	schemaPath := filepath.Join(t.TempDir(), "schema.json")
+	if err := os.WriteFile(schemaPath, []byte(`{"title": "JSON schema for Buildkite pipeline configuration files"}`), 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	loader := NewLoader()
+	loader.SetURL(schemaPath)
+
+	info := loader.Info()
+	if !info.IsStub || info.Source != schemaPath || info.Title != "" || !info.FetchedAt.IsZero() {
+		t.Errorf("Expected a stub before loading, got %+v", info)
+	}
+
+	if _, err := loader.GetSchemaData(); err != nil {
+		t.Fatalf("GetSchemaData failed: %v", err)
+	}
+
+	info = loader.Info()
+	if info.IsStub || info.Title != "JSON schema for Buildkite pipeline configuration files" || info.FetchedAt.IsZero() {
+		t.Errorf("Expected the loaded schema's details, got %+v", info)
+	}
+}
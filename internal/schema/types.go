@@ -0,0 +1,174 @@
+package schema
+
+import (
+	"strings"
+)
+
+// maxRefDepth guards against self-referencing schema definitions
+const maxRefDepth = 10
+
+// PropertyTypeSummary renders a short, readable type description for a schema
+// node, such as "string", "array of string" or "boolean | object". References
+// are resolved against root, which may be nil when the node has no $ref.
+func PropertyTypeSummary(root, node map[string]interface{}) string {
+	return summarizeType(root, node, 0)
+}
+
+func summarizeType(root, node map[string]interface{}, depth int) string {
+	if node == nil || depth > maxRefDepth {
+		return "any"
+	}
+
+	if ref, ok := node["$ref"].(string); ok {
+		if resolved := ResolveRef(root, ref); resolved != nil {
+			return summarizeType(root, resolved, depth+1)
+		}
+		return "any"
+	}
+
+	for _, key := range []string{"oneOf", "anyOf"} {
+		if variants, ok := node[key].([]interface{}); ok && len(variants) > 0 {
+			var parts []string
+			for _, variant := range variants {
+				if variantMap, ok := variant.(map[string]interface{}); ok {
+					parts = append(parts, summarizeType(root, variantMap, depth+1))
+				}
+			}
+			return joinUnion(parts)
+		}
+	}
+
+	if enum, ok := node["enum"].([]interface{}); ok && len(enum) > 0 {
+		var values []string
+		for _, value := range enum {
+			if str, ok := value.(string); ok {
+				values = append(values, `"`+str+`"`)
+			}
+		}
+		if len(values) == len(enum) {
+			return strings.Join(values, " | ")
+		}
+	}
+
+	switch nodeType := node["type"].(type) {
+	case string:
+		return summarizeSingleType(root, node, nodeType, depth)
+	case []interface{}:
+		var parts []string
+		for _, t := range nodeType {
+			if str, ok := t.(string); ok {
+				parts = append(parts, summarizeSingleType(root, node, str, depth))
+			}
+		}
+		return joinUnion(parts)
+	}
+
+	if _, ok := node["properties"]; ok {
+		return "object"
+	}
+
+	return "any"
+}
+
+func summarizeSingleType(root, node map[string]interface{}, nodeType string, depth int) string {
+	if nodeType != "array" {
+		return nodeType
+	}
+
+	items, ok := node["items"].(map[string]interface{})
+	if !ok {
+		return "array"
+	}
+
+	itemType := summarizeType(root, items, depth+1)
+	if strings.Contains(itemType, " | ") {
+		itemType = "(" + itemType + ")"
+	}
+	return "array of " + itemType
+}
+
+// joinUnion joins type names with " | ", dropping duplicates while keeping order
+func joinUnion(parts []string) string {
+	seen := make(map[string]bool)
+	var unique []string
+	for _, part := range parts {
+		for _, single := range splitUnion(part) {
+			if !seen[single] {
+				seen[single] = true
+				unique = append(unique, single)
+			}
+		}
+	}
+
+	if len(unique) == 0 {
+		return "any"
+	}
+	return strings.Join(unique, " | ")
+}
+
+// splitUnion splits a summary on " | " while keeping parenthesized groups intact
+func splitUnion(summary string) []string {
+	var parts []string
+	depth := 0
+	start := 0
+
+	for i := 0; i < len(summary); i++ {
+		switch summary[i] {
+		case '(':
+			depth++
+		case ')':
+			depth--
+		case '|':
+			if depth == 0 && i > 0 && i+1 < len(summary) && summary[i-1] == ' ' && summary[i+1] == ' ' {
+				parts = append(parts, summary[start:i-1])
+				start = i + 2
+			}
+		}
+	}
+
+	return append(parts, summary[start:])
+}
+
+// ResolveRef looks up a local JSON pointer reference such as "#/definitions/label"
+func ResolveRef(root map[string]interface{}, ref string) map[string]interface{} {
+	if root == nil || !strings.HasPrefix(ref, "#/") {
+		return nil
+	}
+
+	var current interface{} = root
+	for _, part := range strings.Split(strings.TrimPrefix(ref, "#/"), "/") {
+		part = strings.ReplaceAll(strings.ReplaceAll(part, "~1", "/"), "~0", "~")
+		currentMap, ok := current.(map[string]interface{})
+		if !ok {
+			return nil
+		}
+		current = currentMap[part]
+	}
+
+	resolved, _ := current.(map[string]interface{})
+	return resolved
+}
+
+// RequiredProperties returns the property names a schema node lists in its
+// `required` array, following a $ref on the node itself
+func RequiredProperties(root, node map[string]interface{}) []string {
+	for depth := 0; node != nil && depth <= maxRefDepth; depth++ {
+		ref, ok := node["$ref"].(string)
+		if !ok {
+			break
+		}
+		node = ResolveRef(root, ref)
+	}
+	if node == nil {
+		return nil
+	}
+
+	list, _ := node["required"].([]interface{})
+	var required []string
+	for _, entry := range list {
+		if name, ok := entry.(string); ok {
+			required = append(required, name)
+		}
+	}
+	return required
+}
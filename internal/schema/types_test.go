@@ -0,0 +1,89 @@
+package schema
+
+import (
+	"encoding/json"
+	"testing"
+)
+
+func TestPropertyTypeSummary(t *testing.T) {
+	root := map[string]interface{}{}
+	if err := json.Unmarshal([]byte(`{
+		"definitions": {
+			"label": {"type": "string"},
+			"commandStep": {"type": "object", "properties": {"command": {"type": "string"}}},
+			"dependsOn": {
+				"oneOf": [
+					{"type": "null"},
+					{"type": "string"},
+					{"type": "array", "items": {"oneOf": [{"type": "string"}, {"type": "object"}]}}
+				]
+			}
+		}
+	}`), &root); err != nil {
+		t.Fatalf("Failed to parse test schema: %v", err)
+	}
+
+	tests := []struct {
+		name     string
+		node     string
+		expected string
+	}{
+		{name: "simple string", node: `{"type": "string"}`, expected: "string"},
+		{name: "integer", node: `{"type": "integer"}`, expected: "integer"},
+		{name: "array of string", node: `{"type": "array", "items": {"type": "string"}}`, expected: "array of string"},
+		{name: "array without items", node: `{"type": "array"}`, expected: "array"},
+		{name: "type list", node: `{"type": ["boolean", "object"]}`, expected: "boolean | object"},
+		{name: "ref", node: `{"$ref": "#/definitions/label"}`, expected: "string"},
+		{name: "ref to object", node: `{"$ref": "#/definitions/commandStep"}`, expected: "object"},
+		{name: "oneOf with nested union", node: `{"$ref": "#/definitions/dependsOn"}`, expected: "null | string | array of (string | object)"},
+		{name: "oneOf duplicates collapse", node: `{"oneOf": [{"type": "string"}, {"$ref": "#/definitions/label"}]}`, expected: "string"},
+		{name: "string enum", node: `{"enum": ["linux", "windows"]}`, expected: `"linux" | "windows"`},
+		{name: "unknown ref", node: `{"$ref": "#/definitions/missing"}`, expected: "any"},
+		{name: "empty node", node: `{}`, expected: "any"},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			node := map[string]interface{}{}
+			if err := json.Unmarshal([]byte(tt.node), &node); err != nil {
+				t.Fatalf("Failed to parse node: %v", err)
+			}
+
+			if got := PropertyTypeSummary(root, node); got != tt.expected {
+				t.Errorf("PropertyTypeSummary(%s) = %q, expected %q", tt.node, got, tt.expected)
+			}
+		})
+	}
+}
+
+func TestPropertyTypeSummary_SelfReference(t *testing.T) {
+	root := map[string]interface{}{
+		"definitions": map[string]interface{}{
+			"loop": map[string]interface{}{"$ref": "#/definitions/loop"},
+		},
+	}
+
+	node := map[string]interface{}{"$ref": "#/definitions/loop"}
+	if got := PropertyTypeSummary(root, node); got != "any" {
+		t.Errorf("Expected self-referencing schema to summarize as 'any', got %q", got)
+	}
+}
+
+func TestRequiredProperties(t *testing.T) {
+	root := map[string]interface{}{
+		"definitions": map[string]interface{}{
+			"triggerStep": map[string]interface{}{
+				"required": []interface{}{"trigger"},
+			},
+		},
+	}
+
+	ref := map[string]interface{}{"$ref": "#/definitions/triggerStep"}
+	if required := RequiredProperties(root, ref); len(required) != 1 || required[0] != "trigger" {
+		t.Errorf("Expected [trigger] through the reference, got %v", required)
+	}
+
+	if required := RequiredProperties(root, map[string]interface{}{"type": "object"}); required != nil {
+		t.Errorf("Expected no required properties, got %v", required)
+	}
+}
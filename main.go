@@ -10,6 +10,7 @@ import (
 	"go.lsp.dev/jsonrpc2"
 
 	"github.com/mcncl/buildkite-ls/internal/lsp"
+	"github.com/mcncl/buildkite-ls/internal/schema"
 )
 
 var (
@@ -25,8 +26,24 @@ func (stdio) Read(p []byte) (n int, err error)  { return os.Stdin.Read(p) }
 func (stdio) Write(p []byte) (n int, err error) { return os.Stdout.Write(p) }
 func (stdio) Close() error                      { return nil }
 
+// usage is printed for --help and when the arguments can't be parsed
+const usage = `Usage:
+  buildkite-ls [--stdio]                Run the language server over stdin/stdout
+  buildkite-ls check-schema [path]      Load the pipeline schema from path, or
+                                        download it, and print a summary
+  buildkite-ls --version                Show version information
+
+Flags:
+`
+
 func main() {
 	showVersion := flag.Bool("version", false, "Show version information")
+	// Editors commonly pass --stdio; it's accepted as stdio is the only transport
+	flag.Bool("stdio", false, "Communicate over stdin/stdout (the default)")
+	flag.Usage = func() {
+		fmt.Fprint(flag.CommandLine.Output(), usage)
+		flag.PrintDefaults()
+	}
 	flag.Parse()
 
 	if *showVersion {
@@ -36,6 +53,17 @@ func main() {
 		return
 	}
 
+	switch flag.Arg(0) {
+	case "check-schema":
+		os.Exit(checkSchema(flag.Arg(1)))
+	case "":
+		// Without a command, run the language server
+	default:
+		fmt.Fprintf(os.Stderr, "Unknown command %q\n\n", flag.Arg(0))
+		flag.Usage()
+		os.Exit(2)
+	}
+
 	server := lsp.NewServer()
 
 	var rw io.ReadWriteCloser = stdio{}
@@ -48,3 +76,34 @@ func main() {
 	go conn.Go(context.Background(), server.Handler())
 	<-conn.Done()
 }
+
+// checkSchema loads the pipeline schema from path, or downloads it when path is
+// empty, and prints a summary. It returns the process exit code.
+func checkSchema(path string) int {
+	var (
+		data []byte
+		err  error
+	)
+
+	if path != "" {
+		data, err = os.ReadFile(path)
+	} else {
+		path = schema.SchemaURL
+		data, err = schema.NewLoader().GetSchemaData()
+	}
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "Failed to load schema: %v\n", err)
+		return 1
+	}
+
+	stats, err := schema.Inspect(data)
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "Invalid schema %s: %v\n", path, err)
+		return 1
+	}
+
+	fmt.Printf("Schema: %s\n", path)
+	fmt.Printf("Definitions: %d\n", stats.Definitions)
+	fmt.Printf("Documented paths: %d\n", stats.DocumentedPaths)
+	return 0
+}